@@ -0,0 +1,64 @@
+//! EIP-55 checksummed addresses: parsed with checksum validation, always printed in checksummed form.
+
+use crate::errors::AppError;
+use crate::utils;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use web3::types::Address;
+
+/// An address that displays and serializes as its EIP-55 checksum. Parsing accepts all-lowercase and
+/// all-uppercase hex, and mixed case only when it is the correct checksum.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChecksumAddress(Address);
+
+impl ChecksumAddress {
+    pub fn address(&self) -> Address {
+        self.0
+    }
+}
+
+impl From<Address> for ChecksumAddress {
+    fn from(address: Address) -> Self {
+        Self(address)
+    }
+}
+
+impl From<ChecksumAddress> for Address {
+    fn from(address: ChecksumAddress) -> Self {
+        address.0
+    }
+}
+
+impl FromStr for ChecksumAddress {
+    type Err = AppError;
+
+    fn from_str(address: &str) -> Result<Self, AppError> {
+        utils::validate_checksummed(address.trim()).map(Self)
+    }
+}
+
+impl fmt::Display for ChecksumAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&utils::to_checksum(&self.0))
+    }
+}
+
+impl fmt::Debug for ChecksumAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Serialize for ChecksumAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChecksumAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        address.parse().map_err(|e: AppError| de::Error::custom(e))
+    }
+}
@@ -1,29 +1,203 @@
+use crate::errors::{AppError, AppResult};
+use crate::secret::SecretString;
+use crate::services::bip32;
+use crate::utils;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::net::IpAddr;
+
+/// Stand-in for the provider key in the default `rpc_url`; a config still containing it was never filled in
+const RPC_URL_PLACEHOLDER: &str = "YOUR_API_KEY";
+const RPC_URL_SCHEMES: [&str; 4] = ["ws://", "wss://", "http://", "https://"];
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub ethereum: EthereumConfig,
     pub wallet: WalletConfig,
+    pub rpc_proxy: RpcProxyConfig,
+    pub testing: TestingConfig,
+    pub audit: AuditConfig,
+    pub security: SecurityConfig,
+    // An empty origin list does not survive config layering, so the section's default is restored here
+    #[serde(default)]
+    pub cors: CorsConfig,
+    #[serde(default)]
+    pub mpc: MpcConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API from a browser; empty sends no CORS headers, `"*"` allows any
+    pub allowed_origins: Vec<String>,
+}
+
+/// Threshold signing: the wallet key is split across co-signer services instead of living in a wallet file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MpcConfig {
+    /// Address of the shared key; setting it replaces the wallet file as the signing account
+    pub address: Option<String>,
+    /// Base URLs of the co-signer services, in party order
+    #[serde(default)]
+    pub peers: Vec<String>,
+    /// Time one signing session may take across all rounds
+    pub timeout_ms: u64,
+}
+
+impl Default for MpcConfig {
+    fn default() -> Self {
+        Self {
+            address: None,
+            peers: Vec::new(),
+            timeout_ms: 30_000,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityConfig {
+    /// Which kind of account `/transaction/send` may pay
+    pub recipient_type: RecipientPolicy,
+    /// Cap on native value sent over any rolling hour; unlimited when unset
+    pub max_eth_per_hour: Option<f64>,
+    /// File keeping the rolling-hour spend window across restarts; in memory only when unset
+    pub spend_window_file: Option<String>,
+    /// Enable `POST /account/export` and `/accounts/:address/export`, which hand out keys; requires `server.api_key`
+    pub allow_key_export: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecipientPolicy {
+    Any,
+    /// Recipient must have code deployed
+    ContractOnly,
+    /// Recipient must have no code
+    EoaOnly,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditConfig {
+    /// JSON-lines file receiving one entry per signing operation; auditing is off when unset
+    pub log_file: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Serve HTTPS directly when set
+    pub tls: Option<TlsConfig>,
+    /// Reject unknown names in `?fields=` with a 400 instead of ignoring them
+    pub strict_field_selection: bool,
+    /// Required (as `Authorization: Bearer` or `X-API-Key`) on signing and sending routes when set
+    pub api_key: Option<String>,
+    /// Per-client-IP budget for sending and gas estimation routes; 0 disables limiting
+    pub rate_limit_per_minute: u32,
+    /// Reverse proxies whose `X-Forwarded-For` is believed; from anyone else the peer address is the client
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Path prefix every route is mounted under, e.g. "/wallet"; empty mounts at the root
+    pub base_path: String,
+    /// Keep `/`, `/health` and `/metrics` at the root instead of under `base_path`
+    pub health_at_root: bool,
+    /// Most addresses accepted by one `POST /balance/batch`
+    pub max_balance_batch_size: usize,
+    /// Most blocks one `GET /transaction/history/:address` may scan
+    pub max_history_blocks: u64,
+    /// How long a completed send is replayed for its `Idempotency-Key`
+    pub idempotency_ttl_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EthereumConfig {
     pub rpc_url: String,
     pub network_id: u64,
+    /// Refuse to use a node whose `eth_chainId` differs from `network_id` instead of adopting its chain ID
+    pub strict_chain_id: bool,
+    /// Symbol of the chain's native currency; derived from `network_id` when unset
+    pub native_symbol: Option<String>,
+    /// Let read-only requests pick another node via the `X-RPC-URL` header
+    pub allow_rpc_override: bool,
+    /// RPC calls slower than this are logged at warn level and counted
+    pub slow_query_ms: u64,
+    /// RPC calls are abandoned with a 504 after this long
+    pub rpc_timeout_ms: u64,
+    /// Extra attempts for a balance batch entry whose lookup failed on the node's side (timeout, transport
+    /// or RPC error) before it is reported as an error; invalid addresses are never retried
+    pub balance_batch_retries: u32,
+    /// Reuse gas estimates per (recipient, method selector) for this long on the send path; 0 disables
+    pub gas_estimate_cache_secs: u64,
+    /// Highest gas price (or 1559 max fee per gas) any outgoing transaction may pay; uncapped when unset
+    pub max_gas_price_wei: Option<u64>,
+    /// Gas estimates are multiplied by this (at least 1.0) before being used as a transaction's gas limit
+    pub gas_limit_multiplier: f64,
+    /// Type of transaction sent when a request gives no fee fields
+    pub default_transaction_type: DefaultTransactionType,
+    /// Names for chains missing from the built-in list (or renaming one of them)
+    #[serde(default)]
+    pub network_names: Vec<NetworkName>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultTransactionType {
+    /// EIP-1559 when the latest block has a base fee, legacy otherwise
+    Auto,
+    /// Always `gas_price`
+    Legacy,
+    /// Always EIP-1559; sends fail on networks without a base fee
+    Eip1559,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkName {
+    pub network_id: u64,
+    pub name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WalletConfig {
     pub config_file: String,
+    /// Highest HD account index accepted in `account_index` of send requests
+    pub max_account_index: u32,
+    /// BIP-32 path under which HD accounts are numbered; account `n` is `<derivation_path>/n`
+    pub derivation_path: String,
+    /// HD accounts derived at startup so their first lookup does not pay for the derivation
+    pub preload_accounts: u32,
+    /// Start without a signing account when the wallet fails to load (read-only mode)
+    pub optional: bool,
+    /// Keystore password for wallet files; `WALLET_PASSWORD` takes precedence
+    pub password: Option<SecretString>,
+}
+
+/// Settings for resilience testing; only honored by builds with the `fault-injection` feature
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TestingConfig {
+    /// Fraction (0.0-1.0) of read requests that fail with a 503
+    pub fail_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RpcProxyConfig {
+    pub mode: RpcFilterMode,
+    pub methods: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RpcFilterMode {
+    /// Only the listed methods are forwarded
+    Allowlist,
+    /// Every method except the listed ones is forwarded
+    Denylist,
 }
 
 impl Default for AppConfig {
@@ -32,20 +206,85 @@ impl Default for AppConfig {
             server: ServerConfig {
                 host: "0.0.0.0".to_string(),
                 port: 3000,
+                tls: None,
+                strict_field_selection: false,
+                api_key: None,
+                rate_limit_per_minute: 0,
+                trusted_proxies: Vec::new(),
+                base_path: String::new(),
+                health_at_root: false,
+                max_balance_batch_size: 100,
+                max_history_blocks: 1000,
+                idempotency_ttl_secs: 86_400,
             },
             ethereum: EthereumConfig {
-                rpc_url: "wss://mainnet.infura.io/ws/v3/YOUR_API_KEY".to_string(),
+                rpc_url: format!("wss://mainnet.infura.io/ws/v3/{}", RPC_URL_PLACEHOLDER),
                 network_id: 1, // Mainnet
+                strict_chain_id: false,
+                native_symbol: None,
+                allow_rpc_override: false,
+                slow_query_ms: 1000,
+                rpc_timeout_ms: 10_000,
+                balance_batch_retries: 2,
+                gas_estimate_cache_secs: 0,
+                max_gas_price_wei: None,
+                gas_limit_multiplier: 1.2,
+                default_transaction_type: DefaultTransactionType::Auto,
+                network_names: Vec::new(),
             },
             wallet: WalletConfig {
                 config_file: "account_config.json".to_string(),
+                max_account_index: 19,
+                derivation_path: bip32::DEFAULT_BASE_PATH.to_string(),
+                preload_accounts: 0,
+                optional: false,
+                password: None,
+            },
+            rpc_proxy: RpcProxyConfig {
+                mode: RpcFilterMode::Allowlist,
+                methods: [
+                    "eth_blockNumber",
+                    "eth_call",
+                    "eth_chainId",
+                    "eth_estimateGas",
+                    "eth_gasPrice",
+                    "eth_getBalance",
+                    "eth_getBlockByHash",
+                    "eth_getBlockByNumber",
+                    "eth_getCode",
+                    "eth_getLogs",
+                    "eth_getTransactionByHash",
+                    "eth_getTransactionCount",
+                    "eth_getTransactionReceipt",
+                    "net_version",
+                ]
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            },
+            testing: TestingConfig { fail_rate: 0.0 },
+            audit: AuditConfig { log_file: None },
+            security: SecurityConfig {
+                recipient_type: RecipientPolicy::Any,
+                max_eth_per_hour: None,
+                spend_window_file: None,
+                allow_key_export: false,
             },
+            cors: CorsConfig {
+                allowed_origins: Vec::new(),
+            },
+            mpc: MpcConfig::default(),
         }
     }
 }
 
 impl AppConfig {
     pub fn load() -> Result<Self> {
+        Self::load_from(env::var("CONFIG_FILE").ok().as_deref())
+    }
+
+    /// Like `load`, with `config_file` in place of the `CONFIG_FILE` variable
+    fn load_from(config_file: Option<&str>) -> Result<Self> {
         let settings = config::Config::builder()
             // Start with default values
             .add_source(config::Config::try_from(&AppConfig::default())?)
@@ -54,9 +293,9 @@ impl AppConfig {
             // Add configuration file if it exists
             .add_source(config::File::with_name("config.toml").required(false));
 
-        // Check for custom config file in environment
-        let settings = if let Ok(config_path) = env::var("CONFIG_FILE") {
-            settings.add_source(config::File::with_name(&config_path).required(false))
+        // Custom config file from `CONFIG_FILE`; naming one that does not exist is an error
+        let settings = if let Some(config_path) = config_file {
+            settings.add_source(config::File::with_name(config_path).required(true))
         } else {
             settings
         };
@@ -65,7 +304,198 @@ impl AppConfig {
         Ok(config)
     }
 
+    /// Reject values that deserialize fine but cannot work, before anything is started with them
+    pub fn validate(&self) -> AppResult<()> {
+        let invalid = |message: String| Err(AppError::ConfigurationError(message));
+
+        let rpc_url = self.ethereum.rpc_url.trim();
+        if rpc_url.contains(RPC_URL_PLACEHOLDER) {
+            return invalid(format!(
+                "ethereum.rpc_url is still the {} placeholder; set it in config.toml or pass --rpc-url",
+                RPC_URL_PLACEHOLDER
+            ));
+        }
+        if !RPC_URL_SCHEMES.iter().any(|scheme| rpc_url.starts_with(scheme)) {
+            return invalid(format!(
+                "ethereum.rpc_url must start with ws://, wss://, http:// or https://, got '{}'",
+                rpc_url
+            ));
+        }
+        if self.server.port == 0 {
+            return invalid("server.port must not be 0".to_string());
+        }
+        if self.ethereum.network_id == 0 {
+            return invalid("ethereum.network_id must not be 0".to_string());
+        }
+        if bip32::validate_path(&self.wallet.derivation_path).is_err() {
+            return invalid(format!(
+                "wallet.derivation_path must look like m/44'/60'/0'/0, got '{}'",
+                self.wallet.derivation_path
+            ));
+        }
+        let multiplier = self.ethereum.gas_limit_multiplier;
+        if !multiplier.is_finite() || multiplier < 1.0 {
+            return invalid(format!("ethereum.gas_limit_multiplier must be at least 1.0, got {}", multiplier));
+        }
+        self.mpc.validate()
+    }
+
     pub fn server_address(&self) -> String {
         format!("{}:{}", self.server.host, self.server.port)
     }
-}
\ No newline at end of file
+}
+
+impl MpcConfig {
+    fn validate(&self) -> AppResult<()> {
+        let invalid = |message: String| Err(AppError::ConfigurationError(message));
+
+        let Some(address) = &self.address else {
+            if !self.peers.is_empty() {
+                return invalid("mpc.peers is set but mpc.address is not".to_string());
+            }
+            return Ok(());
+        };
+        if utils::validate_checksummed(address).is_err() {
+            return invalid(format!("mpc.address is not a valid address: '{}'", address));
+        }
+        if self.peers.is_empty() {
+            return invalid("mpc.address is set but mpc.peers lists no co-signers".to_string());
+        }
+        if let Some(peer) = self
+            .peers
+            .iter()
+            .find(|peer| !peer.starts_with("http://") && !peer.starts_with("https://"))
+        {
+            return invalid(format!("mpc.peers must be http:// or https:// URLs, got '{}'", peer));
+        }
+        if self.timeout_ms == 0 {
+            return invalid("mpc.timeout_ms must not be 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl ServerConfig {
+    /// `base_path` with a leading and no trailing slash, or `None` when routes sit at the root
+    pub fn base_path(&self) -> Option<String> {
+        let path = self.base_path.trim().trim_matches('/');
+        if path.is_empty() {
+            None
+        } else {
+            Some(format!("/{}", path))
+        }
+    }
+}
+
+impl EthereumConfig {
+    /// Configured native currency symbol, falling back to the known symbol for the network
+    pub fn native_symbol(&self) -> String {
+        self.native_symbol
+            .clone()
+            .unwrap_or_else(|| utils::default_native_symbol(self.network_id).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Defaults with a usable `rpc_url`
+    fn valid_config() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.ethereum.rpc_url = "http://127.0.0.1:8545".to_string();
+        config
+    }
+
+    fn with_multiplier(gas_limit_multiplier: f64) -> AppConfig {
+        let mut config = valid_config();
+        config.ethereum.gas_limit_multiplier = gas_limit_multiplier;
+        config
+    }
+
+    /// Message of the `ConfigurationError` `validate` gives for `config`
+    fn validation_error(config: AppConfig) -> String {
+        match config.validate() {
+            Err(AppError::ConfigurationError(message)) => message,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(()) => panic!("invalid config accepted"),
+        }
+    }
+
+    fn config_file(contents: &str) -> String {
+        let directory = env::temp_dir().join(format!("config-test-{}", uuid::Uuid::from_bytes(rand::random())));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("config.toml");
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn accepts_every_supported_rpc_scheme() {
+        for rpc_url in ["ws://127.0.0.1:8546", "wss://node.example/ws", "http://127.0.0.1:8545", "https://node.example"] {
+            let mut config = valid_config();
+            config.ethereum.rpc_url = rpc_url.to_string();
+            assert!(config.validate().is_ok(), "{}", rpc_url);
+        }
+    }
+
+    #[test]
+    fn rejects_the_placeholder_rpc_url() {
+        assert!(validation_error(AppConfig::default()).contains("placeholder"));
+    }
+
+    #[test]
+    fn rejects_an_rpc_url_without_a_known_scheme() {
+        for rpc_url in ["ftp://node.example", "127.0.0.1:8545", ""] {
+            let mut config = valid_config();
+            config.ethereum.rpc_url = rpc_url.to_string();
+            assert!(validation_error(config).contains("ws://, wss://, http:// or https://"), "{}", rpc_url);
+        }
+    }
+
+    #[test]
+    fn rejects_port_zero() {
+        let mut config = valid_config();
+        config.server.port = 0;
+        assert!(validation_error(config).contains("server.port"));
+    }
+
+    #[test]
+    fn rejects_network_id_zero() {
+        let mut config = valid_config();
+        config.ethereum.network_id = 0;
+        assert!(validation_error(config).contains("ethereum.network_id"));
+    }
+
+    #[test]
+    fn rejects_a_malformed_derivation_path() {
+        let mut config = valid_config();
+        config.wallet.derivation_path = "44/60/0".to_string();
+        assert!(validation_error(config).contains("wallet.derivation_path"));
+    }
+
+    #[test]
+    fn a_named_config_file_must_exist_and_parse() {
+        let missing = env::temp_dir().join("config-test-missing.toml");
+        assert!(AppConfig::load_from(Some(&missing.to_string_lossy())).is_err());
+        assert!(AppConfig::load_from(Some(&config_file("[ethereum\nrpc_url = "))).is_err());
+        assert!(AppConfig::load_from(Some(&config_file("[server]\nport = \"eighty\"\n"))).is_err());
+
+        let config = AppConfig::load_from(Some(&config_file("[ethereum]\nrpc_url = \"http://10.0.0.5:8545\"\n"))).unwrap();
+        assert_eq!(config.ethereum.rpc_url, "http://10.0.0.5:8545");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn gas_limit_multiplier_must_be_at_least_one() {
+        for multiplier in [1.0, 1.2, 3.0] {
+            assert!(with_multiplier(multiplier).validate().is_ok(), "{}", multiplier);
+        }
+        for multiplier in [0.99, 0.0, -1.2, f64::NAN, f64::INFINITY] {
+            let Err(AppError::ConfigurationError(message)) = with_multiplier(multiplier).validate() else {
+                panic!("accepted multiplier {}", multiplier);
+            };
+            assert!(message.contains("gas_limit_multiplier"), "{}", message);
+        }
+    }
+}
@@ -1,26 +1,44 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use crate::middleware::request_span;
 use serde::Serialize;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AppError {
     // Wallet related errors
     WalletNotFound,
+    WalletUnavailable,
+    /// The account has no key (watch-only); holds its address
+    WatchOnlyAccount(String),
     WalletCreationFailed(String),
     WalletLoadFailed(String),
     InvalidPrivateKey(String),
     InvalidPublicKey(String),
+    /// A vanity search used up its time or attempt budget without a match
+    VanitySearchExhausted(String),
     
     // Web3 related errors
     Web3ConnectionFailed(String),
     Web3NotAvailable,
+    /// The node did not answer within `ethereum.rpc_timeout_ms`; holds the RPC method
+    Web3Timeout(String),
     InvalidAddress(String),
     TransactionFailed(String),
+    /// A signed transaction went to `eth_sendRawTransaction` but whether the node took it is unknown
+    BroadcastUncertain(String),
+    /// A contract call reverted; holds the decoded reason when there is one
+    ExecutionReverted(String),
     BalanceQueryFailed(String),
+    /// An ENS name has no resolver or no address record
+    EnsResolutionFailed(String),
+    /// An MPC co-signer was unreachable, refused or returned a bad signature
+    CoSignerFailed(String),
+    /// A Safe transaction has fewer owner confirmations than the Safe's threshold
+    SafeThresholdNotMet(String),
     
     // Configuration errors
     ConfigurationError(String),
@@ -29,6 +47,17 @@ pub enum AppError {
     InternalError(String),
     ValidationError(String),
     NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
+    /// The request clashes with an earlier one, e.g. a reused `Idempotency-Key`
+    Conflict(String),
+    /// A service other than the node, such as an MPC co-signer or an override RPC URL, did not answer in time
+    Timeout(String),
+    /// Seconds until the client may retry
+    RateLimited(u64),
+    /// `security.max_eth_per_hour` would be exceeded
+    SpendLimitExceeded { message: String, retry_after_secs: u64 },
+    NotSupported(String),
 }
 
 #[derive(Serialize)]
@@ -36,25 +65,44 @@ pub struct ErrorResponse {
     pub error: String,
     pub message: String,
     pub code: u16,
+    /// Same value as the `X-Request-Id` response header, to find the request in the server logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AppError::WalletNotFound => write!(f, "Wallet not found"),
+            AppError::WalletUnavailable => write!(f, "No wallet loaded; this instance is read-only"),
+            AppError::WatchOnlyAccount(address) => write!(f, "{} is a watch-only account and cannot sign", address),
             AppError::WalletCreationFailed(msg) => write!(f, "Wallet creation failed: {}", msg),
             AppError::WalletLoadFailed(msg) => write!(f, "Failed to load wallet: {}", msg),
             AppError::InvalidPrivateKey(msg) => write!(f, "Invalid private key: {}", msg),
             AppError::InvalidPublicKey(msg) => write!(f, "Invalid public key: {}", msg),
+            AppError::VanitySearchExhausted(msg) => write!(f, "Vanity search exhausted: {}", msg),
             AppError::Web3ConnectionFailed(msg) => write!(f, "Web3 connection failed: {}", msg),
             AppError::Web3NotAvailable => write!(f, "Web3 connection not available"),
+            AppError::Web3Timeout(method) => write!(f, "Node did not answer {} in time", method),
             AppError::InvalidAddress(addr) => write!(f, "Invalid address: {}", addr),
             AppError::TransactionFailed(msg) => write!(f, "Transaction failed: {}", msg),
+            AppError::BroadcastUncertain(msg) => write!(f, "Transaction may have been sent: {}", msg),
+            AppError::ExecutionReverted(reason) => write!(f, "Execution reverted: {}", reason),
             AppError::BalanceQueryFailed(msg) => write!(f, "Balance query failed: {}", msg),
+            AppError::EnsResolutionFailed(msg) => write!(f, "ENS resolution failed: {}", msg),
+            AppError::CoSignerFailed(msg) => write!(f, "MPC co-signer failed: {}", msg),
+            AppError::SafeThresholdNotMet(msg) => write!(f, "Safe threshold not met: {}", msg),
             AppError::ConfigurationError(msg) => write!(f, "Configuration error: {}", msg),
             AppError::InternalError(msg) => write!(f, "Internal error: {}", msg),
             AppError::ValidationError(msg) => write!(f, "Validation error: {}", msg),
             AppError::NotFound(resource) => write!(f, "Resource not found: {}", resource),
+            AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            AppError::Timeout(msg) => write!(f, "Timed out: {}", msg),
+            AppError::RateLimited(secs) => write!(f, "Rate limit exceeded; retry in {} s", secs),
+            AppError::SpendLimitExceeded { message, .. } => write!(f, "Spend limit exceeded: {}", message),
+            AppError::NotSupported(msg) => write!(f, "Not supported: {}", msg),
         }
     }
 }
@@ -65,28 +113,54 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_type, message) = match &self {
             AppError::WalletNotFound => (StatusCode::NOT_FOUND, "WALLET_NOT_FOUND", self.to_string()),
+            AppError::WalletUnavailable => (StatusCode::SERVICE_UNAVAILABLE, "WALLET_UNAVAILABLE", self.to_string()),
+            AppError::WatchOnlyAccount(_) => (StatusCode::FORBIDDEN, "WATCH_ONLY_ACCOUNT", self.to_string()),
             AppError::WalletCreationFailed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "WALLET_CREATION_FAILED", self.to_string()),
             AppError::WalletLoadFailed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "WALLET_LOAD_FAILED", self.to_string()),
             AppError::InvalidPrivateKey(_) => (StatusCode::BAD_REQUEST, "INVALID_PRIVATE_KEY", self.to_string()),
             AppError::InvalidPublicKey(_) => (StatusCode::BAD_REQUEST, "INVALID_PUBLIC_KEY", self.to_string()),
+            AppError::VanitySearchExhausted(_) => (StatusCode::UNPROCESSABLE_ENTITY, "VANITY_SEARCH_EXHAUSTED", self.to_string()),
             AppError::Web3ConnectionFailed(_) => (StatusCode::SERVICE_UNAVAILABLE, "WEB3_CONNECTION_FAILED", self.to_string()),
             AppError::Web3NotAvailable => (StatusCode::SERVICE_UNAVAILABLE, "WEB3_NOT_AVAILABLE", self.to_string()),
+            AppError::Web3Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, "WEB3_TIMEOUT", self.to_string()),
             AppError::InvalidAddress(_) => (StatusCode::BAD_REQUEST, "INVALID_ADDRESS", self.to_string()),
             AppError::TransactionFailed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "TRANSACTION_FAILED", self.to_string()),
+            AppError::BroadcastUncertain(_) => (StatusCode::GATEWAY_TIMEOUT, "BROADCAST_UNCERTAIN", self.to_string()),
+            AppError::ExecutionReverted(_) => (StatusCode::UNPROCESSABLE_ENTITY, "EXECUTION_REVERTED", self.to_string()),
             AppError::BalanceQueryFailed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "BALANCE_QUERY_FAILED", self.to_string()),
+            AppError::EnsResolutionFailed(_) => (StatusCode::UNPROCESSABLE_ENTITY, "ENS_RESOLUTION_FAILED", self.to_string()),
+            AppError::CoSignerFailed(_) => (StatusCode::BAD_GATEWAY, "CO_SIGNER_FAILED", self.to_string()),
+            AppError::SafeThresholdNotMet(_) => (StatusCode::UNPROCESSABLE_ENTITY, "SAFE_THRESHOLD_NOT_MET", self.to_string()),
             AppError::ConfigurationError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "CONFIGURATION_ERROR", self.to_string()),
             AppError::InternalError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR", self.to_string()),
             AppError::ValidationError(_) => (StatusCode::BAD_REQUEST, "VALIDATION_ERROR", self.to_string()),
             AppError::NotFound(_) => (StatusCode::NOT_FOUND, "NOT_FOUND", self.to_string()),
+            AppError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", self.to_string()),
+            AppError::Forbidden(_) => (StatusCode::FORBIDDEN, "FORBIDDEN", self.to_string()),
+            AppError::Conflict(_) => (StatusCode::CONFLICT, "CONFLICT", self.to_string()),
+            AppError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, "TIMEOUT", self.to_string()),
+            AppError::RateLimited(_) => (StatusCode::TOO_MANY_REQUESTS, "RATE_LIMITED", self.to_string()),
+            AppError::SpendLimitExceeded { .. } => (StatusCode::TOO_MANY_REQUESTS, "SPEND_LIMIT_EXCEEDED", self.to_string()),
+            AppError::NotSupported(_) => (StatusCode::NOT_IMPLEMENTED, "NOT_SUPPORTED", self.to_string()),
         };
 
         let error_response = ErrorResponse {
             error: error_type.to_string(),
             message,
             code: status.as_u16(),
+            request_id: request_span::current_request_id(),
         };
 
-        (status, Json(error_response)).into_response()
+        let mut response = (status, Json(error_response)).into_response();
+        let retry_after = match self {
+            AppError::RateLimited(secs) => Some(secs),
+            AppError::SpendLimitExceeded { retry_after_secs, .. } => Some(retry_after_secs),
+            _ => None,
+        };
+        if let Some(secs) = retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
     }
 }
 
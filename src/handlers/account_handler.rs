@@ -1,24 +1,759 @@
-use crate::errors::AppResult;
-use crate::models::{AccountInfo, ApiResponse};
+use crate::address::ChecksumAddress;
+use crate::errors::{AppError, AppResult};
+use crate::handlers::field_filter::{self, FieldsQuery};
+use crate::handlers::rpc_override;
+use crate::models::{
+    Account, AccountImportRequest, AccountInfo, ApiResponse, BackupShares, BackupSharesRequest, BlockHeaderEvent, CreateAccountRequest, ExportedKey, HealthStatus, KeyExportRequest, KeystoreExportRequest, ManagedAccountInfo, ManagedVanityAccountInfo, MempoolInfo, QrQuery, NetworkStatus, ServiceMetrics, SignMessageRequest, SignedMessage, TransactionInfo, TypedDataSignature,
+    VanityProgress, VanityRequest,
+};
+use crate::services::eip712::TypedData;
+use crate::services::keystore::Keystore;
+use crate::services::qr_code;
+use crate::services::wallet_service::VanitySearch;
 use crate::state::AppState;
-use axum::{extract::State, response::Json};
+use crate::utils;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json,
+    },
+};
+use futures::stream::{self, Stream, StreamExt};
+use serde_json::Value;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
+use web3::types::BlockHeader;
 
+const DEFAULT_VANITY_TIMEOUT_SECS: u64 = 30;
+/// Value `confirm` must hold for `POST /account/export` to return a key
+const KEY_EXPORT_CONFIRMATION: &str = "EXPORT";
+const MAX_VANITY_TIMEOUT_SECS: u64 = 300;
+/// How often a streamed vanity search sends a `progress` event
+const VANITY_PROGRESS_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+/// Shortest passphrase accepted for an exported keystore
+const MIN_EXPORT_PASSPHRASE_LENGTH: usize = 8;
+
+/// Liveness probe: the process is up and serving requests
 pub async fn health_check() -> Json<ApiResponse<&'static str>> {
     Json(ApiResponse::success("Ethereum Wallet Server is running"))
 }
 
+/// Readiness probe: 503 unless the node answers `eth_blockNumber`
+pub async fn readiness_check(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<ApiResponse<HealthStatus>>) {
+    let network = state.web3_service.read().await.get_network_status().await;
+    let web3_connected = network.connected && network.block_number.is_some();
+    let status = if web3_connected { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    let health = HealthStatus {
+        server: "ok",
+        web3_connected,
+        network_id: network.network_id,
+        latest_block: network.block_number,
+    };
+    let mut response = ApiResponse::success(health);
+    response.success = web3_connected;
+    (status, Json(response))
+}
+
 pub async fn get_account_info(
     State(state): State<AppState>,
 ) -> AppResult<Json<ApiResponse<AccountInfo>>> {
-    let account = state.account.read().await;
+    let account = state.active_account().await?;
     let account_info = account.to_account_info();
     Ok(Json(ApiResponse::success(account_info)))
 }
 
-pub async fn get_network_info(
+/// Primary account followed by the managed ones; public data only
+pub async fn list_accounts(
+    State(state): State<AppState>,
+) -> AppResult<Json<ApiResponse<Vec<ManagedAccountInfo>>>> {
+    let mut accounts: Vec<ManagedAccountInfo> = state
+        .account
+        .read()
+        .await
+        .iter()
+        .map(|account| account.to_managed_info(true))
+        .collect();
+    accounts.extend(state.account_store.list().iter().map(|account| account.to_managed_info(false)));
+    Ok(Json(ApiResponse::success(accounts)))
+}
+
+/// Generate a managed account, import one from `private_key`, or register `address` as watch-only
+pub async fn create_account(
     State(state): State<AppState>,
-) -> AppResult<Json<ApiResponse<crate::models::NetworkInfo>>> {
+    Json(request): Json<CreateAccountRequest>,
+) -> AppResult<Json<ApiResponse<ManagedAccountInfo>>> {
+    let account = match (&request.private_key, &request.address) {
+        (Some(private_key), None) => state.wallet_service.import_private_key(private_key.expose_secret())?,
+        (None, Some(address)) => {
+            Account::watch_only(&address.parse::<ChecksumAddress>()?.to_string())
+        }
+        (None, None) => state.wallet_service.create_account()?,
+        (Some(_), Some(_)) => {
+            return Err(AppError::ValidationError(
+                "Specify at most one of private_key and address".to_string(),
+            ))
+        }
+    };
+    if is_primary(&state, &account.public_address).await {
+        return Err(AppError::ValidationError(format!(
+            "{} is already the primary account",
+            account.public_address
+        )));
+    }
+
+    let account = state.account_store.insert(account)?;
+    info!("Managed account added: {}", account.public_address);
+    Ok(Json(ApiResponse::success(account.to_managed_info(false))))
+}
+
+/// Import an existing key as a managed account, from hex or from a keystore file's JSON
+pub async fn import_account(
+    State(state): State<AppState>,
+    Json(request): Json<AccountImportRequest>,
+) -> AppResult<Json<ApiResponse<ManagedAccountInfo>>> {
+    let account = match (&request.private_key, &request.keystore) {
+        (Some(private_key), None) => state.wallet_service.import_private_key(private_key.expose_secret())?,
+        (None, Some(keystore)) => {
+            let keystore: Keystore = serde_json::from_value(keystore.clone())
+                .map_err(|e| AppError::ValidationError(format!("Invalid keystore: {}", e)))?;
+            let passphrase = request.passphrase.clone().ok_or_else(|| {
+                AppError::ValidationError("passphrase is required to import a keystore".to_string())
+            })?;
+
+            // Key derivation is deliberately slow, so keep it off the async worker threads
+            let wallet_service = state.wallet_service.clone();
+            tokio::task::spawn_blocking(move || wallet_service.import_keystore(&keystore, passphrase.expose_secret()))
+                .await
+                .map_err(|e| AppError::InternalError(format!("Keystore import failed: {}", e)))?
+                // A wrong passphrase is the caller's mistake, not a server-side wallet failure
+                .map_err(|e| match e {
+                    AppError::WalletLoadFailed(msg) => AppError::ValidationError(msg),
+                    e => e,
+                })?
+        }
+        _ => {
+            return Err(AppError::ValidationError(
+                "Specify exactly one of private_key and keystore".to_string(),
+            ))
+        }
+    };
+    state.wallet_service.validate_account(&account)?;
+    if is_primary(&state, &account.public_address).await {
+        return Err(AppError::ValidationError(format!(
+            "{} is already the primary account",
+            account.public_address
+        )));
+    }
+
+    let account = state.account_store.insert(account)?;
+    info!("Managed account imported: {}", account.public_address);
+    Ok(Json(ApiResponse::success(account.to_managed_info(false))))
+}
+
+pub async fn delete_account(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> AppResult<Json<ApiResponse<ManagedAccountInfo>>> {
+    if is_primary(&state, &address).await {
+        return Err(AppError::ValidationError("The primary account cannot be removed".to_string()));
+    }
+
+    let account = state.account_store.get(&address)?;
+    state.account_store.remove(&address)?;
+    Ok(Json(ApiResponse::success(account.to_managed_info(false))))
+}
+
+/// Return a secret key for backup; off unless `security.allow_key_export` and an API key are set
+pub async fn export_key(
+    State(state): State<AppState>,
+    Json(request): Json<KeyExportRequest>,
+) -> AppResult<Json<ApiResponse<ExportedKey>>> {
+    ensure_key_export_allowed(&state)?;
+    if request.confirm != KEY_EXPORT_CONFIRMATION {
+        return Err(AppError::ValidationError(format!(
+            "Set \"confirm\": \"{}\" to export the secret key",
+            KEY_EXPORT_CONFIRMATION
+        )));
+    }
+
+    let account = state.account_for(request.from.as_deref()).await?;
+    let secret_key = state.wallet_service.export_secret_key(&account)?;
+    Ok(Json(ApiResponse::success(ExportedKey {
+        address: account.public_address,
+        secret_key,
+        derivation_path: account.derivation_path,
+    })))
+}
+
+/// Split an account's key into Shamir backup shares. Enough shares are the key, so it is gated like `/account/export`.
+pub async fn export_backup_shares(
+    State(state): State<AppState>,
+    Json(request): Json<BackupSharesRequest>,
+) -> AppResult<Json<ApiResponse<BackupShares>>> {
+    ensure_key_export_allowed(&state)?;
+
+    let account = state.account_for(request.from.as_deref()).await?;
+    let shares = state
+        .wallet_service
+        .backup_shares(&account, request.threshold, request.shares)?;
+    Ok(Json(ApiResponse::success(BackupShares {
+        address: account.public_address,
+        threshold: request.threshold,
+        shares,
+    })))
+}
+
+/// Return an account's key as a v3 keystore encrypted with the caller's passphrase, for import into
+/// MetaMask or geth. Decrypting it yields the key, so it is gated like `/account/export`.
+pub async fn export_keystore(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+    Json(request): Json<KeystoreExportRequest>,
+) -> AppResult<Json<ApiResponse<Keystore>>> {
+    ensure_key_export_allowed(&state)?;
+    if request.passphrase.expose_secret().chars().count() < MIN_EXPORT_PASSPHRASE_LENGTH {
+        return Err(AppError::ValidationError(format!(
+            "passphrase must be at least {} characters",
+            MIN_EXPORT_PASSPHRASE_LENGTH
+        )));
+    }
+
+    let account = state.account_for(Some(&address)).await?;
+    // Key derivation is deliberately slow, so keep it off the async worker threads
+    let wallet_service = state.wallet_service.clone();
+    let keystore = tokio::task::spawn_blocking(move || wallet_service.export_keystore(&account, request.passphrase.expose_secret()))
+        .await
+        .map_err(|e| AppError::InternalError(format!("Keystore export failed: {}", e)))??;
+    Ok(Json(ApiResponse::success(keystore)))
+}
+
+/// Key material leaves the server only with `security.allow_key_export` and an API key configured
+fn ensure_key_export_allowed(state: &AppState) -> AppResult<()> {
+    if !state.config.security.allow_key_export {
+        return Err(AppError::Forbidden(
+            "Key export is disabled; set security.allow_key_export = true to enable it".to_string(),
+        ));
+    }
+    // The routes are otherwise open when no API key is configured
+    if state.config.server.api_key.is_none() {
+        return Err(AppError::Forbidden("Key export requires server.api_key to be set".to_string()));
+    }
+    Ok(())
+}
+
+async fn is_primary(state: &AppState, address: &str) -> bool {
+    state
+        .account
+        .read()
+        .await
+        .as_ref()
+        .zip(address.parse::<ChecksumAddress>().ok())
+        .is_some_and(|(account, address)| account.public_address == address.to_string())
+}
+
+/// PNG QR code of the wallet address, or of an `ethereum:` payment URI when `?amount=` is given
+pub async fn get_account_qr(
+    Query(query): Query<QrQuery>,
+    State(state): State<AppState>,
+) -> AppResult<impl IntoResponse> {
+    let account = state.active_account().await?;
+    let address = utils::to_checksum_address(&account.public_address);
+
+    let content = match query.amount {
+        Some(amount) => {
+            let wei = utils::parse_units(&amount, utils::NATIVE_DECIMALS).map_err(AppError::ValidationError)?;
+            format!("ethereum:{}@{}?value={}", address, state.config.ethereum.network_id, wei)
+        }
+        None => address,
+    };
+
+    let png = qr_code::render_png(&content)?;
+    Ok(([(header::CONTENT_TYPE, "image/png")], png))
+}
+
+pub async fn sign_message(
+    State(state): State<AppState>,
+    Json(request): Json<SignMessageRequest>,
+) -> AppResult<Json<ApiResponse<SignedMessage>>> {
+    let account = state.active_account().await?;
+    let signature = state.wallet_service.sign_message(&account, &request.message)?;
+
+    Ok(Json(ApiResponse::success(SignedMessage {
+        address: account.public_address.clone(),
+        message: request.message,
+        signature,
+    })))
+}
+
+/// Sign EIP-712 typed data (`eth_signTypedData_v4`) with the active account
+pub async fn sign_typed_data(
+    State(state): State<AppState>,
+    Json(typed_data): Json<TypedData>,
+) -> AppResult<Json<ApiResponse<TypedDataSignature>>> {
+    let account = state.active_account().await?;
+    let digest = typed_data.digest()?;
+    let target = typed_data
+        .domain
+        .get("verifyingContract")
+        .and_then(|contract| contract.as_str())
+        .unwrap_or(&typed_data.primary_type);
+    let signature = state.wallet_service.sign_typed_data(&account, digest, target)?;
+
+    let mut bytes = Vec::with_capacity(65);
+    bytes.extend_from_slice(signature.r.as_bytes());
+    bytes.extend_from_slice(signature.s.as_bytes());
+    bytes.push(signature.v as u8);
+    Ok(Json(ApiResponse::success(TypedDataSignature {
+        address: account.public_address.clone(),
+        digest: format!("{:?}", digest),
+        signature: format!("0x{}", hex::encode(bytes)),
+    })))
+}
+
+pub async fn get_account_mempool(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<MempoolInfo>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let account = state.active_account().await?;
+
+    let mempool = web3_service.get_mempool_transactions(&account.public_address).await?;
+    Ok(Json(ApiResponse::success(mempool)))
+}
+
+pub async fn fill_nonce_gap(
+    Path(nonce): Path<u64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<TransactionInfo>>> {
+    rpc_override::reject_rpc_override(&headers)?;
     let web3_service = state.web3_service.read().await;
+    let account = state.active_account().await?;
+
+    let signer = state.wallet_service.signer(&account)?;
+    let transaction_info = web3_service
+        .fill_nonce_gap(nonce, signer.as_ref(), &account.public_address)
+        .await?;
+
+    Ok(Json(ApiResponse::success(transaction_info)))
+}
+
+/// Search for a vanity address and add it as a managed account
+pub async fn create_managed_vanity_account(
+    State(state): State<AppState>,
+    Json(request): Json<VanityRequest>,
+) -> AppResult<Json<ApiResponse<ManagedVanityAccountInfo>>> {
+    let started = Instant::now();
+    let search = vanity_search(request);
+    let _cancel_on_drop = CancelOnDrop(search.cancelled.clone());
+
+    // The search is CPU-bound, so keep it off the async worker threads
+    let wallet_service = state.wallet_service.clone();
+    let (account, attempts) = tokio::task::spawn_blocking(move || wallet_service.create_vanity_account(&search))
+        .await
+        .map_err(|e| AppError::InternalError(format!("Vanity search failed: {}", e)))??;
+
+    Ok(Json(ApiResponse::success(add_vanity_account(&state, account, attempts, started)?)))
+}
+
+/// `create_managed_vanity_account` as Server-Sent Events: a `progress` event with the attempts so far
+/// every second, then an `account` event with the new account or an `error` event, after which the
+/// stream closes. Closing it early cancels the search.
+pub async fn stream_managed_vanity_account(
+    State(state): State<AppState>,
+    Json(request): Json<VanityRequest>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let started = Instant::now();
+    let search = vanity_search(request);
+    // Bad patterns are a 400 rather than an error event
+    search.patterns()?;
+    let attempts = search.attempts.clone();
+    let cancel_on_drop = CancelOnDrop(search.cancelled.clone());
+
+    let wallet_service = state.wallet_service.clone();
+    let search_task = tokio::task::spawn_blocking(move || wallet_service.create_vanity_account(&search));
+    let ticks = tokio::time::interval_at(tokio::time::Instant::now() + VANITY_PROGRESS_EVENT_INTERVAL, VANITY_PROGRESS_EVENT_INTERVAL);
+
+    let events = stream::unfold(Some((search_task, ticks, cancel_on_drop)), move |running| {
+        let state = state.clone();
+        let attempts = attempts.clone();
+        async move {
+            let (mut search_task, mut ticks, cancel_on_drop) = running?;
+            tokio::select! {
+                result = &mut search_task => {
+                    let event = result
+                        .map_err(|e| AppError::InternalError(format!("Vanity search failed: {}", e)))
+                        .and_then(|found| found)
+                        .and_then(|(account, attempts)| add_vanity_account(&state, account, attempts, started))
+                        .map(|info| json_event("account", info))
+                        .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()));
+                    Some((event, None))
+                }
+                _ = ticks.tick() => {
+                    let progress = VanityProgress {
+                        attempts: attempts.load(Ordering::Relaxed),
+                        elapsed_ms: started.elapsed().as_millis() as u64,
+                    };
+                    Some((json_event("progress", progress), Some((search_task, ticks, cancel_on_drop))))
+                }
+            }
+        }
+    })
+    .map(Ok);
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Cancels a vanity search when dropped. Axum drops a handler's future (or its event stream) when the
+/// client disconnects, which would otherwise leave the search running on every core until its budget ran out.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+fn vanity_search(request: VanityRequest) -> VanitySearch {
+    VanitySearch {
+        prefix: request.prefix,
+        suffix: request.suffix,
+        max_attempts: request.max_attempts,
+        timeout: Duration::from_secs(
+            request
+                .timeout_secs
+                .unwrap_or(DEFAULT_VANITY_TIMEOUT_SECS)
+                .min(MAX_VANITY_TIMEOUT_SECS),
+        ),
+        cancelled: Arc::default(),
+        attempts: Arc::default(),
+    }
+}
+
+/// Store a found vanity account as a managed account
+fn add_vanity_account(state: &AppState, account: Account, attempts: u64, started: Instant) -> AppResult<ManagedVanityAccountInfo> {
+    let account = state.account_store.insert(account)?;
+    info!("Managed vanity account added: {}", account.public_address);
+    Ok(ManagedVanityAccountInfo {
+        account: account.to_managed_info(false),
+        attempts,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+fn json_event(name: &str, data: impl serde::Serialize) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()))
+}
+
+pub async fn get_network_info(
+    Query(fields): Query<FieldsQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<Value>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
     let network_info = web3_service.get_network_info().await?;
-    Ok(Json(ApiResponse::success(network_info)))
-}
\ No newline at end of file
+    let response = field_filter::select_fields(network_info, &fields, state.config.server.strict_field_selection)?;
+    Ok(Json(response))
+}
+
+/// Every network the server is configured for; a single entry until multi-network support lands
+pub async fn list_networks(
+    State(state): State<AppState>,
+) -> AppResult<Json<ApiResponse<Vec<NetworkStatus>>>> {
+    let web3_service = state.web3_service.read().await;
+    let networks = vec![web3_service.get_network_status().await];
+    Ok(Json(ApiResponse::success(networks)))
+}
+
+/// New block headers as Server-Sent Events. Each head is a `block` event; when the subscription
+/// fails or the node connection drops, a final `end` event is sent and the stream closes.
+pub async fn stream_blocks(
+    State(state): State<AppState>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let subscription = state.web3_service.read().await.subscribe_new_heads().await?;
+    Ok(Sse::new(block_events(subscription)).keep_alive(KeepAlive::default()))
+}
+
+/// One `block` event per header, then a single `end` event once `heads` fails or runs dry
+fn block_events<S>(heads: S) -> impl Stream<Item = Result<Event, Infallible>>
+where
+    S: Stream<Item = web3::Result<BlockHeader>> + Unpin,
+{
+    stream::unfold(Some(heads), |heads| async move {
+        let mut heads = heads?;
+        let event = match heads.next().await {
+            Some(Ok(header)) => return Some((block_event(&header), Some(heads))),
+            Some(Err(e)) => format!("Subscription failed: {}", e),
+            None => "Node connection closed".to_string(),
+        };
+        Some((Event::default().event("end").data(event), None))
+    })
+    .map(Ok)
+}
+
+fn block_event(header: &BlockHeader) -> Event {
+    let block = BlockHeaderEvent {
+        number: header.number.map(|number| number.as_u64()),
+        hash: header.hash.map(|hash| format!("{:?}", hash)),
+        timestamp: header.timestamp.as_u64(),
+    };
+    Event::default()
+        .event("block")
+        .json_data(block)
+        .unwrap_or_else(|e| Event::default().event("end").data(e.to_string()))
+}
+
+pub async fn get_metrics(
+    State(state): State<AppState>,
+) -> AppResult<Json<ApiResponse<ServiceMetrics>>> {
+    let web3_service = state.web3_service.read().await;
+    Ok(Json(ApiResponse::success(ServiceMetrics {
+        slow_rpc_queries: web3_service.slow_query_count(),
+        slow_query_threshold_ms: web3_service.slow_query_threshold_ms(),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::body::HttpBody;
+    use serde_json::json;
+
+    fn vanity_request(body: Value) -> Json<VanityRequest> {
+        Json(serde_json::from_value(body).unwrap())
+    }
+
+    /// Body of a streamed vanity search once the stream has closed
+    async fn stream_body(state: AppState, body: Value) -> String {
+        let mut response = stream_managed_vanity_account(State(state), vanity_request(body))
+            .await
+            .unwrap()
+            .into_response()
+            .into_body();
+        let mut text = String::new();
+        while let Some(chunk) = response.data().await {
+            text.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+        }
+        text
+    }
+
+    #[tokio::test]
+    async fn streamed_search_ends_with_the_new_managed_account() {
+        let state = AppState::for_tests(AppConfig::default());
+
+        let body = stream_body(state.clone(), json!({ "prefix": "0" })).await;
+        assert!(body.contains("event:account"), "{}", body);
+        assert!(!body.contains("event:error"), "{}", body);
+        let accounts = state.account_store.list();
+        assert_eq!(accounts.len(), 1);
+        assert!(accounts[0].public_address.starts_with("0x0"));
+        assert!(body.contains(&accounts[0].public_address), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn streamed_search_reports_progress_until_it_gives_up() {
+        let state = AppState::for_tests(AppConfig::default());
+
+        // 40 characters cannot be matched within the two seconds allowed
+        let body = stream_body(state.clone(), json!({ "prefix": "ab".repeat(20), "timeout_secs": 2 })).await;
+        let progress = body.find("event:progress").expect("no progress event");
+        let error = body.find("event:error").expect("no error event");
+        assert!(progress < error, "{}", body);
+        assert!(body.contains("Vanity search exhausted"), "{}", body);
+        assert!(!body.contains("event:account"), "{}", body);
+        assert!(state.account_store.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn streamed_search_rejects_a_bad_pattern_up_front() {
+        let state = AppState::for_tests(AppConfig::default());
+
+        let result = stream_managed_vanity_account(State(state), vanity_request(json!({ "prefix": "xyz" }))).await;
+        assert!(matches!(result, Err(AppError::ValidationError(_))));
+    }
+
+    #[test]
+    fn dropping_the_guard_cancels_the_search() {
+        let search = vanity_search(serde_json::from_value(json!({ "prefix": "ab".repeat(20) })).unwrap());
+        drop(CancelOnDrop(search.cancelled.clone()));
+
+        let started = Instant::now();
+        let error = crate::services::WalletService::new().create_vanity_account(&search).unwrap_err();
+        assert!(error.to_string().contains("cancelled"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn ready_while_the_node_answers() {
+        let node = crate::test_support::MockNode::start(|method, _| {
+            (method == "eth_blockNumber").then(|| crate::test_support::Reply::Value(json!("0x2a")))
+        })
+        .await;
+        let mut state = AppState::for_tests(AppConfig::default());
+        state.web3_service = Arc::new(tokio::sync::RwLock::new(node.connect().await));
+
+        let (status, Json(response)) = readiness_check(State(state)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(response.success);
+        let health = response.data.unwrap();
+        assert!(health.web3_connected);
+        assert_eq!(health.network_id, 1);
+        assert_eq!(health.latest_block, Some(42));
+    }
+
+    #[tokio::test]
+    async fn not_ready_without_a_node() {
+        let state = AppState::for_tests(AppConfig::default());
+
+        let (status, Json(response)) = readiness_check(State(state)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!response.success);
+        let health = response.data.unwrap();
+        assert!(!health.web3_connected);
+        assert_eq!(health.latest_block, None);
+
+        // Liveness does not depend on the node
+        assert!(health_check().await.0.success);
+    }
+
+    /// State with a fresh primary account and key export configured as given
+    async fn export_state(allow_key_export: bool, api_key: Option<&str>) -> AppState {
+        let mut config = AppConfig::default();
+        config.security.allow_key_export = allow_key_export;
+        config.server.api_key = api_key.map(str::to_string);
+        let state = AppState::for_tests(config);
+        *state.account.write().await = Some(state.wallet_service.create_account().unwrap());
+        state
+    }
+
+    fn export_request(confirm: &str) -> Json<KeyExportRequest> {
+        Json(serde_json::from_value(json!({ "confirm": confirm })).unwrap())
+    }
+
+    #[tokio::test]
+    async fn key_export_is_disabled_by_default() {
+        for state in [export_state(false, Some("s3cret")).await, export_state(true, None).await] {
+            let Err(error) = export_key(State(state), export_request(KEY_EXPORT_CONFIRMATION)).await else {
+                panic!("key exported");
+            };
+            assert!(matches!(error, AppError::Forbidden(_)), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn key_export_needs_the_confirmation() {
+        let state = export_state(true, Some("s3cret")).await;
+
+        for confirm in ["", "export", "yes"] {
+            let Err(error) = export_key(State(state.clone()), export_request(confirm)).await else {
+                panic!("key exported with confirm {:?}", confirm);
+            };
+            assert!(matches!(error, AppError::ValidationError(_)), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn confirmed_key_export_returns_the_account_key() {
+        let state = export_state(true, Some("s3cret")).await;
+        let primary = state.active_account().await.unwrap();
+
+        let Json(response) = export_key(State(state.clone()), export_request(KEY_EXPORT_CONFIRMATION)).await.unwrap();
+        let exported = response.data.unwrap();
+        assert_eq!(exported.address, primary.public_address);
+        let reimported = state.wallet_service.import_private_key(&exported.secret_key).unwrap();
+        assert_eq!(reimported.public_address, primary.public_address);
+    }
+
+    fn block_header(number: u64, timestamp: u64) -> BlockHeader {
+        use web3::types::{H160, H2048, H256, U256};
+        BlockHeader {
+            hash: Some(H256::repeat_byte(0xab)),
+            parent_hash: H256::zero(),
+            uncles_hash: H256::zero(),
+            author: H160::zero(),
+            state_root: H256::zero(),
+            transactions_root: H256::zero(),
+            receipts_root: H256::zero(),
+            number: Some(number.into()),
+            gas_used: U256::zero(),
+            gas_limit: U256::from(30_000_000u64),
+            base_fee_per_gas: None,
+            extra_data: Default::default(),
+            logs_bloom: H2048::zero(),
+            timestamp: timestamp.into(),
+            difficulty: U256::zero(),
+            mix_hash: None,
+            nonce: None,
+        }
+    }
+
+    /// SSE body for a finite stream of heads
+    async fn sse_body(heads: Vec<web3::Result<BlockHeader>>) -> String {
+        let mut body = Sse::new(block_events(stream::iter(heads))).into_response().into_body();
+        let mut text = String::new();
+        while let Some(chunk) = body.data().await {
+            text.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+        }
+        text
+    }
+
+    #[tokio::test]
+    async fn a_new_head_becomes_one_block_event() {
+        let body = sse_body(vec![Ok(block_header(42, 1_700_000_000))]).await;
+
+        let frames: Vec<&str> = body.split("\n\n").filter(|frame| !frame.is_empty()).collect();
+        assert_eq!(frames.len(), 2, "{}", body);
+        let data = frames[0].strip_prefix("event:block\ndata:").expect("block frame first");
+        let block: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(block, json!({
+            "number": 42,
+            "hash": format!("0x{}", "ab".repeat(32)),
+            "timestamp": 1_700_000_000u64,
+        }));
+        assert_eq!(frames[1], "event:end\ndata:Node connection closed");
+    }
+
+    #[tokio::test]
+    async fn a_failed_subscription_ends_the_stream() {
+        let body = sse_body(vec![
+            Ok(block_header(1, 1)),
+            Err(web3::Error::Transport(web3::error::TransportError::Message("socket closed".to_string()))),
+            Ok(block_header(2, 2)),
+        ])
+        .await;
+
+        assert_eq!(body.matches("event:block").count(), 1, "{}", body);
+        assert_eq!(body.matches("event:end").count(), 1, "{}", body);
+        assert!(body.trim_end().ends_with("socket closed"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn account_responses_never_carry_the_secret_key() {
+        let state = AppState::for_tests(AppConfig::default());
+        let account = state.wallet_service.create_account().unwrap();
+        let secret = account.secret_key.expose_secret().to_string();
+        *state.account.write().await = Some(account);
+
+        let Json(info) = get_account_info(State(state.clone())).await.unwrap();
+        let Json(listed) = list_accounts(State(state.clone())).await.unwrap();
+        let Json(created) = create_account(State(state), Json(serde_json::from_value(json!({})).unwrap())).await.unwrap();
+        for body in [serde_json::to_string(&info), serde_json::to_string(&listed), serde_json::to_string(&created)] {
+            let body = body.unwrap();
+            assert!(!body.contains(&secret), "{}", body);
+            assert!(!body.contains(secret.trim_start_matches("0x")), "{}", body);
+            assert!(!body.contains("secret"), "{}", body);
+        }
+    }
+}
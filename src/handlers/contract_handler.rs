@@ -0,0 +1,18 @@
+use crate::errors::AppResult;
+use crate::handlers::rpc_override;
+use crate::models::{ApiResponse, ContractCallRequest};
+use crate::state::AppState;
+use crate::utils;
+use axum::{extract::State, http::HeaderMap, response::Json};
+
+/// Call a view function with calldata encoded by the client; returns the raw hex result
+pub async fn call_contract(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ContractCallRequest>,
+) -> AppResult<Json<ApiResponse<String>>> {
+    let block = request.block.as_deref().map(utils::parse_block_number).transpose()?;
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let result = web3_service.eth_call(&request.to, &request.data, block).await?;
+    Ok(Json(ApiResponse::success(result)))
+}
@@ -0,0 +1,45 @@
+use crate::errors::{AppError, AppResult};
+use crate::models::ApiResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Deserialize)]
+pub struct FieldsQuery {
+    pub fields: Option<String>,
+}
+
+/// Build a response containing only the top-level fields named in `?fields=a,b`.
+/// Without the parameter the full payload is returned. Unknown field names are
+/// ignored, or rejected with a 400 when `strict` is set.
+pub fn select_fields<T: Serialize>(
+    data: T,
+    query: &FieldsQuery,
+    strict: bool,
+) -> AppResult<ApiResponse<Value>> {
+    let value = serde_json::to_value(data)
+        .map_err(|e| AppError::InternalError(format!("Failed to serialize response: {}", e)))?;
+
+    let (fields, object) = match (query.fields.as_deref(), value) {
+        (Some(fields), Value::Object(object)) => (fields, object),
+        (_, value) => return Ok(ApiResponse::success(value)),
+    };
+
+    let requested: Vec<&str> = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .collect();
+
+    if strict {
+        if let Some(unknown) = requested.iter().find(|field| !object.contains_key(**field)) {
+            return Err(AppError::ValidationError(format!("Unknown field: {}", unknown)));
+        }
+    }
+
+    let projected: Map<String, Value> = object
+        .into_iter()
+        .filter(|(key, _)| requested.contains(&key.as_str()))
+        .collect();
+
+    Ok(ApiResponse::success(Value::Object(projected)))
+}
@@ -1,2 +1,9 @@
 pub mod account_handler;
-pub mod wallet_handler;
\ No newline at end of file
+pub mod contract_handler;
+pub mod field_filter;
+pub mod rpc_handler;
+pub mod rpc_override;
+pub mod safe_handler;
+pub mod utils_handler;
+pub mod wallet_handler;
+pub mod watch_handler;
\ No newline at end of file
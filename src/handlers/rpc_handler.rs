@@ -0,0 +1,22 @@
+use crate::errors::{AppError, AppResult};
+use crate::handlers::rpc_override;
+use crate::models::{RpcRequest, RpcResponse};
+use crate::services::rpc_proxy;
+use crate::state::AppState;
+use axum::{extract::State, http::HeaderMap, response::Json};
+use tracing::warn;
+
+pub async fn proxy_rpc(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<RpcRequest>,
+) -> AppResult<Json<RpcResponse>> {
+    if !rpc_proxy::is_method_permitted(&state.config.rpc_proxy, &request.method) {
+        warn!("Blocked RPC proxy call to method: {}", request.method);
+        return Err(AppError::Forbidden(format!("RPC method not permitted: {}", request.method)));
+    }
+
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let response = web3_service.raw_request(&request).await?;
+    Ok(Json(response))
+}
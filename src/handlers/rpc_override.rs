@@ -0,0 +1,74 @@
+use crate::errors::{AppError, AppResult};
+use crate::services::Web3Service;
+use crate::state::AppState;
+use axum::http::HeaderMap;
+use std::ops::Deref;
+use std::time::Duration;
+use tokio::sync::RwLockReadGuard;
+use tracing::warn;
+
+/// Header naming an alternative node for a single request
+pub const RPC_URL_HEADER: &str = "x-rpc-url";
+
+/// How long to wait for an overridden endpoint before giving up
+const OVERRIDE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Web3 service serving one request: the shared connection, or a transient one from `X-RPC-URL`
+pub enum RequestWeb3<'a> {
+    Shared(RwLockReadGuard<'a, Web3Service>),
+    Override(Box<Web3Service>),
+}
+
+impl Deref for RequestWeb3<'_> {
+    type Target = Web3Service;
+
+    fn deref(&self) -> &Web3Service {
+        match self {
+            RequestWeb3::Shared(service) => service,
+            RequestWeb3::Override(service) => service,
+        }
+    }
+}
+
+/// Resolve the Web3 service for a read-only request, honoring `X-RPC-URL`
+/// only when `ethereum.allow_rpc_override` is enabled
+pub async fn web3_for_request<'a>(state: &'a AppState, headers: &HeaderMap) -> AppResult<RequestWeb3<'a>> {
+    let Some(value) = headers.get(RPC_URL_HEADER) else {
+        return Ok(RequestWeb3::Shared(state.web3_service.read().await));
+    };
+
+    if !state.config.ethereum.allow_rpc_override {
+        return Err(AppError::Forbidden("X-RPC-URL overrides are disabled".to_string()));
+    }
+
+    let rpc_url = value
+        .to_str()
+        .map_err(|_| AppError::ValidationError("X-RPC-URL must be valid ASCII".to_string()))?;
+    if !["ws://", "wss://", "http://", "https://"].iter().any(|scheme| rpc_url.starts_with(scheme)) {
+        return Err(AppError::ValidationError(
+            "X-RPC-URL must be an http(s):// or ws(s):// endpoint".to_string(),
+        ));
+    }
+
+    warn!("Serving request from overridden RPC endpoint: {}", rpc_url);
+
+    // The override is assumed to serve the configured network; it only swaps the node
+    let mut ethereum_config = state.config.ethereum.clone();
+    ethereum_config.rpc_url = rpc_url.to_string();
+    let mut service = Web3Service::new(&ethereum_config);
+    tokio::time::timeout(OVERRIDE_CONNECT_TIMEOUT, service.connect())
+        .await
+        .map_err(|_| AppError::Timeout(format!("Timed out connecting to {}", rpc_url)))??;
+
+    Ok(RequestWeb3::Override(Box::new(service)))
+}
+
+/// Endpoints that sign with the wallet key always use the configured node
+pub fn reject_rpc_override(headers: &HeaderMap) -> AppResult<()> {
+    if headers.contains_key(RPC_URL_HEADER) {
+        return Err(AppError::ValidationError(
+            "X-RPC-URL is not accepted on endpoints that send transactions".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,419 @@
+use crate::errors::{AppError, AppResult};
+use crate::handlers::rpc_override;
+use crate::models::{
+    Account, ApiResponse, SafeConfirmation, SafeConfirmationRequest, SafeInfo, SafeTransactionInfo, SafeTransactionRequest, TransactionInfo,
+};
+use crate::services::safe::{self, PendingSafeTransaction, SafeState, SafeTransaction};
+use crate::state::AppState;
+use crate::utils;
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Json,
+};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use tracing::info;
+use web3::types::{Address, H256, U256};
+
+/// Owners, threshold and nonce of a Safe
+pub async fn get_safe(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> AppResult<Json<ApiResponse<SafeInfo>>> {
+    let safe = utils::validate_checksummed(&address)?;
+    let safe_state = state.web3_service.read().await.safe_state(safe).await?;
+
+    Ok(Json(ApiResponse::success(SafeInfo {
+        address: safe.into(),
+        owners: safe_state.owners.into_iter().map(Into::into).collect(),
+        threshold: safe_state.threshold,
+        nonce: safe_state.nonce.to_string(),
+    })))
+}
+
+/// Proposals for a Safe that have not been executed, by nonce
+pub async fn list_transactions(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> AppResult<Json<ApiResponse<Vec<SafeTransactionInfo>>>> {
+    let safe = utils::validate_checksummed(&address)?;
+    let safe_state = state.web3_service.read().await.safe_state(safe).await?;
+
+    let pending = state.safe_queue.list(safe, safe_state.nonce);
+    Ok(Json(ApiResponse::success(
+        pending.iter().map(|pending| transaction_info(pending, &safe_state)).collect(),
+    )))
+}
+
+/// Propose a transaction for a Safe the wallet owns, confirming it with the wallet's signature
+pub async fn propose_transaction(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<SafeTransactionRequest>,
+) -> AppResult<Json<ApiResponse<SafeTransactionInfo>>> {
+    rpc_override::reject_rpc_override(&headers)?;
+    let safe = utils::validate_checksummed(&address)?;
+    let account = state.active_account().await?;
+    let safe_state = state.web3_service.read().await.safe_state(safe).await?;
+
+    let operation = request.operation.unwrap_or(safe::CALL);
+    if operation != safe::CALL && operation != safe::DELEGATE_CALL {
+        return Err(AppError::ValidationError(format!(
+            "operation must be {} (call) or {} (delegatecall)",
+            safe::CALL,
+            safe::DELEGATE_CALL
+        )));
+    }
+    let data = match &request.data {
+        Some(data) => hex::decode(data.trim().trim_start_matches("0x"))
+            .map_err(|e| AppError::ValidationError(format!("Invalid data: {}", e)))?,
+        None => Vec::new(),
+    };
+    // Queue behind proposals that are already waiting, as they will execute first
+    let next_nonce = state
+        .safe_queue
+        .list(safe, safe_state.nonce)
+        .last()
+        .map_or(safe_state.nonce, |pending| pending.transaction.nonce + 1);
+    let nonce = request.nonce.map_or(next_nonce, U256::from);
+    if nonce < safe_state.nonce {
+        return Err(AppError::ValidationError(format!(
+            "Nonce {} was already used; the Safe is at nonce {}",
+            nonce, safe_state.nonce
+        )));
+    }
+
+    let transaction = SafeTransaction {
+        to: utils::validate_checksummed(&request.to)?,
+        value: request.value_wei()?,
+        data,
+        operation,
+        nonce,
+    };
+    let safe_tx_hash = transaction.digest(safe_state.domain_separator);
+    let (owner, signature) = sign_as_owner(&state, &account, safe, &safe_state, safe_tx_hash)?;
+
+    let pending = PendingSafeTransaction {
+        safe,
+        safe_tx_hash,
+        transaction,
+        confirmations: BTreeMap::from([(owner, signature)]),
+        proposed_at: chrono::Utc::now(),
+    };
+    state.safe_queue.insert(pending.clone())?;
+
+    info!("Safe transaction {:?} proposed for {} at nonce {}", safe_tx_hash, utils::to_checksum(&safe), nonce);
+    Ok(Json(ApiResponse::success(transaction_info(&pending, &safe_state))))
+}
+
+/// Add an owner's confirmation: a signature made elsewhere, or the wallet's own
+pub async fn confirm_transaction(
+    Path((address, safe_tx_hash)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<SafeConfirmationRequest>,
+) -> AppResult<Json<ApiResponse<SafeTransactionInfo>>> {
+    rpc_override::reject_rpc_override(&headers)?;
+    let safe = utils::validate_checksummed(&address)?;
+    let safe_tx_hash = parse_safe_tx_hash(&safe_tx_hash)?;
+    state.safe_queue.get(safe, safe_tx_hash)?;
+    let safe_state = state.web3_service.read().await.safe_state(safe).await?;
+
+    let (owner, signature) = match &request.signature {
+        Some(signature) => {
+            let signature = hex::decode(signature.trim().trim_start_matches("0x"))
+                .map_err(|e| AppError::ValidationError(format!("Invalid signature: {}", e)))?;
+            let owner = safe::recover_owner(safe_tx_hash, &signature)?;
+            if !safe_state.is_owner(owner) {
+                return Err(AppError::Forbidden(format!(
+                    "Signature is from {}, which is not an owner of Safe {}",
+                    utils::to_checksum(&owner),
+                    utils::to_checksum(&safe)
+                )));
+            }
+            (owner, signature)
+        }
+        None => {
+            let account = state.active_account().await?;
+            sign_as_owner(&state, &account, safe, &safe_state, safe_tx_hash)?
+        }
+    };
+
+    let pending = state.safe_queue.confirm(safe_tx_hash, owner, signature)?;
+    info!("Safe transaction {:?} confirmed by {}", safe_tx_hash, utils::to_checksum(&owner));
+    Ok(Json(ApiResponse::success(transaction_info(&pending, &safe_state))))
+}
+
+/// Send `execTransaction` for a proposal with enough confirmations; the wallet pays the gas
+pub async fn execute_transaction(
+    Path((address, safe_tx_hash)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<TransactionInfo>>> {
+    rpc_override::reject_rpc_override(&headers)?;
+    let safe = utils::validate_checksummed(&address)?;
+    let safe_tx_hash = parse_safe_tx_hash(&safe_tx_hash)?;
+    let pending = state.safe_queue.get(safe, safe_tx_hash)?;
+    let account = state.active_account().await?;
+    let web3_service = state.web3_service.read().await;
+    let safe_state = web3_service.safe_state(safe).await?;
+
+    if pending.transaction.nonce != safe_state.nonce {
+        return Err(AppError::ValidationError(format!(
+            "Safe transaction has nonce {} but the Safe is at nonce {}",
+            pending.transaction.nonce, safe_state.nonce
+        )));
+    }
+    let (confirmations, signatures) = pending.packed_signatures(&safe_state.owners);
+    if (confirmations as u64) < safe_state.threshold {
+        return Err(AppError::SafeThresholdNotMet(format!(
+            "Safe transaction has {} of {} required confirmations",
+            confirmations, safe_state.threshold
+        )));
+    }
+
+    let signer = state.wallet_service.signer(&account)?;
+    let transaction_info = web3_service
+        .send_contract_call(safe, pending.transaction.exec_transaction_data(&signatures), signer.as_ref())
+        .await?;
+    state.safe_queue.remove(safe_tx_hash);
+
+    info!("Safe transaction {:?} executed in {}", safe_tx_hash, transaction_info.transaction_hash);
+    Ok(Json(ApiResponse::success(transaction_info)))
+}
+
+/// Sign a Safe transaction hash with `account`, which must be an owner
+fn sign_as_owner(
+    state: &AppState,
+    account: &Account,
+    safe: Address,
+    safe_state: &SafeState,
+    safe_tx_hash: H256,
+) -> AppResult<(Address, Vec<u8>)> {
+    let owner = utils::validate_checksummed(&account.public_address)?;
+    if !safe_state.is_owner(owner) {
+        return Err(AppError::Forbidden(format!(
+            "{} is not an owner of Safe {}",
+            account.public_address,
+            utils::to_checksum(&safe)
+        )));
+    }
+
+    let signature = state
+        .wallet_service
+        .sign_typed_data(account, safe_tx_hash, &utils::to_checksum(&safe))?;
+    let mut bytes = Vec::with_capacity(safe::SIGNATURE_LENGTH);
+    bytes.extend_from_slice(signature.r.as_bytes());
+    bytes.extend_from_slice(signature.s.as_bytes());
+    bytes.push(signature.v as u8);
+    Ok((owner, bytes))
+}
+
+fn transaction_info(pending: &PendingSafeTransaction, safe_state: &SafeState) -> SafeTransactionInfo {
+    SafeTransactionInfo {
+        safe: pending.safe.into(),
+        safe_tx_hash: format!("{:?}", pending.safe_tx_hash),
+        to: pending.transaction.to.into(),
+        value_wei: pending.transaction.value.to_string(),
+        data: format!("0x{}", hex::encode(&pending.transaction.data)),
+        operation: pending.transaction.operation,
+        nonce: pending.transaction.nonce.to_string(),
+        confirmations: pending
+            .confirmations
+            .iter()
+            .filter(|(owner, _)| safe_state.is_owner(**owner))
+            .map(|(owner, signature)| SafeConfirmation {
+                owner: (*owner).into(),
+                signature: format!("0x{}", hex::encode(signature)),
+            })
+            .collect(),
+        confirmations_required: safe_state.threshold,
+        proposed_at: pending.proposed_at,
+    }
+}
+
+fn parse_safe_tx_hash(hash: &str) -> AppResult<H256> {
+    H256::from_str(hash.trim_start_matches("0x"))
+        .map_err(|e| AppError::ValidationError(format!("Invalid Safe transaction hash {}: {}", hash, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::services::transaction_decoder;
+    use crate::test_support::{self, MockNode, Reply};
+    use axum::{http::StatusCode, response::IntoResponse};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    use web3::ethabi::{self, Token};
+    use web3::signing::{Key, SecretKeyRef};
+
+    const SAFE_NONCE: u64 = 5;
+
+    fn safe() -> Address {
+        Address::from_low_u64_be(0x5afe)
+    }
+
+    fn other_owner_key() -> secp256k1::SecretKey {
+        secp256k1::SecretKey::from_slice(&[0x42; 32]).unwrap()
+    }
+
+    /// Node with a 2-of-2 Safe owned by `owners`, at nonce `SAFE_NONCE`, that accepts any transaction
+    async fn safe_node(owners: Vec<Address>) -> MockNode {
+        MockNode::start(move |method, params| match method {
+            "eth_call" => {
+                let data = hex::decode(params[0]["data"].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                let output = match hex::encode(&data[..4]).as_str() {
+                    // getOwners()
+                    "a0e67e2b" => ethabi::encode(&[Token::Array(owners.iter().copied().map(Token::Address).collect())]),
+                    // getThreshold()
+                    "e75235b8" => ethabi::encode(&[Token::Uint(2.into())]),
+                    // nonce()
+                    "affed0e0" => ethabi::encode(&[Token::Uint(SAFE_NONCE.into())]),
+                    // domainSeparator()
+                    "f698da25" => H256::repeat_byte(0xd0).as_bytes().to_vec(),
+                    _ => return None,
+                };
+                Some(Reply::Value(json!(format!("0x{}", hex::encode(output)))))
+            }
+            "eth_estimateGas" => Some(Reply::Value(json!("0x186a0"))),
+            "eth_gasPrice" => Some(Reply::Value(json!("0x3b9aca00"))),
+            "eth_maxPriorityFeePerGas" => Some(Reply::Value(json!("0x3b9aca00"))),
+            "eth_getBlockByNumber" => {
+                let block = web3::types::Block::<H256> { number: Some(100.into()), base_fee_per_gas: Some(1_000_000_000.into()), ..Default::default() };
+                Some(Reply::Value(json!(block)))
+            }
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x0"))),
+            "eth_sendRawTransaction" => {
+                let raw = hex::decode(params[0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                Some(Reply::Value(json!(format!("0x{}", hex::encode(web3::signing::keccak256(&raw))))))
+            }
+            _ => None,
+        })
+        .await
+    }
+
+    /// State whose active account is one owner of the Safe, with `other_owner_key` the other
+    async fn owner_state() -> (AppState, MockNode) {
+        let mut state = AppState::for_tests(AppConfig::default());
+        let account = state.wallet_service.create_account().unwrap();
+        let owners = vec![
+            utils::validate_checksummed(&account.public_address).unwrap(),
+            SecretKeyRef::new(&other_owner_key()).address(),
+        ];
+        let node = safe_node(owners).await;
+        state.web3_service = Arc::new(RwLock::new(test_support::connect(&node.ethereum_config()).await));
+        state.account = Arc::new(RwLock::new(Some(account)));
+        (state, node)
+    }
+
+    async fn propose(state: &AppState, body: Value) -> AppResult<SafeTransactionInfo> {
+        let request = serde_json::from_value(body).unwrap();
+        let Json(response) =
+            propose_transaction(Path(utils::to_checksum(&safe())), State(state.clone()), HeaderMap::new(), Json(request)).await?;
+        Ok(response.data.unwrap())
+    }
+
+    async fn confirm(state: &AppState, safe_tx_hash: &str, signature: Option<String>) -> AppResult<SafeTransactionInfo> {
+        let path = Path((utils::to_checksum(&safe()), safe_tx_hash.to_string()));
+        let Json(response) =
+            confirm_transaction(path, State(state.clone()), HeaderMap::new(), Json(SafeConfirmationRequest { signature })).await?;
+        Ok(response.data.unwrap())
+    }
+
+    async fn execute(state: &AppState, safe_tx_hash: &str) -> AppResult<TransactionInfo> {
+        let path = Path((utils::to_checksum(&safe()), safe_tx_hash.to_string()));
+        let Json(response) = execute_transaction(path, State(state.clone()), HeaderMap::new()).await?;
+        Ok(response.data.unwrap())
+    }
+
+    /// `r || s || v` signature of `safe_tx_hash` by `key`, as an owner would post it
+    fn external_signature(key: &secp256k1::SecretKey, safe_tx_hash: &str) -> String {
+        let digest = H256::from_str(safe_tx_hash.trim_start_matches("0x")).unwrap();
+        let signature = SecretKeyRef::new(key).sign(digest.as_bytes(), None).unwrap();
+        format!("0x{}{}{:02x}", hex::encode(signature.r), hex::encode(signature.s), signature.v)
+    }
+
+    fn status(error: AppError) -> StatusCode {
+        error.into_response().status()
+    }
+
+    #[tokio::test]
+    async fn proposal_is_confirmed_by_the_owners_then_executed() {
+        let (state, node) = owner_state().await;
+        let proposal = propose(&state, json!({ "to": utils::to_checksum(&Address::from_low_u64_be(0xb0b)), "amount_wei": "7" }))
+            .await
+            .unwrap();
+        assert_eq!(proposal.nonce, SAFE_NONCE.to_string());
+        assert_eq!(proposal.confirmations.len(), 1);
+
+        // A valid signature, but not from an owner
+        let stranger = secp256k1::SecretKey::from_slice(&[0x07; 32]).unwrap();
+        let Err(error) = confirm(&state, &proposal.safe_tx_hash, Some(external_signature(&stranger, &proposal.safe_tx_hash))).await else {
+            panic!("a non-owner confirmed");
+        };
+        assert_eq!(status(error), StatusCode::FORBIDDEN);
+
+        let Err(error) = execute(&state, &proposal.safe_tx_hash).await else {
+            panic!("executed below the threshold");
+        };
+        assert_eq!(status(error), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(node.calls("eth_sendRawTransaction").is_empty());
+
+        let confirmed = confirm(&state, &proposal.safe_tx_hash, Some(external_signature(&other_owner_key(), &proposal.safe_tx_hash)))
+            .await
+            .unwrap();
+        assert_eq!(confirmed.confirmations.len(), 2);
+        let owners: Vec<_> = confirmed.confirmations.iter().map(|confirmation| Address::from(confirmation.owner)).collect();
+        assert!(owners.windows(2).all(|pair| pair[0] < pair[1]), "confirmations out of owner order");
+
+        let sent = execute(&state, &proposal.safe_tx_hash).await.unwrap();
+        let raw = node.calls("eth_sendRawTransaction")[0][0].as_str().unwrap().to_string();
+        let decoded = transaction_decoder::decode_raw_transaction(&raw).unwrap();
+        assert_eq!(decoded.hash, sent.transaction_hash);
+        assert_eq!(decoded.to, Some(safe().into()));
+        let pending = state.safe_queue.list(safe(), SAFE_NONCE.into());
+        assert!(pending.is_empty(), "executed proposal still queued");
+
+        let signatures: String = confirmed.confirmations.iter().map(|confirmation| confirmation.signature.trim_start_matches("0x")).collect();
+        let expected = SafeTransaction {
+            to: Address::from_low_u64_be(0xb0b),
+            value: 7.into(),
+            data: Vec::new(),
+            operation: safe::CALL,
+            nonce: SAFE_NONCE.into(),
+        }
+        .exec_transaction_data(&hex::decode(signatures).unwrap());
+        assert_eq!(decoded.data, format!("0x{}", hex::encode(expected)));
+    }
+
+    #[tokio::test]
+    async fn a_proposal_ahead_of_the_safe_nonce_is_not_executed() {
+        let (state, node) = owner_state().await;
+        let proposal = propose(
+            &state,
+            json!({ "to": utils::to_checksum(&Address::from_low_u64_be(0xb0b)), "nonce": SAFE_NONCE + 1 }),
+        )
+        .await
+        .unwrap();
+        confirm(&state, &proposal.safe_tx_hash, Some(external_signature(&other_owner_key(), &proposal.safe_tx_hash)))
+            .await
+            .unwrap();
+
+        let Err(error) = execute(&state, &proposal.safe_tx_hash).await else {
+            panic!("executed out of nonce order");
+        };
+        assert!(matches!(error, AppError::ValidationError(ref message) if message.contains("nonce")), "{}", error);
+        assert!(node.calls("eth_sendRawTransaction").is_empty());
+
+        // Nonces the Safe has already used cannot be proposed
+        let Err(error) = propose(&state, json!({ "to": utils::to_checksum(&Address::from_low_u64_be(0xb0b)), "nonce": SAFE_NONCE - 1 })).await else {
+            panic!("proposed a used nonce");
+        };
+        assert_eq!(status(error), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,11 @@
+use crate::errors::AppResult;
+use crate::models::{ApiResponse, DecodeTransactionRequest, DecodedTransaction};
+use crate::services::transaction_decoder;
+use axum::response::Json;
+
+pub async fn decode_transaction(
+    Json(request): Json<DecodeTransactionRequest>,
+) -> AppResult<Json<ApiResponse<DecodedTransaction>>> {
+    let decoded = transaction_decoder::decode_raw_transaction(&request.raw)?;
+    Ok(Json(ApiResponse::success(decoded)))
+}
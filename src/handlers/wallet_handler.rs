@@ -1,71 +1,1014 @@
-use crate::errors::AppResult;
-use crate::models::{ApiResponse, BalanceInfo, TransactionInfo, TransactionRequest};
+use crate::config::RecipientPolicy;
+use crate::errors::{AppError, AppResult};
+use crate::handlers::field_filter::{self, FieldsQuery};
+use crate::handlers::rpc_override;
+use crate::models::{
+    Account, AccountQuery, ApiResponse, BalanceBatchEntry, BalanceBatchRequest, BatchTransactionRequest, BatchTransactionResult, DerivedAddress, EnsProfile, FeeEstimate, FeeEstimateQuery, FeeSuggestion, GasEstimateRequest, GasPriceInfo, HistoryQuery, ImportedAccountInfo, PermitRequest, PermitSignature, RestoredAccountInfo, TokenBalanceInfo, TokenTransferRequest, TransactionHistory, TransactionInfo, TransactionReceiptInfo, TransactionRequest,
+    WalletImportRequest, WalletRestoreRequest,
+};
+use crate::secret::SecretString;
+use crate::services::idempotency::Idempotent;
+use crate::services::permit::Permit;
+use crate::services::{bip32, Web3Service};
 use crate::state::AppState;
+use crate::utils;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::HeaderMap,
     response::Json,
 };
+use serde_json::Value;
 use tracing::info;
+use web3::types::U256;
+use zeroize::Zeroizing;
+
+/// Permit validity when the request gives no deadline
+const DEFAULT_PERMIT_DEADLINE_SECS: u64 = 3600;
+/// Header a client sets to make retries of `POST /transaction/send` safe
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+/// History page size when the query gives none
+const DEFAULT_HISTORY_PAGE_SIZE: usize = 50;
+const MAX_HISTORY_PAGE_SIZE: usize = 100;
 
 pub async fn get_wallet_balance(
+    Query(fields): Query<FieldsQuery>,
+    Query(selection): Query<AccountQuery>,
     State(state): State<AppState>,
-) -> AppResult<Json<ApiResponse<BalanceInfo>>> {
-    let web3_service = state.web3_service.read().await;
-    let account = state.account.read().await;
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<Value>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let account = state.account_for(selection.from.as_deref()).await?;
     
-    let balance_info = web3_service.get_balance(&account.public_address).await?;
-    Ok(Json(ApiResponse::success(balance_info)))
+    let balance_info = web3_service.get_balance(account.public_address.parse()?).await?;
+    let response = field_filter::select_fields(balance_info, &fields, state.config.server.strict_field_selection)?;
+    Ok(Json(response))
 }
 
 pub async fn get_address_balance(
     Path(address): Path<String>,
+    Query(fields): Query<FieldsQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<Value>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let address = web3_service.resolve_address(&address).await?;
+    let balance_info = web3_service.get_balance(address).await?;
+    let response = field_filter::select_fields(balance_info, &fields, state.config.server.strict_field_selection)?;
+    Ok(Json(response))
+}
+
+/// Balances of many addresses at once; failures are reported per address
+pub async fn get_balances(
     State(state): State<AppState>,
-) -> AppResult<Json<ApiResponse<BalanceInfo>>> {
+    headers: HeaderMap,
+    Json(request): Json<BalanceBatchRequest>,
+) -> AppResult<Json<ApiResponse<Vec<BalanceBatchEntry>>>> {
+    let max = state.config.server.max_balance_batch_size;
+    if request.addresses.is_empty() || request.addresses.len() > max {
+        return Err(AppError::ValidationError(format!(
+            "A balance batch must contain between 1 and {} addresses",
+            max
+        )));
+    }
+
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let balances = web3_service.get_balances(&request.addresses).await?;
+    Ok(Json(ApiResponse::success(balances)))
+}
+
+pub async fn get_address_balance_at(
+    Path((address, block)): Path<(String, String)>,
+    Query(fields): Query<FieldsQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<Value>>> {
+    let block = utils::parse_block_number(&block)?;
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let address = web3_service.resolve_address(&address).await?;
+    let balance_info = web3_service.get_balance_at(address, Some(block)).await?;
+    let response = field_filter::select_fields(balance_info, &fields, state.config.server.strict_field_selection)?;
+    Ok(Json(response))
+}
+
+pub async fn get_ens_profile(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<EnsProfile>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let profile = web3_service.ens_profile(&name).await?;
+    Ok(Json(ApiResponse::success(profile)))
+}
+
+pub async fn get_token_balance(
+    Path((contract, address)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<TokenBalanceInfo>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let token_balance = web3_service.get_token_balance(&contract, &address).await?;
+    Ok(Json(ApiResponse::success(token_balance)))
+}
+
+pub async fn send_token_transfer(
+    Path(contract): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<TokenTransferRequest>,
+) -> AppResult<Json<ApiResponse<TransactionInfo>>> {
+    rpc_override::reject_rpc_override(&headers)?;
     let web3_service = state.web3_service.read().await;
-    let balance_info = web3_service.get_balance(&address).await?;
-    Ok(Json(ApiResponse::success(balance_info)))
+    let account = state.active_account().await?;
+
+    let signer = state.wallet_service.signer(&account)?;
+    let decimals = web3_service.token_decimals(&contract).await?;
+    let transaction_info = web3_service
+        .send_token_transfer(&contract, &request.to, &request.amount, decimals, signer.as_ref())
+        .await?;
+
+    info!("Token transfer sent: {}", transaction_info.transaction_hash);
+    Ok(Json(ApiResponse::success(transaction_info)))
+}
+
+/// Sign an EIP-2612 permit letting `spender` move the wallet's tokens without an approve transaction
+pub async fn sign_token_permit(
+    Path(contract): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<PermitRequest>,
+) -> AppResult<Json<ApiResponse<PermitSignature>>> {
+    rpc_override::reject_rpc_override(&headers)?;
+    let web3_service = state.web3_service.read().await;
+    let account = state.active_account().await?;
+
+    let token = utils::validate_checksummed(&contract)?;
+    let spender = utils::validate_checksummed(&request.spender)?;
+    let value = if request.amount.trim().eq_ignore_ascii_case("max") {
+        U256::MAX
+    } else {
+        let decimals = web3_service.token_decimals(&contract).await?;
+        utils::parse_units(&request.amount, decimals).map_err(AppError::ValidationError)?
+    };
+    let deadline = request
+        .deadline
+        .unwrap_or_else(|| chrono::Utc::now().timestamp() as u64 + DEFAULT_PERMIT_DEADLINE_SECS);
+
+    let (domain_separator, nonce) = web3_service.permit_domain(&contract, &account.public_address).await?;
+    let permit = Permit {
+        owner: utils::validate_checksummed(&account.public_address)?,
+        spender,
+        value,
+        nonce,
+        deadline: U256::from(deadline),
+    };
+    let digest = permit.digest(domain_separator);
+    let signature = state
+        .wallet_service
+        .sign_typed_data(&account, digest, &utils::to_checksum(&token))?;
+
+    info!("Permit signed for {} on {}", utils::to_checksum(&spender), utils::to_checksum(&token));
+    Ok(Json(ApiResponse::success(PermitSignature {
+        token: token.into(),
+        owner: permit.owner.into(),
+        spender: spender.into(),
+        value: value.to_string(),
+        nonce: nonce.to_string(),
+        deadline,
+        v: signature.v,
+        r: format!("{:?}", signature.r),
+        s: format!("{:?}", signature.s),
+        digest: format!("{:?}", digest),
+    })))
+}
+
+pub async fn import_wallet(
+    State(state): State<AppState>,
+    Json(request): Json<WalletImportRequest>,
+) -> AppResult<Json<ApiResponse<ImportedAccountInfo>>> {
+    let derivation_path = request
+        .path
+        .unwrap_or_else(|| bip32::account_path(&state.config.wallet.derivation_path, 0));
+    let account = state
+        .wallet_service
+        .from_mnemonic(request.mnemonic.expose_secret(), request.passphrase.as_ref().map(SecretString::expose_secret), &derivation_path)?;
+    // Kept so send requests can pick other accounts of the same wallet by index
+    let seed = state
+        .wallet_service
+        .mnemonic_to_seed(request.mnemonic.expose_secret(), request.passphrase.as_ref().map(SecretString::expose_secret))?;
+
+    // The imported key replaces the wallet file, with the seed next to it, so both survive a restart
+    let config_file = state.config.wallet.config_file.clone();
+    state.wallet_service.save_hd_seed(&seed, &account.public_address, &config_file)?;
+    let replaced_file = state.wallet_service.replace_wallet(&account, &config_file)?;
+
+    let account_info = account.to_account_info();
+    *state.account.write().await = Some(account);
+    let mut hd_seed = state.hd_seed.write().await;
+    *hd_seed = Some(seed);
+    state.hd_addresses.clear();
+    drop(hd_seed);
+    info!("Active wallet switched to imported account: {}", account_info.address);
+
+    Ok(Json(ApiResponse::success(ImportedAccountInfo {
+        account: account_info,
+        derivation_path,
+        config_file,
+        replaced_file,
+    })))
+}
+
+/// Rebuild a key from Shamir backup shares and make it the active account
+pub async fn restore_wallet(
+    State(state): State<AppState>,
+    Json(request): Json<WalletRestoreRequest>,
+) -> AppResult<Json<ApiResponse<RestoredAccountInfo>>> {
+    let shares: Vec<&str> = request.shares.iter().map(SecretString::expose_secret).collect();
+    let account = state.wallet_service.restore_from_shares(&shares)?;
+
+    // Keep the previous wallet file intact; the restored key gets its own file next to it
+    let file_name = format!("restored_{}.json", account.public_address);
+    let config_file = std::path::Path::new(&state.config.wallet.config_file)
+        .with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned();
+    state.wallet_service.save_account(&account, &config_file)?;
+
+    let account_info = account.to_account_info();
+    *state.account.write().await = Some(account);
+    // The restored key was not derived from an imported mnemonic
+    let mut hd_seed = state.hd_seed.write().await;
+    *hd_seed = None;
+    state.hd_addresses.clear();
+    drop(hd_seed);
+    info!("Active wallet switched to restored account: {}", account_info.address);
+
+    Ok(Json(ApiResponse::success(RestoredAccountInfo {
+        account: account_info,
+        config_file,
+    })))
 }
 
 pub async fn send_transaction(
     State(state): State<AppState>,
-    Json(request): Json<TransactionRequest>,
+    headers: HeaderMap,
+    Json(mut request): Json<TransactionRequest>,
 ) -> AppResult<Json<ApiResponse<TransactionInfo>>> {
+    rpc_override::reject_rpc_override(&headers)?;
+    let idempotency = match idempotency_key(&headers)? {
+        Some(key) => match state.idempotency.begin(key, &request)? {
+            Idempotent::Replay(transaction_info) => {
+                info!("Replaying send for Idempotency-Key {}: {}", key, transaction_info.transaction_hash);
+                return Ok(Json(ApiResponse::success(*transaction_info)));
+            }
+            Idempotent::New(guard) => Some(guard),
+        },
+        None => None,
+    };
     let web3_service = state.web3_service.read().await;
-    let account = state.account.read().await;
+    let account = match (request.account_index, request.from.as_deref()) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::ValidationError(
+                "Specify at most one of account_index and from".to_string(),
+            ))
+        }
+        (Some(index), None) => derive_hd_account(&state, index).await?,
+        (None, from) => state.account_for(from).await?,
+    };
     
+    request.to = web3_service.resolve_address(&request.to).await?.to_string();
+    enforce_recipient_policy(&state, &web3_service, &request.to).await?;
+
     // Get secret key for signing
-    let secret_key = state.wallet_service.get_secret_key(&account)?;
+    let signer = state.wallet_service.signer(&account)?;
+    let value = request.value_wei()?;
+    let reservation = state.spend_limiter.reserve(value)?;
     
     // Send transaction
-    let transaction_info = web3_service
-        .send_transaction(&request, &secret_key, &account.public_address)
-        .await?;
+    let sent = web3_service
+        .send_transaction(&request, signer.as_ref(), &account.public_address)
+        .await;
+    // A dry run is checked against the spend limit but spends nothing. A send that may have reached the
+    // node counts, or repeated timeouts could move more than the limit.
+    let counts = match &sent {
+        Ok(_) => !request.dry_run,
+        Err(e) => matches!(e, AppError::BroadcastUncertain(_)),
+    };
+    let spent = if counts { value } else { U256::zero() };
+    state.spend_limiter.settle(reservation, spent);
+    let transaction_info = sent?;
+    if let Some(guard) = idempotency {
+        guard.complete(&transaction_info);
+    }
     
-    info!("Transaction sent: {}", transaction_info.transaction_hash);
+    if !request.dry_run {
+        info!("Transaction sent: {}", transaction_info.transaction_hash);
+    }
     Ok(Json(ApiResponse::success(transaction_info)))
 }
 
+pub async fn send_transaction_batch(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut request): Json<BatchTransactionRequest>,
+) -> AppResult<Json<ApiResponse<BatchTransactionResult>>> {
+    rpc_override::reject_rpc_override(&headers)?;
+    if request.transactions.iter().any(|tx| tx.account_index.is_some() || tx.from.is_some()) {
+        return Err(AppError::ValidationError(
+            "account_index and from are not supported in batches; all transactions are sent from the active account".to_string(),
+        ));
+    }
+    if request.transactions.iter().any(|tx| tx.dry_run) {
+        return Err(AppError::ValidationError("dry_run is not supported in batches".to_string()));
+    }
+
+    let web3_service = state.web3_service.read().await;
+    let account = state.active_account().await?;
+    let signer = state.wallet_service.signer(&account)?;
+    for transaction in &mut request.transactions {
+        transaction.to = web3_service.resolve_address(&transaction.to).await?.to_string();
+        enforce_recipient_policy(&state, &web3_service, &transaction.to).await?;
+    }
+
+    let values = request
+        .transactions
+        .iter()
+        .map(|tx| tx.value_wei())
+        .collect::<AppResult<Vec<_>>>()?;
+    let total = values.iter().fold(U256::zero(), |sum, value| sum.saturating_add(*value));
+    let reservation = state.spend_limiter.reserve(total)?;
+
+    let result = web3_service
+        .send_batch(&request.transactions, request.shared_fees, signer.as_ref(), &account.public_address)
+        .await;
+    // A batch stops at its first failure, so what went out is a prefix of the request, plus the failed
+    // transaction when it may have reached the node
+    let sent_count = result.as_ref().map_or(0, |result| {
+        result.sent.len() + result.failed.as_ref().is_some_and(|failed| failed.possibly_sent) as usize
+    });
+    let spent = values[..sent_count].iter().fold(U256::zero(), |sum, value| sum.saturating_add(*value));
+    state.spend_limiter.settle(reservation, spent);
+    let result = result?;
+
+    info!("Batch sent: {} of {} transactions", result.sent.len(), request.transactions.len());
+    Ok(Json(ApiResponse::success(result)))
+}
+
+pub async fn get_transaction_status(
+    Path(hash): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<TransactionInfo>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let transaction_info = web3_service.get_transaction_status(&hash).await?;
+    Ok(Json(ApiResponse::success(transaction_info)))
+}
+
+/// Transactions from or to an address, found by scanning a bounded block range
+pub async fn get_transaction_history(
+    Path(address): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<TransactionHistory>>> {
+    let max_blocks = state.config.server.max_history_blocks;
+    let page = query.page.unwrap_or(1);
+    let page_size = query.page_size.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE);
+    if page == 0 || page_size == 0 || page_size > MAX_HISTORY_PAGE_SIZE {
+        return Err(AppError::ValidationError(format!(
+            "page must be at least 1 and page_size between 1 and {}",
+            MAX_HISTORY_PAGE_SIZE
+        )));
+    }
+
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let address = web3_service.resolve_address(&address).await?;
+    let latest = web3_service.latest_block_number().await?;
+    let to_block = query.to_block.unwrap_or(latest);
+    let from_block = query
+        .from_block
+        .unwrap_or_else(|| to_block.saturating_sub(max_blocks.saturating_sub(1)));
+    if to_block > latest {
+        return Err(AppError::ValidationError(format!(
+            "to_block {} is past the latest block {}",
+            to_block, latest
+        )));
+    }
+    if from_block > to_block {
+        return Err(AppError::ValidationError(format!(
+            "from_block {} is after to_block {}",
+            from_block, to_block
+        )));
+    }
+    if to_block - from_block >= max_blocks {
+        return Err(AppError::ValidationError(format!(
+            "Block range {}..={} spans {} blocks; at most {} may be scanned per request",
+            from_block,
+            to_block,
+            to_block - from_block + 1,
+            max_blocks
+        )));
+    }
+
+    let history = web3_service.get_history(&address.to_string(), from_block, to_block).await?;
+    let total = history.len();
+    let transactions = history.into_iter().skip((page - 1) * page_size).take(page_size).collect();
+
+    Ok(Json(ApiResponse::success(TransactionHistory {
+        address,
+        from_block,
+        to_block,
+        page,
+        page_size,
+        total,
+        transactions,
+    })))
+}
+
+pub async fn get_transaction_receipt(
+    Path(hash): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<TransactionReceiptInfo>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let receipt = web3_service.get_transaction_receipt(&hash).await?;
+    Ok(Json(ApiResponse::success(receipt)))
+}
+
+/// The request's `Idempotency-Key`, if it sent one
+fn idempotency_key(headers: &HeaderMap) -> AppResult<Option<&str>> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|key| !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH)
+        .ok_or_else(|| {
+            AppError::ValidationError(format!(
+                "Idempotency-Key must be 1 to {} visible ASCII characters",
+                MAX_IDEMPOTENCY_KEY_LENGTH
+            ))
+        })?;
+    Ok(Some(key))
+}
+
+/// Reject recipients that `security.recipient_type` rules out
+async fn enforce_recipient_policy(state: &AppState, web3_service: &Web3Service, to: &str) -> AppResult<()> {
+    let policy = state.config.security.recipient_type;
+    if policy == RecipientPolicy::Any {
+        return Ok(());
+    }
+
+    let is_contract = web3_service.is_contract(to).await?;
+    match (policy, is_contract) {
+        (RecipientPolicy::ContractOnly, false) => Err(AppError::Forbidden(format!(
+            "{} is not a contract; security.recipient_type only allows contract recipients",
+            to
+        ))),
+        (RecipientPolicy::EoaOnly, true) => Err(AppError::Forbidden(format!(
+            "{} is a contract; security.recipient_type only allows externally owned accounts",
+            to
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Derive the HD account at `index` from the imported mnemonic's seed
+async fn derive_hd_account(state: &AppState, index: u32) -> AppResult<Account> {
+    let seed = state.hd_seed.read().await;
+    hd_account_from_seed(state, seed.as_ref(), index)
+}
+
+fn hd_account_from_seed(state: &AppState, seed: Option<&Zeroizing<[u8; 64]>>, index: u32) -> AppResult<Account> {
+    let max_index = state.config.wallet.max_account_index;
+    if index > max_index {
+        return Err(AppError::ValidationError(format!(
+            "HD account index {} exceeds wallet.max_account_index ({})",
+            index, max_index
+        )));
+    }
+
+    let seed = seed.ok_or_else(|| {
+        AppError::ValidationError("HD accounts require a wallet imported from a mnemonic".to_string())
+    })?;
+    state
+        .wallet_service
+        .account_from_seed(seed.as_ref(), &bip32::account_path(&state.config.wallet.derivation_path, index))
+}
+
+/// Address of HD account `index`, from the cache or derived and cached on first use
+async fn hd_address(state: &AppState, index: u32) -> AppResult<DerivedAddress> {
+    // Held until the address is cached, so an import cannot replace the seed in between
+    let seed = state.hd_seed.read().await;
+    if let Some(address) = state.hd_addresses.get(index) {
+        return Ok(address);
+    }
+
+    let account = hd_account_from_seed(state, seed.as_ref(), index)?;
+    let address = DerivedAddress {
+        index,
+        derivation_path: account.derivation_path.unwrap_or_default(),
+        address: account.public_address,
+    };
+    state.hd_addresses.insert(address.clone());
+    Ok(address)
+}
+
+/// Address of HD account `index` of the imported mnemonic
+pub async fn derive_address(
+    Path(index): Path<u32>,
+    State(state): State<AppState>,
+) -> AppResult<Json<ApiResponse<DerivedAddress>>> {
+    Ok(Json(ApiResponse::success(hd_address(&state, index).await?)))
+}
+
+/// Addresses of HD accounts `0..=wallet.max_account_index` of the imported mnemonic
+pub async fn list_derived_addresses(State(state): State<AppState>) -> AppResult<Json<ApiResponse<Vec<DerivedAddress>>>> {
+    let mut addresses = Vec::new();
+    for index in 0..=state.config.wallet.max_account_index {
+        addresses.push(hd_address(&state, index).await?);
+    }
+    Ok(Json(ApiResponse::success(addresses)))
+}
+
 pub async fn estimate_gas(
     Path((to, amount)): Path<(String, String)>,
     State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> AppResult<Json<ApiResponse<u64>>> {
-    let web3_service = state.web3_service.read().await;
-    let account = state.account.read().await;
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let account = state.active_account().await?;
     
-    let amount_eth: f64 = amount.parse()
-        .map_err(|_| crate::errors::AppError::ValidationError("Invalid amount format".to_string()))?;
+    let value = utils::parse_units(&amount, utils::NATIVE_DECIMALS).map_err(AppError::ValidationError)?;
     
     let gas_estimate = web3_service
-        .estimate_gas(&to, amount_eth, &account.public_address)
+        .estimate_gas(&to, value, None, &account.public_address)
         .await?;
     
     Ok(Json(ApiResponse::success(gas_estimate)))
 }
 
-pub async fn get_gas_price(
+/// Estimate for a contract interaction or transfer described in the body; supersedes `/estimate-gas/:to/:amount`
+pub async fn estimate_call_gas(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<GasEstimateRequest>,
 ) -> AppResult<Json<ApiResponse<u64>>> {
-    let web3_service = state.web3_service.read().await;
-    let gas_price = web3_service.get_gas_price().await?;
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let account = state.active_account().await?;
+
+    let value = match &request.amount_eth {
+        Some(amount) => utils::parse_units(amount, utils::NATIVE_DECIMALS).map_err(AppError::ValidationError)?,
+        None => U256::zero(),
+    };
+
+    let gas_estimate = web3_service
+        .estimate_gas(&request.to, value, request.data.as_deref(), &account.public_address)
+        .await?;
+
+    Ok(Json(ApiResponse::success(gas_estimate)))
+}
+
+pub async fn estimate_fee(
+    Query(query): Query<FeeEstimateQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<FeeEstimate>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let account = state.account_for(query.from.as_deref()).await?;
+
+    let value = utils::parse_units(&query.amount, utils::NATIVE_DECIMALS).map_err(AppError::ValidationError)?;
+    let to = web3_service.resolve_address(&query.to).await?;
+
+    let estimate = web3_service.estimate_fee(&to.to_string(), value, &account.public_address).await?;
+    Ok(Json(ApiResponse::success(estimate)))
+}
+
+pub async fn get_gas_price(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<GasPriceInfo>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let gas_price = web3_service.get_gas_price_info().await?;
     Ok(Json(ApiResponse::success(gas_price)))
-}
\ No newline at end of file
+}
+
+pub async fn get_fee_suggestion(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<ApiResponse<FeeSuggestion>>> {
+    let web3_service = rpc_override::web3_for_request(&state, &headers).await?;
+    let fees = web3_service.get_fee_suggestion().await?;
+    Ok(Json(ApiResponse::success(fees)))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::services::spend_limiter::SpendLimiter;
+    use crate::test_support::{self, MockNode, Reply};
+    use axum::{http::StatusCode, response::IntoResponse};
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    /// State with a fresh active account, sending through `node`, limited to 1 ETH per hour
+    async fn state_with_spend_limit(node: &MockNode) -> AppState {
+        let mut state = AppState::for_tests(AppConfig::default());
+        let config = crate::config::EthereumConfig { rpc_timeout_ms: 300, ..node.ethereum_config() };
+        state.web3_service = Arc::new(RwLock::new(test_support::connect(&config).await));
+        state.account = Arc::new(RwLock::new(Some(state.wallet_service.create_account().unwrap())));
+        state.spend_limiter = Arc::new(SpendLimiter::new(Some(U256::exp10(18)), None));
+        state
+    }
+
+    fn send_request(amount_eth: &str) -> Json<TransactionRequest> {
+        Json(
+            serde_json::from_value(json!({
+                "to": "0x00000000000000000000000000000000000000b0",
+                "amount_eth": amount_eth,
+                "gas_price": 1_000_000_000u64,
+                "gas_limit": 21_000,
+                "skip_balance_check": true,
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn sends_that_may_have_gone_out_count_against_the_spend_limit() {
+        let node = MockNode::start(|method, _| match method {
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x0"))),
+            "eth_sendRawTransaction" => Some(Reply::Stall),
+            _ => None,
+        })
+        .await;
+        let state = state_with_spend_limit(&node).await;
+
+        let first = send_transaction(State(state.clone()), HeaderMap::new(), send_request("0.6")).await;
+        assert!(matches!(first, Err(AppError::BroadcastUncertain(_))));
+
+        let second = send_transaction(State(state), HeaderMap::new(), send_request("0.6")).await;
+        assert!(matches!(second, Err(AppError::SpendLimitExceeded { .. })));
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sends_the_node_refused_do_not_count() {
+        let node = MockNode::start(|method, _| match method {
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x0"))),
+            "eth_sendRawTransaction" => Some(Reply::Error("insufficient funds for gas * price + value".to_string())),
+            _ => None,
+        })
+        .await;
+        let state = state_with_spend_limit(&node).await;
+
+        for _ in 0..2 {
+            let sent = send_transaction(State(state.clone()), HeaderMap::new(), send_request("0.6")).await;
+            assert!(matches!(sent, Err(AppError::TransactionFailed(_))));
+        }
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 2);
+    }
+
+    /// A node that accepts every send, answering with a hash that differs per raw transaction
+    async fn accepting_node() -> MockNode {
+        MockNode::start(|method, params| match method {
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x0"))),
+            "eth_sendRawTransaction" => {
+                let raw = hex::decode(params[0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                Some(Reply::Value(json!(format!("0x{}", hex::encode(web3::signing::keccak256(&raw))))))
+            }
+            _ => None,
+        })
+        .await
+    }
+
+    fn with_idempotency_key(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, key.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn replayed_send_returns_the_first_result_without_sending_again() {
+        let node = accepting_node().await;
+        let state = state_with_spend_limit(&node).await;
+
+        let first = send_transaction(State(state.clone()), with_idempotency_key("order-17"), send_request("0.1")).await.unwrap();
+        let replay = send_transaction(State(state.clone()), with_idempotency_key("order-17"), send_request("0.1")).await.unwrap();
+        // The cached TransactionInfo, timestamp included; only the envelope is new
+        assert_eq!(serde_json::to_value(&replay.0).unwrap()["data"], serde_json::to_value(&first.0).unwrap()["data"]);
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 1);
+
+        // Another key is another send
+        assert!(send_transaction(State(state), with_idempotency_key("order-18"), send_request("0.1")).await.is_ok());
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn reusing_a_key_for_another_request_is_a_conflict() {
+        let node = accepting_node().await;
+        let state = state_with_spend_limit(&node).await;
+
+        assert!(send_transaction(State(state.clone()), with_idempotency_key("order-17"), send_request("0.1")).await.is_ok());
+        let Err(error) = send_transaction(State(state), with_idempotency_key("order-17"), send_request("0.2")).await else {
+            panic!("a different body was accepted");
+        };
+        assert!(matches!(error, AppError::Conflict(_)), "{}", error);
+        assert_eq!(error.into_response().status(), StatusCode::CONFLICT);
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_send_releases_its_key_for_a_retry() {
+        let refuse = std::sync::atomic::AtomicBool::new(true);
+        let node = MockNode::start(move |method, _| match method {
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x0"))),
+            "eth_sendRawTransaction" if refuse.swap(false, std::sync::atomic::Ordering::SeqCst) => {
+                Some(Reply::Error("txpool is full".to_string()))
+            }
+            "eth_sendRawTransaction" => Some(Reply::Value(json!(format!("{:?}", web3::types::H256::repeat_byte(0xab))))),
+            _ => None,
+        })
+        .await;
+        let state = state_with_spend_limit(&node).await;
+
+        let refused = send_transaction(State(state.clone()), with_idempotency_key("order-17"), send_request("0.1")).await;
+        assert!(matches!(refused, Err(AppError::TransactionFailed(_))));
+        assert!(send_transaction(State(state), with_idempotency_key("order-17"), send_request("0.1")).await.is_ok());
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn balance_batches_must_hold_between_one_and_the_configured_maximum() {
+        let mut config = AppConfig::default();
+        config.server.max_balance_batch_size = 2;
+        let state = AppState::for_tests(config);
+        let batch = |count: u64| {
+            let addresses = (1..=count).map(|n| format!("{:?}", web3::types::Address::from_low_u64_be(n))).collect();
+            Json(BalanceBatchRequest { addresses })
+        };
+
+        for count in [0, 3] {
+            let Err(error) = get_balances(State(state.clone()), HeaderMap::new(), batch(count)).await else {
+                panic!("a batch of {} addresses was accepted", count);
+            };
+            assert!(matches!(error, AppError::ValidationError(_)), "{}", error);
+            assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_from_the_managed_account_named_in_from() {
+        let node = accepting_node().await;
+        let state = state_with_spend_limit(&node).await;
+        let mut created = Vec::new();
+        for _ in 0..2 {
+            let request = serde_json::from_value(json!({})).unwrap();
+            let Json(response) = crate::handlers::account_handler::create_account(State(state.clone()), Json(request)).await.unwrap();
+            created.push(response.data.unwrap().address);
+        }
+
+        let mut request = send_request("0.1");
+        request.from = Some(created[1].to_lowercase());
+        let sent = send_transaction(State(state.clone()), HeaderMap::new(), request).await.unwrap();
+        let sent = sent.0.data.unwrap();
+        assert_eq!(sent.from.to_string(), created[1]);
+
+        let raw = node.calls("eth_sendRawTransaction")[0][0].as_str().unwrap().to_string();
+        let decoded = crate::services::transaction_decoder::decode_raw_transaction(&raw).unwrap();
+        assert_eq!(decoded.from.to_string(), created[1]);
+
+        // Listing shows both, and never a key
+        let Json(listed) = crate::handlers::account_handler::list_accounts(State(state)).await.unwrap();
+        let listed = serde_json::to_string(&listed).unwrap();
+        assert!(created.iter().all(|address| listed.contains(address.as_str())), "{}", listed);
+        assert!(!listed.contains("secret"), "{}", listed);
+    }
+
+    fn history_address() -> web3::types::Address {
+        web3::types::Address::repeat_byte(0x11)
+    }
+
+    /// Node at block 100 whose blocks 96, 98 and 99 hold transactions involving `history_address`,
+    /// next to one that does not
+    async fn history_node() -> MockNode {
+        use web3::types::{Address, Block, Transaction, H256};
+
+        MockNode::start(|method, params| match method {
+            "eth_blockNumber" => Some(Reply::Value(json!("0x64"))),
+            "eth_getBlockByNumber" => {
+                let number = u64::from_str_radix(params[0].as_str()?.trim_start_matches("0x"), 16).ok()?;
+                let transfer = |hash: u8, from: Address, to: Option<Address>| Transaction {
+                    hash: H256::repeat_byte(hash),
+                    block_number: Some(number.into()),
+                    from: Some(from),
+                    to,
+                    value: U256::from(hash),
+                    ..Default::default()
+                };
+                let (other, watched) = (Address::repeat_byte(0x22), history_address());
+                let transactions = match number {
+                    96 => vec![transfer(1, watched, Some(other))],
+                    98 => vec![transfer(2, other, Some(watched)), transfer(3, other, Some(Address::repeat_byte(0x33)))],
+                    99 => vec![transfer(4, watched, None)],
+                    _ => Vec::new(),
+                };
+                Some(Reply::Value(json!(Block { number: Some(number.into()), transactions, ..Default::default() })))
+            }
+            _ => None,
+        })
+        .await
+    }
+
+    async fn history(state: &AppState, query: Value) -> AppResult<TransactionHistory> {
+        let address = utils::to_checksum(&history_address());
+        let query = Query(serde_json::from_value(query).unwrap());
+        let Json(response) = get_transaction_history(Path(address), query, State(state.clone()), HeaderMap::new()).await?;
+        Ok(response.data.unwrap())
+    }
+
+    #[tokio::test]
+    async fn history_lists_transactions_from_or_to_the_address() {
+        let node = history_node().await;
+        let state = state_with_spend_limit(&node).await;
+
+        let found = history(&state, json!({ "from_block": 95, "to_block": 100 })).await.unwrap();
+        assert_eq!(found.total, 3);
+        let hashes: Vec<String> = found.transactions.iter().map(|tx| tx.transaction_hash.clone()).collect();
+        let hash = |byte: u8| format!("0x{}", hex::encode([byte; 32]));
+        assert_eq!(hashes, [hash(1), hash(2), hash(4)]);
+        assert_eq!(found.transactions[1].block_number, Some(98));
+        assert_eq!(found.transactions[1].value_wei, "2");
+        assert!(found.transactions[2].to.is_none());
+
+        let second_page = history(&state, json!({ "from_block": 95, "to_block": 100, "page": 2, "page_size": 2 })).await.unwrap();
+        assert_eq!(second_page.total, 3);
+        assert_eq!(second_page.transactions.len(), 1);
+        assert_eq!(second_page.transactions[0].transaction_hash, hash(4));
+    }
+
+    #[tokio::test]
+    async fn history_scans_a_bounded_block_range() {
+        let node = history_node().await;
+        let mut state = state_with_spend_limit(&node).await;
+        state.config.server.max_history_blocks = 5;
+
+        for query in [
+            json!({ "from_block": 95, "to_block": 100 }),
+            json!({ "from_block": 99, "to_block": 98 }),
+            json!({ "from_block": 97, "to_block": 101 }),
+            json!({ "page": 0 }),
+            json!({ "page_size": 101 }),
+        ] {
+            let Err(error) = history(&state, query.clone()).await else {
+                panic!("accepted {}", query);
+            };
+            assert!(matches!(error, AppError::ValidationError(_)), "{}: {}", query, error);
+        }
+        assert!(node.calls("eth_getBlockByNumber").is_empty());
+
+        let Err(error) = history(&state, json!({ "from_block": 0, "to_block": 100 })).await else {
+            panic!("scanned 101 blocks");
+        };
+        assert!(error.to_string().contains("at most 5"), "{}", error);
+        assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+
+        // Without a range, the last `max_history_blocks` blocks are scanned
+        let latest = history(&state, json!({})).await.unwrap();
+        assert_eq!((latest.from_block, latest.to_block), (96, 100));
+        assert_eq!(latest.total, 3);
+        assert_eq!(node.calls("eth_getBlockByNumber").len(), 5);
+    }
+
+    const TOKEN: &str = "0x00000000000000000000000000000000000000c0";
+
+    /// Node estimating 21000 gas for plain transfers and 46097 for calls with calldata
+    async fn estimating_node() -> MockNode {
+        MockNode::start(|method, params| match method {
+            "eth_estimateGas" if params[0].get("data").is_some() => Some(Reply::Value(json!("0xb411"))),
+            "eth_estimateGas" => Some(Reply::Value(json!("0x5208"))),
+            _ => None,
+        })
+        .await
+    }
+
+    async fn estimate_posted(state: &AppState, body: Value) -> AppResult<u64> {
+        let request = Json(serde_json::from_value(body).unwrap());
+        let Json(response) = estimate_call_gas(State(state.clone()), HeaderMap::new(), request).await?;
+        Ok(response.data.unwrap())
+    }
+
+    #[tokio::test]
+    async fn estimates_a_value_transfer_on_either_route() {
+        let node = estimating_node().await;
+        let state = state_with_spend_limit(&node).await;
+
+        assert_eq!(estimate_posted(&state, json!({ "to": TOKEN, "amount_eth": "0.5" })).await.unwrap(), 21_000);
+        let path = Path((TOKEN.to_string(), "0.5".to_string()));
+        let Json(response) = estimate_gas(path, State(state), HeaderMap::new()).await.unwrap();
+        assert_eq!(response.data, Some(21_000));
+
+        let calls = node.calls("eth_estimateGas");
+        assert!(!calls.is_empty());
+        for params in calls {
+            // 0.5 ETH, converted exactly
+            assert_eq!(params[0]["value"], "0x6f05b59d3b20000");
+            assert!(params[0].get("data").is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn estimates_a_contract_call_with_calldata() {
+        let node = estimating_node().await;
+        let state = state_with_spend_limit(&node).await;
+        let data = format!("0xa9059cbb{}", "00".repeat(64));
+
+        assert_eq!(estimate_posted(&state, json!({ "to": TOKEN, "data": data })).await.unwrap(), 46_097);
+        let calls = node.calls("eth_estimateGas");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0][0]["data"], data.as_str());
+        assert_eq!(calls[0][0]["to"], TOKEN);
+    }
+
+    #[tokio::test]
+    async fn malformed_estimate_bodies_are_rejected() {
+        let node = estimating_node().await;
+        let state = state_with_spend_limit(&node).await;
+
+        for body in [
+            json!({ "to": TOKEN, "data": "0xnot-hex" }),
+            json!({ "to": TOKEN, "amount_eth": "0.0000000000000000001" }),
+        ] {
+            let Err(error) = estimate_posted(&state, body.clone()).await else {
+                panic!("estimated {}", body);
+            };
+            assert!(matches!(error, AppError::ValidationError(_)), "{}: {}", body, error);
+        }
+        assert!(node.calls("eth_estimateGas").is_empty());
+    }
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    /// State holding the seed of `MNEMONIC`, with its first `preload` addresses derived ahead
+    async fn hd_state(preload: u32) -> AppState {
+        let state = AppState::for_tests(AppConfig::default());
+        let seed = state.wallet_service.mnemonic_to_seed(MNEMONIC, None).unwrap();
+        state
+            .hd_addresses
+            .preload(&state.wallet_service, seed.as_ref(), &state.config.wallet.derivation_path, preload)
+            .unwrap();
+        *state.hd_seed.write().await = Some(seed);
+        state
+    }
+
+    #[tokio::test]
+    async fn preloaded_addresses_match_derivation_on_demand() {
+        let preloaded = hd_state(3).await;
+        assert!(preloaded.hd_addresses.get(2).is_some());
+        assert!(preloaded.hd_addresses.get(3).is_none());
+
+        let on_demand = hd_state(0).await;
+        for index in 0..5 {
+            let Json(expected) = derive_address(Path(index), State(on_demand.clone())).await.unwrap();
+            let Json(cached) = derive_address(Path(index), State(preloaded.clone())).await.unwrap();
+            assert_eq!(cached.data.unwrap().address, expected.data.unwrap().address);
+        }
+        // Addresses past the preloaded ones are cached on first use
+        assert!(preloaded.hd_addresses.get(4).is_some());
+        assert_eq!(preloaded.hd_addresses.get(0).unwrap().address, "0x9858EfFD232B4033E47d90003D41EC34EcaEda94");
+    }
+
+    #[tokio::test]
+    async fn listing_covers_every_index_up_to_the_maximum() {
+        let mut state = hd_state(2).await;
+        state.config.wallet.max_account_index = 4;
+
+        let Json(response) = list_derived_addresses(State(state.clone())).await.unwrap();
+        let addresses = response.data.unwrap();
+        assert_eq!(addresses.iter().map(|address| address.index).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+        assert_eq!(addresses[1].derivation_path, "m/44'/60'/0'/0/1");
+        assert_eq!(addresses[1].address, "0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9C0");
+        assert!(state.hd_addresses.get(4).is_some());
+    }
+
+    #[tokio::test]
+    async fn importing_another_mnemonic_drops_the_cached_addresses() {
+        let state = hd_state(2).await;
+        let previous = state.hd_addresses.get(0).unwrap().address;
+
+        let request = serde_json::from_value(json!({
+            "mnemonic": "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        }))
+        .unwrap();
+        let Json(imported) = import_wallet(State(state.clone()), Json(request)).await.unwrap();
+
+        assert!(state.hd_addresses.get(1).is_none());
+        let Json(response) = derive_address(Path(0), State(state)).await.unwrap();
+        let address = response.data.unwrap().address;
+        assert_ne!(address, previous);
+        assert_eq!(address, imported.data.unwrap().account.address);
+    }
+}
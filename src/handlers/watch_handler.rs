@@ -0,0 +1,121 @@
+use crate::errors::{AppError, AppResult};
+use crate::models::{ApiResponse, WatchInfo, WatchRequest};
+use crate::services::address_watcher;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use tracing::info;
+
+/// Report transactions sent to `address` by POSTing them to `callback_url`
+pub async fn create_watch(
+    State(state): State<AppState>,
+    Json(request): Json<WatchRequest>,
+) -> AppResult<Json<ApiResponse<WatchInfo>>> {
+    if !["http://", "https://"].iter().any(|scheme| request.callback_url.starts_with(scheme)) {
+        return Err(AppError::ValidationError("callback_url must be an http(s):// URL".to_string()));
+    }
+
+    let address = {
+        let web3_service = state.web3_service.read().await;
+        if !web3_service.supports_subscriptions() {
+            return Err(AppError::NotSupported(
+                "Watching addresses needs a ws:// or wss:// ethereum.rpc_url".to_string(),
+            ));
+        }
+        web3_service.resolve_address(&request.address).await?
+    };
+
+    let watch = WatchInfo {
+        id: address_watcher::new_watch_id(),
+        address,
+        callback_url: request.callback_url,
+    };
+    state.watches.write().await.insert(watch.id.clone(), watch.clone());
+    info!("Watching {} for incoming transactions (watch {})", watch.address, watch.id);
+    Ok(Json(ApiResponse::success(watch)))
+}
+
+pub async fn delete_watch(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> AppResult<Json<ApiResponse<WatchInfo>>> {
+    let watch = state
+        .watches
+        .write()
+        .await
+        .remove(&id)
+        .ok_or_else(|| AppError::NotFound(format!("Watch {}", id)))?;
+    info!("Stopped watching {} (watch {})", watch.address, watch.id);
+    Ok(Json(ApiResponse::success(watch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, EthereumConfig};
+    use crate::test_support::{self, MockNode};
+    use serde_json::json;
+
+    const ADDRESS: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    fn watch_request(callback_url: &str) -> Json<WatchRequest> {
+        Json(serde_json::from_value(json!({ "address": ADDRESS, "callback_url": callback_url })).unwrap())
+    }
+
+    /// State connected to `node` over WebSocket
+    async fn subscribed_state(node: &MockNode) -> AppState {
+        let state = AppState::for_tests(AppConfig::default());
+        let config = EthereumConfig { rpc_url: node.ws_url.clone(), ..node.ethereum_config() };
+        *state.web3_service.write().await = test_support::connect(&config).await;
+        state
+    }
+
+    #[tokio::test]
+    async fn registers_and_removes_a_watch() {
+        let node = MockNode::start(|_, _| None).await;
+        let state = subscribed_state(&node).await;
+
+        let Json(created) = create_watch(State(state.clone()), watch_request("https://hooks.example/in")).await.unwrap();
+        let watch = created.data.unwrap();
+        assert_eq!(watch.address.to_string(), ADDRESS);
+        assert!(state.watches.read().await.contains_key(&watch.id));
+
+        let Json(deleted) = delete_watch(Path(watch.id.clone()), State(state.clone())).await.unwrap();
+        assert_eq!(deleted.data.unwrap().id, watch.id);
+        assert!(state.watches.read().await.is_empty());
+
+        let Err(error) = delete_watch(Path(watch.id), State(state)).await else {
+            panic!("removed a watch twice");
+        };
+        assert!(matches!(error, AppError::NotFound(_)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn callbacks_must_be_http_urls() {
+        let node = MockNode::start(|_, _| None).await;
+        let state = subscribed_state(&node).await;
+
+        for callback_url in ["ftp://hooks.example/in", "hooks.example/in", ""] {
+            let Err(error) = create_watch(State(state.clone()), watch_request(callback_url)).await else {
+                panic!("accepted callback {:?}", callback_url);
+            };
+            assert!(matches!(error, AppError::ValidationError(_)), "{}", error);
+        }
+        assert!(state.watches.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn watching_needs_a_websocket_node() {
+        let node = MockNode::start(|_, _| None).await;
+        let state = AppState::for_tests(AppConfig::default());
+        *state.web3_service.write().await = node.connect().await;
+
+        let Err(error) = create_watch(State(state.clone()), watch_request("https://hooks.example/in")).await else {
+            panic!("watch registered over HTTP");
+        };
+        assert!(matches!(error, AppError::NotSupported(_)), "{}", error);
+        assert!(state.watches.read().await.is_empty());
+    }
+}
@@ -1,61 +1,277 @@
 use axum::{
-    routing::{get, post},
+    http::{header, HeaderName, HeaderValue, Method},
+    routing::{delete, get, post},
     Router,
 };
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use clap::Parser;
+use std::net::SocketAddr;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
-use tracing::{error, info, warn};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{error, info, warn, Subscriber};
+use tracing_subscriber::{fmt::MakeWriter, util::SubscriberInitExt, EnvFilter};
+use zeroize::Zeroizing;
 
 // Module declarations
+mod address;
 mod config;
 mod errors;
 mod handlers;
+mod middleware;
 mod models;
+mod secret;
 mod services;
 mod state;
+#[cfg(test)]
+mod test_support;
+mod tls;
 mod utils;
 
-use config::AppConfig;
+use address::ChecksumAddress;
+use config::{AppConfig, CorsConfig, SecurityConfig, TlsConfig, WalletConfig};
 use errors::AppResult;
 use models::Account;
+use secret::SecretString;
+use middleware::rate_limit::RateLimiter;
+use services::account_store::AccountStore;
+use services::address_watcher::{self, WatchList};
+use services::audit_logger::AuditLogger;
+use services::hd_addresses::HdAddressCache;
+use services::idempotency::IdempotencyCache;
+use services::mpc_signer::MpcSigner;
+use services::spend_limiter::SpendLimiter;
 use services::{WalletService, Web3Service};
 use state::AppState;
 
+/// Environment variable holding the wallet keystore password; overrides `wallet.password`
+const WALLET_PASSWORD_ENV: &str = "WALLET_PASSWORD";
+/// Environment variable holding the API key; overrides `server.api_key`
+const API_KEY_ENV: &str = "API_KEY";
+/// `json` for one JSON object per line, `pretty` (default) for human-readable output
+const LOG_FORMAT_ENV: &str = "LOG_FORMAT";
+/// Log filter such as `debug` or `server=debug,hyper=warn`; falls back to `RUST_LOG`, then `info`
+const LOG_LEVEL_ENV: &str = "LOG_LEVEL";
+/// How often a healthy Web3 connection is probed
+const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// First and longest wait between reconnect attempts; the delay doubles in between
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+/// Upper bound on one reconnect attempt, which holds the service write lock
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long in-flight requests may run after a shutdown signal before the server exits
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Command-line overrides; each flag given wins over config files and `APP_*` variables
+#[derive(Parser, Debug)]
+#[command(about = "Ethereum wallet REST server")]
+struct CliArgs {
+    /// Node endpoint (ws://, wss://, http:// or https://)
+    #[arg(long)]
+    rpc_url: Option<String>,
+    /// Port to listen on
+    #[arg(long)]
+    port: Option<u16>,
+    /// Address to bind to
+    #[arg(long)]
+    host: Option<String>,
+    /// Chain ID of the node's network
+    #[arg(long)]
+    network_id: Option<u64>,
+    /// Primary wallet file
+    #[arg(long)]
+    wallet_file: Option<String>,
+    /// Encrypt plaintext wallet files with the wallet password, wipe the plaintext and exit
+    #[arg(long)]
+    migrate_keystore: bool,
+}
+
+impl CliArgs {
+    /// Overwrite the loaded values for the flags that were given
+    fn apply(self, config: &mut AppConfig) {
+        if let Some(rpc_url) = self.rpc_url {
+            config.ethereum.rpc_url = rpc_url;
+        }
+        if let Some(port) = self.port {
+            config.server.port = port;
+        }
+        if let Some(host) = self.host {
+            config.server.host = host;
+        }
+        if let Some(network_id) = self.network_id {
+            config.ethereum.network_id = network_id;
+        }
+        if let Some(wallet_file) = self.wallet_file {
+            config.wallet.config_file = wallet_file;
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> AppResult<()> {
+    let args = CliArgs::parse();
+    let migrate_keystore = args.migrate_keystore;
+
     // Initialize logging
-    tracing_subscriber::fmt::init();
+    init_logging();
     info!("Starting Ethereum Wallet Server...");
 
     // Load configuration
-    let config = load_configuration().await?;
+    let mut config = load_configuration().await?;
+    args.apply(&mut config);
+    if let Some(api_key) = std::env::var(API_KEY_ENV).ok().filter(|key| !key.is_empty()) {
+        config.server.api_key = Some(api_key);
+    }
+    if let Some(password) = std::env::var(WALLET_PASSWORD_ENV).ok().filter(|p| !p.is_empty()) {
+        config.wallet.password = Some(SecretString::new(password));
+    }
+    if let Err(e) = config.validate() {
+        error!("{}", e);
+        return Err(e);
+    }
+    check_fault_injection(&config)?;
+    if config.server.api_key.is_none() {
+        warn!("!!! server.api_key is not set: anyone who can reach this port can send transactions and sign with the wallet key !!!");
+    }
+    if config.ethereum.allow_rpc_override {
+        warn!("ethereum.allow_rpc_override is enabled; read requests may pick any node via X-RPC-URL");
+    }
+    if config.security.allow_key_export {
+        warn!("security.allow_key_export is enabled; POST /account/export returns the wallet's secret key");
+    }
+    if config.ethereum.max_gas_price_wei.is_none() {
+        warn!("ethereum.max_gas_price_wei is not set; gas prices are uncapped (e.g. 500000000000 caps them at 500 gwei)");
+    }
 
     // Initialize services
-    let wallet_service = Arc::new(WalletService::new());
-    let mut web3_service = Web3Service::new(
-        config.ethereum.rpc_url.clone(),
-        config.ethereum.network_id,
-    );
+    let audit_logger = Arc::new(AuditLogger::open(config.audit.log_file.as_deref())?);
+    let wallet_service = Arc::new(WalletService::new()
+        .with_audit_logger(audit_logger.clone())
+        .with_password(config.wallet.password.clone())
+        .with_mpc_signer(MpcSigner::from_config(&config.mpc)?));
+    if migrate_keystore {
+        return migrate_wallet_files(wallet_service, &config);
+    }
+    let mut web3_service = Web3Service::new(&config.ethereum).with_audit_logger(audit_logger);
 
     // Initialize wallet
-    let account = initialize_wallet(&wallet_service, &config).await?;
+    let account = match initialize_wallet(&wallet_service, &config).await {
+        Ok(account) => Some(account),
+        Err(e) if config.wallet.optional => {
+            warn!("Wallet unavailable, starting read-only: {}", e);
+            warn!("Signing endpoints will return WALLET_UNAVAILABLE");
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    // A mnemonic imported through /wallet/import keeps its HD accounts across restarts
+    let hd_seed = match account.as_ref().filter(|account| account.kind == models::AccountKind::Full) {
+        Some(account) => wallet_service
+            .load_hd_seed(&account.public_address, &config.wallet.config_file)
+            .unwrap_or_else(|e| {
+                warn!("HD accounts unavailable: {}", e);
+                None
+            }),
+        None => None,
+    };
     let account = Arc::new(RwLock::new(account));
+    let account_store = Arc::new(AccountStore::open(wallet_service.clone(), &config.wallet.config_file));
 
     // Establish Web3 connection
     if let Err(e) = web3_service.connect().await {
+        if matches!(e, errors::AppError::ConfigurationError(_)) {
+            return Err(e);
+        }
         warn!("Failed to establish Web3 connection: {}", e);
         warn!("Some API endpoints will be unavailable");
     }
     let web3_service = Arc::new(RwLock::new(web3_service));
+    spawn_reconnect_loop(web3_service.clone());
+
+    // Watches need the new-heads subscription, which only WebSocket endpoints offer
+    let watches: WatchList = Arc::default();
+    if config.ethereum.rpc_url.starts_with("ws") {
+        address_watcher::spawn(web3_service.clone(), watches.clone());
+    }
 
     // Create and start server
-    let app = create_router(wallet_service, web3_service, account, config.clone()).await;
+    let app = create_router(wallet_service, web3_service, account, hd_seed, account_store, watches, config.clone()).await?;
     start_server(app, &config).await?;
 
     Ok(())
 }
 
+/// Probe the Web3 connection periodically and reconnect with exponential backoff when it drops.
+/// The write lock is only taken for the reconnect itself, so reads continue while the node is healthy.
+fn spawn_reconnect_loop(web3_service: Arc<RwLock<Web3Service>>) {
+    tokio::spawn(async move {
+        let mut attempt: u32 = 0;
+        loop {
+            let delay = if attempt == 0 { CONNECTION_CHECK_INTERVAL } else { reconnect_delay(attempt) };
+            tokio::time::sleep(delay).await;
+
+            if web3_service.read().await.is_live().await {
+                if attempt > 0 {
+                    info!("Web3 connection restored after {} attempt(s)", attempt);
+                }
+                attempt = 0;
+                continue;
+            }
+
+            attempt = attempt.saturating_add(1);
+            warn!("Web3 connection unavailable; reconnect attempt {}", attempt);
+            let mut service = web3_service.write().await;
+            match tokio::time::timeout(RECONNECT_TIMEOUT, service.connect()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!("Reconnect attempt {} failed: {}", attempt, e),
+                Err(_) => warn!("Reconnect attempt {} timed out", attempt),
+            }
+        }
+    });
+}
+
+/// Wait before reconnect attempt `attempt + 1`: 1s, 2s, 4s, ... capped at `RECONNECT_MAX_DELAY`
+fn reconnect_delay(attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    RECONNECT_BASE_DELAY.saturating_mul(factor).min(RECONNECT_MAX_DELAY)
+}
+
+/// Install the global subscriber. Runs before the config is loaded, so it is driven by the environment.
+fn init_logging() {
+    let filter = EnvFilter::try_from_env(LOG_LEVEL_ENV)
+        .or_else(|_| EnvFilter::try_from_default_env())
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let format = std::env::var(LOG_FORMAT_ENV).unwrap_or_default();
+    log_subscriber(format == "json", filter, std::io::stdout).init();
+    if !matches!(format.as_str(), "" | "pretty" | "json") {
+        warn!("Unknown {} '{}'; expected json or pretty, using pretty", LOG_FORMAT_ENV, format);
+    }
+}
+
+/// Formatter writing one JSON object per line when `json` is set, human-readable lines otherwise
+fn log_subscriber<W>(json: bool, filter: EnvFilter, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(writer);
+    if json {
+        Box::new(builder.json().finish())
+    } else {
+        Box::new(builder.finish())
+    }
+}
+
+/// Defaults layered with `APP_*` variables and any config files. A missing `config.toml` is fine;
+/// a config file that cannot be read or parsed stops startup.
 async fn load_configuration() -> AppResult<AppConfig> {
+    if !utils::path_exists("config.toml") && std::env::var("CONFIG_FILE").is_err() {
+        info!("No config file found; using defaults");
+    }
+
     match AppConfig::load() {
         Ok(config) => {
             info!("Configuration loaded successfully");
@@ -63,16 +279,44 @@ async fn load_configuration() -> AppResult<AppConfig> {
         }
         Err(e) => {
             error!("Failed to load configuration: {}", e);
-            info!("Using default configuration");
-            Ok(AppConfig::default())
+            Err(errors::AppError::ConfigurationError(e.to_string()))
         }
     }
 }
 
+fn check_fault_injection(config: &AppConfig) -> AppResult<()> {
+    let fail_rate = config.testing.fail_rate;
+    if !(0.0..=1.0).contains(&fail_rate) {
+        return Err(errors::AppError::ConfigurationError(format!(
+            "testing.fail_rate must be between 0.0 and 1.0, got {}",
+            fail_rate
+        )));
+    }
+
+    if fail_rate > 0.0 {
+        if cfg!(feature = "fault-injection") {
+            warn!("FAULT INJECTION ACTIVE: {:.0}% of read requests will fail with 503", fail_rate * 100.0);
+        } else {
+            warn!("testing.fail_rate is set but ignored; this build lacks the fault-injection feature");
+        }
+    }
+
+    Ok(())
+}
+
 async fn initialize_wallet(
     wallet_service: &WalletService,
     config: &AppConfig,
 ) -> AppResult<Account> {
+    if let Some(address) = &config.mpc.address {
+        let account = Account::mpc(&address.parse::<ChecksumAddress>()?.to_string());
+        info!("Wallet key is held by {} MPC co-signer(s); the wallet file is not used", config.mpc.peers.len());
+        info!("Account address: {}", account.public_address);
+        return Ok(account);
+    }
+    if config.wallet.password.is_none() {
+        warn!("wallet.password / {} not set; wallet keys are stored unencrypted", WALLET_PASSWORD_ENV);
+    }
     let account = wallet_service.initialize_wallet(&config.wallet.config_file)?;
     
     // Validate account integrity
@@ -86,60 +330,880 @@ async fn initialize_wallet(
     Ok(account)
 }
 
+/// `--migrate-keystore`: encrypt the primary wallet file and managed account files that are still plaintext
+fn migrate_wallet_files(wallet_service: Arc<WalletService>, config: &AppConfig) -> AppResult<()> {
+    if config.wallet.password.is_none() {
+        return Err(errors::AppError::ConfigurationError(format!(
+            "--migrate-keystore needs wallet.password or {}",
+            WALLET_PASSWORD_ENV
+        )));
+    }
+
+    let wallet_file = &config.wallet.config_file;
+    let mut migrated = 0;
+    if utils::path_exists(wallet_file) && wallet_service.migrate_to_keystore(wallet_file)? {
+        migrated += 1;
+    }
+    migrated += AccountStore::open(wallet_service, wallet_file).migrate_to_keystore()?;
+
+    info!("Migrated {} plaintext wallet file(s) to encrypted keystores", migrated);
+    Ok(())
+}
+
 async fn create_router(
     wallet_service: Arc<WalletService>,
     web3_service: Arc<RwLock<Web3Service>>,
-    account: Arc<RwLock<Account>>,
+    account: Arc<RwLock<Option<Account>>>,
+    hd_seed: Option<Zeroizing<[u8; 64]>>,
+    account_store: Arc<AccountStore>,
+    watches: WatchList,
     config: AppConfig,
-) -> Router {
+) -> AppResult<Router> {
+    let cors = cors_layer(&config.cors)?;
+    let spend_limiter = spend_limiter(&config.security)?;
+    let idempotency = Arc::new(IdempotencyCache::new(Duration::from_secs(config.server.idempotency_ttl_secs)));
+    IdempotencyCache::spawn_eviction(idempotency.clone());
+    let hd_addresses = match &hd_seed {
+        Some(seed) => preload_hd_addresses(&wallet_service, seed.as_ref(), &config.wallet)?,
+        None => HdAddressCache::default(),
+    };
     let app_state = AppState {
         wallet_service,
         web3_service,
         account,
+        account_store,
+        hd_seed: Arc::new(RwLock::new(hd_seed)),
+        hd_addresses: Arc::new(hd_addresses),
+        rate_limiter: Arc::new(RateLimiter::new(
+            config.server.rate_limit_per_minute,
+            config.server.trusted_proxies.clone(),
+        )),
+        spend_limiter: Arc::new(spend_limiter),
+        idempotency,
+        watches,
+        safe_queue: Arc::default(),
         config,
     };
+    let rate_limit = || axum::middleware::from_fn_with_state(app_state.clone(), middleware::rate_limit::limit_by_ip);
+
+    // Routes that sign with (or replace) the wallet key sit behind the API key
+    let signing_routes = Router::new()
+        .route("/account/fill-gap/:nonce", post(handlers::account_handler::fill_nonce_gap))
+        .route("/sign", post(handlers::account_handler::sign_message))
+        .route("/sign/typed-data", post(handlers::account_handler::sign_typed_data))
+        .route("/account/export", post(handlers::account_handler::export_key))
+        .route("/account/backup/shares", post(handlers::account_handler::export_backup_shares))
+        .route("/wallet/import", post(handlers::wallet_handler::import_wallet))
+        .route("/wallet/restore", post(handlers::wallet_handler::restore_wallet))
+        .route("/accounts", post(handlers::account_handler::create_account))
+        .route("/accounts/import", post(handlers::account_handler::import_account))
+        .route("/accounts/vanity", post(handlers::account_handler::create_managed_vanity_account))
+        // Original route of the vanity search, kept for existing clients
+        .route("/account/vanity", post(handlers::account_handler::create_managed_vanity_account))
+        .route("/accounts/vanity/stream", post(handlers::account_handler::stream_managed_vanity_account))
+        .route("/accounts/:address", delete(handlers::account_handler::delete_account))
+        .route("/accounts/:address/export", post(handlers::account_handler::export_keystore))
+        .route("/token/:contract/transfer", post(handlers::wallet_handler::send_token_transfer))
+        .route("/token/:contract/permit", post(handlers::wallet_handler::sign_token_permit))
+        .route("/transaction/send", post(handlers::wallet_handler::send_transaction))
+        .route("/transaction/batch", post(handlers::wallet_handler::send_transaction_batch))
+        .route("/safe/:address/transactions", post(handlers::safe_handler::propose_transaction))
+        .route(
+            "/safe/:address/transactions/:safe_tx_hash/confirmations",
+            post(handlers::safe_handler::confirm_transaction),
+        )
+        .route(
+            "/safe/:address/transactions/:safe_tx_hash/execute",
+            post(handlers::safe_handler::execute_transaction),
+        )
+        .route("/watch", post(handlers::watch_handler::create_watch))
+        .route("/watch/:id", delete(handlers::watch_handler::delete_watch))
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::auth::require_api_key,
+        ))
+        .route_layer(rate_limit());
 
-    Router::new()
-        // Health and info endpoints
+    // Probed by load balancers and scrapers, which may expect them outside `base_path`
+    let health_routes = Router::new()
         .route("/", get(handlers::account_handler::health_check))
-        .route("/health", get(handlers::account_handler::health_check))
+        .route("/health", get(handlers::account_handler::readiness_check))
+        .route("/metrics", get(handlers::account_handler::get_metrics));
+
+    let api_routes = Router::new()
+        // Info endpoints
         .route("/network", get(handlers::account_handler::get_network_info))
+        .route("/networks", get(handlers::account_handler::list_networks))
+        .route("/blocks/stream", get(handlers::account_handler::stream_blocks))
         
         // Account endpoints
         .route("/account", get(handlers::account_handler::get_account_info))
+        .route("/account/qr", get(handlers::account_handler::get_account_qr))
+        .route("/account/mempool", get(handlers::account_handler::get_account_mempool))
+        .route("/accounts", get(handlers::account_handler::list_accounts))
+        .route("/wallet/derive", get(handlers::wallet_handler::list_derived_addresses))
+        .route("/wallet/derive/:index", get(handlers::wallet_handler::derive_address))
         
         // Wallet endpoints
         .route("/balance", get(handlers::wallet_handler::get_wallet_balance))
         .route("/balance/:address", get(handlers::wallet_handler::get_address_balance))
+        .route("/balance/batch", post(handlers::wallet_handler::get_balances))
+        .route("/balance/:address/at/:block", get(handlers::wallet_handler::get_address_balance_at))
+        .route("/ens/:name", get(handlers::wallet_handler::get_ens_profile))
+        .route("/token/:contract/balance/:address", get(handlers::wallet_handler::get_token_balance))
         .route("/gas-price", get(handlers::wallet_handler::get_gas_price))
-        .route("/estimate-gas/:to/:amount", get(handlers::wallet_handler::estimate_gas))
+        .route("/fees", get(handlers::wallet_handler::get_fee_suggestion))
+        .route(
+            "/estimate-gas/:to/:amount",
+            get(handlers::wallet_handler::estimate_gas).route_layer(rate_limit()),
+        )
+        .route(
+            "/estimate-gas",
+            post(handlers::wallet_handler::estimate_call_gas).route_layer(rate_limit()),
+        )
         
         // Transaction endpoints
-        .route("/transaction/send", post(handlers::wallet_handler::send_transaction))
+        .route(
+            "/transaction/fee-estimate",
+            get(handlers::wallet_handler::estimate_fee).route_layer(rate_limit()),
+        )
+        .route("/transaction/history/:address", get(handlers::wallet_handler::get_transaction_history))
+        .route("/transaction/:hash", get(handlers::wallet_handler::get_transaction_status))
+        .route("/transaction/:hash/receipt", get(handlers::wallet_handler::get_transaction_receipt))
         
-        // Shared state
-        .with_state(app_state)
+        // Contract reads
+        .route("/contract/call", post(handlers::contract_handler::call_contract))
+        .route("/safe/:address", get(handlers::safe_handler::get_safe))
+        .route("/safe/:address/transactions", get(handlers::safe_handler::list_transactions))
+
+        // Raw JSON-RPC passthrough
+        .route("/rpc", post(handlers::rpc_handler::proxy_rpc))
+
+        // Offline utilities
+        .route("/utils/decode-transaction", post(handlers::utils_handler::decode_transaction))
+
+        .merge(signing_routes);
+
+    let router = match app_state.config.server.base_path() {
+        None => api_routes.merge(health_routes),
+        Some(base_path) if app_state.config.server.health_at_root => {
+            Router::new().nest(&base_path, api_routes).merge(health_routes)
+        }
+        Some(base_path) => Router::new().nest(&base_path, api_routes.merge(health_routes)),
+    };
+
+    #[cfg(feature = "fault-injection")]
+    let router = router.layer(axum::middleware::from_fn_with_state(
+        app_state.clone(),
+        middleware::fault_injection::inject_faults,
+    ));
+
+    let router = router.layer(axum::middleware::from_fn(middleware::request_span::request_span));
+
+    let router = match cors {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+
+    // Shared state
+    Ok(router.with_state(app_state))
+}
+
+/// Cache of the first `wallet.preload_accounts` HD addresses of the imported mnemonic
+fn preload_hd_addresses(wallet_service: &WalletService, seed: &[u8], config: &WalletConfig) -> AppResult<HdAddressCache> {
+    let cache = HdAddressCache::default();
+    // Indexes past max_account_index are refused anyway
+    let count = config.preload_accounts.min(config.max_account_index.saturating_add(1));
+    if count < config.preload_accounts {
+        warn!("wallet.preload_accounts capped at {} by wallet.max_account_index", count);
+    }
+    if count == 0 {
+        return Ok(cache);
+    }
+
+    let started = Instant::now();
+    cache.preload(wallet_service, seed, &config.derivation_path, count)?;
+    info!("Derived {} HD account(s) in {:?}", count, started.elapsed());
+    Ok(cache)
+}
+
+/// Rolling-hour spend cap from `security.max_eth_per_hour`
+fn spend_limiter(config: &SecurityConfig) -> AppResult<SpendLimiter> {
+    let max_wei = config
+        .max_eth_per_hour
+        .map(|eth| utils::parse_units(&eth.to_string(), utils::NATIVE_DECIMALS))
+        .transpose()
+        .map_err(|e| errors::AppError::ConfigurationError(format!("security.max_eth_per_hour: {}", e)))?;
+    if let Some(max_eth) = config.max_eth_per_hour {
+        info!("Outgoing value limited to {} per rolling hour", max_eth);
+    }
+    Ok(SpendLimiter::new(max_wei, config.spend_window_file.clone()))
+}
+
+/// CORS policy for the configured origins; `None` (no CORS headers) when none are configured
+fn cors_layer(config: &CorsConfig) -> AppResult<Option<CorsLayer>> {
+    if config.allowed_origins.is_empty() {
+        return Ok(None);
+    }
+
+    let allow_origin = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        warn!("CORS allows any origin; restrict cors.allowed_origins outside development");
+        AllowOrigin::any()
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin).map_err(|_| {
+                    errors::AppError::ConfigurationError(format!("Invalid CORS origin: {}", origin))
+                })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([
+                header::CONTENT_TYPE,
+                header::AUTHORIZATION,
+                HeaderName::from_static(middleware::auth::API_KEY_HEADER),
+                HeaderName::from_static(handlers::rpc_override::RPC_URL_HEADER),
+            ]),
+    ))
 }
 
 async fn start_server(app: Router, config: &AppConfig) -> AppResult<()> {
     let server_addr = config.server_address();
+    let tls_config = match &config.server.tls {
+        Some(tls) => Some(load_tls_config(tls).await?),
+        None => None,
+    };
+    let scheme = if tls_config.is_some() { "https" } else { "http" };
     
-    info!("Server starting on http://{}", server_addr);
+    info!("Server starting on {}://{}", scheme, server_addr);
+    if let Some(base_path) = config.server.base_path() {
+        info!("Routes are mounted under {}", base_path);
+        if config.server.health_at_root {
+            info!("  (/, /health and /metrics stay at the root)");
+        }
+    }
     info!("Available endpoints:");
-    info!("  GET  /              - Health check");
-    info!("  GET  /health        - Health check");
+    info!("  GET  /              - Liveness check");
+    info!("  GET  /health        - Readiness check (503 while the node is unreachable)");
     info!("  GET  /network       - Network information");
+    info!("  GET  /networks      - Configured networks and their status");
+    info!("  GET  /blocks/stream - New block headers as Server-Sent Events (WebSocket node only)");
+    info!("  GET  /metrics       - Service metrics");
     info!("  GET  /account       - Account information");
+    info!("  GET  /account/qr    - Wallet address as a PNG QR code");
+    info!("  GET  /account/mempool - Node mempool entries from the wallet");
+    info!("  POST /account/fill-gap/:nonce - Unstick a nonce gap with a self-transfer");
+    info!("  GET  /accounts      - Primary and managed accounts (public data only)");
+    info!("  POST /accounts      - Create, import or watch a managed account");
+    info!("  POST /accounts/import - Import a managed account from a private key or keystore");
+    info!("  POST /accounts/vanity - Create a managed account with a vanity address");
+    info!("  POST /account/vanity  - Alias of /accounts/vanity");
+    info!("  POST /accounts/vanity/stream - The same, with progress as Server-Sent Events");
+    info!("  DELETE /accounts/:address - Remove a managed account");
+    info!("  POST /accounts/:address/export - Account key as a passphrase-encrypted keystore (disabled by default)");
+    info!("  POST /sign          - Sign a message (EIP-191 personal_sign)");
+    info!("  POST /sign/typed-data - Sign EIP-712 typed data");
+    info!("  POST /account/export - Export the wallet's secret key (security.allow_key_export)");
+    info!("  POST /account/backup/shares - Split the wallet key into Shamir backup shares (security.allow_key_export)");
+    info!("  POST /wallet/import - Import wallet from a BIP-39 mnemonic");
+    info!("  POST /wallet/restore - Restore the wallet from Shamir backup shares");
+    info!("  GET  /wallet/derive/:index - Address of HD account n of the imported mnemonic");
+    info!("  GET  /wallet/derive      - Addresses of every HD account up to wallet.max_account_index");
     info!("  GET  /balance       - Wallet balance");
     info!("  GET  /balance/:addr - Balance for any address");
-    info!("  GET  /gas-price     - Current gas price");
-    info!("  GET  /estimate-gas/:to/:amount - Estimate gas for transaction");
+    info!("  POST /balance/batch - Balances of up to server.max_balance_batch_size addresses");
+    info!("  GET  /balance/:addr/at/:block - Balance at a block number or tag");
+    info!("  GET  /ens/:name     - ENS address, text records and reverse record");
+    info!("  GET  /token/:contract/balance/:addr - ERC-20 token balance");
+    info!("  POST /token/:contract/transfer - Send ERC-20 tokens");
+    info!("  POST /token/:contract/permit - Sign an EIP-2612 permit for a relayer");
+    info!("  GET  /gas-price     - Current gas price and EIP-1559 fee suggestions");
+    info!("  GET  /fees          - Gas price and priority fee suggestion");
+    info!("  POST /estimate-gas  - Estimate gas for a transfer or contract call");
+    info!("  GET  /estimate-gas/:to/:amount - Estimate gas for a transfer (deprecated)");
+    info!("  GET  /transaction/fee-estimate?to=&amount= - Total fee and whether the wallet can afford it");
     info!("  POST /transaction/send - Send transaction");
+    info!("  POST /transaction/batch - Send several transactions with sequential nonces");
+    info!("  POST /watch         - Webhook on transactions to an address (WebSocket node only)");
+    info!("  DELETE /watch/:id   - Stop a watch");
+    info!("  GET  /transaction/history/:address - Transactions from or to an address in a block range");
+    info!("  GET  /transaction/:hash - Transaction status (pending/confirmed/failed)");
+    info!("  GET  /transaction/:hash/receipt - Transaction receipt and confirmations");
+    info!("  POST /contract/call - eth_call with client-encoded calldata");
+    info!("  GET  /safe/:address - Safe owners, threshold and nonce");
+    info!("  GET  /safe/:address/transactions - Proposed Safe transactions and their confirmations");
+    info!("  POST /safe/:address/transactions - Propose and confirm a Safe transaction");
+    info!("  POST /safe/:address/transactions/:hash/confirmations - Add an owner confirmation");
+    info!("  POST /safe/:address/transactions/:hash/execute - Execute a confirmed Safe transaction");
+    info!("  POST /rpc           - Filtered JSON-RPC passthrough");
+    info!("  POST /utils/decode-transaction - Decode a raw signed transaction");
 
-    axum::Server::bind(&server_addr.parse().unwrap())
-        .serve(app.into_make_service())
-        .await
-        .map_err(|e| errors::AppError::InternalError(format!("Server error: {}", e)))?;
+    let socket_addr: SocketAddr = server_addr.parse().unwrap();
+    let listener = std::net::TcpListener::bind(socket_addr)
+        .map_err(|e| errors::AppError::InternalError(format!("Failed to bind {}: {}", socket_addr, e)))?;
+    serve(app, listener, tls_config, shutdown_signal()).await?;
 
+    info!("Server stopped");
     Ok(())
 }
+
+/// Serve `app` on `listener` until `shutdown` completes, then give in-flight requests up to
+/// `SHUTDOWN_DRAIN_TIMEOUT` to finish
+async fn serve(
+    app: Router,
+    listener: std::net::TcpListener,
+    tls_config: Option<RustlsConfig>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> AppResult<()> {
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown.await;
+            info!(
+                "Shutdown initiated; draining in-flight requests for up to {}s",
+                SHUTDOWN_DRAIN_TIMEOUT.as_secs()
+            );
+            handle.graceful_shutdown(Some(SHUTDOWN_DRAIN_TIMEOUT));
+        }
+    });
+
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let served = match tls_config {
+        Some(tls_config) => {
+            axum_server::from_tcp_rustls(listener, tls_config)
+                .handle(handle)
+                .serve(make_service)
+                .await
+        }
+        None => axum_server::from_tcp(listener).handle(handle).serve(make_service).await,
+    };
+    served.map_err(|e| errors::AppError::InternalError(format!("Server error: {}", e)))
+}
+
+/// Completes on Ctrl-C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+async fn load_tls_config(tls: &TlsConfig) -> AppResult<RustlsConfig> {
+    let read = |path: &str| {
+        std::fs::read(path)
+            .map_err(|e| errors::AppError::ConfigurationError(format!("Failed to read TLS file '{}': {}", path, e)))
+    };
+    let server_config = tls::server_config(&read(&tls.cert_path)?, &read(&tls.key_path)?).map_err(|e| {
+        errors::AppError::ConfigurationError(format!(
+            "Failed to load TLS certificate '{}' / key '{}': {}",
+            tls.cert_path, tls.key_path, e
+        ))
+    })?;
+
+    info!("TLS certificate loaded from: {}", tls.cert_path);
+    Ok(RustlsConfig::from_config(Arc::new(server_config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, HttpBody};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    #[test]
+    fn reconnect_delay_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=8).map(|attempt| reconnect_delay(attempt).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(reconnect_delay(u32::MAX), RECONNECT_MAX_DELAY);
+    }
+
+    /// Collects what a subscriber writes
+    struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Output of one event logged inside a request span
+    fn logged_request_event(json: bool) -> String {
+        let buffer = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let writer = buffer.clone();
+        let subscriber = log_subscriber(json, EnvFilter::new("info"), move || LogBuffer(writer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", method = "POST", path = "/transaction/send", request_id = "req-42");
+            let _entered = span.enter();
+            info!(nonce = 7, "Transaction sent");
+            tracing::debug!("below the filter");
+        });
+        let output = buffer.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn json_logs_are_one_object_per_line_with_request_fields() {
+        let output = logged_request_event(true);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1, "{}", output);
+
+        let event: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(event["level"], "INFO");
+        assert_eq!(event["fields"]["message"], "Transaction sent");
+        assert_eq!(event["fields"]["nonce"], 7);
+        assert_eq!(event["span"]["name"], "request");
+        assert_eq!(event["span"]["method"], "POST");
+        assert_eq!(event["span"]["path"], "/transaction/send");
+        assert_eq!(event["span"]["request_id"], "req-42");
+    }
+
+    #[test]
+    fn pretty_logs_are_plain_text() {
+        let output = logged_request_event(false);
+        assert_eq!(output.lines().count(), 1, "{}", output);
+        assert!(serde_json::from_str::<serde_json::Value>(&output).is_err(), "{}", output);
+        assert!(output.contains("Transaction sent"), "{}", output);
+        assert!(output.contains("req-42"), "{}", output);
+    }
+
+    /// The full router over a fresh test state, without a node or wallet
+    async fn router(config: AppConfig) -> Router {
+        let state = AppState::for_tests(config);
+        create_router(
+            state.wallet_service,
+            state.web3_service,
+            state.account,
+            None,
+            state.account_store,
+            state.watches,
+            state.config,
+        )
+        .await
+        .unwrap()
+    }
+
+    fn sign_request(auth: Option<(&str, &str)>) -> Request<Body> {
+        let mut request = Request::post("/sign").header(header::CONTENT_TYPE, "application/json");
+        if let Some((name, value)) = auth {
+            request = request.header(name, value);
+        }
+        request.body(Body::from(r#"{"message":"hello"}"#)).unwrap()
+    }
+
+    async fn json_body(response: axum::response::Response) -> serde_json::Value {
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn with_api_key(api_key: &str) -> AppConfig {
+        let mut config = AppConfig::default();
+        config.server.api_key = Some(api_key.to_string());
+        config
+    }
+
+    #[tokio::test]
+    async fn signing_routes_require_the_api_key() {
+        let router = router(with_api_key("s3cret")).await;
+
+        for auth in [None, Some(("authorization", "Bearer wrong")), Some(("x-api-key", "s3cre"))] {
+            let response = router.clone().oneshot(sign_request(auth)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{:?}", auth);
+            let body = json_body(response).await;
+            assert_eq!(body["error"], "UNAUTHORIZED");
+            assert_eq!(body["code"], 401);
+        }
+    }
+
+    #[tokio::test]
+    async fn the_right_api_key_gets_through() {
+        let router = router(with_api_key("s3cret")).await;
+
+        for auth in [("authorization", "Bearer s3cret"), ("x-api-key", "s3cret")] {
+            let response = router.clone().oneshot(sign_request(Some(auth))).await.unwrap();
+            // Past the key check, on to a server without a wallet
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE, "{:?}", auth);
+            assert_eq!(json_body(response).await["error"], "WALLET_UNAVAILABLE");
+        }
+    }
+
+    #[tokio::test]
+    async fn health_and_reads_stay_open() {
+        let router = router(with_api_key("s3cret")).await;
+
+        let response = router.clone().oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router.oneshot(Request::get("/account").body(Body::empty()).unwrap()).await.unwrap();
+        assert_ne!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn without_an_api_key_nothing_is_checked() {
+        let router = router(AppConfig::default()).await;
+
+        let response = router.oneshot(sign_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    fn with_cors_origins(origins: &[&str]) -> AppConfig {
+        let mut config = with_api_key("s3cret");
+        config.cors.allowed_origins = origins.iter().map(|origin| origin.to_string()).collect();
+        config
+    }
+
+    fn from_origin(origin: &str) -> Request<Body> {
+        Request::get("/").header(header::ORIGIN, origin).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn cors_allows_only_the_configured_origins() {
+        let router = router(with_cors_origins(&["https://app.example"])).await;
+
+        let response = router.clone().oneshot(from_origin("https://app.example")).await.unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example");
+        let response = router.oneshot(from_origin("https://evil.example")).await.unwrap();
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn cors_is_off_by_default() {
+        let router = router(AppConfig::default()).await;
+
+        let response = router.oneshot(from_origin("https://app.example")).await.unwrap();
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn cors_wildcard_allows_any_origin() {
+        let router = router(with_cors_origins(&["*"])).await;
+
+        let response = router.oneshot(from_origin("https://anywhere.example")).await.unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    }
+
+    #[tokio::test]
+    async fn preflight_for_a_send_is_answered_without_the_api_key() {
+        let router = router(with_cors_origins(&["https://app.example"])).await;
+
+        let preflight = Request::options("/transaction/send")
+            .header(header::ORIGIN, "https://app.example")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type,x-api-key")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(preflight).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example");
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_METHODS].to_str().unwrap().contains("POST"));
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap().contains("x-api-key"));
+    }
+
+    #[tokio::test]
+    async fn malformed_origins_are_a_configuration_error() {
+        let error = cors_layer(&CorsConfig { allowed_origins: vec!["https://bad\norigin".to_string()] }).unwrap_err();
+        assert!(matches!(error, errors::AppError::ConfigurationError(_)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn shutdown_lets_in_flight_requests_finish() {
+        let app = Router::new().route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                "done"
+            }),
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/slow", listener.local_addr().unwrap());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(app, listener, None, async {
+            stopped.await.ok();
+        }));
+
+        let request = tokio::spawn(reqwest::get(url));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        stop.send(()).unwrap();
+
+        let response = request.await.unwrap().unwrap();
+        assert_eq!(response.text().await.unwrap(), "done");
+        let served = tokio::time::timeout(Duration::from_secs(5), server).await.expect("server kept running");
+        assert!(served.unwrap().is_ok());
+    }
+
+    /// A config as loaded from files and `APP_*` variables, with every CLI-settable value changed
+    fn loaded_config() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.ethereum.rpc_url = "wss://from-env.example".to_string();
+        config.server.port = 4000;
+        config.server.host = "10.0.0.1".to_string();
+        config.ethereum.network_id = 5;
+        config.wallet.config_file = "env_wallet.json".to_string();
+        config
+    }
+
+    #[test]
+    fn cli_flags_win_over_loaded_values() {
+        let mut config = loaded_config();
+        let args = CliArgs::try_parse_from([
+            "server",
+            "--rpc-url",
+            "http://127.0.0.1:8545",
+            "--port",
+            "8080",
+            "--host",
+            "127.0.0.1",
+            "--network-id",
+            "31337",
+            "--wallet-file",
+            "cli_wallet.json",
+        ])
+        .unwrap();
+
+        args.apply(&mut config);
+        assert_eq!(config.ethereum.rpc_url, "http://127.0.0.1:8545");
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.ethereum.network_id, 31337);
+        assert_eq!(config.wallet.config_file, "cli_wallet.json");
+    }
+
+    #[test]
+    fn missing_flags_leave_loaded_values_alone() {
+        let mut config = loaded_config();
+        CliArgs::try_parse_from(["server", "--port", "8080"]).unwrap().apply(&mut config);
+
+        assert_eq!(config.server.port, 8080);
+        let loaded = loaded_config();
+        assert_eq!(config.ethereum.rpc_url, loaded.ethereum.rpc_url);
+        assert_eq!(config.server.host, loaded.server.host);
+        assert_eq!(config.ethereum.network_id, loaded.ethereum.network_id);
+        assert_eq!(config.wallet.config_file, loaded.wallet.config_file);
+    }
+
+    #[test]
+    fn malformed_flags_are_refused() {
+        assert!(CliArgs::try_parse_from(["server", "--port", "eighty"]).is_err());
+        assert!(CliArgs::try_parse_from(["server", "--network-id", "-1"]).is_err());
+        assert!(CliArgs::try_parse_from(["server", "--verbose"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn error_bodies_carry_the_request_id_from_the_header() {
+        let router = router(AppConfig::default()).await;
+
+        let mut request = sign_request(None);
+        request.headers_mut().insert("x-request-id", header::HeaderValue::from_static("client-7"));
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["x-request-id"], "client-7");
+        let body = json_body(response).await;
+        assert_eq!(body["error"], "WALLET_UNAVAILABLE");
+        assert_eq!(body["request_id"], "client-7");
+    }
+
+    #[tokio::test]
+    async fn requests_without_an_id_get_a_generated_one() {
+        let router = router(with_api_key("s3cret")).await;
+        let too_long = "x".repeat(129);
+
+        for supplied in [None, Some(too_long.as_str())] {
+            let mut request = sign_request(None);
+            if let Some(id) = supplied {
+                request.headers_mut().insert("x-request-id", header::HeaderValue::from_str(id).unwrap());
+            }
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let header = response.headers()["x-request-id"].to_str().unwrap().to_string();
+            assert!(uuid::Uuid::parse_str(&header).is_ok(), "{}", header);
+            assert_eq!(json_body(response).await["request_id"], header.as_str());
+        }
+
+        // Successful responses are tagged too
+        let response = router.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().contains_key("x-request-id"));
+    }
+
+    #[tokio::test]
+    async fn the_account_vanity_alias_still_requires_the_api_key() {
+        let router = router(with_api_key("s3cret")).await;
+        let vanity_request = |api_key: Option<&str>| {
+            let mut request = Request::post("/account/vanity").header(header::CONTENT_TYPE, "application/json");
+            if let Some(api_key) = api_key {
+                request = request.header("x-api-key", api_key);
+            }
+            request.body(Body::from(r#"{"prefix":"0"}"#)).unwrap()
+        };
+
+        let response = router.clone().oneshot(vanity_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router.oneshot(vanity_request(Some("s3cret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json_body(response).await;
+        let address = body["data"]["account"]["address"].as_str().unwrap();
+        assert!(address.starts_with("0x0"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn backup_shares_restore_the_same_wallet() {
+        let mut config = with_api_key("s3cret");
+        config.security.allow_key_export = true;
+        let state = AppState::for_tests(config);
+        let account = state.wallet_service.create_account().unwrap();
+        let address = account.public_address.clone();
+        *state.account.write().await = Some(account);
+        let router = create_router(
+            state.wallet_service,
+            state.web3_service,
+            state.account.clone(),
+            None,
+            state.account_store,
+            state.watches,
+            state.config,
+        )
+        .await
+        .unwrap();
+        let post = |path: &str, body: serde_json::Value| {
+            Request::post(path)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("x-api-key", "s3cret")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(post("/account/backup/shares", serde_json::json!({ "threshold": 2, "shares": 3 })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let backup = json_body(response).await;
+        assert_eq!(backup["data"]["address"], address.as_str());
+        let shares = backup["data"]["shares"].as_array().unwrap().clone();
+        assert_eq!(shares.len(), 3);
+
+        // Lose the wallet, then restore it from two of the three shares
+        *state.account.write().await = None;
+        let response = router
+            .oneshot(post("/wallet/restore", serde_json::json!({ "shares": [shares[2], shares[0]] })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let restored = json_body(response).await;
+        assert_eq!(restored["data"]["account"]["address"], address.as_str());
+        assert_eq!(state.account.read().await.as_ref().unwrap().public_address, address);
+    }
+
+    #[tokio::test]
+    async fn derives_hd_addresses_of_the_imported_mnemonic() {
+        let mut config = AppConfig::default();
+        config.wallet.preload_accounts = 2;
+        let state = AppState::for_tests(config);
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = state.wallet_service.mnemonic_to_seed(mnemonic, None).unwrap();
+        let router = create_router(
+            state.wallet_service,
+            state.web3_service,
+            state.account,
+            Some(seed),
+            state.account_store,
+            state.watches,
+            state.config,
+        )
+        .await
+        .unwrap();
+        let get = |path: &str| router.clone().oneshot(Request::get(path).body(Body::empty()).unwrap());
+
+        let response = get("/wallet/derive/0").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json_body(response).await;
+        assert_eq!(body["data"]["index"], 0);
+        assert_eq!(body["data"]["derivation_path"], "m/44'/60'/0'/0/0");
+        assert_eq!(body["data"]["address"], "0x9858EfFD232B4033E47d90003D41EC34EcaEda94");
+
+        // Past the preloaded accounts, derived on demand
+        let body = json_body(get("/wallet/derive/5").await.unwrap()).await;
+        assert_eq!(body["data"]["derivation_path"], "m/44'/60'/0'/0/5");
+
+        for path in ["/wallet/derive/20", "/wallet/derive/-1", "/wallet/derive/one"] {
+            assert_eq!(get(path).await.unwrap().status(), StatusCode::BAD_REQUEST, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn deriving_needs_an_imported_mnemonic() {
+        let response = router(AppConfig::default())
+            .await
+            .oneshot(Request::get("/wallet/derive/0").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = json_body(response).await;
+        assert_eq!(body["error"], "VALIDATION_ERROR");
+        assert!(body["message"].as_str().unwrap().contains("imported from a mnemonic"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn decoding_a_malformed_transaction_is_a_bad_request() {
+        let router = router(AppConfig::default()).await;
+        let decode = |raw: &str| {
+            let request = Request::post("/utils/decode-transaction")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::json!({ "raw": raw }).to_string()))
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+
+        for raw in ["", "0xzz", "0x03f8", "0xc88080808080808080"] {
+            let response = decode(raw).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", raw);
+            assert_eq!(json_body(response).await["error"], "VALIDATION_ERROR");
+        }
+
+        let response = decode("0x02f87301078459682f008509502f900082ea6094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca0084a9059cbbc001a0fa5d0561442c2f6fcf396d6698401dae89dabb0a208569e51bd5d4fe70d42bb5a0332216c4a95c2e7ecc020f168adda3affaa7ac6308630f2c1ab786fe247015b5").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json_body(response).await["data"]["from"], "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23");
+    }
+}
@@ -0,0 +1,48 @@
+//! API key check for routes that sign with the wallet key.
+
+use crate::errors::AppError;
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{header, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+/// Alternative to `Authorization: Bearer <key>`
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Reject requests without the configured `server.api_key`; a no-op when no key is set
+pub async fn require_api_key<B>(
+    State(state): State<AppState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(expected) = state.config.server.api_key.as_deref() else {
+        return next.run(request).await;
+    };
+
+    let headers = request.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let api_key = headers.get(API_KEY_HEADER).and_then(|value| value.to_str().ok());
+
+    match bearer.or(api_key) {
+        Some(provided) if constant_time_eq(provided.trim().as_bytes(), expected.as_bytes()) => {
+            next.run(request).await
+        }
+        Some(_) => {
+            warn!("Rejected {} {}: invalid API key", request.method(), request.uri().path());
+            AppError::Unauthorized("Invalid API key".to_string()).into_response()
+        }
+        None => AppError::Unauthorized("Missing API key".to_string()).into_response(),
+    }
+}
+
+/// Compare without short-circuiting, so response timing doesn't reveal how much of the key matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,34 @@
+//! Random failure injection for exercising client retry logic.
+//! Only compiled with the `fault-injection` feature so it can never be
+//! switched on in a production build through configuration alone.
+
+use crate::errors::AppError;
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{Method, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+/// Liveness probes are never failed, so orchestrators don't restart the instance
+const EXEMPT_PATHS: [&str; 2] = ["/", "/health"];
+
+/// Fail a `testing.fail_rate` fraction of read requests with `Web3NotAvailable`
+pub async fn inject_faults<B>(
+    State(state): State<AppState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let fail_rate = state.config.testing.fail_rate;
+    let is_read = request.method() == Method::GET;
+    let exempt = EXEMPT_PATHS.contains(&request.uri().path());
+
+    if is_read && !exempt && rand::random::<f64>() < fail_rate {
+        warn!("Injecting failure for GET {}", request.uri().path());
+        return AppError::Web3NotAvailable.into_response();
+    }
+
+    next.run(request).await
+}
@@ -0,0 +1,5 @@
+pub mod auth;
+pub mod rate_limit;
+pub mod request_span;
+#[cfg(feature = "fault-injection")]
+pub mod fault_injection;
@@ -0,0 +1,190 @@
+//! Per-client-IP token bucket limiting for routes that reach the node on every call.
+
+use crate::errors::AppError;
+use crate::state::AppState;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::warn;
+
+/// Past this many tracked clients, buckets that have refilled completely are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token buckets holding `per_minute` requests per client, refilled continuously
+pub struct RateLimiter {
+    per_minute: u32,
+    /// Peers whose `X-Forwarded-For` names the client
+    trusted_proxies: Vec<IpAddr>,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// `per_minute = 0` disables limiting
+    pub fn new(per_minute: u32, trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            per_minute,
+            trusted_proxies,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The peer address, or behind trusted proxies the `X-Forwarded-For` hop they received the request
+    /// from. Hops are read from the right, so entries a client adds itself are never reached.
+    fn client_ip<B>(&self, request: &Request<B>) -> Option<IpAddr> {
+        let ConnectInfo(peer) = request.extensions().get::<ConnectInfo<SocketAddr>>()?;
+        let hops: Vec<&str> = request
+            .headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+
+        let mut client = peer.ip();
+        for hop in hops.iter().rev() {
+            if !self.trusted_proxies.contains(&client) {
+                break;
+            }
+            match hop.trim().parse() {
+                Ok(ip) => client = ip,
+                Err(_) => break,
+            }
+        }
+        Some(client)
+    }
+
+    /// Take one token for `client`, or return the seconds until one is available
+    fn acquire(&self, client: IpAddr) -> Result<(), u64> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+
+        let capacity = self.per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated_at).as_secs_f64() * refill_per_sec < capacity
+            });
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket { tokens: capacity, updated_at: now });
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / refill_per_sec).ceil() as u64)
+        }
+    }
+}
+
+/// Answer 429 with `Retry-After` once a client exceeds `server.rate_limit_per_minute`
+pub async fn limit_by_ip<B>(
+    State(state): State<AppState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(client) = state.rate_limiter.client_ip(&request) else {
+        return next.run(request).await;
+    };
+
+    match state.rate_limiter.acquire(client) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after_secs) => {
+            warn!("Rate limit exceeded by {} on {}", client, request.uri().path());
+            AppError::RateLimited(retry_after_secs).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::{body::Body, http::StatusCode, routing::get, Router};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    const PEER: &str = "203.0.113.7:52000";
+    const PROXY: &str = "10.0.0.5:40000";
+
+    fn request(peer: &str, forwarded_for: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder().uri("/limited");
+        if let Some(forwarded_for) = forwarded_for {
+            request = request.header("x-forwarded-for", forwarded_for);
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        request
+    }
+
+    fn limiter(trusted_proxies: &[&str]) -> RateLimiter {
+        RateLimiter::new(3, trusted_proxies.iter().map(|ip| ip.parse().unwrap()).collect())
+    }
+
+    #[tokio::test]
+    async fn rejects_the_request_past_the_budget() {
+        let mut state = AppState::for_tests(AppConfig::default());
+        state.rate_limiter = Arc::new(limiter(&[]));
+        let router = Router::new()
+            .route("/limited", get(|| async { "ok" }))
+            .route_layer(axum::middleware::from_fn_with_state(state.clone(), limit_by_ip))
+            .with_state(state);
+
+        for _ in 0..3 {
+            let response = router.clone().oneshot(request(PEER, None)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = router.clone().oneshot(request(PEER, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+
+        // A spoofed X-Forwarded-For from an untrusted peer does not buy a fresh bucket
+        let response = router.oneshot(request(PEER, Some("198.51.100.1"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn ignores_forwarded_for_from_untrusted_peers() {
+        let limiter = limiter(&["10.0.0.5"]);
+        let client = limiter.client_ip(&request(PEER, Some("198.51.100.1")));
+        assert_eq!(client, Some("203.0.113.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn takes_the_hop_a_trusted_proxy_received_from() {
+        let limiter = limiter(&["10.0.0.5", "10.0.0.6"]);
+
+        let client = limiter.client_ip(&request(PROXY, Some("198.51.100.1")));
+        assert_eq!(client, Some("198.51.100.1".parse().unwrap()));
+
+        // The client prepended its own entry; the proxies appended the real one
+        let client = limiter.client_ip(&request(PROXY, Some("192.0.2.99, 198.51.100.1, 10.0.0.6")));
+        assert_eq!(client, Some("198.51.100.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn falls_back_to_the_proxy_on_a_malformed_hop() {
+        let limiter = limiter(&["10.0.0.5"]);
+        let client = limiter.client_ip(&request(PROXY, Some("not-an-ip")));
+        assert_eq!(client, Some("10.0.0.5".parse().unwrap()));
+    }
+}
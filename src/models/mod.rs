@@ -1,11 +1,38 @@
-use serde::{Deserialize, Serialize};
+use crate::address::ChecksumAddress;
+use crate::errors::{AppError, AppResult};
+use crate::secret::SecretString;
+use crate::utils;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::str::FromStr;
+use web3::types::U256;
 
-// Account model
-#[derive(Serialize, Deserialize, Debug, Clone)]
+// Account model. Deliberately not `Serialize` so the secret key cannot end up in a response;
+// plaintext wallet files are written through `WalletService::save_account`.
+#[derive(Debug, Deserialize, Clone)]
 pub struct Account {
-    pub secret_key: String,
+    /// Empty for watch-only accounts, as is `public_key`
+    #[serde(default)]
+    pub secret_key: SecretString,
+    #[serde(default)]
     pub public_key: String,
     pub public_address: String,
+    /// BIP-32 path the key was derived at, for accounts restored from a mnemonic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation_path: Option<String>,
+    #[serde(default)]
+    pub kind: AccountKind,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    /// Holds a key and can sign
+    #[default]
+    Full,
+    /// Address only: balances and history can be queried, but nothing can be signed
+    WatchOnly,
+    /// Key split across MPC co-signers (`mpc.address`): transactions are signed, other signatures are not
+    Mpc,
 }
 
 // API Request/Response models
@@ -19,43 +46,673 @@ pub struct ApiResponse<T> {
 #[derive(Serialize)]
 pub struct AccountInfo {
     pub public_key: String,
+    pub public_key_uncompressed: Option<String>,
     pub address: String,
+    /// EIP-55 mixed-case form of `address`
+    pub address_checksum: String,
+    pub derivation_path: Option<String>,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+#[derive(Deserialize)]
+pub struct CreateAccountRequest {
+    /// Hex private key to import; a new key is generated when omitted
+    pub private_key: Option<SecretString>,
+    /// Register this address as a watch-only account instead
+    pub address: Option<String>,
+}
+
+/// Body of `POST /accounts/import`: a hex private key, or a v3 keystore with its passphrase
+#[derive(Deserialize)]
+pub struct AccountImportRequest {
+    pub private_key: Option<SecretString>,
+    pub keystore: Option<serde_json::Value>,
+    pub passphrase: Option<SecretString>,
+}
+
+/// Public identity of a signing account; never includes key material
 #[derive(Serialize)]
-pub struct BalanceInfo {
+pub struct ManagedAccountInfo {
+    pub address: String,
+    /// `None` for watch-only accounts
+    pub public_key: Option<String>,
+    pub kind: AccountKind,
+    /// The wallet loaded at startup (or last imported), used when a request names no `from`
+    pub primary: bool,
+}
+
+#[derive(Deserialize)]
+pub struct AccountQuery {
+    /// Address of the account to act as instead of the primary one
+    pub from: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct WatchRequest {
+    /// Hex address or ENS name to watch for incoming transactions
+    pub address: String,
+    /// http(s) URL receiving a POST per matching transaction
+    pub callback_url: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct WatchInfo {
+    pub id: String,
+    pub address: ChecksumAddress,
+    pub callback_url: String,
+}
+
+/// Webhook payload for a transaction sent to a watched address
+#[derive(Serialize)]
+pub struct TransferNotification {
+    pub watch_id: String,
+    pub address: ChecksumAddress,
+    pub transaction_hash: String,
+    pub from: Option<ChecksumAddress>,
+    pub value_wei: String,
+    pub value_eth: f64,
+    pub block_number: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// First block scanned; defaults to the start of the largest allowed range ending at `to_block`
+    pub from_block: Option<u64>,
+    /// Last block scanned; defaults to the latest block
+    pub to_block: Option<u64>,
+    /// 1-based page of matching transactions
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
+
+/// A transaction sent from or to the queried address
+#[derive(Serialize)]
+pub struct HistoryTransaction {
+    pub transaction_hash: String,
+    pub block_number: Option<u64>,
+    pub from: Option<ChecksumAddress>,
+    /// None for contract creations
+    pub to: Option<ChecksumAddress>,
+    pub value_wei: String,
+    pub value_eth: f64,
+}
+
+/// One page of the transactions involving `address` in the scanned block range
+#[derive(Serialize)]
+pub struct TransactionHistory {
+    pub address: ChecksumAddress,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub page: usize,
+    pub page_size: usize,
+    /// Matching transactions in the whole range, across all pages
+    pub total: usize,
+    pub transactions: Vec<HistoryTransaction>,
+}
+
+#[derive(Deserialize)]
+pub struct VanityRequest {
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub suffix: String,
+    /// Give up after this many keypairs, in addition to the time limit
+    pub max_attempts: Option<u64>,
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct QrQuery {
+    /// Encode an EIP-681 payment request for this many ETH instead of the bare address
+    pub amount: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct WalletImportRequest {
+    pub mnemonic: SecretString,
+    pub passphrase: Option<SecretString>,
+    pub path: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportedAccountInfo {
+    pub account: AccountInfo,
+    pub derivation_path: String,
+    pub config_file: String,
+    /// Copy of the wallet file the imported key replaced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replaced_file: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DerivedAddress {
+    pub index: u32,
+    pub derivation_path: String,
     pub address: String,
+}
+
+#[derive(Deserialize)]
+pub struct SignMessageRequest {
+    pub message: String,
+}
+
+/// Body of `POST /account/export`; `confirm` must be exactly `KEY_EXPORT_CONFIRMATION`
+#[derive(Deserialize)]
+pub struct KeyExportRequest {
+    pub confirm: String,
+    /// Managed account to export instead of the primary one
+    pub from: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BackupSharesRequest {
+    /// Shares needed to restore the key
+    pub threshold: u8,
+    /// Shares to create
+    pub shares: u8,
+    /// Managed account to back up instead of the primary one
+    pub from: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct WalletRestoreRequest {
+    pub shares: Vec<SecretString>,
+}
+
+#[derive(Deserialize)]
+pub struct KeystoreExportRequest {
+    /// Passphrase the returned keystore is encrypted with
+    pub passphrase: SecretString,
+}
+
+#[derive(Serialize)]
+pub struct ExportedKey {
+    pub address: String,
+    pub secret_key: String,
+    /// BIP-32 path, for accounts restored from a mnemonic (the mnemonic itself is never stored)
+    pub derivation_path: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BackupShares {
+    pub address: String,
+    pub threshold: u8,
+    pub shares: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct RestoredAccountInfo {
+    pub account: AccountInfo,
+    pub config_file: String,
+}
+
+#[derive(Serialize)]
+pub struct SignedMessage {
+    pub address: String,
+    pub message: String,
+    pub signature: String,
+}
+
+#[derive(Serialize)]
+pub struct TypedDataSignature {
+    pub address: String,
+    /// EIP-712 digest that was signed
+    pub digest: String,
+    /// 65-byte `r || s || v` signature as hex
+    pub signature: String,
+}
+
+/// How far a streamed vanity search has got
+#[derive(Serialize)]
+pub struct VanityProgress {
+    pub attempts: u64,
+    pub elapsed_ms: u64,
+}
+
+/// A vanity address found by `POST /accounts/vanity` and stored as a managed account
+#[derive(Serialize)]
+pub struct ManagedVanityAccountInfo {
+    pub account: ManagedAccountInfo,
+    pub attempts: u64,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Serialize)]
+pub struct BalanceInfo {
+    pub address: ChecksumAddress,
     pub balance_wei: String,
     pub balance_eth: f64,
     pub network_id: u64,
 }
 
 #[derive(Deserialize)]
+pub struct BalanceBatchRequest {
+    /// Hex addresses or ENS names
+    pub addresses: Vec<String>,
+}
+
+/// One address of a balance batch, in request order; exactly one of `balance` and `error` is set
+#[derive(Serialize)]
+pub struct BalanceBatchEntry {
+    pub address: String,
+    pub balance: Option<BalanceInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TokenBalanceInfo {
+    pub contract: ChecksumAddress,
+    pub holder: ChecksumAddress,
+    pub balance_raw: String,
+    pub decimals: u8,
+    /// `balance_raw` scaled by `decimals`, as an exact decimal string
+    pub balance: String,
+    pub network_id: u64,
+}
+
+#[derive(Deserialize)]
+pub struct PermitRequest {
+    pub spender: String,
+    /// Decimal amount in token units, or "max" for an unlimited allowance
+    pub amount: String,
+    /// Unix time after which the permit is invalid; one hour from now when omitted
+    pub deadline: Option<u64>,
+}
+
+/// EIP-2612 permit signature with the values a relayer passes to `permit()`
+#[derive(Serialize)]
+pub struct PermitSignature {
+    pub token: ChecksumAddress,
+    pub owner: ChecksumAddress,
+    pub spender: ChecksumAddress,
+    pub value: String,
+    pub nonce: String,
+    pub deadline: u64,
+    pub v: u64,
+    pub r: String,
+    pub s: String,
+    /// EIP-712 digest that was signed
+    pub digest: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenTransferRequest {
+    pub to: String,
+    /// Decimal amount in token units, e.g. "12.5"; a string so no precision is lost
+    pub amount: String,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct TransactionRequest {
     pub to: String,
-    pub amount_eth: f64,
+    /// Amount in ETH as a decimal string ("0.1") or JSON integer, converted with exact 18-decimal math
+    #[serde(default, deserialize_with = "deserialize_decimal")]
+    pub amount_eth: Option<String>,
+    /// Exact amount in wei as an integer string; alternative to `amount_eth`
+    pub amount_wei: Option<String>,
     pub gas_price: Option<u64>,
     pub gas_limit: Option<u64>,
+    pub max_fee_per_gas: Option<u64>,
+    pub max_priority_fee_per_gas: Option<u64>,
+    /// Set `max_fee_per_gas` to this multiple of the current base fee plus the priority fee
+    pub max_fee_multiplier: Option<f64>,
+    /// Send from this HD account (`m/44'/60'/0'/0/<index>`) of the imported mnemonic
+    pub account_index: Option<u32>,
+    /// Send from this managed account (see `/accounts`) instead of the primary one
+    pub from: Option<String>,
+    /// Send at exactly this nonce instead of the next tracked one
+    pub nonce: Option<u64>,
+    /// Broadcast without first checking that the balance covers value plus maximum fee
+    #[serde(default)]
+    pub skip_balance_check: bool,
+    /// Build, check with `eth_call` and sign, but do not broadcast
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl TransactionRequest {
+    /// Value to send in wei; exactly one of `amount_eth` and `amount_wei` must be given
+    pub fn value_wei(&self) -> AppResult<U256> {
+        parse_amount(self.amount_eth.as_deref(), self.amount_wei.as_deref())?.ok_or_else(|| {
+            AppError::ValidationError("Specify exactly one of amount_eth and amount_wei".to_string())
+        })
+    }
+}
+
+/// Wei from an ETH decimal or a wei integer, `None` when neither is given
+fn parse_amount(amount_eth: Option<&str>, amount_wei: Option<&str>) -> AppResult<Option<U256>> {
+    match (amount_eth, amount_wei) {
+        (Some(eth), None) => utils::parse_units(eth, utils::NATIVE_DECIMALS)
+            .map(Some)
+            .map_err(AppError::ValidationError),
+        (None, Some(wei)) => U256::from_dec_str(wei.trim())
+            .map(Some)
+            .map_err(|_| AppError::ValidationError(format!("Invalid amount_wei: {}", wei))),
+        (None, None) => Ok(None),
+        (Some(_), Some(_)) => Err(AppError::ValidationError(
+            "Specify exactly one of amount_eth and amount_wei".to_string(),
+        )),
+    }
+}
+
+/// A transaction for a Safe, proposed and first confirmed by the wallet
+#[derive(Deserialize)]
+pub struct SafeTransactionRequest {
+    pub to: String,
+    /// Value the Safe sends, in ETH; defaults to 0
+    #[serde(default, deserialize_with = "deserialize_decimal")]
+    pub amount_eth: Option<String>,
+    /// Value in wei; alternative to `amount_eth`
+    pub amount_wei: Option<String>,
+    /// Hex calldata
+    pub data: Option<String>,
+    /// 0 for a call (default), 1 for a delegatecall
+    pub operation: Option<u8>,
+    /// Safe nonce; defaults to the next one after the Safe's nonce and any queued proposals
+    pub nonce: Option<u64>,
+}
+
+impl SafeTransactionRequest {
+    /// Value the Safe sends in wei; at most one of `amount_eth` and `amount_wei` may be given
+    pub fn value_wei(&self) -> AppResult<U256> {
+        parse_amount(self.amount_eth.as_deref(), self.amount_wei.as_deref()).map(Option::unwrap_or_default)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SafeConfirmationRequest {
+    /// Another owner's 65-byte `r || s || v` signature of the Safe transaction hash, as hex.
+    /// Without it the wallet confirms with its own key.
+    pub signature: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SafeInfo {
+    pub address: ChecksumAddress,
+    pub owners: Vec<ChecksumAddress>,
+    pub threshold: u64,
+    pub nonce: String,
 }
 
 #[derive(Serialize)]
+pub struct SafeConfirmation {
+    pub owner: ChecksumAddress,
+    pub signature: String,
+}
+
+#[derive(Serialize)]
+pub struct SafeTransactionInfo {
+    pub safe: ChecksumAddress,
+    pub safe_tx_hash: String,
+    pub to: ChecksumAddress,
+    pub value_wei: String,
+    pub data: String,
+    pub operation: u8,
+    pub nonce: String,
+    /// Signatures by current owners, in the order `execTransaction` needs them
+    pub confirmations: Vec<SafeConfirmation>,
+    pub confirmations_required: u64,
+    pub proposed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Accept a decimal amount as a string, or a whole amount as a JSON integer. Fractional JSON numbers are
+/// refused: serde_json reads them as f64, so e.g. 0.1 would already have lost its exact value.
+fn deserialize_decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Decimal {
+        Text(String),
+        Number(serde_json::Number),
+    }
+
+    match Option::<Decimal>::deserialize(deserializer)? {
+        Some(Decimal::Text(text)) => Ok(Some(text)),
+        Some(Decimal::Number(number)) => match number.as_u64() {
+            Some(integer) => Ok(Some(integer.to_string())),
+            None => Err(serde::de::Error::custom(format!(
+                "amount {} must be a decimal string, e.g. \"{}\", to keep its exact value",
+                number, number
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BatchTransactionRequest {
+    pub transactions: Vec<TransactionRequest>,
+    /// Fetch fees once and price every transaction with them; per-transaction fee fields are then rejected
+    #[serde(default)]
+    pub shared_fees: bool,
+}
+
+/// Transactions are sent in order and the batch stops at the first failure
+#[derive(Serialize)]
+pub struct BatchTransactionResult {
+    pub sent: Vec<TransactionInfo>,
+    pub failed: Option<BatchFailure>,
+}
+
+#[derive(Serialize)]
+pub struct BatchFailure {
+    pub index: usize,
+    pub error: String,
+    /// The failed transaction may still have reached the node; look up its hash before resending
+    pub possibly_sent: bool,
+}
+
+#[derive(Serialize, Clone)]
 pub struct TransactionInfo {
     pub transaction_hash: String,
-    pub from: String,
+    pub from: ChecksumAddress,
+    /// Empty for contract creations
     pub to: String,
+    /// Exact value in wei
+    pub amount_wei: String,
+    /// Convenience value in ETH, the nearest float to `amount_wei`
     pub amount_eth: f64,
+    pub amount_native: NativeAmount,
+    pub transaction_type: u64,
     pub gas_price: Option<String>,
+    pub max_fee_per_gas: Option<String>,
+    pub max_priority_fee_per_gas: Option<String>,
     pub gas_limit: Option<u64>,
     pub status: TransactionStatus,
+    pub block_number: Option<u64>,
+    pub confirmations: Option<u64>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Serialize)]
+pub struct ReceiptLog {
+    pub address: ChecksumAddress,
+    pub topics: Vec<String>,
+    pub data: String,
+    pub log_index: Option<u64>,
+    /// Present for ERC-20 `Transfer` and `Approval` events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<TokenEvent>,
+}
+
+/// An ERC-20 event decoded against the standard ABI
+#[derive(Serialize)]
+pub struct TokenEvent {
+    /// "Transfer" or "Approval"
+    pub event: &'static str,
+    /// Sender of a transfer, owner of an approval
+    pub from: ChecksumAddress,
+    /// Recipient of a transfer, spender of an approval
+    pub to: ChecksumAddress,
+    pub value_raw: String,
+    /// `value_raw` scaled by the token's decimals; `None` when `decimals()` could not be read
+    pub value: Option<String>,
+    pub decimals: Option<u8>,
+}
+
+/// Receipt of a transaction; every receipt field is `None` until it is mined
+#[derive(Serialize)]
+pub struct TransactionReceiptInfo {
+    pub transaction_hash: String,
+    pub confirmations: u64,
+    pub block_number: Option<u64>,
+    pub status: Option<u64>,
+    pub gas_used: Option<String>,
+    pub effective_gas_price: Option<String>,
+    pub contract_address: Option<ChecksumAddress>,
+    pub logs: Vec<ReceiptLog>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct NativeAmount {
+    pub value: f64,
+    pub symbol: String,
+}
+
+#[derive(Serialize, Clone)]
 pub enum TransactionStatus {
     Pending,
     Confirmed,
     Failed,
+    /// Checked and signed by a dry run, never broadcast
+    Simulated,
+}
+
+#[derive(Serialize)]
+pub struct MempoolTransaction {
+    pub hash: String,
+    pub nonce: u64,
+    pub to: Option<ChecksumAddress>,
+    pub value_wei: String,
+    pub gas_price: Option<String>,
+    pub gas: String,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct MempoolInfo {
+    pub address: ChecksumAddress,
+    pub source: String,
+    pub transactions: Vec<MempoolTransaction>,
+}
+
+/// A price per gas in full-precision wei, with gwei for display
+#[derive(Serialize)]
+pub struct GasPrice {
+    pub wei: String,
+    pub gwei: f64,
+}
+
+impl From<U256> for GasPrice {
+    fn from(wei: U256) -> Self {
+        Self {
+            wei: wei.to_string(),
+            gwei: utils::wei_to_gwei(wei),
+        }
+    }
+}
+
+/// Current gas prices: the legacy price everywhere, plus EIP-1559 suggestions on London-enabled chains
+#[derive(Serialize)]
+pub struct GasPriceInfo {
+    pub legacy_gas_price: GasPrice,
+    /// Base fee of the latest block; `None` before London, and so are the suggestions
+    pub base_fee: Option<GasPrice>,
+    pub suggested_priority_fee: Option<GasPrice>,
+    /// `maxFeePerGas` the server would use for a send without fee fields
+    pub suggested_max_fee: Option<GasPrice>,
+}
+
+#[derive(Serialize)]
+pub struct FeeSuggestion {
+    pub gas_price: String,
+    pub max_priority_fee_per_gas: String,
+    pub priority_fee_source: String,
+}
+
+/// Body of `POST /estimate-gas`; either field may be left out to estimate a plain call or a plain transfer
+#[derive(Deserialize)]
+pub struct GasEstimateRequest {
+    pub to: String,
+    /// Amount in ETH as a decimal string or JSON integer, converted with exact 18-decimal math
+    #[serde(default, deserialize_with = "deserialize_decimal")]
+    pub amount_eth: Option<String>,
+    /// ABI-encoded calldata as hex
+    pub data: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FeeEstimateQuery {
+    pub to: String,
+    /// Amount in ETH as a decimal string
+    pub amount: String,
+    /// Managed account to estimate for instead of the primary one
+    pub from: Option<String>,
+}
+
+/// Gas, fee rates and total cost of a plain transfer, with whether the sender can pay for it
+#[derive(Serialize)]
+pub struct FeeEstimate {
+    pub from: ChecksumAddress,
+    pub to: ChecksumAddress,
+    pub gas_limit: u64,
+    /// 2 for EIP-1559, 0 for legacy
+    pub transaction_type: u64,
+    pub gas_price: Option<GasPrice>,
+    pub base_fee_per_gas: Option<GasPrice>,
+    pub max_priority_fee_per_gas: Option<GasPrice>,
+    pub max_fee_per_gas: Option<GasPrice>,
+    /// Fee at the current base fee plus priority fee (or gas price)
+    pub estimated_fee_wei: String,
+    pub estimated_fee_eth: f64,
+    /// Most the transaction can cost in fees; what the node reserves from the balance
+    pub max_fee_wei: String,
+    pub amount_wei: String,
+    /// `amount_wei + max_fee_wei`
+    pub total_cost_wei: String,
+    pub balance_wei: String,
+    pub can_afford: bool,
+}
+
+#[derive(Serialize)]
+pub struct ServiceMetrics {
+    pub slow_rpc_queries: u64,
+    pub slow_query_threshold_ms: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct EnsProfile {
+    pub name: String,
+    pub address: Option<ChecksumAddress>,
+    pub avatar: Option<String>,
+    pub url: Option<String>,
+    pub email: Option<String>,
+    /// Primary name the resolved address points back to; may differ from `name`
+    pub reverse_name: Option<String>,
+}
+
+/// Readiness report for `/health`
+#[derive(Serialize)]
+pub struct HealthStatus {
+    pub server: &'static str,
+    pub web3_connected: bool,
+    pub network_id: u64,
+    pub latest_block: Option<u64>,
+}
+
+/// A new chain head, as sent on `/blocks/stream`
+#[derive(Serialize)]
+pub struct BlockHeaderEvent {
+    pub number: Option<u64>,
+    pub hash: Option<String>,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize)]
+pub struct NetworkStatus {
+    pub network_id: u64,
+    pub network_name: String,
+    pub connected: bool,
+    pub block_number: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -64,6 +721,72 @@ pub struct NetworkInfo {
     pub network_name: String,
     pub rpc_url: String,
     pub block_number: Option<u64>,
+    pub avg_block_time_secs: Option<f64>,
+    /// Latest block carries a base fee; false also when it could not be fetched, as legacy
+    /// transactions are accepted either way
+    pub supports_eip1559: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ContractCallRequest {
+    pub to: String,
+    /// ABI-encoded calldata as hex
+    pub data: String,
+    /// Block number or tag to call at; latest when omitted
+    pub block: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RpcRequest {
+    #[serde(default)]
+    pub id: serde_json::Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+pub struct DecodeTransactionRequest {
+    pub raw: String,
+}
+
+#[derive(Serialize)]
+pub struct AccessListEntry {
+    pub address: ChecksumAddress,
+    pub storage_keys: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct DecodedTransaction {
+    pub hash: String,
+    pub transaction_type: u64,
+    pub chain_id: Option<u64>,
+    pub from: ChecksumAddress,
+    pub to: Option<ChecksumAddress>,
+    pub nonce: String,
+    pub value: String,
+    pub gas: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<String>,
+    pub data: String,
+    pub access_list: Vec<AccessListEntry>,
+    pub v: u64,
+    pub r: String,
+    pub s: String,
 }
 
 impl<T> ApiResponse<T> {
@@ -77,19 +800,123 @@ impl<T> ApiResponse<T> {
 }
 
 impl Account {
-    pub fn new(secret_key: &str, public_key: &str, public_address: &str) -> Self {
+    pub fn new(secret_key: SecretString, public_key: &str, public_address: &str) -> Self {
         Self {
-            secret_key: secret_key.to_string(),
+            secret_key,
             public_key: public_key.to_string(),
             public_address: public_address.to_string(),
+            derivation_path: None,
+            kind: AccountKind::Full,
+        }
+    }
+
+    /// An address tracked without a key
+    pub fn watch_only(public_address: &str) -> Self {
+        Self {
+            secret_key: SecretString::default(),
+            public_key: String::new(),
+            public_address: public_address.to_string(),
+            derivation_path: None,
+            kind: AccountKind::WatchOnly,
+        }
+    }
+
+    /// The `mpc.address` account, whose transactions the co-signers sign
+    pub fn mpc(public_address: &str) -> Self {
+        Self {
+            kind: AccountKind::Mpc,
+            ..Self::watch_only(public_address)
+        }
+    }
+
+    pub fn is_watch_only(&self) -> bool {
+        self.kind == AccountKind::WatchOnly
+    }
+
+    pub fn to_managed_info(&self, primary: bool) -> ManagedAccountInfo {
+        ManagedAccountInfo {
+            address: self.public_address.clone(),
+            public_key: Some(self.public_key.clone()).filter(|key| !key.is_empty()),
+            kind: self.kind,
+            primary,
         }
     }
 
     pub fn to_account_info(&self) -> AccountInfo {
+        let public_key_uncompressed = secp256k1::PublicKey::from_str(&self.public_key)
+            .ok()
+            .map(|key| hex::encode(key.serialize_uncompressed()));
+
         AccountInfo {
             public_key: self.public_key.clone(),
+            public_key_uncompressed,
             address: self.public_address.clone(),
+            address_checksum: utils::to_checksum_address(&self.public_address),
+            derivation_path: self.derivation_path.clone(),
             created_at: Some(chrono::Utc::now()),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn amount_eth(amount: serde_json::Value) -> Result<Option<String>, serde_json::Error> {
+        serde_json::from_value::<TransactionRequest>(json!({ "to": "0x00000000000000000000000000000000000000b0", "amount_eth": amount }))
+            .map(|request| request.amount_eth)
+    }
+
+    #[test]
+    fn account_debug_output_masks_the_secret_key() {
+        let key = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+        let account: Account = serde_json::from_value(json!({
+            "secret_key": key,
+            "public_key": "04ab",
+            "public_address": "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23",
+        }))
+        .unwrap();
+
+        let debug = format!("{:?}", account);
+        assert!(!debug.contains(key), "{}", debug);
+        assert!(debug.contains("<redacted>"), "{}", debug);
+        assert!(debug.contains("0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"), "{}", debug);
+        assert!(!format!("{:#?}", account).contains(key));
+    }
+
+    #[test]
+    fn keeps_decimal_strings_exactly() {
+        assert_eq!(amount_eth(json!("0.1")).unwrap().as_deref(), Some("0.1"));
+        assert_eq!(amount_eth(json!("0.300000000000000001")).unwrap().as_deref(), Some("0.300000000000000001"));
+        assert_eq!(amount_eth(json!("12345678901234567890")).unwrap().as_deref(), Some("12345678901234567890"));
+    }
+
+    #[test]
+    fn accepts_whole_json_numbers() {
+        assert_eq!(amount_eth(json!(2)).unwrap().as_deref(), Some("2"));
+        let request: TransactionRequest = serde_json::from_str(r#"{"to": "0x00000000000000000000000000000000000000b0"}"#).unwrap();
+        assert_eq!(request.amount_eth, None);
+    }
+
+    #[test]
+    fn refuses_fractional_json_numbers() {
+        // Parsed from text, as a request body would be, so the float is serde_json's own reading
+        let body = r#"{"to": "0x00000000000000000000000000000000000000b0", "amount_eth": 0.1}"#;
+        let error = serde_json::from_str::<TransactionRequest>(body).err().unwrap().to_string();
+        assert!(error.contains("must be a decimal string"), "{}", error);
+        assert!(amount_eth(json!(-1)).is_err());
+        assert!(amount_eth(json!(1e21)).is_err());
+    }
+
+    #[test]
+    fn gas_prices_beyond_u64_keep_every_digit() {
+        let above_u64 = U256::from(u64::MAX) + 1;
+        let price = GasPrice::from(above_u64);
+        assert_eq!(price.wei, "18446744073709551616");
+        assert!((price.gwei - 18_446_744_073.709_552).abs() < 1e-3, "{}", price.gwei);
+
+        let price = GasPrice::from(U256::MAX);
+        assert_eq!(price.wei, U256::MAX.to_string());
+        assert!(price.gwei.is_finite());
+    }
+}
@@ -0,0 +1,112 @@
+//! Wrappers that keep key material out of logs and responses and overwrite it when dropped.
+
+use secp256k1::SecretKey;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{compiler_fence, Ordering};
+use zeroize::Zeroizing;
+
+const REDACTED: &str = "<redacted>";
+
+/// A secret held as text (a hex private key). `Debug` and `Serialize` print `<redacted>`;
+/// the only way to the value is `expose_secret`.
+#[derive(Clone, Default)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        Self(Zeroizing::new(secret))
+    }
+
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// A parsed secp256k1 key that is overwritten when dropped. `SecretKey` is `Copy`, so keep keys in
+/// this guard and borrow them instead of passing copies around.
+pub struct SecretKeyGuard(SecretKey);
+
+impl SecretKeyGuard {
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self(secret_key)
+    }
+}
+
+impl Deref for SecretKeyGuard {
+    type Target = SecretKey;
+
+    fn deref(&self) -> &SecretKey {
+        &self.0
+    }
+}
+
+impl DerefMut for SecretKeyGuard {
+    fn deref_mut(&mut self) -> &mut SecretKey {
+        &mut self.0
+    }
+}
+
+impl Drop for SecretKeyGuard {
+    fn drop(&mut self) {
+        // SAFETY: `self.0` is a valid, aligned `SecretKey` we own; it is replaced with another valid key.
+        // The volatile write keeps the compiler from dropping the store as dead.
+        unsafe { std::ptr::write_volatile(&mut self.0, secp256k1::ONE_KEY) };
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl fmt::Debug for SecretKeyGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[test]
+    fn secrets_print_and_serialize_redacted() {
+        let secret = SecretString::new(KEY.to_string());
+        assert_eq!(format!("{:?}", secret), REDACTED);
+        assert_eq!(serde_json::to_string(&secret).unwrap(), format!("\"{}\"", REDACTED));
+        assert_eq!(secret.expose_secret(), KEY);
+
+        let guard = SecretKeyGuard::new(SecretKey::from_slice(&hex::decode(KEY).unwrap()).unwrap());
+        assert_eq!(format!("{:?}", guard), REDACTED);
+    }
+
+    #[test]
+    fn secrets_deserialize_to_the_real_value() {
+        let secret: SecretString = serde_json::from_str(&format!("\"{}\"", KEY)).unwrap();
+        assert_eq!(secret.expose_secret(), KEY);
+    }
+}
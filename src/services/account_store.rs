@@ -0,0 +1,180 @@
+//! Additional accounts (signing or watch-only) kept next to the primary wallet file, one file per account.
+
+use crate::address::ChecksumAddress;
+use crate::errors::{AppError, AppResult};
+use crate::models::Account;
+use crate::services::WalletService;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+use web3::types::Address;
+
+/// File name prefix of stored accounts: `account_<checksummed address>.json`
+const ACCOUNT_FILE_PREFIX: &str = "account_";
+
+/// Accounts keyed by checksummed address, persisted in the wallet directory
+pub struct AccountStore {
+    wallet_service: Arc<WalletService>,
+    directory: PathBuf,
+    accounts: RwLock<BTreeMap<String, Account>>,
+}
+
+impl AccountStore {
+    /// Load every stored account in the directory of `wallet_file`; unreadable files are skipped
+    pub fn open(wallet_service: Arc<WalletService>, wallet_file: &str) -> Self {
+        let directory = Path::new(wallet_file)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let scan_directory = if directory.as_os_str().is_empty() { Path::new(".") } else { directory.as_path() };
+        let mut accounts = BTreeMap::new();
+        for entry in fs::read_dir(scan_directory).into_iter().flatten().flatten() {
+            // Only `account_<address>.json`; the default primary wallet is `account_config.json`
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let is_account_file = file_name
+                .strip_prefix(ACCOUNT_FILE_PREFIX)
+                .and_then(|rest| rest.strip_suffix(".json"))
+                .is_some_and(|address| Address::from_str(address).is_ok());
+            if !is_account_file {
+                continue;
+            }
+
+            let path = entry.path().to_string_lossy().into_owned();
+            match wallet_service.load_account(&path).and_then(|account| wallet_service.validate_account(&account).map(|_| account)) {
+                Ok(account) => {
+                    accounts.insert(account.public_address.clone(), account);
+                }
+                Err(e) => warn!("Skipping account file {}: {}", path, e),
+            }
+        }
+        if !accounts.is_empty() {
+            info!("Loaded {} additional account(s)", accounts.len());
+        }
+
+        Self {
+            wallet_service,
+            directory,
+            accounts: RwLock::new(accounts),
+        }
+    }
+
+    /// Persist `account` and make it available for signing
+    pub fn insert(&self, account: Account) -> AppResult<Account> {
+        if self.accounts.read().unwrap().contains_key(&account.public_address) {
+            return Err(AppError::ValidationError(format!(
+                "Account {} already exists",
+                account.public_address
+            )));
+        }
+
+        let path = self.file_path(&account.public_address);
+        self.wallet_service.save_account(&account, &path)?;
+
+        self.accounts
+            .write()
+            .unwrap()
+            .insert(account.public_address.clone(), account.clone());
+        Ok(account)
+    }
+
+    /// Account stored under `address`, in any letter case
+    pub fn get(&self, address: &str) -> AppResult<Account> {
+        let address = address.parse::<ChecksumAddress>()?.to_string();
+        self.accounts
+            .read()
+            .unwrap()
+            .get(&address)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Account {}", address)))
+    }
+
+    /// Stored accounts, ordered by address
+    pub fn list(&self) -> Vec<Account> {
+        self.accounts.read().unwrap().values().cloned().collect()
+    }
+
+    /// Forget `address` and delete its file
+    pub fn remove(&self, address: &str) -> AppResult<()> {
+        let address = address.parse::<ChecksumAddress>()?.to_string();
+        if self.accounts.write().unwrap().remove(&address).is_none() {
+            return Err(AppError::NotFound(format!("Account {}", address)));
+        }
+
+        let path = self.file_path(&address);
+        fs::remove_file(&path)
+            .map_err(|e| AppError::InternalError(format!("Failed to delete {}: {}", path, e)))?;
+        info!("Account {} removed", address);
+        Ok(())
+    }
+
+    /// Re-encrypt every plaintext account file with the wallet password; returns how many were migrated
+    pub fn migrate_to_keystore(&self) -> AppResult<usize> {
+        let mut migrated = 0;
+        for address in self.accounts.read().unwrap().keys() {
+            if self.wallet_service.migrate_to_keystore(&self.file_path(address))? {
+                migrated += 1;
+            }
+        }
+        Ok(migrated)
+    }
+
+    fn file_path(&self, address: &str) -> String {
+        self.directory
+            .join(format!("{}{}.json", ACCOUNT_FILE_PREFIX, address))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A store in a fresh temporary wallet directory
+    fn open_store() -> (AccountStore, String) {
+        let directory = std::env::temp_dir().join(format!("account-store-test-{}", uuid::Uuid::from_bytes(rand::random())));
+        fs::create_dir_all(&directory).unwrap();
+        let wallet_file = directory.join("account_config.json").to_string_lossy().into_owned();
+        (AccountStore::open(Arc::new(WalletService::new()), &wallet_file), wallet_file)
+    }
+
+    #[test]
+    fn accounts_persist_across_restarts() {
+        let (store, wallet_file) = open_store();
+        let wallet_service = WalletService::new();
+        let first = store.insert(wallet_service.create_account().unwrap()).unwrap();
+        let second = store.insert(wallet_service.create_account().unwrap()).unwrap();
+
+        let reopened = AccountStore::open(Arc::new(WalletService::new()), &wallet_file);
+        let mut expected = vec![first.public_address.clone(), second.public_address.clone()];
+        expected.sort();
+        let addresses: Vec<String> = reopened.list().into_iter().map(|account| account.public_address).collect();
+        assert_eq!(addresses, expected);
+        assert_eq!(reopened.get(&second.public_address.to_lowercase()).unwrap().public_key, second.public_key);
+    }
+
+    #[test]
+    fn removed_accounts_are_gone_from_disk() {
+        let (store, wallet_file) = open_store();
+        let account = store.insert(WalletService::new().create_account().unwrap()).unwrap();
+
+        store.remove(&account.public_address).unwrap();
+        assert!(matches!(store.get(&account.public_address), Err(AppError::NotFound(_))));
+        assert!(AccountStore::open(Arc::new(WalletService::new()), &wallet_file).list().is_empty());
+        assert!(matches!(store.remove(&account.public_address), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn an_address_is_stored_once() {
+        let (store, _) = open_store();
+        let account = WalletService::new().create_account().unwrap();
+
+        store.insert(account.clone()).unwrap();
+        assert!(matches!(store.insert(account), Err(AppError::ValidationError(_))));
+        assert_eq!(store.list().len(), 1);
+    }
+}
@@ -0,0 +1,241 @@
+//! Webhooks for transactions sent to watched addresses, driven by the new-heads subscription.
+
+use crate::address::ChecksumAddress;
+use crate::models::{TransferNotification, WatchInfo};
+use crate::services::Web3Service;
+use crate::utils;
+use futures::StreamExt;
+use rand::{rngs::OsRng, RngCore};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use web3::types::Transaction;
+
+/// Registered watches keyed by id
+pub type WatchList = Arc<RwLock<HashMap<String, WatchInfo>>>;
+
+/// Wait before subscribing again after the subscription ends or cannot be opened
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+/// Delivery attempts per notification, the first included
+const WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+/// Wait after the first failed delivery; doubles for each further attempt
+const WEBHOOK_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound on one webhook request
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Random id for a new watch
+pub fn new_watch_id() -> String {
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    uuid::Builder::from_random_bytes(id).into_uuid().to_string()
+}
+
+/// Scan every new block for transactions to watched addresses and notify their callbacks.
+/// Blocks mined while the subscription is being re-established are not scanned.
+pub fn spawn(web3_service: Arc<RwLock<Web3Service>>, watches: WatchList) {
+    let client = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    tokio::spawn(async move {
+        loop {
+            let subscription = web3_service.read().await.subscribe_new_heads().await;
+            let mut heads = match subscription {
+                Ok(heads) => heads,
+                Err(e) => {
+                    warn!("Address watcher could not subscribe to new heads: {}", e);
+                    tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+                    continue;
+                }
+            };
+
+            while let Some(header) = heads.next().await {
+                let Some(hash) = header.ok().and_then(|header| header.hash) else {
+                    break;
+                };
+                if watches.read().await.is_empty() {
+                    continue;
+                }
+
+                let block = web3_service.read().await.block_transactions(hash).await;
+                let (block_number, transactions) = match block {
+                    Ok(block) => block,
+                    Err(e) => {
+                        warn!("Address watcher skipped block {:?}: {}", hash, e);
+                        continue;
+                    }
+                };
+
+                let notifications = matching_transfers(&*watches.read().await, &transactions, block_number);
+                for (callback_url, notification) in notifications {
+                    tokio::spawn(deliver(client.clone(), callback_url, notification));
+                }
+            }
+
+            warn!("New heads subscription ended; address watcher resubscribing");
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    });
+}
+
+/// Callback URL and payload for every transaction in `transactions` sent to a watched address
+pub fn matching_transfers(
+    watches: &HashMap<String, WatchInfo>,
+    transactions: &[Transaction],
+    block_number: Option<u64>,
+) -> Vec<(String, TransferNotification)> {
+    let mut notifications = Vec::new();
+    for transaction in transactions {
+        let Some(to) = transaction.to else {
+            continue;
+        };
+        for watch in watches.values().filter(|watch| watch.address.address() == to) {
+            notifications.push((
+                watch.callback_url.clone(),
+                TransferNotification {
+                    watch_id: watch.id.clone(),
+                    address: watch.address,
+                    transaction_hash: format!("{:?}", transaction.hash),
+                    from: transaction.from.map(ChecksumAddress::from),
+                    value_wei: transaction.value.to_string(),
+                    value_eth: utils::wei_to_eth(transaction.value),
+                    block_number,
+                },
+            ));
+        }
+    }
+    notifications
+}
+
+/// POST `notification` to `callback_url`, retrying failures with exponential backoff
+async fn deliver(client: reqwest::Client, callback_url: String, notification: TransferNotification) {
+    let mut delay = WEBHOOK_RETRY_BASE_DELAY;
+    for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+        let result = client
+            .post(&callback_url)
+            .json(&notification)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => {
+                info!(
+                    "Notified {} of {} (watch {})",
+                    callback_url, notification.transaction_hash, notification.watch_id
+                );
+                return;
+            }
+            Err(e) if attempt < WEBHOOK_MAX_ATTEMPTS => {
+                warn!("Webhook {} failed (attempt {}): {}; retrying in {:?}", callback_url, attempt, e, delay);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => warn!(
+                "Giving up on webhook {} for {} after {} attempts: {}",
+                callback_url, notification.transaction_hash, attempt, e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::post, Json, Router};
+    use serde_json::Value;
+    use std::sync::Mutex;
+    use web3::types::{Address, H256, U256};
+
+    fn watch(id: &str, address: Address) -> (String, WatchInfo) {
+        let watch = WatchInfo {
+            id: id.to_string(),
+            address: address.into(),
+            callback_url: format!("http://hooks.example/{}", id),
+        };
+        (id.to_string(), watch)
+    }
+
+    fn transfer(hash: u8, to: Option<Address>, value: U256) -> Transaction {
+        Transaction {
+            hash: H256::repeat_byte(hash),
+            from: Some(Address::repeat_byte(0xf0)),
+            to,
+            value,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_transactions_sent_to_watched_addresses() {
+        let watched = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let watches = HashMap::from([watch("first", watched), watch("second", watched), watch("idle", other)]);
+        let one_and_a_half_eth = U256::from(1_500_000_000_000_000_000u64);
+        let block = [
+            transfer(1, Some(watched), one_and_a_half_eth),
+            transfer(2, Some(Address::repeat_byte(0x33)), U256::one()),
+            transfer(3, None, U256::one()),
+        ];
+
+        let mut notifications = matching_transfers(&watches, &block, Some(7));
+        notifications.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(notifications.len(), 2);
+        let (callback_url, notification) = &notifications[0];
+        assert_eq!(callback_url, "http://hooks.example/first");
+        assert_eq!(notification.watch_id, "first");
+        assert_eq!(notification.address.address(), watched);
+        assert_eq!(notification.transaction_hash, format!("0x{}", "01".repeat(32)));
+        assert_eq!(notification.from.map(|from| from.address()), Some(Address::repeat_byte(0xf0)));
+        assert_eq!(notification.value_wei, "1500000000000000000");
+        assert_eq!(notification.value_eth, 1.5);
+        assert_eq!(notification.block_number, Some(7));
+        assert_eq!(notifications[1].0, "http://hooks.example/second");
+    }
+
+    #[test]
+    fn an_empty_watch_list_matches_nothing() {
+        let block = [transfer(1, Some(Address::repeat_byte(0x11)), U256::one())];
+        assert!(matching_transfers(&HashMap::new(), &block, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_are_retried() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let hook = received.clone();
+        let app = Router::new().route(
+            "/",
+            post(move |Json(body): Json<Value>| async move {
+                let mut received = hook.lock().unwrap();
+                received.push(body);
+                if received.len() == 1 {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::OK
+                }
+            }),
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let callback_url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        let (_, watch) = watch("w", Address::repeat_byte(0x11));
+        let mut notifications = matching_transfers(
+            &HashMap::from([("w".to_string(), watch)]),
+            &[transfer(1, Some(Address::repeat_byte(0x11)), U256::from(5))],
+            Some(9),
+        );
+        let (_, notification) = notifications.pop().unwrap();
+        deliver(reqwest::Client::new(), callback_url, notification).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0], received[1]);
+        assert_eq!(received[1]["watch_id"], "w");
+        assert_eq!(received[1]["value_wei"], "5");
+        assert_eq!(received[1]["block_number"], 9);
+    }
+}
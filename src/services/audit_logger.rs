@@ -0,0 +1,85 @@
+use crate::errors::{AppError, AppResult};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use tracing::{error, info};
+
+/// One signing operation, written as a JSON line to the audit log.
+/// Only public data is recorded: never keys or signature components.
+#[derive(Serialize)]
+pub struct AuditEntry {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub operation: &'static str,
+    pub signer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_wei: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_hash: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(operation: &'static str, signer: &str) -> Self {
+        Self {
+            timestamp: chrono::Utc::now(),
+            operation,
+            signer: signer.to_string(),
+            target: None,
+            value_wei: None,
+            transaction_hash: None,
+            message_hash: None,
+        }
+    }
+}
+
+/// Append-only audit trail of signing operations, kept apart from application logs
+pub struct AuditLogger {
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLogger {
+    /// Open (or create) the audit log at `path`; `None` disables auditing
+    pub fn open(path: Option<&str>) -> AppResult<Self> {
+        let Some(path) = path else {
+            return Ok(Self::disabled());
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| AppError::ConfigurationError(format!("Failed to open audit log {}: {}", path, e)))?;
+
+        info!("Audit log: {}", path);
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+        })
+    }
+
+    pub fn disabled() -> Self {
+        Self { file: None }
+    }
+
+    /// Write an entry. Signing callers fail when this fails, so nothing is signed unaudited.
+    pub fn record(&self, entry: &AuditEntry) -> AppResult<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize audit entry: {}", e)))?;
+        line.push('\n');
+
+        let mut file = file.lock().unwrap();
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| {
+                error!("Failed to write audit log: {}", e);
+                AppError::InternalError(format!("Failed to write audit log: {}", e))
+            })
+    }
+}
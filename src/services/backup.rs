@@ -0,0 +1,363 @@
+//! Shamir secret sharing of a wallet key, after SLIP-39: any `threshold` shares rebuild the key, fewer
+//! reveal nothing about it, and a digest hidden in the sharing catches shares that don't belong together.
+//! Shares are written as checksummed hex (`sss1-...`) rather than SLIP-39 word lists.
+
+use crate::errors::{AppError, AppResult};
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+use tiny_keccak::keccak256;
+use zeroize::Zeroizing;
+
+/// Most shares one secret can be split into, as in SLIP-39
+pub const MAX_SHARES: u8 = 16;
+
+const SHARE_PREFIX: &str = "sss1-";
+const SHARE_VERSION: u8 = 1;
+/// Version, identifier (2 bytes), threshold and index
+const HEADER_LENGTH: usize = 5;
+const CHECKSUM_LENGTH: usize = 4;
+const DIGEST_LENGTH: usize = 4;
+const MIN_SECRET_LENGTH: usize = 16;
+/// x-coordinates of the digest and the secret, above those of the shares
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+
+/// Exponent and logarithm tables of GF(256) modulo x^8 + x^4 + x^3 + x + 1, with generator x + 1
+static GF_TABLES: ([u8; 255], [u8; 256]) = gf_tables();
+
+type Point = (u8, Zeroizing<Vec<u8>>);
+
+struct Share {
+    identifier: u16,
+    threshold: u8,
+    index: u8,
+    value: Zeroizing<Vec<u8>>,
+}
+
+/// Split `secret` into `count` shares, any `threshold` of which rebuild it
+pub fn split(secret: &[u8], threshold: u8, count: u8) -> AppResult<Vec<String>> {
+    if threshold == 0 || threshold > count || count > MAX_SHARES {
+        return Err(AppError::ValidationError(format!(
+            "Need 1 <= threshold <= shares <= {}, got a threshold of {} for {} shares",
+            MAX_SHARES, threshold, count
+        )));
+    }
+    if secret.len() < MIN_SECRET_LENGTH {
+        return Err(AppError::InternalError(format!("Secrets to split need at least {} bytes", MIN_SECRET_LENGTH)));
+    }
+
+    let mut identifier = [0u8; 2];
+    OsRng.fill_bytes(&mut identifier);
+    let identifier = u16::from_be_bytes(identifier);
+
+    let values: Vec<_> = if threshold == 1 {
+        (0..count).map(|_| Zeroizing::new(secret.to_vec())).collect()
+    } else {
+        // threshold - 2 random shares, the digest and the secret fix a polynomial of degree threshold - 1
+        let mut points: Vec<Point> = (0..threshold - 2).map(|index| (index, random_bytes(secret.len()))).collect();
+        let random_part = random_bytes(secret.len() - DIGEST_LENGTH);
+        let mut digest_value = Zeroizing::new(digest(&random_part, secret).to_vec());
+        digest_value.extend_from_slice(&random_part);
+        points.push((DIGEST_INDEX, digest_value));
+        points.push((SECRET_INDEX, Zeroizing::new(secret.to_vec())));
+        (0..count).map(|index| interpolate(&points, index)).collect()
+    };
+
+    Ok(values
+        .into_iter()
+        .zip(0..)
+        .map(|(value, index)| encode(&Share { identifier, threshold, index, value }))
+        .collect())
+}
+
+/// Rebuild a secret from at least `threshold` shares of the same split
+pub fn combine(encoded: &[&str]) -> AppResult<Zeroizing<Vec<u8>>> {
+    let shares = encoded.iter().map(|share| decode(share)).collect::<AppResult<Vec<_>>>()?;
+    let first = shares
+        .first()
+        .ok_or_else(|| AppError::ValidationError("No backup shares given".to_string()))?;
+    let threshold = first.threshold as usize;
+    if shares
+        .iter()
+        .any(|share| share.identifier != first.identifier || share.threshold != first.threshold || share.value.len() != first.value.len())
+    {
+        return Err(AppError::ValidationError("Backup shares are from different backups".to_string()));
+    }
+
+    let mut points: Vec<Point> = Vec::with_capacity(shares.len());
+    for share in shares {
+        if points.iter().any(|(index, _)| *index == share.index) {
+            return Err(AppError::ValidationError(format!("Backup share {} was given twice", share.index + 1)));
+        }
+        points.push((share.index, share.value));
+    }
+    if points.len() < threshold {
+        return Err(AppError::ValidationError(format!(
+            "Need {} backup shares, got {}",
+            threshold,
+            points.len()
+        )));
+    }
+    points.truncate(threshold);
+    if threshold == 1 {
+        return Ok(points.swap_remove(0).1);
+    }
+
+    let secret = interpolate(&points, SECRET_INDEX);
+    let digest_value = interpolate(&points, DIGEST_INDEX);
+    if digest(&digest_value[DIGEST_LENGTH..], &secret)[..] != digest_value[..DIGEST_LENGTH] {
+        return Err(AppError::ValidationError(
+            "Backup shares do not combine to a valid secret".to_string(),
+        ));
+    }
+    Ok(secret)
+}
+
+/// `sss1-` and the hex of header, value and checksum
+fn encode(share: &Share) -> String {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(HEADER_LENGTH + share.value.len() + CHECKSUM_LENGTH));
+    bytes.push(SHARE_VERSION);
+    bytes.extend_from_slice(&share.identifier.to_be_bytes());
+    bytes.push(share.threshold);
+    bytes.push(share.index);
+    bytes.extend_from_slice(&share.value);
+    let checksum = keccak256(&bytes);
+    bytes.extend_from_slice(&checksum[..CHECKSUM_LENGTH]);
+
+    let encoded = Zeroizing::new(hex::encode(&bytes));
+    format!("{}{}", SHARE_PREFIX, encoded.as_str())
+}
+
+fn decode(encoded: &str) -> AppResult<Share> {
+    let invalid = |reason: &str| AppError::ValidationError(format!("Invalid backup share: {}", reason));
+
+    let hex_part = encoded
+        .trim()
+        .strip_prefix(SHARE_PREFIX)
+        .ok_or_else(|| invalid(&format!("expected the {} prefix", SHARE_PREFIX)))?;
+    let bytes = Zeroizing::new(hex::decode(hex_part).map_err(|_| invalid("not hex"))?);
+    if bytes.len() < HEADER_LENGTH + MIN_SECRET_LENGTH + CHECKSUM_LENGTH {
+        return Err(invalid("too short"));
+    }
+
+    let (body, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LENGTH);
+    if keccak256(body)[..CHECKSUM_LENGTH] != *checksum {
+        return Err(invalid("checksum mismatch, check it for typos"));
+    }
+    if body[0] != SHARE_VERSION {
+        return Err(invalid(&format!("unsupported version {}", body[0])));
+    }
+    let (threshold, index) = (body[3], body[4]);
+    if threshold == 0 || threshold > MAX_SHARES || index >= MAX_SHARES {
+        return Err(invalid("bad threshold or index"));
+    }
+
+    Ok(Share {
+        identifier: u16::from_be_bytes([body[1], body[2]]),
+        threshold,
+        index,
+        value: Zeroizing::new(body[HEADER_LENGTH..].to_vec()),
+    })
+}
+
+/// First bytes of HMAC-SHA256 of the secret keyed with the digest's random part
+fn digest(random_part: &[u8], secret: &[u8]) -> [u8; DIGEST_LENGTH] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(random_part).expect("HMAC accepts keys of any length");
+    mac.update(secret);
+    let mut digest = [0u8; DIGEST_LENGTH];
+    digest.copy_from_slice(&mac.finalize().into_bytes()[..DIGEST_LENGTH]);
+    digest
+}
+
+fn random_bytes(length: usize) -> Zeroizing<Vec<u8>> {
+    let mut bytes = Zeroizing::new(vec![0u8; length]);
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Value at `x` of the polynomial through `points`, byte by byte (Lagrange interpolation over GF(256))
+fn interpolate(points: &[Point], x: u8) -> Zeroizing<Vec<u8>> {
+    if let Some((_, value)) = points.iter().find(|(index, _)| *index == x) {
+        return value.clone();
+    }
+
+    let mut result = Zeroizing::new(vec![0u8; points[0].1.len()]);
+    for (xi, yi) in points {
+        // Subtraction is XOR in GF(256)
+        let basis = points
+            .iter()
+            .filter(|(xj, _)| xj != xi)
+            .fold(1, |basis, (xj, _)| gf_mul(basis, gf_div(x ^ xj, xi ^ xj)));
+        for (out, y) in result.iter_mut().zip(yi.iter()) {
+            *out ^= gf_mul(basis, *y);
+        }
+    }
+    result
+}
+
+const fn gf_tables() -> ([u8; 255], [u8; 256]) {
+    let mut exp = [0u8; 255];
+    let mut log = [0u8; 256];
+    let mut value: u16 = 1;
+    let mut power = 0;
+    while power < 255 {
+        exp[power] = value as u8;
+        log[value as usize] = power as u8;
+        // Multiply by x + 1 and reduce
+        value ^= value << 1;
+        if value & 0x100 != 0 {
+            value ^= 0x11b;
+        }
+        power += 1;
+    }
+    (exp, log)
+}
+
+fn gf_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    let (exp, log) = &GF_TABLES;
+    exp[(log[a as usize] as usize + log[b as usize] as usize) % 255]
+}
+
+/// `a / b`; `b` is never 0 because interpolation points are distinct
+fn gf_div(a: u8, b: u8) -> u8 {
+    if a == 0 {
+        return 0;
+    }
+    let (exp, log) = &GF_TABLES;
+    exp[(log[a as usize] as usize + 255 - log[b as usize] as usize) % 255]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: [u8; 32] = [
+        0x4c, 0x08, 0x83, 0xa6, 0x91, 0x02, 0x93, 0x7d, 0x62, 0x31, 0x47, 0x1b, 0x5d, 0xbb, 0x62, 0x04,
+        0xfe, 0x51, 0x29, 0x61, 0x70, 0x82, 0x79, 0x2a, 0xe4, 0x68, 0xd0, 0x1a, 0x3f, 0x36, 0x23, 0x18,
+    ];
+
+    fn combined(shares: &[&String]) -> AppResult<Zeroizing<Vec<u8>>> {
+        let shares: Vec<&str> = shares.iter().map(|share| share.as_str()).collect();
+        combine(&shares)
+    }
+
+    /// Every `size`-element subset of `items`, in order
+    fn subsets<T: Copy>(items: &[T], size: usize) -> Vec<Vec<T>> {
+        if size == 0 {
+            return vec![Vec::new()];
+        }
+        if items.len() < size {
+            return Vec::new();
+        }
+        let mut with_first = subsets(&items[1..], size - 1);
+        for subset in &mut with_first {
+            subset.insert(0, items[0]);
+        }
+        with_first.extend(subsets(&items[1..], size));
+        with_first
+    }
+
+    fn validation_message(result: AppResult<Zeroizing<Vec<u8>>>) -> String {
+        match result {
+            Err(AppError::ValidationError(message)) => message,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("shares combined"),
+        }
+    }
+
+    #[test]
+    fn gf256_division_inverts_multiplication() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_div(1, a)), 1, "{}", a);
+            for b in [1u8, 2, 3, 0x53, 0xca, 0xff] {
+                assert_eq!(gf_div(gf_mul(a, b), b), a);
+            }
+        }
+    }
+
+    #[test]
+    fn any_threshold_of_the_shares_rebuild_the_secret() {
+        for (threshold, count) in [(1, 1), (1, 3), (2, 2), (2, 3), (3, 5), (4, 4), (5, 7), (16, 16)] {
+            let shares = split(&SECRET, threshold, count).unwrap();
+            assert_eq!(shares.len(), count as usize);
+            assert!(shares.iter().all(|share| share.starts_with(SHARE_PREFIX)));
+
+            let all: Vec<&String> = shares.iter().collect();
+            // Every subset for small splits; the first and last `threshold` shares for 16-of-16
+            let chosen = if count <= 7 { subsets(&all, threshold as usize) } else { vec![all.clone()] };
+            for subset in chosen {
+                assert_eq!(&combined(&subset).unwrap()[..], &SECRET[..], "{}-of-{}", threshold, count);
+            }
+            // More shares than needed are fine too
+            assert_eq!(&combined(&all).unwrap()[..], &SECRET[..]);
+        }
+    }
+
+    #[test]
+    fn fewer_than_threshold_shares_are_refused() {
+        let shares = split(&SECRET, 3, 5).unwrap();
+        let message = validation_message(combined(&[&shares[0], &shares[4]]));
+        assert_eq!(message, "Need 3 backup shares, got 2");
+        assert!(matches!(combine(&[]), Err(AppError::ValidationError(_))));
+    }
+
+    #[test]
+    fn duplicate_shares_are_refused() {
+        let shares = split(&SECRET, 2, 3).unwrap();
+        let message = validation_message(combined(&[&shares[1], &shares[1]]));
+        assert!(message.contains("given twice"), "{}", message);
+    }
+
+    #[test]
+    fn shares_from_different_backups_are_refused() {
+        let first = split(&SECRET, 2, 3).unwrap();
+        let identifier = decode(&first[0]).unwrap().identifier;
+        // Identifiers are random; split again in the unlikely case they collide
+        let second = loop {
+            let second = split(&SECRET, 2, 3).unwrap();
+            if decode(&second[0]).unwrap().identifier != identifier {
+                break second;
+            }
+        };
+
+        let message = validation_message(combined(&[&first[0], &second[1]]));
+        assert_eq!(message, "Backup shares are from different backups");
+    }
+
+    #[test]
+    fn a_mistyped_share_fails_its_checksum() {
+        let shares = split(&SECRET, 2, 3).unwrap();
+        let mut typo = shares[0].clone().into_bytes();
+        let position = SHARE_PREFIX.len() + 12;
+        typo[position] = if typo[position] == b'0' { b'1' } else { b'0' };
+        let typo = String::from_utf8(typo).unwrap();
+
+        let message = validation_message(combined(&[&typo, &shares[1]]));
+        assert!(message.contains("checksum mismatch"), "{}", message);
+        assert!(validation_message(combined(&[&format!("sss2-{}", &shares[0][SHARE_PREFIX.len()..])])).contains("prefix"));
+    }
+
+    #[test]
+    fn the_digest_catches_a_tampered_share_body() {
+        let shares = split(&SECRET, 2, 3).unwrap();
+        // A share with one value byte changed and a checksum that matches the change
+        let mut share = decode(&shares[0]).unwrap();
+        share.value[7] ^= 0x01;
+        let tampered = encode(&share);
+
+        let message = validation_message(combined(&[&tampered, &shares[1]]));
+        assert_eq!(message, "Backup shares do not combine to a valid secret");
+    }
+
+    #[test]
+    fn split_parameters_are_checked() {
+        for (threshold, count) in [(0, 3), (4, 3), (2, MAX_SHARES + 1)] {
+            assert!(matches!(split(&SECRET, threshold, count), Err(AppError::ValidationError(_))), "{}-of-{}", threshold, count);
+        }
+        assert!(split(&SECRET[..MIN_SECRET_LENGTH - 1], 2, 3).is_err());
+    }
+}
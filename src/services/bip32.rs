@@ -0,0 +1,183 @@
+use crate::errors::{AppError, AppResult};
+use crate::secret::SecretKeyGuard;
+use hmac::{Hmac, Mac};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use sha2::Sha512;
+use zeroize::Zeroizing;
+
+/// Standard Ethereum (BIP-44) path under which accounts are numbered; account `n` is `m/44'/60'/0'/0/n`
+pub const DEFAULT_BASE_PATH: &str = "m/44'/60'/0'/0";
+
+const MASTER_KEY_SALT: &[u8] = b"Bitcoin seed";
+const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// Path of the account at `index` under `base_path`
+pub fn account_path(base_path: &str, index: u32) -> String {
+    format!("{}/{}", base_path.trim().trim_end_matches('/'), index)
+}
+
+/// Check that `path` is a well-formed derivation path
+pub fn validate_path(path: &str) -> AppResult<()> {
+    parse_path(path).map(|_| ())
+}
+
+/// Derive the private key at a BIP-32 path such as `m/44'/60'/0'/0/0` from a BIP-39 seed
+pub fn derive_secret_key<C: secp256k1::Signing>(
+    secp: &Secp256k1<C>,
+    seed: &[u8],
+    path: &str,
+) -> AppResult<SecretKeyGuard> {
+    derive_extended_key(secp, seed, path).map(|(secret_key, _)| secret_key)
+}
+
+/// Private key and chain code at `path`
+fn derive_extended_key<C: secp256k1::Signing>(
+    secp: &Secp256k1<C>,
+    seed: &[u8],
+    path: &str,
+) -> AppResult<(SecretKeyGuard, Zeroizing<[u8; 32]>)> {
+    let indices = parse_path(path)?;
+
+    let (mut secret_key, mut chain_code) = split_key(&hmac_sha512(MASTER_KEY_SALT, seed))?;
+    for index in indices {
+        let mut data = Zeroizing::new(Vec::with_capacity(37));
+        if index >= HARDENED_OFFSET {
+            data.push(0);
+            data.extend_from_slice(secret_key.as_ref());
+        } else {
+            data.extend_from_slice(&PublicKey::from_secret_key(secp, &secret_key).serialize());
+        }
+        data.extend_from_slice(&index.to_be_bytes());
+
+        let (tweak, next_chain_code) = split_key(&hmac_sha512(chain_code.as_ref(), &data))?;
+        secret_key
+            .add_assign(tweak.as_ref())
+            .map_err(|e| AppError::InternalError(format!("Key derivation failed at index {}: {}", index, e)))?;
+        chain_code = next_chain_code;
+    }
+
+    Ok((secret_key, chain_code))
+}
+
+/// Parse `m/a'/b/...` into child indices; `'` or `h` marks a hardened index
+fn parse_path(path: &str) -> AppResult<Vec<u32>> {
+    let invalid = || AppError::ValidationError(format!("Invalid derivation path: {}", path));
+
+    let mut segments = path.trim().split('/');
+    if segments.next() != Some("m") {
+        return Err(invalid());
+    }
+
+    segments
+        .map(|segment| {
+            let (number, hardened) = match segment.strip_suffix('\'').or_else(|| segment.strip_suffix('h')) {
+                Some(number) => (number, true),
+                None => (segment, false),
+            };
+            let index: u32 = number.parse().map_err(|_| invalid())?;
+            if index >= HARDENED_OFFSET {
+                return Err(invalid());
+            }
+            Ok(if hardened { index + HARDENED_OFFSET } else { index })
+        })
+        .collect()
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> Zeroizing<[u8; 64]> {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    Zeroizing::new(mac.finalize().into_bytes().into())
+}
+
+/// Split an HMAC output into the key half and the chain code half
+fn split_key(output: &[u8; 64]) -> AppResult<(SecretKeyGuard, Zeroizing<[u8; 32]>)> {
+    let secret_key = SecretKey::from_slice(&output[..32])
+        .map_err(|e| AppError::InternalError(format!("Derived an invalid key: {}", e)))?;
+    let mut chain_code = Zeroizing::new([0u8; 32]);
+    chain_code.copy_from_slice(&output[32..]);
+    Ok((SecretKeyGuard::new(secret_key), chain_code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(path, chain code, private key)` along a BIP-32 test vector
+    type Vector = [(&'static str, &'static str, &'static str)];
+
+    fn assert_vector(seed: &str, vector: &Vector) {
+        let secp = Secp256k1::new();
+        let seed = hex::decode(seed).unwrap();
+        for (path, chain_code, secret_key) in vector {
+            let (derived_key, derived_chain_code) = derive_extended_key(&secp, &seed, path).unwrap();
+            assert_eq!(hex::encode(derived_chain_code.as_ref()), *chain_code, "chain code at {}", path);
+            assert_eq!(hex::encode(derived_key.as_ref()), *secret_key, "key at {}", path);
+        }
+    }
+
+    #[test]
+    fn derives_bip32_test_vector_1() {
+        assert_vector(
+            "000102030405060708090a0b0c0d0e0f",
+            &[
+                ("m", "873dff81c02f525623fd1fe5167eac3a55a049de3d314bb42ee227ffed37d508", "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35"),
+                ("m/0'", "47fdacbd0f1097043b78c63c20c34ef4ed9a111d980047ad16282c7ae6236141", "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea"),
+                ("m/0'/1", "2a7857631386ba23dacac34180dd1983734e444fdbf774041578e9b6adb37c19", "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368"),
+                ("m/0'/1/2'", "04466b9cc8e161e966409ca52986c584f07e9dc81f735db683c3ff6ec7b1503f", "cbce0d719ecf7431d88e6a89fa1483e02e35092af60c042b1df2ff59fa424dca"),
+                ("m/0'/1/2'/2", "cfb71883f01676f587d023cc53a35bc7f88f724b1f8c2892ac1275ac822a3edd", "0f479245fb19a38a1954c5c7c0ebab2f9bdfd96a17563ef28a6a4b1a2a764ef4"),
+                ("m/0'/1/2'/2/1000000000", "c783e67b921d2beb8f6b389cc646d7263b4145701dadd2161548a8b078e65e9e", "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8"),
+            ],
+        );
+    }
+
+    #[test]
+    fn derives_bip32_test_vector_2() {
+        assert_vector(
+            "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542",
+            &[
+                ("m", "60499f801b896d83179a4374aeb7822aaeaceaa0db1f85ee3e904c4defbd9689", "4b03d6fc340455b363f51020ad3ecca4f0850280cf436c70c727923f6db46c3e"),
+                ("m/0", "f0909affaa7ee7abe5dd4e100598d4dc53cd709d5a5c2cac40e7412f232f7c9c", "abe74a98f6c7eabee0428f53798f0ab8aa1bd37873999041703c742f15ac7e1e"),
+                ("m/0/2147483647'", "be17a268474a6bb9c61e1d720cf6215e2a88c5406c4aee7b38547f585c9a37d9", "877c779ad9687164e9c2f4f0f4ff0340814392330693ce95a58fe18fd52e6e93"),
+                ("m/0/2147483647'/1", "f366f48f1ea9f2d1d3fe958c95ca84ea18e4c4ddb9366c336c927eb246fb38cb", "704addf544a06e5ee4bea37098463c23613da32020d604506da8c0518e1da4b7"),
+                ("m/0/2147483647'/1/2147483646'", "637807030d55d01f9a0cb3a7839515d796bd07706386a6eddf06cc29a65a0e29", "f1c7c871a54a804afe328b4c83a1c33b8e5ff48f5087273f04efa83b247d6a2d"),
+                ("m/0/2147483647'/1/2147483646'/2", "9452b549be8cea3ecb7a84bec10dcfd94afe4d129ebfd3b3cb58eedf394ed271", "bb7d39bdb83ecf58f2fd82b6d918341cbef428661ef01ab97c28a4842125ac23"),
+            ],
+        );
+    }
+
+    #[test]
+    fn parses_hardened_markers() {
+        assert_eq!(parse_path("m").unwrap(), Vec::<u32>::new());
+        assert_eq!(parse_path("m/0'").unwrap(), [HARDENED_OFFSET]);
+        assert_eq!(parse_path("m/0h").unwrap(), [HARDENED_OFFSET]);
+        assert_eq!(parse_path(" m/44'/60h/0'/0/7 ").unwrap(), [HARDENED_OFFSET + 44, HARDENED_OFFSET + 60, HARDENED_OFFSET, 0, 7]);
+        assert_eq!(parse_path("m/2147483647'").unwrap(), [u32::MAX]);
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        for path in [
+            "",
+            "44'/60'",
+            "M/0",
+            "m/",
+            "m//0",
+            "m/-1",
+            "m/0''",
+            "m/x",
+            // Indexes from 2^31 up are only reachable with a hardened marker on a smaller number
+            "m/2147483648",
+            "m/2147483648'",
+            "m/2147483648h",
+            "m/4294967296",
+        ] {
+            assert!(matches!(parse_path(path), Err(AppError::ValidationError(_))), "accepted {:?}", path);
+        }
+    }
+
+    #[test]
+    fn account_paths_append_the_index() {
+        assert_eq!(account_path(DEFAULT_BASE_PATH, 3), "m/44'/60'/0'/0/3");
+        assert_eq!(account_path("m/44'/60'/0'/ ", 0), "m/44'/60'/0'/0");
+    }
+}
@@ -0,0 +1,393 @@
+//! EIP-712 typed structured data hashing for arbitrary `eth_signTypedData_v4` payloads.
+
+use crate::errors::{AppError, AppResult};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use tiny_keccak::keccak256;
+use web3::types::{Address, H256, U256};
+
+/// Name of the domain struct type
+const DOMAIN_TYPE: &str = "EIP712Domain";
+/// Domain fields in the order wallets use when `types` does not declare `EIP712Domain`
+const DOMAIN_FIELDS: [(&str, &str); 5] = [
+    ("name", "string"),
+    ("version", "string"),
+    ("chainId", "uint256"),
+    ("verifyingContract", "address"),
+    ("salt", "bytes32"),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TypedField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// The standard typed-data JSON (`domain`, `types`, `primaryType`, `message`)
+#[derive(Debug, Deserialize)]
+pub struct TypedData {
+    pub types: HashMap<String, Vec<TypedField>>,
+    #[serde(rename = "primaryType")]
+    pub primary_type: String,
+    pub domain: Map<String, Value>,
+    #[serde(default)]
+    pub message: Value,
+}
+
+impl TypedData {
+    /// Digest to sign: `keccak256(0x1901 || domainSeparator || hashStruct(message))`.
+    /// The message hash is left out when the primary type is the domain itself.
+    pub fn digest(&self) -> AppResult<H256> {
+        let mut encoded = Vec::with_capacity(2 + 2 * 32);
+        encoded.extend_from_slice(&[0x19, 0x01]);
+        encoded.extend_from_slice(self.domain_separator()?.as_bytes());
+        if self.primary_type != DOMAIN_TYPE {
+            encoded.extend_from_slice(self.hash_struct(&self.primary_type, &self.message)?.as_bytes());
+        }
+        Ok(H256::from(keccak256(&encoded)))
+    }
+
+    /// `hashStruct(domain)`
+    pub fn domain_separator(&self) -> AppResult<H256> {
+        self.hash_struct(DOMAIN_TYPE, &Value::Object(self.domain.clone()))
+    }
+
+    /// `keccak256(typeHash || encodeData(value))`
+    pub fn hash_struct(&self, type_name: &str, value: &Value) -> AppResult<H256> {
+        let mut encoded = keccak256(self.encode_type(type_name)?.as_bytes()).to_vec();
+        encoded.extend(self.encode_data(type_name, value)?);
+        Ok(H256::from(keccak256(&encoded)))
+    }
+
+    /// `Primary(type name,...)` followed by every referenced struct type, sorted by name
+    pub fn encode_type(&self, type_name: &str) -> AppResult<String> {
+        let mut dependencies = BTreeSet::new();
+        self.collect_dependencies(type_name, &mut dependencies)?;
+        dependencies.remove(type_name);
+
+        let mut encoded = String::new();
+        for name in std::iter::once(type_name).chain(dependencies.iter().map(String::as_str)) {
+            let fields = self.fields(name)?;
+            let members: Vec<String> = fields.iter().map(|field| format!("{} {}", field.kind, field.name)).collect();
+            encoded.push_str(&format!("{}({})", name, members.join(",")));
+        }
+        Ok(encoded)
+    }
+
+    /// Fields of a struct type; an undeclared `EIP712Domain` is inferred from the domain's keys
+    fn fields(&self, type_name: &str) -> AppResult<Vec<TypedField>> {
+        if let Some(fields) = self.types.get(type_name) {
+            return Ok(fields.clone());
+        }
+        if type_name == DOMAIN_TYPE {
+            return Ok(DOMAIN_FIELDS
+                .iter()
+                .filter(|(name, _)| self.domain.contains_key(*name))
+                .map(|(name, kind)| TypedField { name: name.to_string(), kind: kind.to_string() })
+                .collect());
+        }
+        Err(AppError::ValidationError(format!("Unknown type {}", type_name)))
+    }
+
+    fn is_struct(&self, type_name: &str) -> bool {
+        type_name == DOMAIN_TYPE || self.types.contains_key(type_name)
+    }
+
+    fn collect_dependencies(&self, type_name: &str, found: &mut BTreeSet<String>) -> AppResult<()> {
+        if !self.is_struct(type_name) || !found.insert(type_name.to_string()) {
+            return Ok(());
+        }
+        for field in self.fields(type_name)? {
+            self.collect_dependencies(element_type(&field.kind), found)?;
+        }
+        Ok(())
+    }
+
+    /// Each field of `value` encoded to one 32-byte word, in declaration order
+    fn encode_data(&self, type_name: &str, value: &Value) -> AppResult<Vec<u8>> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| AppError::ValidationError(format!("Expected an object for {}", type_name)))?;
+
+        let fields = self.fields(type_name)?;
+        let mut encoded = Vec::with_capacity(fields.len() * 32);
+        for field in &fields {
+            let field_value = object.get(&field.name).ok_or_else(|| {
+                AppError::ValidationError(format!("Missing field {} of {}", field.name, type_name))
+            })?;
+            let word = self
+                .encode_value(&field.kind, field_value)
+                .map_err(|e| AppError::ValidationError(format!("{}.{}: {}", type_name, field.name, e)))?;
+            encoded.extend_from_slice(word.as_bytes());
+        }
+        Ok(encoded)
+    }
+
+    /// One field as a word: structs and arrays by their hash, dynamic values by their keccak,
+    /// atomic values padded to 32 bytes
+    fn encode_value(&self, kind: &str, value: &Value) -> Result<H256, String> {
+        if let Some(element) = kind.strip_suffix(']') {
+            let (element, length) = element.rsplit_once('[').ok_or_else(|| format!("Invalid type {}", kind))?;
+            let items = value.as_array().ok_or("expected an array")?;
+            if !length.is_empty() && length.parse::<usize>().ok() != Some(items.len()) {
+                return Err(format!("expected {} items, got {}", length, items.len()));
+            }
+            let mut encoded = Vec::with_capacity(items.len() * 32);
+            for item in items {
+                encoded.extend_from_slice(self.encode_value(element, item)?.as_bytes());
+            }
+            return Ok(H256::from(keccak256(&encoded)));
+        }
+
+        if self.is_struct(kind) {
+            return self.hash_struct(kind, value).map_err(|e| match e {
+                AppError::ValidationError(message) => message,
+                e => e.to_string(),
+            });
+        }
+
+        match kind {
+            "string" => {
+                let text = value.as_str().ok_or("expected a string")?;
+                Ok(H256::from(keccak256(text.as_bytes())))
+            }
+            "bytes" => Ok(H256::from(keccak256(&hex_bytes(value)?))),
+            "bool" => {
+                let flag = value.as_bool().ok_or("expected true or false")?;
+                Ok(H256::from_low_u64_be(flag as u64))
+            }
+            "address" => {
+                let text = value.as_str().ok_or("expected an address string")?;
+                let address = Address::from_str(text.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+                Ok(H256::from(address))
+            }
+            _ => {
+                if let Some(size) = kind.strip_prefix("bytes") {
+                    let size = parse_size(size, 32).ok_or_else(|| format!("Unknown type {}", kind))?;
+                    let bytes = hex_bytes(value)?;
+                    if bytes.len() != size {
+                        return Err(format!("expected {} bytes, got {}", size, bytes.len()));
+                    }
+                    let mut word = [0u8; 32];
+                    word[..size].copy_from_slice(&bytes);
+                    return Ok(H256::from(word));
+                }
+                if let Some(bits) = kind.strip_prefix("uint") {
+                    let bits = parse_size(bits, 256).filter(|bits| bits % 8 == 0).ok_or_else(|| format!("Unknown type {}", kind))?;
+                    let (negative, magnitude) = parse_integer(value)?;
+                    if negative || (bits < 256 && magnitude >> bits != U256::zero()) {
+                        return Err(format!("{} does not fit in {}", value, kind));
+                    }
+                    return Ok(word_of(magnitude));
+                }
+                if let Some(bits) = kind.strip_prefix("int") {
+                    let bits = parse_size(bits, 256).filter(|bits| bits % 8 == 0).ok_or_else(|| format!("Unknown type {}", kind))?;
+                    let (negative, magnitude) = parse_integer(value)?;
+                    // Two's complement range: -2^(bits-1) ..= 2^(bits-1) - 1
+                    let limit = U256::one() << (bits - 1);
+                    if (negative && magnitude > limit) || (!negative && magnitude >= limit) {
+                        return Err(format!("{} does not fit in {}", value, kind));
+                    }
+                    let word = if negative { (!magnitude).overflowing_add(U256::one()).0 } else { magnitude };
+                    return Ok(word_of(word));
+                }
+                Err(format!("Unknown type {}", kind))
+            }
+        }
+    }
+}
+
+/// `Mail[]` and `Mail[2]` reference `Mail`
+fn element_type(kind: &str) -> &str {
+    kind.split('[').next().unwrap_or(kind)
+}
+
+/// Size suffix of `bytesN`/`uintN`/`intN`; empty means the maximum
+fn parse_size(suffix: &str, max: usize) -> Option<usize> {
+    if suffix.is_empty() {
+        return Some(max);
+    }
+    suffix.parse().ok().filter(|size| (1..=max).contains(size))
+}
+
+/// Sign and magnitude of a JSON number or a decimal / `0x` hex string
+fn parse_integer(value: &Value) -> Result<(bool, U256), String> {
+    let text = match value {
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => text.trim().to_string(),
+        _ => return Err("expected a number or numeric string".to_string()),
+    };
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.as_str()),
+    };
+    let magnitude = match digits.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(digits).ok(),
+    }
+    .ok_or_else(|| format!("invalid integer {}", text))?;
+    Ok((negative && !magnitude.is_zero(), magnitude))
+}
+
+fn hex_bytes(value: &Value) -> Result<Vec<u8>, String> {
+    let text = value.as_str().ok_or("expected a 0x hex string")?;
+    hex::decode(text.trim_start_matches("0x")).map_err(|e| format!("invalid hex: {}", e))
+}
+
+fn word_of(value: U256) -> H256 {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    H256::from(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::WalletService;
+    use serde_json::json;
+
+    /// The `Mail` example from the EIP-712 specification
+    fn mail() -> TypedData {
+        serde_json::from_value(json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallet", "type": "address" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person" },
+                    { "name": "contents", "type": "string" }
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+                "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+                "contents": "Hello, Bob!"
+            }
+        }))
+        .unwrap()
+    }
+
+    fn h256(hex: &str) -> H256 {
+        H256::from_str(hex).unwrap()
+    }
+
+    /// Typed data with a single `Value` struct holding one field of `kind`
+    fn single_field(kind: &str) -> TypedData {
+        serde_json::from_value(json!({
+            "types": { "Value": [{ "name": "value", "type": kind }] },
+            "primaryType": "Value",
+            "domain": {}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn hashes_the_specification_example() {
+        let typed_data = mail();
+        assert_eq!(
+            typed_data.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            typed_data.domain_separator().unwrap(),
+            h256("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+        assert_eq!(
+            typed_data.hash_struct("Mail", &typed_data.message).unwrap(),
+            h256("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+        );
+        assert_eq!(
+            typed_data.digest().unwrap(),
+            h256("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn signs_the_specification_example_with_the_cow_key() {
+        let wallet_service = WalletService::new();
+        let cow = wallet_service.import_private_key(&hex::encode(keccak256(b"cow"))).unwrap();
+        assert_eq!(cow.public_address, "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826");
+
+        let signature = wallet_service.sign_typed_data(&cow, mail().digest().unwrap(), "Mail").unwrap();
+        assert_eq!(signature.v, 28);
+        assert_eq!(signature.r, h256("4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d"));
+        assert_eq!(signature.s, h256("07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562"));
+    }
+
+    #[test]
+    fn hashes_arrays_of_atomic_values_and_structs() {
+        let mut typed_data = mail();
+        typed_data.types.insert(
+            "Group".to_string(),
+            serde_json::from_value(json!([
+                { "name": "members", "type": "Person[]" },
+                { "name": "scores", "type": "uint8[2]" }
+            ]))
+            .unwrap(),
+        );
+        let cow = json!({ "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" });
+        let bob = json!({ "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" });
+        let group = json!({ "members": [cow, bob], "scores": [1, 2] });
+
+        assert_eq!(
+            typed_data.encode_type("Group").unwrap(),
+            "Group(Person[] members,uint8[2] scores)Person(string name,address wallet)"
+        );
+        // An array is the keccak of its elements' encodings; struct elements are encoded by their hash
+        let members = [cow.clone(), bob.clone()].map(|person| typed_data.hash_struct("Person", &person).unwrap().0).concat();
+        let scores = [word_of(U256::from(1)).0, word_of(U256::from(2)).0].concat();
+        let type_hash = keccak256(typed_data.encode_type("Group").unwrap().as_bytes());
+        let expected = keccak256(&[&type_hash[..], &keccak256(&members), &keccak256(&scores)].concat());
+        assert_eq!(typed_data.hash_struct("Group", &group).unwrap(), H256::from(expected));
+
+        let short = json!({ "members": [], "scores": [1] });
+        let error = typed_data.hash_struct("Group", &short).unwrap_err().to_string();
+        assert!(error.contains("expected 2 items, got 1"), "{}", error);
+    }
+
+    #[test]
+    fn checks_unsigned_integer_ranges() {
+        let typed_data = single_field("uint8");
+        assert_eq!(typed_data.encode_value("uint8", &json!(255)).unwrap(), word_of(U256::from(255)));
+        assert_eq!(typed_data.encode_value("uint8", &json!("0xff")).unwrap(), word_of(U256::from(255)));
+        for out_of_range in [json!(256), json!(-1), json!("-0x01")] {
+            assert!(typed_data.encode_value("uint8", &out_of_range).is_err(), "{}", out_of_range);
+        }
+        assert_eq!(typed_data.encode_value("uint256", &json!(U256::MAX.to_string())).unwrap(), word_of(U256::MAX));
+        assert!(typed_data.encode_value("uint7", &json!(1)).is_err());
+        assert!(typed_data.encode_value("uint264", &json!(1)).is_err());
+    }
+
+    #[test]
+    fn checks_signed_integer_ranges_and_encodes_twos_complement() {
+        let typed_data = single_field("int8");
+        assert_eq!(typed_data.encode_value("int8", &json!(127)).unwrap(), word_of(U256::from(127)));
+        assert_eq!(typed_data.encode_value("int8", &json!(-1)).unwrap(), word_of(U256::MAX));
+        assert_eq!(typed_data.encode_value("int8", &json!(-128)).unwrap(), word_of(U256::MAX - U256::from(127)));
+        for out_of_range in [json!(128), json!(-129)] {
+            assert!(typed_data.encode_value("int8", &out_of_range).is_err(), "{}", out_of_range);
+        }
+        let min_int256 = format!("-{}", U256::one() << 255);
+        assert_eq!(typed_data.encode_value("int256", &json!(min_int256)).unwrap(), word_of(U256::one() << 255));
+        let error = typed_data.hash_struct("Value", &json!({ "value": 300 })).unwrap_err().to_string();
+        assert!(error.contains("Value.value: 300 does not fit in int8"), "{}", error);
+    }
+}
@@ -0,0 +1,43 @@
+//! Addresses of the imported mnemonic's HD accounts, kept so listing and deriving them does not repeat
+//! the BIP-32 derivation on every request.
+
+use crate::errors::AppResult;
+use crate::models::DerivedAddress;
+use crate::services::{bip32, WalletService};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Derived HD addresses by account index; only valid for the seed they were derived from, so it is
+/// cleared whenever the seed is replaced
+#[derive(Default)]
+pub struct HdAddressCache {
+    addresses: RwLock<HashMap<u32, DerivedAddress>>,
+}
+
+impl HdAddressCache {
+    pub fn get(&self, index: u32) -> Option<DerivedAddress> {
+        self.addresses.read().unwrap().get(&index).cloned()
+    }
+
+    pub fn insert(&self, address: DerivedAddress) {
+        self.addresses.write().unwrap().insert(address.index, address);
+    }
+
+    pub fn clear(&self) {
+        self.addresses.write().unwrap().clear();
+    }
+
+    /// Derive accounts `0..count` under `base_path` ahead of their first request
+    pub fn preload(&self, wallet_service: &WalletService, seed: &[u8], base_path: &str, count: u32) -> AppResult<()> {
+        for index in 0..count {
+            let derivation_path = bip32::account_path(base_path, index);
+            let account = wallet_service.account_from_seed(seed, &derivation_path)?;
+            self.insert(DerivedAddress {
+                index,
+                derivation_path,
+                address: account.public_address,
+            });
+        }
+        Ok(())
+    }
+}
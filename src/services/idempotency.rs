@@ -0,0 +1,137 @@
+//! `Idempotency-Key` replay protection for sends: a retried request gets the first result back
+//! instead of signing again.
+
+use crate::errors::{AppError, AppResult};
+use crate::models::TransactionInfo;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tiny_keccak::keccak256;
+use tracing::debug;
+
+/// How often expired keys are swept
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+enum KeyState {
+    /// The first request with the key is still being processed
+    InProgress,
+    Completed { info: Box<TransactionInfo>, at: Instant },
+}
+
+struct KeyEntry {
+    /// Hash of the request the key was first used with
+    fingerprint: [u8; 32],
+    state: KeyState,
+}
+
+/// Results of completed sends by idempotency key, kept for `server.idempotency_ttl_secs`
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: RwLock<HashMap<String, KeyEntry>>,
+}
+
+/// Outcome of presenting a key
+pub enum Idempotent<'a> {
+    /// The key already completed with the same request; answer with this instead of sending
+    Replay(Box<TransactionInfo>),
+    /// First use of the key; complete the guard with the result, or drop it to release the key
+    New(IdempotencyGuard<'a>),
+}
+
+/// Holds a key while its request is processed. Dropping it without `complete` (an error, or the
+/// client going away) releases the key so a retry can try again.
+#[must_use]
+pub struct IdempotencyGuard<'a> {
+    cache: &'a IdempotencyCache,
+    key: Option<String>,
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Claim `key` for `request`, or get the stored result when it was already used for the same request.
+    /// Reusing a key for a different request, or while its first request is in flight, is a conflict.
+    pub fn begin(&self, key: &str, request: &impl Serialize) -> AppResult<Idempotent<'_>> {
+        let body = serde_json::to_vec(request)
+            .map_err(|e| AppError::InternalError(format!("Failed to fingerprint request: {}", e)))?;
+        let fingerprint = keccak256(&body);
+
+        let mut entries = self.entries.write().unwrap();
+        if let Some(entry) = entries.get(key) {
+            let expired = matches!(entry.state, KeyState::Completed { at, .. } if at.elapsed() >= self.ttl);
+            if !expired {
+                if entry.fingerprint != fingerprint {
+                    return Err(AppError::Conflict(format!(
+                        "Idempotency-Key {} was already used with a different request",
+                        key
+                    )));
+                }
+                return match &entry.state {
+                    KeyState::InProgress => Err(AppError::Conflict(format!(
+                        "A request with Idempotency-Key {} is still in progress",
+                        key
+                    ))),
+                    KeyState::Completed { info, .. } => Ok(Idempotent::Replay(info.clone())),
+                };
+            }
+        }
+
+        entries.insert(key.to_string(), KeyEntry { fingerprint, state: KeyState::InProgress });
+        Ok(Idempotent::New(IdempotencyGuard {
+            cache: self,
+            key: Some(key.to_string()),
+        }))
+    }
+
+    /// Drop completed keys older than the TTL
+    pub fn evict_expired(&self) {
+        let mut entries = self.entries.write().unwrap();
+        let before = entries.len();
+        entries.retain(|_, entry| match entry.state {
+            KeyState::InProgress => true,
+            KeyState::Completed { at, .. } => at.elapsed() < self.ttl,
+        });
+        if entries.len() < before {
+            debug!("Evicted {} expired idempotency keys", before - entries.len());
+        }
+    }
+
+    /// Sweep expired keys in the background
+    pub fn spawn_eviction(cache: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+            loop {
+                interval.tick().await;
+                cache.evict_expired();
+            }
+        });
+    }
+}
+
+impl IdempotencyGuard<'_> {
+    /// Store the result for replays of the key
+    pub fn complete(mut self, info: &TransactionInfo) {
+        if let Some(key) = self.key.take() {
+            if let Some(entry) = self.cache.entries.write().unwrap().get_mut(&key) {
+                entry.state = KeyState::Completed {
+                    info: Box::new(info.clone()),
+                    at: Instant::now(),
+                };
+            }
+        }
+    }
+}
+
+impl Drop for IdempotencyGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.cache.entries.write().unwrap().remove(&key);
+        }
+    }
+}
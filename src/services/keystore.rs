@@ -0,0 +1,305 @@
+use crate::errors::{AppError, AppResult};
+use aes::Aes128;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use tiny_keccak::keccak256;
+use zeroize::Zeroizing;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+const KEYSTORE_VERSION: u32 = 3;
+const CIPHER: &str = "aes-128-ctr";
+const KDF: &str = "scrypt";
+/// Accepted when decrypting keystores written by other wallets; new files always use scrypt
+const KDF_PBKDF2: &str = "pbkdf2";
+const PBKDF2_PRF: &str = "hmac-sha256";
+const DERIVED_KEY_LENGTH: usize = 32;
+
+/// scrypt cost for newly written keystores (N = 2^14). Geth's "standard" 2^18 takes
+/// seconds per unlock; files written elsewhere are decrypted with their own parameters.
+const SCRYPT_LOG_N: u8 = 14;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// Upper bounds on the cost of keystores written elsewhere, which arrive through `/accounts/import`.
+/// Past these a file could make scrypt allocate gigabytes or keep pbkdf2 busy for hours; geth's
+/// "standard" setting is N = 2^18, r = 8, p = 1 and MetaMask's pbkdf2 uses 262144 rounds.
+/// scrypt needs 128 * N * r bytes, so the largest accepted file takes 256 MiB per request.
+const MAX_SCRYPT_N: u64 = 1 << 18;
+const MAX_SCRYPT_R: u32 = 8;
+const MAX_SCRYPT_P: u32 = 16;
+const MAX_PBKDF2_ROUNDS: u32 = 10_000_000;
+
+/// Web3 Secret Storage (v3) keystore file
+#[derive(Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub id: String,
+    pub address: String,
+    pub crypto: KeystoreCrypto,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    pub cipher: String,
+    pub cipherparams: CipherParams,
+    pub ciphertext: String,
+    pub kdf: String,
+    pub kdfparams: KdfParams,
+    pub mac: String,
+}
+
+/// Parameters of the key derivation named by `kdf`
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KdfParams {
+    Scrypt(ScryptParams),
+    Pbkdf2(Pbkdf2Params),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CipherParams {
+    pub iv: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScryptParams {
+    pub dklen: usize,
+    pub n: u64,
+    pub r: u32,
+    pub p: u32,
+    pub salt: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Pbkdf2Params {
+    pub dklen: usize,
+    pub c: u32,
+    pub prf: String,
+    pub salt: String,
+}
+
+impl KdfParams {
+    fn salt(&self) -> &str {
+        match self {
+            KdfParams::Scrypt(params) => &params.salt,
+            KdfParams::Pbkdf2(params) => &params.salt,
+        }
+    }
+}
+
+/// Encrypt a secret key into a v3 keystore for the given address
+pub fn encrypt(secret: &[u8], address: &str, password: &str) -> AppResult<Keystore> {
+    let mut salt = [0u8; 32];
+    let mut iv = [0u8; 16];
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut iv);
+    OsRng.fill_bytes(&mut id);
+
+    let kdfparams = ScryptParams {
+        dklen: DERIVED_KEY_LENGTH,
+        n: 1 << SCRYPT_LOG_N,
+        r: SCRYPT_R,
+        p: SCRYPT_P,
+        salt: hex::encode(salt),
+    };
+    let derived_key = derive_scrypt_key(password, &salt, &kdfparams)
+        .map_err(|e| AppError::WalletCreationFailed(format!("Failed to derive key: {}", e)))?;
+
+    let mut ciphertext = secret.to_vec();
+    Aes128Ctr::new(derived_key[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+
+    Ok(Keystore {
+        version: KEYSTORE_VERSION,
+        id: uuid::Builder::from_random_bytes(id).into_uuid().to_string(),
+        address: address.trim_start_matches("0x").to_lowercase(),
+        crypto: KeystoreCrypto {
+            cipher: CIPHER.to_string(),
+            cipherparams: CipherParams { iv: hex::encode(iv) },
+            mac: hex::encode(mac(&derived_key, &ciphertext)),
+            ciphertext: hex::encode(ciphertext),
+            kdf: KDF.to_string(),
+            kdfparams: KdfParams::Scrypt(kdfparams),
+        },
+    })
+}
+
+/// Decrypt the secret key held by a v3 keystore
+pub fn decrypt(keystore: &Keystore, password: &str) -> AppResult<Zeroizing<Vec<u8>>> {
+    let crypto = &keystore.crypto;
+    if keystore.version != KEYSTORE_VERSION {
+        return Err(AppError::WalletLoadFailed(format!("Unsupported keystore version: {}", keystore.version)));
+    }
+    let supported_kdf = matches!(
+        (crypto.kdf.as_str(), &crypto.kdfparams),
+        (KDF, KdfParams::Scrypt(_)) | (KDF_PBKDF2, KdfParams::Pbkdf2(_))
+    );
+    if crypto.cipher != CIPHER || !supported_kdf {
+        return Err(AppError::WalletLoadFailed(format!(
+            "Unsupported keystore cipher/kdf: {}/{}",
+            crypto.cipher, crypto.kdf
+        )));
+    }
+
+    let salt = decode_hex("salt", crypto.kdfparams.salt())?;
+    let iv = decode_hex("iv", &crypto.cipherparams.iv)?;
+    let mut ciphertext = Zeroizing::new(decode_hex("ciphertext", &crypto.ciphertext)?);
+    let expected_mac = decode_hex("mac", &crypto.mac)?;
+    if iv.len() != 16 {
+        return Err(AppError::WalletLoadFailed("Keystore iv must be 16 bytes".to_string()));
+    }
+
+    let derived_key = match &crypto.kdfparams {
+        KdfParams::Scrypt(params) => derive_scrypt_key(password, &salt, params),
+        KdfParams::Pbkdf2(params) => derive_pbkdf2_key(password, &salt, params),
+    }
+    .map_err(AppError::WalletLoadFailed)?;
+    // Constant time, so response timing does not reveal how much of a guessed MAC matched
+    if !bool::from(mac(&derived_key, &ciphertext).as_slice().ct_eq(&expected_mac)) {
+        return Err(AppError::WalletLoadFailed(
+            "Incorrect wallet password (keystore MAC mismatch)".to_string(),
+        ));
+    }
+
+    Aes128Ctr::new(derived_key[..16].into(), iv.as_slice().into()).apply_keystream(&mut ciphertext);
+    Ok(ciphertext)
+}
+
+fn derive_scrypt_key(password: &str, salt: &[u8], params: &ScryptParams) -> Result<Zeroizing<Vec<u8>>, String> {
+    if !params.n.is_power_of_two() || params.dklen != DERIVED_KEY_LENGTH {
+        return Err(format!("Invalid scrypt parameters: n={}, dklen={}", params.n, params.dklen));
+    }
+    if params.n > MAX_SCRYPT_N || params.r > MAX_SCRYPT_R || params.p > MAX_SCRYPT_P {
+        return Err(format!(
+            "scrypt parameters n={}, r={}, p={} exceed the supported n={}, r={}, p={}",
+            params.n, params.r, params.p, MAX_SCRYPT_N, MAX_SCRYPT_R, MAX_SCRYPT_P
+        ));
+    }
+
+    let log_n = params.n.trailing_zeros() as u8;
+    let scrypt_params = scrypt::Params::new(log_n, params.r, params.p, params.dklen)
+        .map_err(|e| format!("Invalid scrypt parameters: {}", e))?;
+
+    let mut derived_key = Zeroizing::new(vec![0u8; params.dklen]);
+    scrypt::scrypt(password.as_bytes(), salt, &scrypt_params, &mut derived_key)
+        .map_err(|e| format!("scrypt failed: {}", e))?;
+    Ok(derived_key)
+}
+
+fn derive_pbkdf2_key(password: &str, salt: &[u8], params: &Pbkdf2Params) -> Result<Zeroizing<Vec<u8>>, String> {
+    if params.prf != PBKDF2_PRF {
+        return Err(format!("Unsupported pbkdf2 prf: {}", params.prf));
+    }
+    if params.c == 0 || params.dklen != DERIVED_KEY_LENGTH {
+        return Err(format!("Invalid pbkdf2 parameters: c={}, dklen={}", params.c, params.dklen));
+    }
+    if params.c > MAX_PBKDF2_ROUNDS {
+        return Err(format!("pbkdf2 rounds c={} exceed the supported {}", params.c, MAX_PBKDF2_ROUNDS));
+    }
+
+    let mut derived_key = Zeroizing::new(vec![0u8; params.dklen]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, params.c, &mut derived_key);
+    Ok(derived_key)
+}
+
+fn mac(derived_key: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    let mut preimage = Zeroizing::new(derived_key[16..32].to_vec());
+    preimage.extend_from_slice(ciphertext);
+    keccak256(&preimage)
+}
+
+fn decode_hex(field: &str, value: &str) -> AppResult<Vec<u8>> {
+    hex::decode(value).map_err(|e| AppError::WalletLoadFailed(format!("Invalid keystore {}: {}", field, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: [u8; 32] = [0x4c; 32];
+    const ADDRESS: &str = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+
+    #[test]
+    fn round_trips_a_secret() {
+        let keystore = encrypt(&SECRET, ADDRESS, "correct horse").unwrap();
+        assert_eq!(keystore.address, "2c7536e3605d9c16a7a3d7b1898e529396a65c23");
+
+        let decrypted = decrypt(&keystore, "correct horse").unwrap();
+        assert_eq!(decrypted.as_slice(), SECRET.as_slice());
+    }
+
+    #[test]
+    fn rejects_a_wrong_password() {
+        let keystore = encrypt(&SECRET, ADDRESS, "correct horse").unwrap();
+
+        match decrypt(&keystore, "battery staple") {
+            Err(AppError::WalletLoadFailed(message)) => assert!(message.contains("Incorrect wallet password")),
+            other => panic!("expected a password error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    fn with_scrypt_params(update: impl FnOnce(&mut ScryptParams)) -> Keystore {
+        let mut keystore = encrypt(&SECRET, ADDRESS, "correct horse").unwrap();
+        if let KdfParams::Scrypt(params) = &mut keystore.crypto.kdfparams {
+            update(params);
+        }
+        keystore
+    }
+
+    fn assert_rejected(keystore: &Keystore, expected: &str) {
+        match decrypt(keystore, "correct horse") {
+            Err(AppError::WalletLoadFailed(message)) => assert!(message.contains(expected), "{}", message),
+            other => panic!("expected the parameters to be rejected, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_scrypt_parameters() {
+        assert_rejected(&with_scrypt_params(|params| params.n = 1 << 40), "exceed the supported");
+        assert_rejected(&with_scrypt_params(|params| params.r = 1 << 20), "exceed the supported");
+        assert_rejected(&with_scrypt_params(|params| params.p = 1 << 20), "exceed the supported");
+        assert_rejected(&with_scrypt_params(|params| params.dklen = 1 << 40), "Invalid scrypt parameters");
+    }
+
+    #[test]
+    fn refuses_scrypt_costlier_than_geth_standard_before_deriving() {
+        // One step past the cap would take 512 MiB and seconds to derive
+        let keystore = with_scrypt_params(|params| params.n = MAX_SCRYPT_N * 2);
+        let started = std::time::Instant::now();
+        assert_rejected(&keystore, "exceed the supported n=262144");
+        assert!(started.elapsed() < std::time::Duration::from_millis(500), "derived before refusing");
+    }
+
+    #[test]
+    fn rejects_oversized_pbkdf2_parameters() {
+        let mut keystore = encrypt(&SECRET, ADDRESS, "correct horse").unwrap();
+        let pbkdf2 = |c: u32, dklen: usize| {
+            KdfParams::Pbkdf2(Pbkdf2Params {
+                dklen,
+                c,
+                prf: PBKDF2_PRF.to_string(),
+                salt: hex::encode([7u8; 32]),
+            })
+        };
+        keystore.crypto.kdf = KDF_PBKDF2.to_string();
+
+        keystore.crypto.kdfparams = pbkdf2(u32::MAX, DERIVED_KEY_LENGTH);
+        assert_rejected(&keystore, "exceed the supported");
+        keystore.crypto.kdfparams = pbkdf2(1, 1 << 40);
+        assert_rejected(&keystore, "Invalid pbkdf2 parameters");
+    }
+
+    #[test]
+    fn uses_fresh_salt_and_iv() {
+        let first = encrypt(&SECRET, ADDRESS, "correct horse").unwrap();
+        let second = encrypt(&SECRET, ADDRESS, "correct horse").unwrap();
+
+        assert_ne!(first.crypto.kdfparams.salt(), second.crypto.kdfparams.salt());
+        assert_ne!(first.crypto.cipherparams.iv, second.crypto.cipherparams.iv);
+        assert_ne!(first.id, second.id);
+    }
+}
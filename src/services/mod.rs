@@ -1,3 +1,20 @@
+pub mod account_store;
+pub mod address_watcher;
+pub mod audit_logger;
+pub mod backup;
+pub mod bip32;
+pub mod eip712;
+pub mod hd_addresses;
+pub mod idempotency;
+pub mod keystore;
+pub mod mpc_signer;
+pub mod permit;
+pub mod qr_code;
+pub mod rpc_proxy;
+pub mod safe;
+pub mod signer;
+pub mod spend_limiter;
+pub mod transaction_decoder;
 pub mod wallet_service;
 pub mod web3_service;
 
@@ -0,0 +1,379 @@
+//! Threshold (MPC) signing. The wallet key exists only as shares held by co-signer services, which run a
+//! threshold ECDSA protocol (GG20, two-party ECDSA, ...) among themselves. This server coordinates the
+//! session: it relays each round's messages between the peers and checks the signature they produce,
+//! never seeing a share or the key.
+//!
+//! Every peer answers `POST <peer>/sign`, called once per round with the hash to sign and the messages
+//! addressed to it. It replies with its outgoing messages, or with the signature once the protocol is done.
+
+use crate::config::MpcConfig;
+use crate::errors::{AppError, AppResult};
+use crate::services::signer::{self, Signer};
+use crate::utils;
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, info};
+use web3::signing;
+use web3::types::{Address, SignedTransaction, TransactionParameters, H256, U256};
+
+/// Rounds after which a session that has not produced a signature is abandoned; GG20 signing needs 9
+const MAX_ROUNDS: u32 = 16;
+
+/// Order of the secp256k1 group
+const CURVE_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+#[derive(Serialize)]
+struct RoundRequest<'a> {
+    session_id: &'a str,
+    /// Index of the called peer in `mpc.peers`
+    party: usize,
+    parties: usize,
+    address: String,
+    message_hash: H256,
+    round: u32,
+    messages: Vec<&'a PeerMessage>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PeerMessage {
+    /// Sender's party index, set by the coordinator
+    #[serde(default)]
+    from: usize,
+    /// Recipient's party index, or `None` to broadcast
+    #[serde(default)]
+    to: Option<usize>,
+    /// Opaque to the coordinator
+    payload: String,
+}
+
+#[derive(Deserialize)]
+struct RoundResponse {
+    #[serde(default)]
+    messages: Vec<PeerMessage>,
+    signature: Option<PeerSignature>,
+}
+
+#[derive(Deserialize)]
+struct PeerSignature {
+    r: H256,
+    s: H256,
+    /// Recovery id, as 0/1 or 27/28
+    v: u64,
+}
+
+/// Signs transactions for `mpc.address` by running a session with the co-signers in `mpc.peers`
+#[derive(Clone)]
+pub struct MpcSigner {
+    address: Address,
+    peers: Vec<String>,
+    client: reqwest::Client,
+    timeout: Duration,
+}
+
+impl MpcSigner {
+    /// The signer `config` describes, or `None` when MPC signing is not configured
+    pub fn from_config(config: &MpcConfig) -> AppResult<Option<Self>> {
+        let Some(address) = &config.address else {
+            return Ok(None);
+        };
+        let client = reqwest::Client::builder()
+            .build()
+            .map_err(|e| AppError::ConfigurationError(format!("Failed to build the MPC client: {}", e)))?;
+
+        Ok(Some(Self {
+            address: utils::validate_checksummed(address)?,
+            peers: config.peers.iter().map(|peer| peer.trim_end_matches('/').to_string()).collect(),
+            client,
+            timeout: Duration::from_millis(config.timeout_ms),
+        }))
+    }
+
+    /// Have the co-signers sign `message_hash`; returns the recovery id (0 or 1), `r` and a low `s`
+    async fn sign_hash(&self, message_hash: H256) -> AppResult<(u64, H256, H256)> {
+        tokio::time::timeout(self.timeout, self.run_session(message_hash))
+            .await
+            .map_err(|_| {
+                AppError::Timeout(format!("MPC signing session did not finish within {} ms", self.timeout.as_millis()))
+            })?
+    }
+
+    async fn run_session(&self, message_hash: H256) -> AppResult<(u64, H256, H256)> {
+        let session_id = uuid::Builder::from_random_bytes(rand::random()).into_uuid().to_string();
+        let address = utils::to_checksum(&self.address);
+        let mut inbox: Vec<PeerMessage> = Vec::new();
+
+        for round in 0..MAX_ROUNDS {
+            let responses = try_join_all(self.peers.iter().enumerate().map(|(party, peer)| {
+                let request = RoundRequest {
+                    session_id: &session_id,
+                    party,
+                    parties: self.peers.len(),
+                    address: address.clone(),
+                    message_hash,
+                    round,
+                    messages: inbox
+                        .iter()
+                        .filter(|message| message.from != party && message.to.is_none_or(|to| to == party))
+                        .collect(),
+                };
+                async move { self.call_peer(party, peer, &request).await }
+            }))
+            .await?;
+
+            let mut outbox = Vec::new();
+            for (party, response) in responses.into_iter().enumerate() {
+                if let Some(signature) = response.signature {
+                    info!("MPC session {} signed {:?} in {} round(s)", session_id, message_hash, round + 1);
+                    return self.check_signature(message_hash, signature, party);
+                }
+                outbox.extend(response.messages.into_iter().map(|message| PeerMessage { from: party, ..message }));
+            }
+            if outbox.is_empty() {
+                return Err(AppError::CoSignerFailed(format!(
+                    "Co-signers stopped after round {} without a signature",
+                    round
+                )));
+            }
+            debug!("MPC session {} round {}: relaying {} message(s)", session_id, round, outbox.len());
+            inbox = outbox;
+        }
+
+        Err(AppError::CoSignerFailed(format!("No signature after {} rounds", MAX_ROUNDS)))
+    }
+
+    async fn call_peer(&self, party: usize, peer: &str, request: &RoundRequest<'_>) -> AppResult<RoundResponse> {
+        let failed = |reason: String| AppError::CoSignerFailed(format!("Co-signer {} ({}): {}", party, peer, reason));
+
+        let response = self
+            .client
+            .post(format!("{}/sign", peer))
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(failed(format!("{} {}", status, body.trim())));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| failed(format!("unreadable response: {}", e)))
+    }
+
+    /// Normalize `s` to the lower half of the curve order (EIP-2) and make sure the signature is the
+    /// shared key's
+    fn check_signature(&self, message_hash: H256, signature: PeerSignature, party: usize) -> AppResult<(u64, H256, H256)> {
+        let mut recovery_id = match signature.v {
+            0 | 1 => signature.v,
+            27 | 28 => signature.v - 27,
+            other => {
+                return Err(AppError::CoSignerFailed(format!(
+                    "Co-signer {} returned a signature with v = {}",
+                    party, other
+                )))
+            }
+        };
+        let order = U256::from_big_endian(&CURVE_ORDER);
+        let mut s = U256::from_big_endian(signature.s.as_bytes());
+        if s.is_zero() || s >= order {
+            return Err(AppError::CoSignerFailed(format!("Co-signer {} returned an out-of-range s", party)));
+        }
+        if s > order / 2 {
+            s = order - s;
+            recovery_id ^= 1;
+        }
+        let mut s_bytes = [0u8; 32];
+        s.to_big_endian(&mut s_bytes);
+        let s = H256::from(s_bytes);
+
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(signature.r.as_bytes());
+        compact[32..].copy_from_slice(s.as_bytes());
+        match signing::recover(message_hash.as_bytes(), &compact, recovery_id as i32) {
+            Ok(signer) if signer == self.address => Ok((recovery_id, signature.r, s)),
+            _ => Err(AppError::CoSignerFailed(format!(
+                "Co-signer {} returned a signature that is not from {}",
+                party,
+                utils::to_checksum(&self.address)
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for MpcSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(&self, transaction: &TransactionParameters) -> AppResult<SignedTransaction> {
+        let message_hash = signer::signing_hash(transaction)?;
+        let (recovery_id, r, s) = self.sign_hash(message_hash).await?;
+        signer::attach_signature(transaction, recovery_id, r, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{response::IntoResponse, routing::post, Json, Router};
+    use secp256k1::SecretKey;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use web3::signing::{Key, SecretKeyRef};
+
+    const KEY: [u8; 32] = [0x11; 32];
+
+    fn shared_key() -> SecretKey {
+        SecretKey::from_slice(&KEY).unwrap()
+    }
+
+    fn hash() -> H256 {
+        H256::from(signing::keccak256(b"transaction"))
+    }
+
+    /// `(v, r, s)` of the shared key over `hash`, as the co-signers would produce it
+    fn sign(hash: H256) -> (u64, H256, H256) {
+        let signature = SecretKeyRef::new(&shared_key()).sign_message(hash.as_bytes()).unwrap();
+        (signature.v, signature.r, signature.s)
+    }
+
+    /// A co-signer that answers each round with `reply(request)` and records the requests it got
+    async fn start_peer(reply: impl Fn(&Value) -> Value + Send + Sync + 'static) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let reply = Arc::new(reply);
+        let recorded = requests.clone();
+        let app = Router::new().route(
+            "/sign",
+            post(move |Json(request): Json<Value>| {
+                let reply = reply.clone();
+                let recorded = recorded.clone();
+                async move {
+                    recorded.lock().unwrap().push(request.clone());
+                    Json(reply(&request))
+                }
+            }),
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        (url, requests)
+    }
+
+    fn signer(peers: Vec<String>, timeout_ms: u64) -> MpcSigner {
+        let address = SecretKeyRef::new(&shared_key()).address();
+        MpcSigner::from_config(&MpcConfig {
+            address: Some(utils::to_checksum(&address)),
+            peers,
+            timeout_ms,
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    fn signature_reply(v: u64, r: H256, s: H256) -> Value {
+        json!({ "signature": { "r": r, "s": s, "v": v } })
+    }
+
+    #[tokio::test]
+    async fn relays_each_rounds_messages_to_their_recipients() {
+        let (v, r, s) = sign(hash());
+        let (first, first_requests) = start_peer(move |request| match request["round"].as_u64().unwrap() {
+            0 => json!({ "messages": [{ "to": 1, "payload": "first-to-second" }] }),
+            _ => signature_reply(v, r, s),
+        })
+        .await;
+        let (second, second_requests) = start_peer(|request| match request["round"].as_u64().unwrap() {
+            0 => json!({ "messages": [{ "payload": "second-to-all" }] }),
+            _ => json!({ "messages": [] }),
+        })
+        .await;
+
+        let (recovery_id, signed_r, signed_s) = signer(vec![first, second], 5_000).sign_hash(hash()).await.unwrap();
+        assert_eq!((recovery_id, signed_r, signed_s), (v, r, s));
+
+        let first_requests = first_requests.lock().unwrap();
+        let second_requests = second_requests.lock().unwrap();
+        assert_eq!(first_requests.len(), 2);
+        assert_eq!(first_requests[0]["party"], 0);
+        assert_eq!(first_requests[0]["parties"], 2);
+        assert_eq!(first_requests[0]["messages"], json!([]));
+        assert_eq!(first_requests[1]["session_id"], first_requests[0]["session_id"]);
+        // Each party gets what was addressed to it or broadcast by the others, never its own messages
+        assert_eq!(first_requests[1]["messages"], json!([{ "from": 1, "to": null, "payload": "second-to-all" }]));
+        assert_eq!(second_requests[1]["party"], 1);
+        assert_eq!(second_requests[1]["messages"], json!([{ "from": 0, "to": 1, "payload": "first-to-second" }]));
+    }
+
+    #[tokio::test]
+    async fn normalizes_a_high_s_signature() {
+        let (v, r, s) = sign(hash());
+        let order = U256::from_big_endian(&CURVE_ORDER);
+        let mut high_s = [0u8; 32];
+        (order - U256::from_big_endian(s.as_bytes())).to_big_endian(&mut high_s);
+        let (peer, _) = start_peer(move |_| signature_reply((v ^ 1) + 27, r, H256::from(high_s))).await;
+
+        let (recovery_id, signed_r, signed_s) = signer(vec![peer], 5_000).sign_hash(hash()).await.unwrap();
+        assert_eq!((recovery_id, signed_r, signed_s), (v, r, s));
+        assert!(U256::from_big_endian(signed_s.as_bytes()) <= order / 2);
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(signed_r.as_bytes());
+        compact[32..].copy_from_slice(signed_s.as_bytes());
+        let recovered = signing::recover(hash().as_bytes(), &compact, recovery_id as i32).unwrap();
+        assert_eq!(recovered, SecretKeyRef::new(&shared_key()).address());
+    }
+
+    #[tokio::test]
+    async fn rejects_a_signature_from_another_key() {
+        let other = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let signature = SecretKeyRef::new(&other).sign_message(hash().as_bytes()).unwrap();
+        let (peer, _) = start_peer(move |_| signature_reply(signature.v, signature.r, signature.s)).await;
+
+        let error = signer(vec![peer], 5_000).sign_hash(hash()).await.unwrap_err();
+        assert!(matches!(error, AppError::CoSignerFailed(_)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_bad_recovery_id() {
+        let (_, r, s) = sign(hash());
+        let (peer, _) = start_peer(move |_| signature_reply(5, r, s)).await;
+
+        let error = signer(vec![peer], 5_000).sign_hash(hash()).await.unwrap_err();
+        assert!(matches!(error, AppError::CoSignerFailed(_)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_peers_stop_without_a_signature() {
+        let (peer, requests) = start_peer(|_| json!({ "messages": [] })).await;
+
+        let error = signer(vec![peer], 5_000).sign_hash(hash()).await.unwrap_err();
+        assert!(matches!(error, AppError::CoSignerFailed(_)), "{}", error);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_session_over_the_timeout_is_a_gateway_timeout() {
+        // A peer that never answers its first round
+        let app = Router::new().route(
+            "/sign",
+            post(|| async {
+                tokio::time::sleep(Duration::from_secs(300)).await;
+                Json(json!({ "messages": [] }))
+            }),
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let peer = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        let error = signer(vec![peer], 200).sign_hash(hash()).await.unwrap_err();
+        assert!(matches!(error, AppError::Timeout(_)), "{}", error);
+        assert_eq!(error.into_response().status(), axum::http::StatusCode::GATEWAY_TIMEOUT);
+    }
+}
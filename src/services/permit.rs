@@ -0,0 +1,41 @@
+//! EIP-2612 `permit` typed data hashing (EIP-712).
+
+use tiny_keccak::keccak256;
+use web3::types::{Address, H256, U256};
+
+/// Type string hashed into `PERMIT_TYPEHASH` by EIP-2612 tokens
+const PERMIT_TYPE: &str = "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)";
+
+/// The `Permit` message an owner signs to approve `spender` without a transaction
+pub struct Permit {
+    pub owner: Address,
+    pub spender: Address,
+    pub value: U256,
+    pub nonce: U256,
+    pub deadline: U256,
+}
+
+impl Permit {
+    /// `keccak256(abi.encode(PERMIT_TYPEHASH, owner, spender, value, nonce, deadline))`
+    pub fn struct_hash(&self) -> H256 {
+        let mut encoded = Vec::with_capacity(6 * 32);
+        encoded.extend_from_slice(&keccak256(PERMIT_TYPE.as_bytes()));
+        encoded.extend_from_slice(H256::from(self.owner).as_bytes());
+        encoded.extend_from_slice(H256::from(self.spender).as_bytes());
+        for word in [self.value, self.nonce, self.deadline] {
+            let mut bytes = [0u8; 32];
+            word.to_big_endian(&mut bytes);
+            encoded.extend_from_slice(&bytes);
+        }
+        H256::from(keccak256(&encoded))
+    }
+
+    /// EIP-712 digest to sign: `keccak256(0x1901 || domainSeparator || structHash)`
+    pub fn digest(&self, domain_separator: H256) -> H256 {
+        let mut encoded = Vec::with_capacity(2 + 2 * 32);
+        encoded.extend_from_slice(&[0x19, 0x01]);
+        encoded.extend_from_slice(domain_separator.as_bytes());
+        encoded.extend_from_slice(self.struct_hash().as_bytes());
+        H256::from(keccak256(&encoded))
+    }
+}
@@ -0,0 +1,39 @@
+use crate::errors::{AppError, AppResult};
+use qrcode::{Color, QrCode};
+
+/// Pixels per QR module
+const MODULE_SIZE: usize = 8;
+/// Blank border around the code, in modules, as scanners expect
+const QUIET_ZONE: usize = 4;
+
+/// Render `data` as a black-on-white grayscale PNG QR code
+pub fn render_png(data: &str) -> AppResult<Vec<u8>> {
+    let code = QrCode::new(data.as_bytes())
+        .map_err(|e| AppError::ValidationError(format!("Cannot encode QR code: {}", e)))?;
+    let modules = code.width();
+    let colors = code.to_colors();
+
+    let size = (modules + 2 * QUIET_ZONE) * MODULE_SIZE;
+    let mut pixels = vec![0xffu8; size * size];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let left = (i % modules + QUIET_ZONE) * MODULE_SIZE;
+        let top = (i / modules + QUIET_ZONE) * MODULE_SIZE;
+        for y in top..top + MODULE_SIZE {
+            pixels[y * size + left..y * size + left + MODULE_SIZE].fill(0);
+        }
+    }
+
+    let mut png_bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_bytes, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| AppError::InternalError(format!("Failed to encode QR code PNG: {}", e)))?;
+
+    Ok(png_bytes)
+}
@@ -0,0 +1,62 @@
+use crate::config::{RpcFilterMode, RpcProxyConfig};
+
+/// Method namespaces that expose node administration or key material.
+/// These are never forwarded, regardless of the configured filter mode.
+pub const BLOCKED_METHOD_PREFIXES: [&str; 4] = ["personal_", "admin_", "miner_", "debug_"];
+
+/// Decide whether the raw RPC proxy may forward a method to the node
+pub fn is_method_permitted(config: &RpcProxyConfig, method: &str) -> bool {
+    if BLOCKED_METHOD_PREFIXES
+        .iter()
+        .any(|prefix| method.starts_with(prefix))
+    {
+        return false;
+    }
+
+    let listed = config.methods.iter().any(|m| m == method);
+    match config.mode {
+        RpcFilterMode::Allowlist => listed,
+        RpcFilterMode::Denylist => !listed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(mode: RpcFilterMode, methods: &[&str]) -> RpcProxyConfig {
+        RpcProxyConfig { mode, methods: methods.iter().map(|m| m.to_string()).collect() }
+    }
+
+    #[test]
+    fn blocked_prefixes_are_refused_in_either_mode() {
+        let allow_all = config(RpcFilterMode::Allowlist, &["personal_sign", "admin_peers", "miner_start", "debug_traceTransaction"]);
+        let deny_none = config(RpcFilterMode::Denylist, &[]);
+        for method in ["personal_sign", "personal_unlockAccount", "admin_peers", "miner_start", "debug_traceTransaction"] {
+            assert!(!is_method_permitted(&allow_all, method), "{} allowed by allowlist", method);
+            assert!(!is_method_permitted(&deny_none, method), "{} allowed by denylist", method);
+        }
+    }
+
+    #[test]
+    fn prefixes_only_match_at_the_start_of_the_method() {
+        let deny_none = config(RpcFilterMode::Denylist, &[]);
+        assert!(is_method_permitted(&deny_none, "eth_getBalance"));
+        assert!(is_method_permitted(&deny_none, "eth_debug_personal_"));
+        assert!(!is_method_permitted(&deny_none, "debug_"));
+    }
+
+    #[test]
+    fn allowlist_forwards_only_listed_methods() {
+        let allowlist = config(RpcFilterMode::Allowlist, &["eth_blockNumber"]);
+        assert!(is_method_permitted(&allowlist, "eth_blockNumber"));
+        assert!(!is_method_permitted(&allowlist, "eth_getBalance"));
+    }
+
+    #[test]
+    fn denylist_forwards_everything_but_listed_methods() {
+        let denylist = config(RpcFilterMode::Denylist, &["eth_sendRawTransaction"]);
+        assert!(!is_method_permitted(&denylist, "eth_sendRawTransaction"));
+        assert!(is_method_permitted(&denylist, "eth_getBalance"));
+    }
+}
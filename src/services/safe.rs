@@ -0,0 +1,407 @@
+//! Safe (formerly Gnosis Safe) multisig transactions: EIP-712 hashing, owner signatures and
+//! `execTransaction` calldata, plus the in-memory queue of proposals collecting confirmations.
+
+use crate::errors::{AppError, AppResult};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use tiny_keccak::keccak256;
+use web3::signing;
+use web3::types::{Address, H256, U256};
+
+/// Type string hashed into the Safe's `SAFE_TX_TYPEHASH`
+const SAFE_TX_TYPE: &str = "SafeTx(address to,uint256 value,bytes data,uint8 operation,uint256 safeTxGas,uint256 baseGas,uint256 gasPrice,address gasToken,address refundReceiver,uint256 nonce)";
+/// `execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)`
+const EXEC_TRANSACTION_SELECTOR: [u8; 4] = [0x6a, 0x76, 0x12, 0x02];
+/// Arguments of `execTransaction`, each one head word
+const EXEC_TRANSACTION_ARGS: usize = 10;
+/// `r || s || v`
+pub const SIGNATURE_LENGTH: usize = 65;
+
+/// `operation` values a Safe accepts
+pub const CALL: u8 = 0;
+pub const DELEGATE_CALL: u8 = 1;
+
+/// On-chain configuration of a Safe
+pub struct SafeState {
+    pub owners: Vec<Address>,
+    pub threshold: u64,
+    pub nonce: U256,
+    pub domain_separator: H256,
+}
+
+impl SafeState {
+    pub fn is_owner(&self, address: Address) -> bool {
+        self.owners.contains(&address)
+    }
+}
+
+/// A transaction for a Safe to make. The gas refund fields (`safeTxGas`, `baseGas`, `gasPrice`,
+/// `gasToken`, `refundReceiver`) are always zero: whoever executes it pays the gas.
+#[derive(Clone)]
+pub struct SafeTransaction {
+    pub to: Address,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub operation: u8,
+    pub nonce: U256,
+}
+
+impl SafeTransaction {
+    /// `keccak256(abi.encode(SAFE_TX_TYPEHASH, to, value, keccak256(data), operation, 0, 0, 0, 0, 0, nonce))`
+    pub fn struct_hash(&self) -> H256 {
+        let mut encoded = Vec::with_capacity(11 * 32);
+        encoded.extend_from_slice(&keccak256(SAFE_TX_TYPE.as_bytes()));
+        encoded.extend_from_slice(H256::from(self.to).as_bytes());
+        encoded.extend_from_slice(&word(self.value));
+        encoded.extend_from_slice(&keccak256(&self.data));
+        encoded.extend_from_slice(&word(self.operation.into()));
+        for _ in 0..5 {
+            encoded.extend_from_slice(&[0u8; 32]);
+        }
+        encoded.extend_from_slice(&word(self.nonce));
+        H256::from(keccak256(&encoded))
+    }
+
+    /// EIP-712 digest the owners sign, the "Safe transaction hash":
+    /// `keccak256(0x1901 || domainSeparator || structHash)`
+    pub fn digest(&self, domain_separator: H256) -> H256 {
+        let mut encoded = Vec::with_capacity(2 + 2 * 32);
+        encoded.extend_from_slice(&[0x19, 0x01]);
+        encoded.extend_from_slice(domain_separator.as_bytes());
+        encoded.extend_from_slice(self.struct_hash().as_bytes());
+        H256::from(keccak256(&encoded))
+    }
+
+    /// Calldata of `execTransaction` with the packed owner `signatures`
+    pub fn exec_transaction_data(&self, signatures: &[u8]) -> Vec<u8> {
+        let data_offset = EXEC_TRANSACTION_ARGS * 32;
+        let signatures_offset = data_offset + 32 + padded_length(self.data.len());
+
+        let mut encoded = EXEC_TRANSACTION_SELECTOR.to_vec();
+        encoded.extend_from_slice(H256::from(self.to).as_bytes());
+        encoded.extend_from_slice(&word(self.value));
+        encoded.extend_from_slice(&word(data_offset.into()));
+        encoded.extend_from_slice(&word(self.operation.into()));
+        // safeTxGas, baseGas, gasPrice, gasToken, refundReceiver
+        for _ in 0..5 {
+            encoded.extend_from_slice(&[0u8; 32]);
+        }
+        encoded.extend_from_slice(&word(signatures_offset.into()));
+        append_bytes(&mut encoded, &self.data);
+        append_bytes(&mut encoded, signatures);
+        encoded
+    }
+}
+
+/// Owner that produced a 65-byte `r || s || v` signature (`v` = 27/28) of a Safe transaction hash
+pub fn recover_owner(digest: H256, signature: &[u8]) -> AppResult<Address> {
+    if signature.len() != SIGNATURE_LENGTH {
+        return Err(AppError::ValidationError(format!(
+            "Signature must be {} bytes, got {}",
+            SIGNATURE_LENGTH,
+            signature.len()
+        )));
+    }
+    let v = signature[SIGNATURE_LENGTH - 1];
+    if v != 27 && v != 28 {
+        return Err(AppError::ValidationError(
+            "Only plain ECDSA signatures of the Safe transaction hash (v = 27 or 28) are accepted".to_string(),
+        ));
+    }
+    signing::recover(digest.as_bytes(), &signature[..64], i32::from(v - 27))
+        .map_err(|_| AppError::ValidationError("Signature does not recover to an address".to_string()))
+}
+
+/// Decode an ABI-encoded `address[]` return value, or `None` if it is malformed
+pub fn decode_addresses(output: &[u8]) -> Option<Vec<Address>> {
+    let word_at = |offset: usize| -> Option<usize> {
+        let value = U256::from_big_endian(output.get(offset..offset.checked_add(32)?)?);
+        (value <= U256::from(output.len())).then(|| value.as_usize())
+    };
+    let start = word_at(0)?;
+    let length = word_at(start)?;
+
+    (0..length)
+        .map(|index| {
+            let offset = start + 32 * (index + 1);
+            output.get(offset + 12..offset + 32).map(Address::from_slice)
+        })
+        .collect()
+}
+
+/// A proposed Safe transaction and the owner signatures collected for it
+#[derive(Clone)]
+pub struct PendingSafeTransaction {
+    pub safe: Address,
+    pub safe_tx_hash: H256,
+    pub transaction: SafeTransaction,
+    /// Keyed by owner; `execTransaction` wants signatures in ascending owner order, which this keeps
+    pub confirmations: BTreeMap<Address, Vec<u8>>,
+    pub proposed_at: DateTime<Utc>,
+}
+
+impl PendingSafeTransaction {
+    /// Confirmations by current `owners`, concatenated in ascending owner order
+    pub fn packed_signatures(&self, owners: &[Address]) -> (usize, Vec<u8>) {
+        let signatures: Vec<_> = self
+            .confirmations
+            .iter()
+            .filter(|(owner, _)| owners.contains(owner))
+            .map(|(_, signature)| signature.as_slice())
+            .collect();
+        (signatures.len(), signatures.concat())
+    }
+}
+
+/// Proposals by Safe transaction hash, kept in memory until executed
+#[derive(Default)]
+pub struct SafeQueue {
+    transactions: RwLock<HashMap<H256, PendingSafeTransaction>>,
+}
+
+impl SafeQueue {
+    pub fn insert(&self, pending: PendingSafeTransaction) -> AppResult<()> {
+        let mut transactions = self.transactions.write().unwrap();
+        if transactions.contains_key(&pending.safe_tx_hash) {
+            return Err(AppError::ValidationError(format!(
+                "Safe transaction {:?} was already proposed",
+                pending.safe_tx_hash
+            )));
+        }
+        transactions.insert(pending.safe_tx_hash, pending);
+        Ok(())
+    }
+
+    /// The proposal `safe_tx_hash` for `safe`
+    pub fn get(&self, safe: Address, safe_tx_hash: H256) -> AppResult<PendingSafeTransaction> {
+        self.transactions
+            .read()
+            .unwrap()
+            .get(&safe_tx_hash)
+            .filter(|pending| pending.safe == safe)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Safe transaction {:?}", safe_tx_hash)))
+    }
+
+    /// Record `owner`'s signature, replacing an earlier one by the same owner
+    pub fn confirm(&self, safe_tx_hash: H256, owner: Address, signature: Vec<u8>) -> AppResult<PendingSafeTransaction> {
+        let mut transactions = self.transactions.write().unwrap();
+        let pending = transactions
+            .get_mut(&safe_tx_hash)
+            .ok_or_else(|| AppError::NotFound(format!("Safe transaction {:?}", safe_tx_hash)))?;
+        pending.confirmations.insert(owner, signature);
+        Ok(pending.clone())
+    }
+
+    /// Proposals for `safe` ordered by nonce, dropping those below `nonce` (executed or replaced on-chain)
+    pub fn list(&self, safe: Address, nonce: U256) -> Vec<PendingSafeTransaction> {
+        let mut transactions = self.transactions.write().unwrap();
+        transactions.retain(|_, pending| pending.safe != safe || pending.transaction.nonce >= nonce);
+
+        let mut pending: Vec<_> = transactions.values().filter(|pending| pending.safe == safe).cloned().collect();
+        pending.sort_by_key(|pending| (pending.transaction.nonce, pending.proposed_at));
+        pending
+    }
+
+    pub fn remove(&self, safe_tx_hash: H256) {
+        self.transactions.write().unwrap().remove(&safe_tx_hash);
+    }
+}
+
+fn word(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+fn padded_length(length: usize) -> usize {
+    length.div_ceil(32) * 32
+}
+
+/// ABI tail of a `bytes` argument: length, then the bytes zero-padded to a whole word
+fn append_bytes(encoded: &mut Vec<u8>, bytes: &[u8]) {
+    encoded.extend_from_slice(&word(bytes.len().into()));
+    encoded.extend_from_slice(bytes);
+    encoded.resize(encoded.len() + padded_length(bytes.len()) - bytes.len(), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use web3::ethabi::{self, Token};
+
+    /// `SAFE_TX_TYPEHASH` as declared in the Safe v1.3.0 contract
+    const SAFE_TX_TYPEHASH: &str = "bb8310d486368db6bd6f849402fdd73ad53d316b5a4b2644ad6efe0f941286d8";
+    /// `DOMAIN_SEPARATOR_TYPEHASH` of Safe v1.3.0: `EIP712Domain(uint256 chainId,address verifyingContract)`
+    const DOMAIN_SEPARATOR_TYPEHASH: &str = "47e79534a245952e8b16893a336b85a3d9ea9fa8c573f3d803afb92a79469218";
+
+    fn address(hex: &str) -> Address {
+        Address::from_str(hex.trim_start_matches("0x")).unwrap()
+    }
+
+    /// An ERC-20 `transfer(0xb0b, 1e18)` made by the Safe
+    fn token_transfer() -> SafeTransaction {
+        let mut data = hex::decode("a9059cbb").unwrap();
+        data.extend_from_slice(&ethabi::encode(&[
+            Token::Address(Address::from_low_u64_be(0xb0b)),
+            Token::Uint(U256::exp10(18)),
+        ]));
+        SafeTransaction {
+            to: address("0x6B175474E89094C44Da98b954EedeAC495271d0F"),
+            value: U256::zero(),
+            data,
+            operation: CALL,
+            nonce: 42.into(),
+        }
+    }
+
+    /// `domainSeparator()` of a v1.3.0 Safe at `safe` on mainnet
+    fn domain_separator(safe: Address) -> H256 {
+        H256::from(keccak256(&ethabi::encode(&[
+            Token::FixedBytes(hex::decode(DOMAIN_SEPARATOR_TYPEHASH).unwrap()),
+            Token::Uint(1.into()),
+            Token::Address(safe),
+        ])))
+    }
+
+    #[test]
+    fn the_type_string_hashes_to_the_contract_type_hash() {
+        assert_eq!(hex::encode(keccak256(SAFE_TX_TYPE.as_bytes())), SAFE_TX_TYPEHASH);
+    }
+
+    #[test]
+    fn safe_tx_hash_matches_the_contract_encoding() {
+        let transaction = token_transfer();
+        let domain_separator = domain_separator(address("0x849D52316331967b6fF1198e5E32A0eB168D039d"));
+
+        // getTransactionHash(to, value, data, operation, safeTxGas, baseGas, gasPrice, gasToken, refundReceiver, nonce)
+        // with every refund field zero
+        let struct_hash = keccak256(&ethabi::encode(&[
+            Token::FixedBytes(hex::decode(SAFE_TX_TYPEHASH).unwrap()),
+            Token::Address(transaction.to),
+            Token::Uint(transaction.value),
+            Token::FixedBytes(keccak256(&transaction.data).to_vec()),
+            Token::Uint(transaction.operation.into()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Address(Address::zero()),
+            Token::Address(Address::zero()),
+            Token::Uint(transaction.nonce),
+        ]));
+        assert_eq!(transaction.struct_hash(), H256::from(struct_hash));
+
+        let digest = keccak256(&[&[0x19, 0x01][..], domain_separator.as_bytes(), &struct_hash].concat());
+        assert_eq!(transaction.digest(domain_separator), H256::from(digest));
+
+        // Every field is part of the hash
+        let mut other_nonce = transaction.clone();
+        other_nonce.nonce = 43.into();
+        assert_ne!(other_nonce.digest(domain_separator), transaction.digest(domain_separator));
+        let mut delegate_call = transaction.clone();
+        delegate_call.operation = DELEGATE_CALL;
+        assert_ne!(delegate_call.digest(domain_separator), transaction.digest(domain_separator));
+    }
+
+    fn word_at(encoded: &[u8], index: usize) -> U256 {
+        U256::from_big_endian(&encoded[4 + 32 * index..4 + 32 * (index + 1)])
+    }
+
+    #[test]
+    fn exec_transaction_calldata_follows_the_abi_layout() {
+        let transaction = SafeTransaction {
+            to: Address::from_low_u64_be(0xb0b),
+            value: 7.into(),
+            data: vec![0xde, 0xad, 0xbe, 0xef, 0x01],
+            operation: CALL,
+            nonce: 0.into(),
+        };
+        let signatures = [[0x11u8; SIGNATURE_LENGTH], [0x22u8; SIGNATURE_LENGTH]].concat();
+        let encoded = transaction.exec_transaction_data(&signatures);
+
+        let signature = "execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)";
+        assert_eq!(encoded[..4], keccak256(signature.as_bytes())[..4]);
+        // Ten head words, then data (length + one padded word), then signatures (length + 130 bytes in five words)
+        assert_eq!(encoded.len(), 4 + 10 * 32 + 32 + 32 + 32 + 5 * 32);
+        assert_eq!(word_at(&encoded, 2), U256::from(320));
+        assert_eq!(word_at(&encoded, 9), U256::from(320 + 32 + 32));
+        for refund_field in 4..9 {
+            assert!(word_at(&encoded, refund_field).is_zero());
+        }
+
+        assert_eq!(word_at(&encoded, 10), U256::from(5));
+        assert_eq!(encoded[4 + 11 * 32..4 + 11 * 32 + 5], transaction.data[..]);
+        assert!(encoded[4 + 11 * 32 + 5..4 + 12 * 32].iter().all(|byte| *byte == 0));
+        assert_eq!(word_at(&encoded, 12), U256::from(2 * SIGNATURE_LENGTH));
+        let signatures_start = 4 + 13 * 32;
+        assert_eq!(encoded[signatures_start..signatures_start + 2 * SIGNATURE_LENGTH], signatures[..]);
+        assert!(encoded[signatures_start + 2 * SIGNATURE_LENGTH..].iter().all(|byte| *byte == 0));
+
+        // Same bytes as a general ABI encoder produces
+        let arguments = ethabi::encode(&[
+            Token::Address(transaction.to),
+            Token::Uint(transaction.value),
+            Token::Bytes(transaction.data.clone()),
+            Token::Uint(transaction.operation.into()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Address(Address::zero()),
+            Token::Address(Address::zero()),
+            Token::Bytes(signatures),
+        ]);
+        assert_eq!(encoded[4..], arguments[..]);
+    }
+
+    #[test]
+    fn calldata_without_data_points_signatures_right_after_the_empty_bytes() {
+        let transaction = SafeTransaction { data: Vec::new(), ..token_transfer() };
+        let encoded = transaction.exec_transaction_data(&[0x33; SIGNATURE_LENGTH]);
+
+        assert_eq!(word_at(&encoded, 2), U256::from(320));
+        assert!(word_at(&encoded, 10).is_zero());
+        assert_eq!(word_at(&encoded, 9), U256::from(320 + 32));
+        assert_eq!(word_at(&encoded, 11), U256::from(SIGNATURE_LENGTH));
+        assert_eq!(encoded.len(), 4 + 10 * 32 + 32 + 32 + 3 * 32);
+    }
+
+    #[test]
+    fn packed_signatures_are_ordered_by_owner_and_skip_non_owners() {
+        let (low, middle, high) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2), Address::from_low_u64_be(3));
+        let removed_owner = Address::from_low_u64_be(0xdead);
+        let pending = PendingSafeTransaction {
+            safe: Address::from_low_u64_be(0x5afe),
+            safe_tx_hash: H256::zero(),
+            transaction: token_transfer(),
+            // Confirmed high first, then by an address that is no longer an owner, then low
+            confirmations: BTreeMap::from([
+                (high, vec![0x03; SIGNATURE_LENGTH]),
+                (removed_owner, vec![0xee; SIGNATURE_LENGTH]),
+                (low, vec![0x01; SIGNATURE_LENGTH]),
+            ]),
+            proposed_at: Utc::now(),
+        };
+
+        let (count, packed) = pending.packed_signatures(&[high, middle, low]);
+        assert_eq!(count, 2);
+        assert_eq!(packed, [vec![0x01; SIGNATURE_LENGTH], vec![0x03; SIGNATURE_LENGTH]].concat());
+    }
+
+    #[test]
+    fn recovers_the_owner_of_a_plain_signature() {
+        let key = secp256k1::SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let owner = signing::SecretKeyRef::new(&key);
+        let digest = token_transfer().digest(domain_separator(Address::from_low_u64_be(0x5afe)));
+        let signature = signing::Key::sign(&owner, digest.as_bytes(), None).unwrap();
+        let mut bytes = [signature.r.as_bytes(), signature.s.as_bytes()].concat();
+        bytes.push(signature.v as u8);
+
+        assert_eq!(recover_owner(digest, &bytes).unwrap(), signing::Key::address(&owner));
+        // eth_sign signatures (v + 4) and contract signatures (v = 0 or 1) are not accepted
+        bytes[64] += 4;
+        assert!(matches!(recover_owner(digest, &bytes), Err(AppError::ValidationError(_))));
+        assert!(matches!(recover_owner(digest, &bytes[..64]), Err(AppError::ValidationError(_))));
+    }
+}
@@ -0,0 +1,196 @@
+//! Transaction signing behind a trait, so keys can live somewhere other than this process's memory.
+
+use crate::errors::{AppError, AppResult};
+use crate::secret::SecretKeyGuard;
+use async_trait::async_trait;
+use rlp::RlpStream;
+use web3::signing::{self, Key, SecretKeyRef};
+use web3::types::{AccessList, Address, Bytes, SignedTransaction, TransactionParameters, H256, U256};
+
+const ACCESS_LIST_TX_TYPE: u64 = 1;
+const EIP1559_TX_TYPE: u64 = 2;
+
+/// Something that can sign transactions for one address
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// Address the signatures recover to
+    fn address(&self) -> Address;
+
+    /// Sign a transaction whose nonce, fees and chain ID are already filled in
+    async fn sign_transaction(&self, transaction: &TransactionParameters) -> AppResult<SignedTransaction>;
+}
+
+/// Signs with a secp256k1 key held in memory (the wallet file's key); the key is scrubbed when the signer is dropped
+pub struct LocalSigner {
+    secret_key: SecretKeyGuard,
+}
+
+impl LocalSigner {
+    pub fn new(secret_key: SecretKeyGuard) -> Self {
+        Self { secret_key }
+    }
+}
+
+#[async_trait]
+impl Signer for LocalSigner {
+    fn address(&self) -> Address {
+        SecretKeyRef::new(&self.secret_key).address()
+    }
+
+    async fn sign_transaction(&self, transaction: &TransactionParameters) -> AppResult<SignedTransaction> {
+        let message_hash = signing_hash(transaction)?;
+        let signature = SecretKeyRef::new(&self.secret_key)
+            .sign_message(message_hash.as_bytes())
+            .map_err(|e| AppError::TransactionFailed(format!("Failed to sign transaction: {}", e)))?;
+        attach_signature(transaction, signature.v, signature.r, signature.s)
+    }
+}
+
+/// Hash a signer signs for `transaction`, whose nonce, fees and chain ID are already filled in
+pub fn signing_hash(transaction: &TransactionParameters) -> AppResult<H256> {
+    let unsigned = UnsignedTransaction::from_parameters(transaction)?;
+    Ok(H256::from(signing::keccak256(&unsigned.encode(None))))
+}
+
+/// `transaction` ready to broadcast, with an `(r, s)` signature of its [`signing_hash`] whose
+/// recovery id (0 or 1) is `recovery_id`
+pub fn attach_signature(transaction: &TransactionParameters, recovery_id: u64, r: H256, s: H256) -> AppResult<SignedTransaction> {
+    let unsigned = UnsignedTransaction::from_parameters(transaction)?;
+    let message_hash = signing::keccak256(&unsigned.encode(None));
+    // Typed transactions carry the bare recovery id; legacy ones fold the chain ID into v (EIP-155)
+    let v = match unsigned.transaction_type {
+        Some(_) => recovery_id,
+        None => recovery_id + 35 + unsigned.chain_id * 2,
+    };
+
+    let raw_transaction = unsigned.encode(Some((v, r, s)));
+    Ok(SignedTransaction {
+        message_hash: H256::from(message_hash),
+        v,
+        r,
+        s,
+        transaction_hash: H256::from(signing::keccak256(&raw_transaction)),
+        raw_transaction: Bytes(raw_transaction),
+    })
+}
+
+/// The signed fields of a legacy, EIP-2930 or EIP-1559 transaction
+struct UnsignedTransaction<'a> {
+    /// `None` for legacy transactions
+    transaction_type: Option<u64>,
+    chain_id: u64,
+    nonce: U256,
+    /// Gas price, or `maxFeePerGas` for EIP-1559
+    gas_price: U256,
+    max_priority_fee_per_gas: U256,
+    gas: U256,
+    to: Option<Address>,
+    value: U256,
+    data: &'a [u8],
+    access_list: Option<&'a AccessList>,
+}
+
+impl<'a> UnsignedTransaction<'a> {
+    fn from_parameters(transaction: &'a TransactionParameters) -> AppResult<Self> {
+        let missing = |field: &str| AppError::InternalError(format!("Transaction {} must be set before signing", field));
+
+        let transaction_type = match transaction.transaction_type.map(|kind| kind.as_u64()) {
+            None | Some(0) => None,
+            Some(kind @ (ACCESS_LIST_TX_TYPE | EIP1559_TX_TYPE)) => Some(kind),
+            Some(other) => {
+                return Err(AppError::ValidationError(format!("Unsupported transaction type: {}", other)))
+            }
+        };
+        let (gas_price, max_priority_fee_per_gas) = if transaction_type == Some(EIP1559_TX_TYPE) {
+            let max_fee = transaction.max_fee_per_gas.ok_or_else(|| missing("max_fee_per_gas"))?;
+            (max_fee, transaction.max_priority_fee_per_gas.unwrap_or(max_fee))
+        } else {
+            let gas_price = transaction.gas_price.ok_or_else(|| missing("gas_price"))?;
+            (gas_price, gas_price)
+        };
+
+        Ok(Self {
+            transaction_type,
+            chain_id: transaction.chain_id.ok_or_else(|| missing("chain_id"))?,
+            nonce: transaction.nonce.ok_or_else(|| missing("nonce"))?,
+            gas_price,
+            max_priority_fee_per_gas,
+            gas: transaction.gas,
+            to: transaction.to,
+            value: transaction.value,
+            data: &transaction.data.0,
+            access_list: transaction.access_list.as_ref(),
+        })
+    }
+
+    /// RLP payload to hash for signing, or with `(v, r, s)` the raw transaction to broadcast
+    fn encode(&self, signature: Option<(u64, H256, H256)>) -> Vec<u8> {
+        let mut stream = RlpStream::new();
+        stream.begin_unbounded_list();
+
+        match self.transaction_type {
+            None => {
+                self.append_common(&mut stream);
+                if signature.is_none() {
+                    stream.append(&self.chain_id);
+                    stream.append(&0u8);
+                    stream.append(&0u8);
+                }
+            }
+            Some(transaction_type) => {
+                stream.append(&self.chain_id);
+                stream.append(&self.nonce);
+                if transaction_type == EIP1559_TX_TYPE {
+                    stream.append(&self.max_priority_fee_per_gas);
+                }
+                stream.append(&self.gas_price);
+                stream.append(&self.gas);
+                self.append_call(&mut stream);
+                self.append_access_list(&mut stream);
+            }
+        }
+        if let Some((v, r, s)) = signature {
+            stream.append(&v);
+            stream.append(&U256::from_big_endian(r.as_bytes()));
+            stream.append(&U256::from_big_endian(s.as_bytes()));
+        }
+        stream.finalize_unbounded_list();
+
+        let payload = stream.out().to_vec();
+        match self.transaction_type {
+            None => payload,
+            Some(transaction_type) => {
+                let mut typed = vec![transaction_type as u8];
+                typed.extend_from_slice(&payload);
+                typed
+            }
+        }
+    }
+
+    fn append_common(&self, stream: &mut RlpStream) {
+        stream.append(&self.nonce);
+        stream.append(&self.gas_price);
+        stream.append(&self.gas);
+        self.append_call(stream);
+    }
+
+    /// `to`, `value` and `data`; contract creations have an empty `to`
+    fn append_call(&self, stream: &mut RlpStream) {
+        match &self.to {
+            Some(to) => stream.append(to),
+            None => stream.append(&""),
+        };
+        stream.append(&self.value);
+        stream.append(&self.data);
+    }
+
+    fn append_access_list(&self, stream: &mut RlpStream) {
+        let entries = self.access_list.map(Vec::as_slice).unwrap_or_default();
+        stream.begin_list(entries.len());
+        for entry in entries {
+            stream.begin_list(2);
+            stream.append(&entry.address);
+            stream.append_list(&entry.storage_keys);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Rolling one-hour cap on the total native value sent by this server.
+
+use crate::errors::{AppError, AppResult};
+use crate::utils;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::Mutex;
+use tracing::{info, warn};
+use web3::types::U256;
+
+/// Length of the sliding window
+const WINDOW_MINUTES: i64 = 60;
+
+/// Value sent (or reserved for a send in progress) at a point in time
+#[derive(Serialize, Deserialize, Clone)]
+struct SpendEntry {
+    id: u64,
+    at: DateTime<Utc>,
+    value_wei: String,
+}
+
+impl SpendEntry {
+    fn value(&self) -> U256 {
+        U256::from_dec_str(&self.value_wei).unwrap_or_default()
+    }
+}
+
+/// Value held against the window while a send is in flight; settle it with what was actually sent
+#[must_use]
+pub struct SpendReservation {
+    id: Option<u64>,
+}
+
+struct Window {
+    entries: VecDeque<SpendEntry>,
+    next_id: u64,
+}
+
+/// Enforces `security.max_eth_per_hour` across all sending routes
+pub struct SpendLimiter {
+    max_wei: Option<U256>,
+    state_file: Option<String>,
+    window: Mutex<Window>,
+}
+
+impl SpendLimiter {
+    /// `max_wei = None` disables the limit; `state_file` keeps the window across restarts
+    pub fn new(max_wei: Option<U256>, state_file: Option<String>) -> Self {
+        let entries = match (&max_wei, &state_file) {
+            (Some(_), Some(path)) => load_entries(path),
+            _ => VecDeque::new(),
+        };
+        let next_id = entries.iter().map(|entry| entry.id + 1).max().unwrap_or(0);
+
+        Self {
+            max_wei,
+            state_file,
+            window: Mutex::new(Window { entries, next_id }),
+        }
+    }
+
+    /// Hold `value` against the window, or fail with the time enough capacity frees up
+    pub fn reserve(&self, value: U256) -> AppResult<SpendReservation> {
+        let Some(max_wei) = self.max_wei else {
+            return Ok(SpendReservation { id: None });
+        };
+        if value.is_zero() {
+            return Ok(SpendReservation { id: None });
+        }
+
+        let max_eth = utils::format_units(max_wei, utils::NATIVE_DECIMALS);
+        if value > max_wei {
+            return Err(AppError::ValidationError(format!(
+                "Amount exceeds security.max_eth_per_hour of {} on its own",
+                max_eth
+            )));
+        }
+
+        let now = Utc::now();
+        let mut window = self.window.lock().unwrap();
+        prune(&mut window.entries, now);
+
+        let spent = window.entries.iter().fold(U256::zero(), |sum, entry| sum.saturating_add(entry.value()));
+        if spent.saturating_add(value) > max_wei {
+            // Capacity returns as the oldest entries leave the window
+            let mut remaining = spent;
+            let mut reset_at = now;
+            for entry in &window.entries {
+                remaining = remaining.saturating_sub(entry.value());
+                reset_at = entry.at + Duration::minutes(WINDOW_MINUTES);
+                if remaining.saturating_add(value) <= max_wei {
+                    break;
+                }
+            }
+            let retry_after_secs = (reset_at - now).num_seconds().max(1) as u64;
+            return Err(AppError::SpendLimitExceeded {
+                message: format!(
+                    "Sending {} would exceed the limit of {} per hour ({} already sent); capacity frees up at {}",
+                    utils::format_units(value, utils::NATIVE_DECIMALS),
+                    max_eth,
+                    utils::format_units(spent, utils::NATIVE_DECIMALS),
+                    reset_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                ),
+                retry_after_secs,
+            });
+        }
+
+        let id = window.next_id;
+        window.next_id += 1;
+        window.entries.push_back(SpendEntry {
+            id,
+            at: now,
+            value_wei: value.to_string(),
+        });
+        self.persist(&window.entries);
+        Ok(SpendReservation { id: Some(id) })
+    }
+
+    /// Replace a reservation with the value actually sent; zero releases it entirely
+    pub fn settle(&self, reservation: SpendReservation, sent: U256) {
+        let Some(id) = reservation.id else {
+            return;
+        };
+
+        let mut window = self.window.lock().unwrap();
+        if sent.is_zero() {
+            window.entries.retain(|entry| entry.id != id);
+        } else if let Some(entry) = window.entries.iter_mut().find(|entry| entry.id == id) {
+            entry.value_wei = sent.to_string();
+        }
+        self.persist(&window.entries);
+    }
+
+    fn persist(&self, entries: &VecDeque<SpendEntry>) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+
+        let result = serde_json::to_string(entries)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Failed to persist spend window to {}: {}", path, e);
+        }
+    }
+}
+
+/// Drop entries older than the window
+fn prune(entries: &mut VecDeque<SpendEntry>, now: DateTime<Utc>) {
+    let cutoff = now - Duration::minutes(WINDOW_MINUTES);
+    while entries.front().is_some_and(|entry| entry.at <= cutoff) {
+        entries.pop_front();
+    }
+}
+
+/// Entries saved by a previous run; a missing or unreadable file starts an empty window
+fn load_entries(path: &str) -> VecDeque<SpendEntry> {
+    if !utils::path_exists(path) {
+        return VecDeque::new();
+    }
+
+    match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|json| {
+        serde_json::from_str::<VecDeque<SpendEntry>>(&json).map_err(|e| e.to_string())
+    }) {
+        Ok(mut entries) => {
+            prune(&mut entries, Utc::now());
+            info!("Restored {} spend window entries from {}", entries.len(), path);
+            entries
+        }
+        Err(e) => {
+            warn!("Ignoring unreadable spend window {}: {}", path, e);
+            VecDeque::new()
+        }
+    }
+}
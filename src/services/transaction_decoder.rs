@@ -0,0 +1,305 @@
+use crate::address::ChecksumAddress;
+use crate::errors::{AppError, AppResult};
+use crate::models::{AccessListEntry, DecodedTransaction};
+use rlp::{Rlp, RlpStream};
+use web3::signing;
+use web3::types::{Address, H256, U256};
+
+const ACCESS_LIST_TX_TYPE: u8 = 1;
+const EIP1559_TX_TYPE: u8 = 2;
+
+/// Decode a raw signed transaction (legacy, EIP-2930 or EIP-1559) and recover its sender
+pub fn decode_raw_transaction(raw: &str) -> AppResult<DecodedTransaction> {
+    let bytes = hex::decode(raw.trim_start_matches("0x"))
+        .map_err(|e| AppError::ValidationError(format!("Raw transaction is not valid hex: {}", e)))?;
+
+    let first = *bytes
+        .first()
+        .ok_or_else(|| AppError::ValidationError("Raw transaction is empty".to_string()))?;
+
+    // Typed envelopes (EIP-2718) start with a type byte below 0x7f, legacy ones with an RLP list prefix
+    let mut decoded = match first {
+        ACCESS_LIST_TX_TYPE | EIP1559_TX_TYPE => decode_typed(first, &bytes[1..])?,
+        0xc0..=0xff => decode_legacy(&bytes)?,
+        other => {
+            return Err(AppError::ValidationError(format!(
+                "Unsupported transaction type: {:#04x}",
+                other
+            )))
+        }
+    };
+
+    decoded.hash = format!("{:?}", H256::from(signing::keccak256(&bytes)));
+    Ok(decoded)
+}
+
+fn decode_legacy(bytes: &[u8]) -> AppResult<DecodedTransaction> {
+    let rlp = Rlp::new(bytes);
+    expect_fields(&rlp, 9)?;
+
+    let v: u64 = field(&rlp, 6)?;
+    let r: U256 = field(&rlp, 7)?;
+    let s: U256 = field(&rlp, 8)?;
+
+    // EIP-155 signatures fold the chain id into v; pre-155 ones use 27/28
+    let (chain_id, recovery_id) = match v {
+        27 | 28 => (None, v - 27),
+        v if v >= 35 => (Some((v - 35) / 2), (v - 35) % 2),
+        other => {
+            return Err(AppError::ValidationError(format!("Invalid signature v value: {}", other)))
+        }
+    };
+
+    let mut unsigned = RlpStream::new_list(if chain_id.is_some() { 9 } else { 6 });
+    for index in 0..6 {
+        unsigned.append_raw(raw_field(&rlp, index)?, 1);
+    }
+    if let Some(chain_id) = chain_id {
+        unsigned.append(&chain_id);
+        unsigned.append_empty_data();
+        unsigned.append_empty_data();
+    }
+    let from = recover_sender(&unsigned.out(), recovery_id, r, s)?;
+
+    Ok(DecodedTransaction {
+        transaction_type: 0,
+        chain_id,
+        nonce: field::<U256>(&rlp, 0)?.to_string(),
+        gas_price: Some(field::<U256>(&rlp, 1)?.to_string()),
+        max_priority_fee_per_gas: None,
+        max_fee_per_gas: None,
+        gas: field::<U256>(&rlp, 2)?.to_string(),
+        to: recipient(&rlp, 3)?,
+        value: field::<U256>(&rlp, 4)?.to_string(),
+        data: format!("0x{}", hex::encode(field::<Vec<u8>>(&rlp, 5)?)),
+        access_list: Vec::new(),
+        v,
+        r: format!("{:#x}", r),
+        s: format!("{:#x}", s),
+        from,
+        hash: String::new(),
+    })
+}
+
+fn decode_typed(tx_type: u8, payload: &[u8]) -> AppResult<DecodedTransaction> {
+    let rlp = Rlp::new(payload);
+    let field_count = if tx_type == EIP1559_TX_TYPE { 12 } else { 11 };
+    expect_fields(&rlp, field_count)?;
+
+    // EIP-1559 inserts maxPriorityFeePerGas before the fee cap, shifting later fields by one
+    let offset = if tx_type == EIP1559_TX_TYPE { 1 } else { 0 };
+    let y_parity: u64 = field(&rlp, 8 + offset)?;
+    let r: U256 = field(&rlp, 9 + offset)?;
+    let s: U256 = field(&rlp, 10 + offset)?;
+
+    let mut unsigned = RlpStream::new_list(field_count - 3);
+    for index in 0..field_count - 3 {
+        unsigned.append_raw(raw_field(&rlp, index)?, 1);
+    }
+    let mut preimage = vec![tx_type];
+    preimage.extend_from_slice(&unsigned.out());
+    let from = recover_sender(&preimage, y_parity, r, s)?;
+
+    let fee: U256 = field(&rlp, 2 + offset)?;
+    let (gas_price, max_priority_fee_per_gas, max_fee_per_gas) = if tx_type == EIP1559_TX_TYPE {
+        (None, Some(field::<U256>(&rlp, 2)?.to_string()), Some(fee.to_string()))
+    } else {
+        (Some(fee.to_string()), None, None)
+    };
+
+    Ok(DecodedTransaction {
+        transaction_type: tx_type as u64,
+        chain_id: Some(field(&rlp, 0)?),
+        nonce: field::<U256>(&rlp, 1)?.to_string(),
+        gas_price,
+        max_priority_fee_per_gas,
+        max_fee_per_gas,
+        gas: field::<U256>(&rlp, 3 + offset)?.to_string(),
+        to: recipient(&rlp, 4 + offset)?,
+        value: field::<U256>(&rlp, 5 + offset)?.to_string(),
+        data: format!("0x{}", hex::encode(field::<Vec<u8>>(&rlp, 6 + offset)?)),
+        access_list: access_list(&rlp, 7 + offset)?,
+        v: y_parity,
+        r: format!("{:#x}", r),
+        s: format!("{:#x}", s),
+        from,
+        hash: String::new(),
+    })
+}
+
+fn expect_fields(rlp: &Rlp, count: usize) -> AppResult<()> {
+    let actual = rlp.item_count().map_err(decode_error)?;
+    if actual != count {
+        return Err(AppError::ValidationError(format!(
+            "Expected {} transaction fields, found {}",
+            count, actual
+        )));
+    }
+    Ok(())
+}
+
+fn field<T: rlp::Decodable>(rlp: &Rlp, index: usize) -> AppResult<T> {
+    rlp.val_at(index).map_err(decode_error)
+}
+
+fn raw_field<'a>(rlp: &Rlp<'a>, index: usize) -> AppResult<&'a [u8]> {
+    rlp.at(index).map(|item| item.as_raw()).map_err(decode_error)
+}
+
+/// Recipient address, or `None` for contract creation
+fn recipient(rlp: &Rlp, index: usize) -> AppResult<Option<ChecksumAddress>> {
+    let item = rlp.at(index).map_err(decode_error)?;
+    if item.is_empty() {
+        return Ok(None);
+    }
+    let address: Address = item.as_val().map_err(decode_error)?;
+    Ok(Some(address.into()))
+}
+
+fn access_list(rlp: &Rlp, index: usize) -> AppResult<Vec<AccessListEntry>> {
+    let list = rlp.at(index).map_err(decode_error)?;
+    list.iter()
+        .map(|entry| {
+            let address: Address = entry.val_at(0).map_err(decode_error)?;
+            let storage_keys: Vec<H256> = entry.list_at(1).map_err(decode_error)?;
+            Ok(AccessListEntry {
+                address: address.into(),
+                storage_keys: storage_keys.iter().map(|key| format!("{:?}", key)).collect(),
+            })
+        })
+        .collect()
+}
+
+fn recover_sender(preimage: &[u8], recovery_id: u64, r: U256, s: U256) -> AppResult<ChecksumAddress> {
+    let mut signature = [0u8; 64];
+    r.to_big_endian(&mut signature[..32]);
+    s.to_big_endian(&mut signature[32..]);
+
+    let message = signing::keccak256(preimage);
+    let address = signing::recover(&message, &signature, recovery_id as i32)
+        .map_err(|e| AppError::ValidationError(format!("Failed to recover sender: {:?}", e)))?;
+    Ok(address.into())
+}
+
+fn decode_error(error: rlp::DecoderError) -> AppError {
+    AppError::ValidationError(format!("Malformed transaction RLP: {}", error))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example from EIP-155: nonce 9, 20 gwei, 1 ETH to 0x3535...35, signed by key 0x4646...46 for chain 1
+    const EIP155_TRANSFER: &str = "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+    /// Address of the key behind the web3 signing vectors, `0x4c0883a6...3f362318`
+    const WEB3_TEST_SENDER: &str = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+
+    fn decoded(raw: &str) -> DecodedTransaction {
+        decode_raw_transaction(raw).unwrap()
+    }
+
+    fn validation_message(raw: &str) -> String {
+        match decode_raw_transaction(raw) {
+            Err(AppError::ValidationError(message)) => message,
+            Err(other) => panic!("expected a validation error, got {}", other),
+            Ok(decoded) => panic!("decoded {} as {}", raw, decoded.hash),
+        }
+    }
+
+    #[test]
+    fn decodes_a_pre_eip155_contract_creation() {
+        // Deployment of the deterministic CREATE2 factory at 0x4e59b448...b4956c, sent on mainnet
+        // before chain ids were signed ("Nick's method": v = 27, r = s = 0x2222...)
+        let transaction = decoded("0xf8a58085174876e800830186a08080b853604580600e600039806000f350fe7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe03601600081602082378035828234f58015156039578182fd5b8082525050506014600cf31ba02222222222222222222222222222222222222222222222222222222222222222a02222222222222222222222222222222222222222222222222222222222222222");
+
+        assert_eq!(transaction.from.to_string(), "0x3fAB184622Dc19b6109349B94811493BF2a45362");
+        assert_eq!(transaction.hash, "0xeddf9e61fb9d8f5111840daef55e5fde0041f5702856532cdbb5a02998033d26");
+        assert_eq!(transaction.transaction_type, 0);
+        assert_eq!(transaction.chain_id, None);
+        assert_eq!(transaction.v, 27);
+        assert_eq!(transaction.to, None);
+        assert_eq!(transaction.gas_price.as_deref(), Some("100000000000"));
+        assert!(transaction.data.starts_with("0x604580600e"));
+    }
+
+    #[test]
+    fn decodes_an_eip155_transfer() {
+        let transaction = decoded(EIP155_TRANSFER);
+
+        assert_eq!(transaction.from.to_string(), "0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F");
+        assert_eq!(transaction.chain_id, Some(1));
+        assert_eq!(transaction.v, 37);
+        assert_eq!(transaction.nonce, "9");
+        assert_eq!(transaction.value, "1000000000000000000");
+        assert_eq!(transaction.to.unwrap().to_string(), "0x3535353535353535353535353535353535353535");
+
+        // web3's own legacy signing vector
+        let transaction = decoded("0xf86a8086d55698372431831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a009ebb6ca057a0535d6186462bc0b465b561c94a295bdb0621fc19208ab149a9ca0440ffd775ce91a833ab410777204d5341a6f9fa91216a6f3ee2c051fea6a0428");
+        assert_eq!(transaction.from.to_string(), WEB3_TEST_SENDER);
+        assert_eq!(transaction.hash, "0xd8f64a42b57be0d565f385378db2f6bf324ce14a594afc05de90436e9ce01f60");
+    }
+
+    #[test]
+    fn decodes_an_access_list_transaction() {
+        // Signed with web3's encoder: an access list naming two storage slots of the recipient
+        let transaction = decoded("0x01f8ca01078506fc23ac0082ea6094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca0084a9059cbbf85bf85994f0109fc8df283027b6285cc889f5aa624eac1f55f842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000280a0dbdaba8c5196604296fac31c12b0581a24524d05d9224f6e78d366a45728441ca0619ceea938307758357139e6c2351f624386581ae812d529100568f9324b9fd1");
+
+        assert_eq!(transaction.from.to_string(), WEB3_TEST_SENDER);
+        assert_eq!(transaction.hash, "0x4ca7afa32c8845b636c82d778eecbbc22c8615c166af6b9286d0f70c51bebd19");
+        assert_eq!(transaction.transaction_type, 1);
+        assert_eq!(transaction.gas_price.as_deref(), Some("30000000000"));
+        assert_eq!(transaction.access_list.len(), 1);
+        assert_eq!(transaction.access_list[0].address.to_string(), "0xF0109fC8DF283027b6285cc889F5aA624EaC1F55");
+        assert_eq!(
+            transaction.access_list[0].storage_keys,
+            [format!("{:?}", H256::from_low_u64_be(1)), format!("{:?}", H256::from_low_u64_be(2))]
+        );
+    }
+
+    #[test]
+    fn decodes_an_eip1559_transaction() {
+        let transaction = decoded("0x02f87301078459682f008509502f900082ea6094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca0084a9059cbbc001a0fa5d0561442c2f6fcf396d6698401dae89dabb0a208569e51bd5d4fe70d42bb5a0332216c4a95c2e7ecc020f168adda3affaa7ac6308630f2c1ab786fe247015b5");
+
+        assert_eq!(transaction.from.to_string(), WEB3_TEST_SENDER);
+        assert_eq!(transaction.hash, "0x5279589089f0d389b8366ee5b7e70b5ddfe832e1df600b835deb3a2014148571");
+        assert_eq!(transaction.transaction_type, 2);
+        assert_eq!(transaction.chain_id, Some(1));
+        assert_eq!(transaction.gas_price, None);
+        assert_eq!(transaction.max_priority_fee_per_gas.as_deref(), Some("1500000000"));
+        assert_eq!(transaction.max_fee_per_gas.as_deref(), Some("40000000000"));
+        assert_eq!(transaction.gas, "60000");
+        assert_eq!(transaction.data, "0xa9059cbb");
+        assert!(transaction.access_list.is_empty());
+    }
+
+    #[test]
+    fn refuses_malformed_input() {
+        assert!(validation_message("").contains("empty"));
+        assert!(validation_message("0x").contains("empty"));
+        assert!(validation_message("0xzz").contains("not valid hex"));
+        assert!(validation_message("0x03f8").contains("Unsupported transaction type: 0x03"));
+        assert!(validation_message("0x7f").contains("Unsupported transaction type"));
+        // A legacy list with eight fields, and an EIP-1559 one with the fields of an access list transaction
+        assert!(validation_message("0xc88080808080808080").contains("Expected 9 transaction fields, found 8"));
+        assert!(validation_message("0x02cb8080808080808080808080").contains("Expected 12 transaction fields, found 11"));
+    }
+
+    #[test]
+    fn refuses_legacy_v_values_that_encode_no_chain() {
+        let transfer = hex::decode(EIP155_TRANSFER).unwrap();
+        for v in 29u64..=34 {
+            let rlp = Rlp::new(&transfer);
+            let mut stream = RlpStream::new_list(9);
+            for index in 0..9 {
+                if index == 6 {
+                    stream.append(&v);
+                } else {
+                    stream.append_raw(rlp.at(index).unwrap().as_raw(), 1);
+                }
+            }
+            let raw = format!("0x{}", hex::encode(stream.out()));
+            assert_eq!(validation_message(&raw), format!("Invalid signature v value: {}", v));
+        }
+    }
+}
@@ -1,31 +1,216 @@
+use crate::address::ChecksumAddress;
 use crate::errors::{AppError, AppResult};
-use crate::models::Account;
+use crate::models::{Account, AccountKind};
+use crate::secret::{SecretKeyGuard, SecretString};
+use crate::services::audit_logger::{AuditEntry, AuditLogger};
+use crate::services::backup;
+use crate::services::bip32;
+use crate::services::keystore::{self, Keystore};
+use crate::services::mpc_signer::MpcSigner;
+use crate::services::signer::{LocalSigner, Signer};
 use crate::utils;
-use secp256k1::{rand::rngs, PublicKey, SecretKey, Secp256k1};
+use rand::{rngs::OsRng, RngCore};
+use secp256k1::{PublicKey, SecretKey, Secp256k1};
+use serde::{Deserialize, Serialize};
 use serde_json;
-use std::fs::OpenOptions;
-use std::io::{BufReader, BufWriter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 use tiny_keccak::keccak256;
-use tracing::info;
-use web3::types::Address;
+use tracing::{info, warn};
+use web3::signing::{self as web3_signing, Key, SecretKeyRef, Signature};
+use web3::types::{Address, H256};
+use zeroize::Zeroizing;
+
+/// Patterns longer than this take minutes to hours to find on typical hardware
+const VANITY_PATTERN_WARN_LENGTH: usize = 5;
+/// How often the vanity search checks whether its workers are done
+const VANITY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How often the vanity search logs its attempt count
+const VANITY_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What a vanity search looks for and how long it may run
+pub struct VanitySearch {
+    /// Hex the address must start with; may be empty when `suffix` is set
+    pub prefix: String,
+    /// Hex the address must end with; may be empty when `prefix` is set
+    pub suffix: String,
+    /// Stop after this many keypairs even if time remains
+    pub max_attempts: Option<u64>,
+    pub timeout: Duration,
+    /// Set to stop the search early, e.g. when the client that asked for it has gone away
+    pub cancelled: Arc<AtomicBool>,
+    /// Keypairs tried so far, for following the search while it runs; may overshoot `max_attempts` slightly
+    pub attempts: Arc<AtomicU64>,
+}
+
+impl VanitySearch {
+    /// The prefix and suffix lowercased, checked to be hex with 1 to 40 characters between them
+    pub fn patterns(&self) -> AppResult<(String, String)> {
+        let prefix = normalize_vanity_pattern(&self.prefix)?;
+        let suffix = normalize_vanity_pattern(&self.suffix)?;
+        let pattern_length = prefix.len() + suffix.len();
+        if pattern_length == 0 || pattern_length > 40 {
+            return Err(AppError::ValidationError(
+                "Vanity prefix and suffix must have 1 to 40 hex characters between them".to_string(),
+            ));
+        }
+        Ok((prefix, suffix))
+    }
+}
+
+/// Appended to a wallet file's path while its keystore replacement is written and checked
+const MIGRATION_SUFFIX: &str = ".migrating";
+/// Appended to a wallet file's path while the key replacing it is written
+const REPLACEMENT_SUFFIX: &str = ".importing";
+/// Appended to the wallet file's path for the file holding the imported mnemonic's seed
+const HD_SEED_SUFFIX: &str = ".hd_seed";
+
+/// Layout of a wallet file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletFileFormat {
+    /// Legacy JSON holding the hex secret key; also used for watch-only accounts, which have none
+    Plaintext,
+    /// Web3 Secret Storage keystore of the given version; only version 3 can be decrypted
+    Keystore(u64),
+}
+
+impl WalletFileFormat {
+    /// Keystores are recognised by their `crypto` object
+    fn of(contents: &serde_json::Value) -> Self {
+        match contents.get("crypto") {
+            Some(_) => Self::Keystore(contents.get("version").and_then(serde_json::Value::as_u64).unwrap_or_default()),
+            None => Self::Plaintext,
+        }
+    }
+}
+
+/// On-disk form of a plaintext wallet file, matching `Account`'s fields
+#[derive(Serialize)]
+struct AccountFile<'a> {
+    #[serde(skip_serializing_if = "str::is_empty")]
+    secret_key: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    public_key: &'a str,
+    public_address: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    derivation_path: Option<&'a str>,
+    #[serde(skip_serializing_if = "is_full")]
+    kind: AccountKind,
+}
+
+/// On-disk form of an imported mnemonic's seed when no wallet password is set; with one it is a keystore
+#[derive(Serialize, Deserialize)]
+struct HdSeedFile {
+    /// Primary wallet address the seed was imported with
+    address: String,
+    seed: String,
+}
+
+/// Keeps `kind` out of full accounts' files, so they stay readable by older versions
+fn is_full(kind: &AccountKind) -> bool {
+    *kind == AccountKind::Full
+}
+
+/// Lowercase a vanity prefix or suffix and check that it is hex
+fn normalize_vanity_pattern(pattern: &str) -> AppResult<String> {
+    let pattern = pattern.trim_start_matches("0x").to_lowercase();
+    if !pattern.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::ValidationError(format!("Vanity pattern '{}' is not hex", pattern)));
+    }
+    Ok(pattern)
+}
+
+/// Parse a wallet file of either format
+fn read_wallet_file(file_path: &str) -> AppResult<serde_json::Value> {
+    if !utils::path_exists(file_path) {
+        return Err(AppError::WalletNotFound);
+    }
+
+    let file = OpenOptions::new()
+        .read(true)
+        .open(file_path)
+        .map_err(|e| AppError::WalletLoadFailed(format!("Failed to open file: {}", e)))?;
+
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| AppError::WalletLoadFailed(format!("Failed to parse wallet file: {}", e)))
+}
+
+/// Overwrite a file's contents with zeros and flush them to disk. Copy-on-write filesystems and SSD
+/// wear levelling may still keep the old blocks, so this narrows rather than closes the exposure.
+fn wipe(file: &mut File) -> std::io::Result<()> {
+    let length = file.metadata()?.len();
+    file.write_all(&vec![0u8; length as usize])?;
+    file.sync_all()
+}
+
+impl<'a> From<&'a Account> for AccountFile<'a> {
+    fn from(account: &'a Account) -> Self {
+        Self {
+            secret_key: account.secret_key.expose_secret(),
+            public_key: &account.public_key,
+            public_address: &account.public_address,
+            derivation_path: account.derivation_path.as_deref(),
+            kind: account.kind,
+        }
+    }
+}
 
 pub struct WalletService {
     secp: Secp256k1<secp256k1::All>,
+    audit: Arc<AuditLogger>,
+    /// Wallet files are written as encrypted keystores when set
+    password: Option<SecretString>,
+    /// Signs for the `mpc.address` account when MPC signing is configured
+    mpc_signer: Option<MpcSigner>,
 }
 
 impl WalletService {
     pub fn new() -> Self {
         Self {
             secp: Secp256k1::new(),
+            audit: Arc::new(AuditLogger::disabled()),
+            password: None,
+            mpc_signer: None,
         }
     }
 
-    /// Generate a new cryptographic keypair
-    pub fn generate_keypair(&self) -> AppResult<(SecretKey, PublicKey)> {
-        let mut rng = rngs::JitterRng::new_with_timer(utils::get_nstime);
-        let keypair = self.secp.generate_keypair(&mut rng);
-        Ok(keypair)
+    /// Encrypt wallet files with this password (`wallet.password` / `WALLET_PASSWORD`)
+    pub fn with_password(mut self, password: Option<SecretString>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Sign transactions of the MPC account through these co-signers
+    pub fn with_mpc_signer(mut self, mpc_signer: Option<MpcSigner>) -> Self {
+        self.mpc_signer = mpc_signer;
+        self
+    }
+
+    /// Record signing operations in the given audit log
+    pub fn with_audit_logger(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Generate a new cryptographic keypair from the operating system's CSPRNG
+    pub fn generate_keypair(&self) -> AppResult<(SecretKeyGuard, PublicKey)> {
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        // Nearly every 32-byte string is a valid key; draw again on the rare one past the curve order
+        let secret_key = loop {
+            OsRng
+                .try_fill_bytes(bytes.as_mut())
+                .map_err(|e| AppError::InternalError(format!("OS random number generator failed: {}", e)))?;
+            if let Ok(secret_key) = SecretKey::from_slice(bytes.as_ref()) {
+                break SecretKeyGuard::new(secret_key);
+            }
+        };
+        let public_key = PublicKey::from_secret_key(&self.secp, &secret_key);
+        Ok((secret_key, public_key))
     }
 
     /// Convert public key to Ethereum address
@@ -39,20 +224,177 @@ impl WalletService {
     /// Create a new account
     pub fn create_account(&self) -> AppResult<Account> {
         let (secret_key, public_key) = self.generate_keypair()?;
-        let address = self.public_key_to_address(&public_key);
-        
-        let account = Account::new(
-            &secret_key.to_string(),
-            &public_key.to_string(),
-            &format!("{:?}", address),
-        );
+        let account = self.account_from_keypair(&secret_key, &public_key);
 
         info!("New account created with address: {}", account.public_address);
         Ok(account)
     }
 
-    /// Save account to file
+    /// Build an account from a hex-encoded private key
+    pub fn import_private_key(&self, private_key: &str) -> AppResult<Account> {
+        let secret_key = SecretKey::from_str(private_key.trim().trim_start_matches("0x"))
+            .map(SecretKeyGuard::new)
+            .map_err(|e| AppError::InvalidPrivateKey(e.to_string()))?;
+        let public_key = PublicKey::from_secret_key(&self.secp, &secret_key);
+        Ok(self.account_from_keypair(&secret_key, &public_key))
+    }
+
+    /// Restore an account from a BIP-39 mnemonic, deriving the key at a BIP-32 path
+    #[allow(clippy::wrong_self_convention)] // needs the service's secp context
+    pub fn from_mnemonic(&self, phrase: &str, passphrase: Option<&str>, derivation_path: &str) -> AppResult<Account> {
+        let seed = self.mnemonic_to_seed(phrase, passphrase)?;
+        let account = self.account_from_seed(seed.as_ref(), derivation_path)?;
+
+        info!("Account imported from mnemonic at {}: {}", derivation_path, account.public_address);
+        Ok(account)
+    }
+
+    /// Validate a BIP-39 mnemonic and compute its seed
+    pub fn mnemonic_to_seed(&self, phrase: &str, passphrase: Option<&str>) -> AppResult<Zeroizing<[u8; 64]>> {
+        let mnemonic = bip39::Mnemonic::parse(phrase.trim())
+            .map_err(|e| AppError::ValidationError(format!("Invalid mnemonic: {}", e)))?;
+        Ok(Zeroizing::new(mnemonic.to_seed(passphrase.unwrap_or(""))))
+    }
+
+    /// Derive the account at a BIP-32 path from a BIP-39 seed
+    pub fn account_from_seed(&self, seed: &[u8], derivation_path: &str) -> AppResult<Account> {
+        let secret_key = bip32::derive_secret_key(&self.secp, seed, derivation_path)?;
+        let public_key = PublicKey::from_secret_key(&self.secp, &secret_key);
+
+        let mut account = self.account_from_keypair(&secret_key, &public_key);
+        account.derivation_path = Some(derivation_path.to_string());
+        Ok(account)
+    }
+
+    /// Build an account from a keypair
+    fn account_from_keypair(&self, secret_key: &SecretKey, public_key: &PublicKey) -> Account {
+        let address = self.public_key_to_address(public_key);
+
+        Account::new(
+            SecretString::new(secret_key.display_secret().to_string()),
+            &public_key.to_string(),
+            &ChecksumAddress::from(address).to_string(),
+        )
+    }
+
+    /// Create an account whose address starts with `search.prefix` and ends with `search.suffix`.
+    /// The search runs on every available core until a match is found, the attempt or time budget
+    /// runs out or the search is cancelled, counting keypairs in `search.attempts` and logging progress
+    /// every few seconds. Returns the account with the number of keypairs tried.
+    pub fn create_vanity_account(&self, search: &VanitySearch) -> AppResult<(Account, u64)> {
+        let (prefix, suffix) = search.patterns()?;
+        let pattern_length = prefix.len() + suffix.len();
+        let expected_attempts = 16f64.powi(pattern_length as i32);
+        if pattern_length > VANITY_PATTERN_WARN_LENGTH {
+            warn!(
+                "Vanity pattern '{}...{}' has {} characters; expect ~{:.0} attempts (16x per character)",
+                prefix, suffix, pattern_length, expected_attempts
+            );
+        }
+
+        let started = Instant::now();
+        let deadline = started + search.timeout;
+        let max_attempts = search.max_attempts.unwrap_or(u64::MAX);
+        let found = AtomicBool::new(false);
+        let attempts = &search.attempts;
+        let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+        let results = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| -> AppResult<Option<(SecretKeyGuard, PublicKey)>> {
+                        while !found.load(Ordering::Relaxed)
+                            && !search.cancelled.load(Ordering::Relaxed)
+                            && Instant::now() < deadline
+                        {
+                            if attempts.fetch_add(1, Ordering::Relaxed) >= max_attempts {
+                                break;
+                            }
+                            let (secret_key, public_key) = self.generate_keypair()?;
+
+                            let address = format!("{:x}", self.public_key_to_address(&public_key));
+                            if address.starts_with(&prefix)
+                                && address.ends_with(&suffix)
+                                && !found.swap(true, Ordering::Relaxed)
+                            {
+                                return Ok(Some((secret_key, public_key)));
+                            }
+                        }
+                        Ok(None)
+                    })
+                })
+                .collect();
+
+            // Report progress from this thread while the workers search
+            let mut last_report = started;
+            while !handles.iter().all(|handle| handle.is_finished()) {
+                thread::sleep(VANITY_POLL_INTERVAL);
+                if last_report.elapsed() >= VANITY_PROGRESS_INTERVAL {
+                    last_report = Instant::now();
+                    let tried = attempts.load(Ordering::Relaxed).min(max_attempts);
+                    let elapsed = started.elapsed().as_secs_f64();
+                    info!(
+                        "Vanity search '{}...{}': {} attempts in {:.0}s ({:.0}/s, ~{:.0} expected)",
+                        prefix,
+                        suffix,
+                        tried,
+                        elapsed,
+                        tried as f64 / elapsed,
+                        expected_attempts
+                    );
+                }
+            }
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|_| Err(AppError::InternalError("Vanity worker panicked".to_string()))))
+                .collect::<Vec<_>>()
+        });
+
+        // Workers bump the counter once more when they hit the budget
+        let attempts = attempts.load(Ordering::Relaxed).min(max_attempts);
+        let mut keypair = None;
+        for result in results {
+            if let Some(found_keypair) = result? {
+                keypair = Some(found_keypair);
+            }
+        }
+
+        match keypair {
+            Some((secret_key, public_key)) => {
+                let account = self.account_from_keypair(&secret_key, &public_key);
+                info!("Vanity account created with address: {} after {} attempts", account.public_address, attempts);
+                Ok((account, attempts))
+            }
+            None if search.cancelled.load(Ordering::Relaxed) => {
+                info!("Vanity search '{}...{}' cancelled after {} attempts", prefix, suffix, attempts);
+                Err(AppError::VanitySearchExhausted(format!(
+                    "Search for '{}...{}' was cancelled after {} attempts",
+                    prefix, suffix, attempts
+                )))
+            }
+            None if attempts >= max_attempts => Err(AppError::VanitySearchExhausted(format!(
+                "No address matching '{}...{}' found within {} attempts",
+                prefix, suffix, attempts
+            ))),
+            None => Err(AppError::VanitySearchExhausted(format!(
+                "No address matching '{}...{}' found within {}s ({} attempts)",
+                prefix,
+                suffix,
+                search.timeout.as_secs(),
+                attempts
+            ))),
+        }
+    }
+
+    /// Save account to file (the only place an account's secret key is serialized):
+    /// an encrypted keystore when a wallet password is set, plaintext JSON otherwise.
+    /// Watch-only accounts have no secret, so they are always plaintext.
     pub fn save_account(&self, account: &Account, file_path: &str) -> AppResult<()> {
+        if let (Some(password), false) = (&self.password, account.is_watch_only()) {
+            return self.save_account_encrypted(account, file_path, password.expose_secret());
+        }
+
         let file = OpenOptions::new()
             .write(true)
             .create(true)
@@ -61,15 +403,121 @@ impl WalletService {
             .map_err(|e| AppError::WalletCreationFailed(format!("Failed to create file: {}", e)))?;
 
         let buf_writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(buf_writer, account)
+        serde_json::to_writer_pretty(buf_writer, &AccountFile::from(account))
             .map_err(|e| AppError::WalletCreationFailed(format!("Failed to serialize account: {}", e)))?;
 
         info!("Account saved to: {}", file_path);
         Ok(())
     }
 
-    /// Load account from file
+    /// Load account from file, decrypting it with the wallet password when it is a keystore
     pub fn load_account(&self, file_path: &str) -> AppResult<Account> {
+        let contents = read_wallet_file(file_path)?;
+        if let WalletFileFormat::Keystore(_) = WalletFileFormat::of(&contents) {
+            let password = self.password.as_ref().map(SecretString::expose_secret).ok_or_else(|| {
+                AppError::WalletLoadFailed(format!(
+                    "{} is an encrypted keystore; set WALLET_PASSWORD or wallet.password",
+                    file_path
+                ))
+            })?;
+            return self.load_account_encrypted(file_path, password);
+        }
+
+        let mut account: Account = serde_json::from_value(contents)
+            .map_err(|e| AppError::WalletLoadFailed(format!("Failed to deserialize account: {}", e)))?;
+        if self.password.is_some() && !account.is_watch_only() {
+            warn!(
+                "{} is a plaintext wallet file although a wallet password is set; run with --migrate-keystore to encrypt it",
+                file_path
+            );
+        }
+        // Files written before checksummed addresses hold the lowercase form
+        let address: ChecksumAddress = account
+            .public_address
+            .parse()
+            .map_err(|e| AppError::WalletLoadFailed(format!("{}: {}", file_path, e)))?;
+        account.public_address = address.to_string();
+
+        info!("Account loaded from: {}", file_path);
+        Ok(account)
+    }
+
+    /// Format of the wallet file at `file_path`
+    pub fn wallet_file_format(&self, file_path: &str) -> AppResult<WalletFileFormat> {
+        read_wallet_file(file_path).map(|contents| WalletFileFormat::of(&contents))
+    }
+
+    /// Re-encrypt a plaintext wallet file as a keystore with the wallet password and wipe the plaintext.
+    /// The keystore is written and decrypted again before it replaces the original, so a failure leaves
+    /// the original in place. Returns false when there is nothing to migrate: the file is already a
+    /// keystore or belongs to a watch-only account.
+    pub fn migrate_to_keystore(&self, file_path: &str) -> AppResult<bool> {
+        let password = self.password.as_ref().map(SecretString::expose_secret).ok_or_else(|| {
+            AppError::ConfigurationError("Migrating wallet files needs WALLET_PASSWORD or wallet.password".to_string())
+        })?;
+        if self.wallet_file_format(file_path)? != WalletFileFormat::Plaintext {
+            return Ok(false);
+        }
+        let account = self.load_account(file_path)?;
+        if account.is_watch_only() {
+            return Ok(false);
+        }
+        self.validate_account(&account)?;
+
+        let staged = format!("{}{}", file_path, MIGRATION_SUFFIX);
+        self.save_account_encrypted(&account, &staged, password)?;
+        let verified = File::open(&staged)
+            .and_then(|file| file.sync_all())
+            .map_err(|e| AppError::WalletCreationFailed(format!("Failed to flush {}: {}", staged, e)))
+            .and_then(|_| self.load_account_encrypted(&staged, password))
+            .and_then(|reloaded| {
+                if reloaded.public_address == account.public_address {
+                    Ok(())
+                } else {
+                    Err(AppError::WalletCreationFailed(format!("{} decrypts to a different address", staged)))
+                }
+            });
+        if let Err(e) = verified {
+            let _ = fs::remove_file(&staged);
+            return Err(e);
+        }
+
+        // Keep a handle on the plaintext so it can be overwritten after the keystore has atomically taken its name
+        let mut plaintext = OpenOptions::new()
+            .write(true)
+            .open(file_path)
+            .map_err(|e| AppError::WalletLoadFailed(format!("Failed to open {}: {}", file_path, e)))?;
+        fs::rename(&staged, file_path)
+            .map_err(|e| AppError::WalletCreationFailed(format!("Failed to replace {}: {}", file_path, e)))?;
+        wipe(&mut plaintext)
+            .map_err(|e| AppError::InternalError(format!("{} was encrypted but its plaintext could not be wiped: {}", file_path, e)))?;
+
+        info!("Migrated {} to an encrypted keystore", file_path);
+        Ok(true)
+    }
+
+    /// Save account to file as an encrypted (Web3 Secret Storage v3) keystore
+    pub fn save_account_encrypted(&self, account: &Account, file_path: &str, password: &str) -> AppResult<()> {
+        let secret_key = self.get_secret_key(account)?;
+        let keystore = keystore::encrypt(secret_key.as_ref(), &account.public_address, password)?;
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(file_path)
+            .map_err(|e| AppError::WalletCreationFailed(format!("Failed to create file: {}", e)))?;
+
+        let buf_writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(buf_writer, &keystore)
+            .map_err(|e| AppError::WalletCreationFailed(format!("Failed to serialize keystore: {}", e)))?;
+
+        info!("Encrypted account saved to: {}", file_path);
+        Ok(())
+    }
+
+    /// Load account from an encrypted keystore file
+    pub fn load_account_encrypted(&self, file_path: &str, password: &str) -> AppResult<Account> {
         if !utils::path_exists(file_path) {
             return Err(AppError::WalletNotFound);
         }
@@ -80,14 +528,32 @@ impl WalletService {
             .map_err(|e| AppError::WalletLoadFailed(format!("Failed to open file: {}", e)))?;
 
         let buf_reader = BufReader::new(file);
-        let account: Account = serde_json::from_reader(buf_reader)
-            .map_err(|e| AppError::WalletLoadFailed(format!("Failed to deserialize account: {}", e)))?;
+        let keystore: Keystore = serde_json::from_reader(buf_reader)
+            .map_err(|e| AppError::WalletLoadFailed(format!("File is not an encrypted keystore: {}", e)))?;
+        let account = self.import_keystore(&keystore, password)?;
 
-        info!("Account loaded from: {}", file_path);
+        info!("Encrypted account loaded from: {}", file_path);
         Ok(account)
     }
 
-    /// Initialize wallet - load existing or create new
+    /// Build an account from a v3 keystore and its password
+    pub fn import_keystore(&self, keystore: &Keystore, password: &str) -> AppResult<Account> {
+        let secret = keystore::decrypt(keystore, password)?;
+        let secret_key = SecretKey::from_slice(&secret)
+            .map(SecretKeyGuard::new)
+            .map_err(|e| AppError::InvalidPrivateKey(e.to_string()))?;
+        let public_key = PublicKey::from_secret_key(&self.secp, &secret_key);
+        let account = self.account_from_keypair(&secret_key, &public_key);
+
+        let keystore_address = keystore.address.trim_start_matches("0x");
+        if !account.public_address.trim_start_matches("0x").eq_ignore_ascii_case(keystore_address) {
+            return Err(AppError::WalletLoadFailed("Keystore address doesn't match decrypted key".to_string()));
+        }
+        Ok(account)
+    }
+
+    /// Initialize wallet - load existing or create new.
+    /// With a password a new wallet file is an encrypted keystore, otherwise plaintext JSON.
     pub fn initialize_wallet(&self, file_path: &str) -> AppResult<Account> {
         if utils::path_exists(file_path) {
             info!("Loading existing wallet from: {}", file_path);
@@ -100,9 +566,206 @@ impl WalletService {
         }
     }
 
-    /// Get secret key from account
-    pub fn get_secret_key(&self, account: &Account) -> AppResult<SecretKey> {
-        SecretKey::from_str(&account.secret_key)
+    /// Make `account` the wallet in `file_path`, so it is loaded again after a restart. The file it
+    /// replaces is copied to `<file_path>.replaced-<timestamp>` first, and the new key takes its name
+    /// atomically, so a failure leaves a loadable wallet. Returns the path of the copy, if there was a file.
+    pub fn replace_wallet(&self, account: &Account, file_path: &str) -> AppResult<Option<String>> {
+        let staged = format!("{}{}", file_path, REPLACEMENT_SUFFIX);
+        self.save_account(account, &staged)?;
+
+        let backup = if utils::path_exists(file_path) {
+            let backup = format!("{}.replaced-{}", file_path, chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"));
+            if let Err(e) = fs::copy(file_path, &backup) {
+                let _ = fs::remove_file(&staged);
+                return Err(AppError::WalletCreationFailed(format!("Failed to keep a copy of {}: {}", file_path, e)));
+            }
+            Some(backup)
+        } else {
+            None
+        };
+        fs::rename(&staged, file_path)
+            .map_err(|e| AppError::WalletCreationFailed(format!("Failed to replace {}: {}", file_path, e)))?;
+
+        info!("Wallet file {} now holds {}", file_path, account.public_address);
+        Ok(backup)
+    }
+
+    /// Save the seed of the mnemonic the wallet at `wallet_file` (address `address`) was imported from,
+    /// encrypted with the wallet password when one is set
+    pub fn save_hd_seed(&self, seed: &[u8; 64], address: &str, wallet_file: &str) -> AppResult<()> {
+        let file_path = format!("{}{}", wallet_file, HD_SEED_SUFFIX);
+        let contents = match &self.password {
+            Some(password) => serde_json::to_value(keystore::encrypt(seed, address, password.expose_secret())?),
+            None => serde_json::to_value(HdSeedFile { address: address.to_string(), seed: hex::encode(seed) }),
+        }
+        .map_err(|e| AppError::WalletCreationFailed(format!("Failed to serialize seed: {}", e)))?;
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&file_path)
+            .map_err(|e| AppError::WalletCreationFailed(format!("Failed to create file: {}", e)))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &contents)
+            .map_err(|e| AppError::WalletCreationFailed(format!("Failed to write seed: {}", e)))?;
+
+        info!("HD seed saved to: {}", file_path);
+        Ok(())
+    }
+
+    /// Load the seed saved by `save_hd_seed` for the wallet at `wallet_file`. Returns None when there is
+    /// none, or when it belongs to another address because the wallet was replaced since.
+    pub fn load_hd_seed(&self, address: &str, wallet_file: &str) -> AppResult<Option<Zeroizing<[u8; 64]>>> {
+        let file_path = format!("{}{}", wallet_file, HD_SEED_SUFFIX);
+        if !utils::path_exists(&file_path) {
+            return Ok(None);
+        }
+        let belongs_to_wallet = |seed_address: &str| {
+            let matches = address.trim_start_matches("0x").eq_ignore_ascii_case(seed_address.trim_start_matches("0x"));
+            if !matches {
+                warn!("Ignoring {}: it belongs to {}, not the wallet {}", file_path, seed_address, address);
+            }
+            matches
+        };
+
+        let contents = read_wallet_file(&file_path)?;
+        let seed = match WalletFileFormat::of(&contents) {
+            WalletFileFormat::Keystore(_) => {
+                let keystore: Keystore = serde_json::from_value(contents)
+                    .map_err(|e| AppError::WalletLoadFailed(format!("{} is not a keystore: {}", file_path, e)))?;
+                if !belongs_to_wallet(&keystore.address) {
+                    return Ok(None);
+                }
+                let password = self.password.as_ref().map(SecretString::expose_secret).ok_or_else(|| {
+                    AppError::WalletLoadFailed(format!(
+                        "{} is an encrypted keystore; set WALLET_PASSWORD or wallet.password",
+                        file_path
+                    ))
+                })?;
+                keystore::decrypt(&keystore, password)?
+            }
+            WalletFileFormat::Plaintext => {
+                let file: HdSeedFile = serde_json::from_value(contents)
+                    .map_err(|e| AppError::WalletLoadFailed(format!("{} is not a seed file: {}", file_path, e)))?;
+                if !belongs_to_wallet(&file.address) {
+                    return Ok(None);
+                }
+                Zeroizing::new(hex::decode(&file.seed).map_err(|e| {
+                    AppError::InternalError(format!("HD seed file is corrupted: {}: {}", file_path, e))
+                })?)
+            }
+        };
+
+        let seed: [u8; 64] = seed
+            .as_slice()
+            .try_into()
+            .map_err(|_| AppError::WalletLoadFailed(format!("{} does not hold a 64-byte seed", file_path)))?;
+        info!("HD seed loaded from: {}", file_path);
+        Ok(Some(Zeroizing::new(seed)))
+    }
+
+    /// Sign a message with the EIP-191 `personal_sign` prefix.
+    /// Returns the 65-byte `r || s || v` signature as hex, with `v` in {27, 28}.
+    pub fn sign_message(&self, account: &Account, message: &str) -> AppResult<String> {
+        let secret_key = self.get_secret_key(account)?;
+        let hash = web3_signing::hash_message(message.as_bytes());
+
+        let signature = SecretKeyRef::new(&secret_key)
+            .sign(hash.as_bytes(), None)
+            .map_err(|e| AppError::InternalError(format!("Failed to sign message: {}", e)))?;
+
+        let mut entry = AuditEntry::new("sign_message", &account.public_address);
+        entry.message_hash = Some(format!("{:?}", hash));
+        self.audit.record(&entry)?;
+
+        let mut bytes = Vec::with_capacity(65);
+        bytes.extend_from_slice(signature.r.as_bytes());
+        bytes.extend_from_slice(signature.s.as_bytes());
+        bytes.push(signature.v as u8);
+        Ok(format!("0x{}", hex::encode(bytes)))
+    }
+
+    /// Sign an EIP-712 digest (e.g. a permit) as-is, without the `personal_sign` prefix.
+    /// `v` is in {27, 28}.
+    pub fn sign_typed_data(&self, account: &Account, digest: H256, target: &str) -> AppResult<Signature> {
+        let secret_key = self.get_secret_key(account)?;
+        let signature = SecretKeyRef::new(&secret_key)
+            .sign(digest.as_bytes(), None)
+            .map_err(|e| AppError::InternalError(format!("Failed to sign typed data: {}", e)))?;
+
+        let mut entry = AuditEntry::new("sign_typed_data", &account.public_address);
+        entry.target = Some(target.to_string());
+        entry.message_hash = Some(format!("{:?}", digest));
+        self.audit.record(&entry)?;
+
+        Ok(signature)
+    }
+
+    /// Hex secret key of `account` for backup; audited like a signature
+    pub fn export_secret_key(&self, account: &Account) -> AppResult<String> {
+        let secret_key = self.get_secret_key(account)?;
+        self.audit.record(&AuditEntry::new("export_key", &account.public_address))?;
+        warn!("!!! Secret key of {} exported via the API !!!", account.public_address);
+        let encoded = Zeroizing::new(hex::encode(secret_key.as_ref()));
+        Ok(format!("0x{}", encoded.as_str()))
+    }
+
+    /// Split an account's key into `count` Shamir backup shares, any `threshold` of which restore it
+    pub fn backup_shares(&self, account: &Account, threshold: u8, count: u8) -> AppResult<Vec<String>> {
+        let secret_key = self.get_secret_key(account)?;
+        let shares = backup::split(secret_key.as_ref(), threshold, count)?;
+        self.audit.record(&AuditEntry::new("backup_shares", &account.public_address))?;
+        warn!(
+            "!!! Secret key of {} exported as {}-of-{} backup shares via the API !!!",
+            account.public_address, threshold, count
+        );
+        Ok(shares)
+    }
+
+    /// Rebuild an account from Shamir backup shares
+    pub fn restore_from_shares(&self, shares: &[&str]) -> AppResult<Account> {
+        let secret = backup::combine(shares)?;
+        let secret_key = SecretKey::from_slice(&secret)
+            .map(SecretKeyGuard::new)
+            .map_err(|e| AppError::InvalidPrivateKey(e.to_string()))?;
+        let public_key = PublicKey::from_secret_key(&self.secp, &secret_key);
+        Ok(self.account_from_keypair(&secret_key, &public_key))
+    }
+
+    /// Encrypt an account's key into a v3 keystore with a caller-chosen passphrase, for moving it to another wallet
+    pub fn export_keystore(&self, account: &Account, passphrase: &str) -> AppResult<Keystore> {
+        let secret_key = self.get_secret_key(account)?;
+        let keystore = keystore::encrypt(secret_key.as_ref(), &account.public_address, passphrase)?;
+        self.audit.record(&AuditEntry::new("export_keystore", &account.public_address))?;
+        warn!("Keystore of {} exported via the API", account.public_address);
+        Ok(keystore)
+    }
+
+    /// Signer for an account's transactions; handlers sign only through this
+    pub fn signer(&self, account: &Account) -> AppResult<Box<dyn Signer>> {
+        if account.kind == AccountKind::Mpc {
+            return match &self.mpc_signer {
+                Some(signer) if utils::to_checksum(&signer.address()) == account.public_address => Ok(Box::new(signer.clone())),
+                _ => Err(AppError::ConfigurationError(format!("No MPC co-signers are configured for {}", account.public_address))),
+            };
+        }
+        Ok(Box::new(LocalSigner::new(self.get_secret_key(account)?)))
+    }
+
+    /// Get secret key from account; watch-only accounts have none.
+    /// The guard overwrites the key when dropped, so keep it only as long as it is needed.
+    pub fn get_secret_key(&self, account: &Account) -> AppResult<SecretKeyGuard> {
+        if account.is_watch_only() {
+            return Err(AppError::WatchOnlyAccount(account.public_address.clone()));
+        }
+        if account.kind == AccountKind::Mpc {
+            return Err(AppError::NotSupported(format!(
+                "The key of {} is split across MPC co-signers, which only sign transactions",
+                account.public_address
+            )));
+        }
+        SecretKey::from_str(account.secret_key.expose_secret())
+            .map(SecretKeyGuard::new)
             .map_err(|e| AppError::InvalidPrivateKey(e.to_string()))
     }
 
@@ -112,8 +775,13 @@ impl WalletService {
             .map_err(|e| AppError::InvalidPublicKey(e.to_string()))
     }
 
-    /// Validate account integrity
+    /// Validate account integrity; for watch-only and MPC accounts only the address is checked
     pub fn validate_account(&self, account: &Account) -> AppResult<bool> {
+        if account.is_watch_only() || account.kind == AccountKind::Mpc {
+            account.public_address.parse::<ChecksumAddress>()?;
+            return Ok(true);
+        }
+
         let secret_key = self.get_secret_key(account)?;
         let public_key = self.get_public_key(account)?;
         
@@ -125,8 +793,7 @@ impl WalletService {
 
         // Verify that the address matches the public key
         let derived_address = self.public_key_to_address(&public_key);
-        let expected_address = format!("{:?}", derived_address);
-        if expected_address != account.public_address {
+        if ChecksumAddress::from(derived_address).to_string() != account.public_address {
             return Err(AppError::ValidationError("Address doesn't match public key".to_string()));
         }
 
@@ -138,4 +805,131 @@ impl Default for WalletService {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn generated_keypairs_are_unique_and_valid() {
+        let wallet_service = WalletService::new();
+        let mut addresses = HashSet::new();
+        for _ in 0..256 {
+            let account = wallet_service.create_account().unwrap();
+            assert!(wallet_service.validate_account(&account).unwrap());
+            assert!(addresses.insert(account.public_address.clone()), "duplicate key {}", account.public_address);
+        }
+    }
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn temp_wallet_file() -> String {
+        let directory = std::env::temp_dir().join(format!("wallet-test-{}", uuid::Uuid::from_bytes(rand::random())));
+        fs::create_dir_all(&directory).unwrap();
+        directory.join("account_config.json").to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn derives_the_bip39_test_vector_address() {
+        let wallet_service = WalletService::new();
+        let account = wallet_service.from_mnemonic(MNEMONIC, None, "m/44'/60'/0'/0/0").unwrap();
+        assert_eq!(account.public_address, "0x9858EfFD232B4033E47d90003D41EC34EcaEda94");
+    }
+
+    #[test]
+    fn normalizes_the_mnemonic_before_checking_it() {
+        let wallet_service = WalletService::new();
+        // Fullwidth letters are not in the word list until NFKD-normalized
+        let fullwidth = MNEMONIC.replace("about", "\u{ff41}bout");
+        let seed = wallet_service.mnemonic_to_seed(&fullwidth, None).unwrap();
+        assert_eq!(*seed, *wallet_service.mnemonic_to_seed(MNEMONIC, None).unwrap());
+    }
+
+    #[test]
+    fn replaced_wallet_is_loaded_after_a_restart_with_its_seed() {
+        let wallet_service = WalletService::new();
+        let wallet_file = temp_wallet_file();
+        let previous = wallet_service.initialize_wallet(&wallet_file).unwrap();
+
+        let seed = wallet_service.mnemonic_to_seed(MNEMONIC, None).unwrap();
+        let imported = wallet_service.account_from_seed(seed.as_ref(), "m/44'/60'/0'/0/0").unwrap();
+        wallet_service.save_hd_seed(&seed, &imported.public_address, &wallet_file).unwrap();
+        let backup = wallet_service.replace_wallet(&imported, &wallet_file).unwrap().unwrap();
+
+        assert_eq!(wallet_service.initialize_wallet(&wallet_file).unwrap().public_address, imported.public_address);
+        assert_eq!(wallet_service.load_account(&backup).unwrap().public_address, previous.public_address);
+        let reloaded = wallet_service.load_hd_seed(&imported.public_address, &wallet_file).unwrap().unwrap();
+        assert_eq!(*reloaded, *seed);
+        // A seed saved for another wallet is not handed out
+        assert!(wallet_service.load_hd_seed(&previous.public_address, &wallet_file).unwrap().is_none());
+    }
+
+    #[test]
+    fn a_seed_file_that_is_not_hex_is_reported_as_corrupted() {
+        let wallet_service = WalletService::new();
+        let wallet_file = temp_wallet_file();
+        let account = wallet_service.create_account().unwrap();
+        let seed = wallet_service.mnemonic_to_seed(MNEMONIC, None).unwrap();
+        wallet_service.save_hd_seed(&seed, &account.public_address, &wallet_file).unwrap();
+
+        let seed_file = format!("{}{}", wallet_file, HD_SEED_SUFFIX);
+        let contents = fs::read_to_string(&seed_file).unwrap().replace(&hex::encode(seed.as_ref()), "not hex");
+        fs::write(&seed_file, contents).unwrap();
+
+        assert!(matches!(
+            wallet_service.load_hd_seed(&account.public_address, &wallet_file),
+            Err(AppError::InternalError(message)) if message.starts_with("HD seed file is corrupted")
+        ));
+    }
+
+    #[test]
+    fn signed_message_recovers_to_the_account_address() {
+        let wallet_service = WalletService::new();
+        let account = wallet_service.create_account().unwrap();
+        let message = "Sign in to example.com\nNonce: 42";
+
+        let signature = wallet_service.sign_message(&account, message).unwrap();
+        let bytes = hex::decode(signature.trim_start_matches("0x")).unwrap();
+        assert_eq!(bytes.len(), 65);
+        let v = bytes[64];
+        assert!(v == 27 || v == 28, "v = {}", v);
+
+        let hash = web3_signing::hash_message(message.as_bytes());
+        let signer = web3_signing::recover(hash.as_bytes(), &bytes[..64], (v - 27) as i32).unwrap();
+        assert_eq!(ChecksumAddress::from(signer).to_string(), account.public_address);
+    }
+
+    #[test]
+    fn plaintext_wallet_files_keep_the_key() {
+        let wallet_service = WalletService::new();
+        let account = wallet_service.create_account().unwrap();
+        let file = temp_wallet_file();
+
+        wallet_service.save_account(&account, &file).unwrap();
+        let contents: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(contents["secret_key"], account.secret_key.expose_secret());
+        let loaded = wallet_service.load_account(&file).unwrap();
+        assert_eq!(loaded.secret_key.expose_secret(), account.secret_key.expose_secret());
+        assert_eq!(loaded.public_address, account.public_address);
+    }
+
+    #[test]
+    fn imports_keystores_whose_address_has_a_0x_prefix() {
+        let wallet_service = WalletService::new();
+        let account = wallet_service.create_account().unwrap();
+        let mut keystore = wallet_service.export_keystore(&account, "correct horse").unwrap();
+
+        // Some wallets write the address checksummed and prefixed
+        keystore.address = account.public_address.clone();
+        let imported = wallet_service.import_keystore(&keystore, "correct horse").unwrap();
+        assert_eq!(imported.public_address, account.public_address);
+
+        keystore.address = wallet_service.create_account().unwrap().public_address;
+        assert!(matches!(
+            wallet_service.import_keystore(&keystore, "correct horse"),
+            Err(AppError::WalletLoadFailed(message)) if message.contains("doesn't match")
+        ));
+    }
+}
@@ -1,40 +1,363 @@
+use crate::address::ChecksumAddress;
+use crate::config::{DefaultTransactionType, EthereumConfig};
 use crate::errors::{AppError, AppResult};
-use crate::models::{BalanceInfo, NetworkInfo, TransactionInfo, TransactionRequest, TransactionStatus};
+use crate::models::{BalanceBatchEntry, BalanceInfo, BatchFailure, BatchTransactionResult, EnsProfile, FeeEstimate, FeeSuggestion, GasPriceInfo, HistoryTransaction, MempoolInfo, MempoolTransaction, NativeAmount, NetworkInfo, NetworkStatus, ReceiptLog, TokenEvent, RpcRequest, RpcResponse, TokenBalanceInfo, TransactionInfo, TransactionReceiptInfo, TransactionRequest, TransactionStatus};
+use crate::services::audit_logger::{AuditEntry, AuditLogger};
+use crate::services::safe::{self, SafeState};
+use crate::services::signer::Signer;
 use crate::utils;
-use secp256k1::SecretKey;
+use futures::stream::{self, StreamExt};
+use std::future::Future;
+use std::collections::{BTreeSet, HashMap};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tracing::{info, warn, error};
 use web3::{
-    transports::WebSocket,
-    types::{Address, TransactionParameters, CallRequest},
-    Web3,
+    api::{Namespace, SubscriptionStream},
+    contract::ens::Ens,
+    transports::{Either, Http, WebSocket},
+    types::{
+        Address, BlockHeader, BlockId, BlockNumber, CallRequest, Log, ParityPendingTransactionFilter, SignedTransaction, Transaction,
+        TransactionId, TransactionParameters, H256, U256, U64,
+    },
+    Transport, Web3,
 };
 
+/// `http(s)://` endpoints use the HTTP transport, `ws(s)://` endpoints a WebSocket
+pub type RpcTransport = Either<WebSocket, Http>;
+
+/// How long a resolved ENS profile is served from cache
+const ENS_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Networks with the ENS registry deployed: mainnet, Goerli, Holesky and Sepolia
+const ENS_NETWORKS: [u64; 4] = [1, 5, 17000, 11155111];
+
+/// Shared result of one in-flight `eth_estimateGas`, awaited by every identical request
+type InFlightEstimate = Arc<tokio::sync::OnceCell<AppResult<U256>>>;
+
+/// Gas estimate cache key: recipient and the 4-byte method selector of the call data, if any
+type GasEstimateKey = (Option<Address>, Option<[u8; 4]>);
+
+/// How long a node's pending count may lag the nonces handed out before they are assumed dropped
+const NONCE_RESYNC_AFTER: Duration = Duration::from_secs(60);
+/// Number of recent blocks sampled when computing the average block time
+const BLOCK_TIME_SAMPLE_SIZE: u64 = 20;
+/// How long a computed average block time is reused before sampling again
+const BLOCK_TIME_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Number of recent blocks whose priority fees are sampled via `eth_feeHistory`
+const FEE_HISTORY_BLOCKS: u64 = 10;
+/// Reward percentile requested from `eth_feeHistory`
+const FEE_HISTORY_PERCENTILE: f64 = 50.0;
+/// Gas used by a plain value transfer with no calldata
+const SIMPLE_TRANSFER_GAS: u64 = 21_000;
+/// EIP-2718 type byte of an EIP-1559 fee market transaction
+const EIP1559_TX_TYPE: u64 = 2;
+/// Multiple of the current base fee allowed in a suggested `maxFeePerGas`,
+/// leaving headroom for base fee increases over the next few blocks
+const BASE_FEE_HEADROOM: u64 = 2;
+/// ERC-20 `balanceOf(address)` selector
+const ERC20_BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+/// ERC-20 `transfer(address,uint256)` selector
+const ERC20_TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// ERC-20 `decimals()` selector
+const ERC20_DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+/// EIP-2612 `DOMAIN_SEPARATOR()` selector
+const EIP2612_DOMAIN_SEPARATOR_SELECTOR: [u8; 4] = [0x36, 0x44, 0xe5, 0x15];
+/// EIP-2612 `nonces(address)` selector
+const EIP2612_NONCES_SELECTOR: [u8; 4] = [0x7e, 0xce, 0xbe, 0x00];
+/// Safe `getOwners()` selector
+const SAFE_GET_OWNERS_SELECTOR: [u8; 4] = [0xa0, 0xe6, 0x7e, 0x2b];
+/// Safe `getThreshold()` selector
+const SAFE_GET_THRESHOLD_SELECTOR: [u8; 4] = [0xe7, 0x52, 0x35, 0xb8];
+/// Safe `nonce()` selector
+const SAFE_NONCE_SELECTOR: [u8; 4] = [0xaf, 0xfe, 0xd0, 0xe0];
+/// Safe `domainSeparator()` selector
+const SAFE_DOMAIN_SEPARATOR_SELECTOR: [u8; 4] = [0xf6, 0x98, 0xda, 0x25];
+/// keccak256("Transfer(address,address,uint256)")
+const ERC20_TRANSFER_TOPIC: &str = "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+/// keccak256("Approval(address,address,uint256)")
+const ERC20_APPROVAL_TOPIC: &str = "8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+/// Largest decimals value whose scale (10^decimals) fits in a uint256
+const MAX_TOKEN_DECIMALS: u64 = 77;
+/// JSON-RPC error code for an unknown method
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+/// JSON-RPC error code geth uses for reverted calls
+const EXECUTION_REVERTED_CODE: i64 = 3;
+/// Selector of Solidity's `Error(string)` revert payload
+const REVERT_ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of Solidity's `Panic(uint256)` revert payload
+const REVERT_PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Largest number of transactions accepted in one batch
+const MAX_BATCH_SIZE: usize = 100;
+/// Balance lookups of one batch that may be in flight at once
+const BALANCE_BATCH_CONCURRENCY: usize = 8;
+/// Wait before the first retry of a failed balance batch entry; doubles with every further retry
+const BALANCE_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Blocks of a history scan that may be fetched at once
+const HISTORY_SCAN_CONCURRENCY: usize = 8;
+
+/// Fee fields for a transaction, by pricing model
+#[derive(Clone, Copy)]
+enum FeeParams {
+    Legacy {
+        gas_price: U256,
+    },
+    Eip1559 {
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+/// Caller-supplied fee settings; whatever is unset is filled in from the node
+#[derive(Default)]
+struct FeeOverrides {
+    gas_price: Option<u64>,
+    max_fee_per_gas: Option<u64>,
+    max_priority_fee_per_gas: Option<u64>,
+    max_fee_multiplier: Option<f64>,
+}
+
+impl FeeOverrides {
+    fn is_empty(&self) -> bool {
+        self.gas_price.is_none()
+            && self.max_fee_per_gas.is_none()
+            && self.max_priority_fee_per_gas.is_none()
+            && self.max_fee_multiplier.is_none()
+    }
+}
+
+impl From<&TransactionRequest> for FeeOverrides {
+    fn from(request: &TransactionRequest) -> Self {
+        Self {
+            gas_price: request.gas_price,
+            max_fee_per_gas: request.max_fee_per_gas,
+            max_priority_fee_per_gas: request.max_priority_fee_per_gas,
+            max_fee_multiplier: request.max_fee_multiplier,
+        }
+    }
+}
+
+impl FeeParams {
+    /// Highest price per gas the transaction may pay: `gas_price`, or `max_fee_per_gas` for 1559
+    fn max_price_per_gas(&self) -> U256 {
+        match self {
+            FeeParams::Legacy { gas_price } => *gas_price,
+            FeeParams::Eip1559 { max_fee_per_gas, .. } => *max_fee_per_gas,
+        }
+    }
+
+    /// Set the fee fields (and type, for 1559) on a transaction
+    fn apply(self, tx: &mut TransactionParameters) {
+        match self {
+            FeeParams::Legacy { gas_price } => {
+                tx.gas_price = Some(gas_price);
+            }
+            FeeParams::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => {
+                tx.transaction_type = Some(U64::from(EIP1559_TX_TYPE));
+                tx.max_fee_per_gas = Some(max_fee_per_gas);
+                tx.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+            }
+        }
+    }
+}
+
+/// Nonces handed out per sending address. A reservation takes the lowest nonce that is neither
+/// reserved nor below the node's pending count, so concurrent sends from one account never share
+/// one; a send that fails before its transaction reaches the node releases the nonce for reuse.
+pub struct NonceManager {
+    accounts: Mutex<HashMap<Address, AccountNonces>>,
+    /// How long after an account's last broadcast its own count is preferred to a lower node count
+    resync_after: Duration,
+}
+
+#[derive(Default)]
+struct AccountNonces {
+    /// Lowest nonce not handed out yet
+    next: u64,
+    /// Released nonces below `next`, handed out again before `next` so no gap is left
+    released: BTreeSet<u64>,
+    /// Reservations whose send has not finished yet
+    in_flight: usize,
+    last_broadcast: Option<Instant>,
+}
+
+impl Default for NonceManager {
+    fn default() -> Self {
+        Self::new(NONCE_RESYNC_AFTER)
+    }
+}
+
+impl NonceManager {
+    pub fn new(resync_after: Duration) -> Self {
+        Self {
+            accounts: Mutex::new(HashMap::new()),
+            resync_after,
+        }
+    }
+
+    /// Reserve a nonce for `address`; `pending` is the node's pending transaction count for it.
+    /// Finish the reservation with `release` or `broadcast`.
+    pub fn reserve(&self, address: Address, pending: u64) -> u64 {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.entry(address).or_default();
+        account.catch_up(pending, self.resync_after);
+        account.in_flight += 1;
+        account.released.pop_first().unwrap_or_else(|| {
+            account.next += 1;
+            account.next - 1
+        })
+    }
+
+    /// The nonce `reserve` would hand out now, without reserving it
+    pub fn peek(&self, address: Address, pending: u64) -> u64 {
+        let accounts = self.accounts.lock().unwrap();
+        let Some(account) = accounts.get(&address) else {
+            return pending;
+        };
+        if account.should_resync(pending, self.resync_after) {
+            return pending;
+        }
+        account
+            .released
+            .range(pending..)
+            .next()
+            .copied()
+            .unwrap_or_else(|| account.next.max(pending))
+    }
+
+    /// Give back a reserved nonce whose transaction never reached the node
+    pub fn release(&self, address: Address, nonce: u64) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let Some(account) = accounts.get_mut(&address) else {
+            return;
+        };
+        account.in_flight = account.in_flight.saturating_sub(1);
+        account.released.insert(nonce);
+        // Released nonces at the top need no filling; hand them out as `next` again
+        while account.next > 0 && account.released.remove(&(account.next - 1)) {
+            account.next -= 1;
+        }
+    }
+
+    /// Keep a reserved nonce whose transaction was handed to the node, or may have been
+    pub fn broadcast(&self, address: Address) {
+        let mut accounts = self.accounts.lock().unwrap();
+        if let Some(account) = accounts.get_mut(&address) {
+            account.in_flight = account.in_flight.saturating_sub(1);
+            account.last_broadcast = Some(Instant::now());
+        }
+    }
+}
+
+impl AccountNonces {
+    /// Skip nonces the node counts as used, e.g. by another wallet holding the same key. Start over
+    /// from the node's count when it has fallen behind for good (see `should_resync`).
+    fn catch_up(&mut self, pending: u64, resync_after: Duration) {
+        if self.should_resync(pending, resync_after) {
+            warn!(
+                "Node counts {} sent transactions but {} nonces were handed out; assuming the rest were dropped",
+                pending, self.next
+            );
+            self.next = pending;
+        }
+        self.next = self.next.max(pending);
+        self.released = self.released.split_off(&pending);
+    }
+
+    /// Whether transactions above the node's count were dropped: nothing is being sent, and the node
+    /// still counts fewer than were handed out `resync_after` after the last broadcast. Until then a
+    /// lower count is taken for a node that has not seen the latest sends yet.
+    fn should_resync(&self, pending: u64, resync_after: Duration) -> bool {
+        pending < self.next
+            && self.in_flight == 0
+            && self.last_broadcast.is_none_or(|at| at.elapsed() >= resync_after)
+    }
+}
+
 pub struct Web3Service {
-    connection: Option<Web3<WebSocket>>,
+    connection: Option<Web3<RpcTransport>>,
+    /// Chain ID used for naming and signing; replaced by the node's `eth_chainId` on connect
     network_id: u64,
+    strict_chain_id: bool,
+    /// `ethereum.network_names`, consulted before the built-in names
+    network_names: HashMap<u64, String>,
     rpc_url: String,
+    native_symbol: String,
+    block_time_cache: Mutex<Option<(Instant, f64)>>,
+    ens_cache: Mutex<HashMap<String, (Instant, EnsProfile)>>,
+    ens_addresses: Mutex<HashMap<String, (Instant, Address)>>,
+    gas_estimate_cache: Mutex<HashMap<GasEstimateKey, (Instant, U256)>>,
+    gas_estimate_ttl: Duration,
+    /// Refuse to sign anything priced above this per gas; `None` leaves prices uncapped
+    max_gas_price: Option<U256>,
+    /// Estimates used as a transaction's gas limit are scaled by this
+    gas_limit_multiplier: f64,
+    /// How requests without fee fields are priced
+    default_transaction_type: DefaultTransactionType,
+    /// Identical concurrent estimates, keyed by the serialized call, share one RPC request
+    estimates_in_flight: Mutex<HashMap<String, InFlightEstimate>>,
+    slow_query_threshold: Duration,
+    rpc_timeout: Duration,
+    balance_batch_retries: u32,
+    slow_queries: AtomicU64,
+    audit: Arc<AuditLogger>,
+    /// Nonces reserved by sends in progress and the next one per sending address
+    nonces: NonceManager,
 }
 
 impl Web3Service {
-    pub fn new(rpc_url: String, network_id: u64) -> Self {
+    pub fn new(config: &EthereumConfig) -> Self {
         Self {
             connection: None,
-            network_id,
-            rpc_url,
+            network_id: config.network_id,
+            strict_chain_id: config.strict_chain_id,
+            network_names: config
+                .network_names
+                .iter()
+                .map(|network| (network.network_id, network.name.clone()))
+                .collect(),
+            rpc_url: config.rpc_url.clone(),
+            native_symbol: config.native_symbol(),
+            block_time_cache: Mutex::new(None),
+            ens_cache: Mutex::new(HashMap::new()),
+            ens_addresses: Mutex::new(HashMap::new()),
+            gas_estimate_cache: Mutex::new(HashMap::new()),
+            gas_estimate_ttl: Duration::from_secs(config.gas_estimate_cache_secs),
+            max_gas_price: config.max_gas_price_wei.map(U256::from),
+            gas_limit_multiplier: config.gas_limit_multiplier,
+            default_transaction_type: config.default_transaction_type,
+            estimates_in_flight: Mutex::new(HashMap::new()),
+            slow_query_threshold: Duration::from_millis(config.slow_query_ms),
+            rpc_timeout: Duration::from_millis(config.rpc_timeout_ms),
+            balance_batch_retries: config.balance_batch_retries,
+            slow_queries: AtomicU64::new(0),
+            audit: Arc::new(AuditLogger::disabled()),
+            nonces: NonceManager::default(),
         }
     }
 
-    /// Establish connection to Ethereum network
+    /// Record signing operations in the given audit log
+    pub fn with_audit_logger(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Establish connection to Ethereum network; a failed attempt leaves the service disconnected.
+    /// A node on another chain than `network_id` fails with `ConfigurationError` under `strict_chain_id`.
     pub async fn connect(&mut self) -> AppResult<()> {
-        match web3::transports::WebSocket::new(&self.rpc_url).await {
+        match open_transport(&self.rpc_url).await {
             Ok(transport) => {
-                self.connection = Some(Web3::new(transport));
+                let web3 = Web3::new(transport);
+                self.check_chain_id(&web3).await?;
+                self.connection = Some(web3);
                 info!("Web3 connection established to: {}", self.rpc_url);
                 Ok(())
             }
             Err(e) => {
                 error!("Failed to connect to Web3: {}", e);
+                self.connection = None;
                 Err(AppError::Web3ConnectionFailed(e.to_string()))
             }
         }
@@ -45,154 +368,2797 @@ impl Web3Service {
         self.connection.is_some()
     }
 
+    /// Compare the node's `eth_chainId` with `network_id`, adopting the node's value on a mismatch
+    /// unless `strict_chain_id` is set. An unanswered query keeps the configured value.
+    async fn check_chain_id(&mut self, web3: &Web3<RpcTransport>) -> AppResult<()> {
+        let chain_id = self.timed("eth_chainId", web3.eth().chain_id()).await
+            .and_then(|result| result.map_err(AppError::from));
+        let chain_id = match chain_id {
+            Ok(chain_id) => chain_id.as_u64(),
+            Err(e) => {
+                warn!("Could not read eth_chainId, assuming network {}: {}", self.network_id, e);
+                return Ok(());
+            }
+        };
+
+        if chain_id == self.network_id {
+            return Ok(());
+        }
+        if self.strict_chain_id {
+            self.connection = None;
+            return Err(AppError::ConfigurationError(format!(
+                "{} serves chain {}, but ethereum.network_id is {}",
+                self.rpc_url, chain_id, self.network_id
+            )));
+        }
+
+        warn!(
+            "ethereum.network_id is {} but {} serves chain {}; using chain {}",
+            self.network_id, self.rpc_url, chain_id, chain_id
+        );
+        // Keep a symbol set in config; replace the one derived from the wrong network
+        if self.native_symbol == utils::default_native_symbol(self.network_id) {
+            self.native_symbol = utils::default_native_symbol(chain_id).to_string();
+        }
+        self.network_id = chain_id;
+        Ok(())
+    }
+
+    /// Cheap liveness probe: connected and answering `eth_blockNumber`
+    pub async fn is_live(&self) -> bool {
+        match self.web3() {
+            Ok(web3) => matches!(self.timed("eth_blockNumber", web3.eth().block_number()).await, Ok(Ok(_))),
+            Err(_) => false,
+        }
+    }
+
+    /// Get the active connection, or `Web3NotAvailable` (503) when disconnected.
+    /// Every RPC-backed method goes through here so handlers report it consistently.
+    fn web3(&self) -> AppResult<&Web3<RpcTransport>> {
+        self.connection.as_ref().ok_or(AppError::Web3NotAvailable)
+    }
+
+    /// Await an RPC call, logging it as slow when it exceeds `ethereum.slow_query_ms`
+    /// and giving up with `Web3Timeout` after `ethereum.rpc_timeout_ms`
+    async fn timed<F: Future>(&self, method: &str, call: F) -> AppResult<F::Output> {
+        let started = Instant::now();
+        let output = tokio::time::timeout(self.rpc_timeout, call).await;
+
+        let elapsed = started.elapsed();
+        if elapsed >= self.slow_query_threshold {
+            self.slow_queries.fetch_add(1, Ordering::Relaxed);
+            warn!("Slow RPC call {} took {} ms", method, elapsed.as_millis());
+        }
+        output.map_err(|_| {
+            warn!("RPC call {} timed out after {} ms", method, self.rpc_timeout.as_millis());
+            AppError::Web3Timeout(method.to_string())
+        })
+    }
+
+    /// Number of RPC calls that exceeded the slow query threshold
+    pub fn slow_query_count(&self) -> u64 {
+        self.slow_queries.load(Ordering::Relaxed)
+    }
+
+    /// Slow query threshold in milliseconds
+    pub fn slow_query_threshold_ms(&self) -> u64 {
+        self.slow_query_threshold.as_millis() as u64
+    }
+
     /// Get network information
     pub async fn get_network_info(&self) -> AppResult<NetworkInfo> {
-        let web3 = self.connection.as_ref()
-            .ok_or(AppError::Web3NotAvailable)?;
+        let web3 = self.web3()?;
 
-        let block_number = match web3.eth().block_number().await {
-            Ok(block) => Some(block.as_u64()),
+        let block_number = match self.timed("eth_blockNumber", web3.eth().block_number()).await {
+            Ok(Ok(block)) => Some(block.as_u64()),
+            Ok(Err(e)) => {
+                warn!("Failed to get block number: {}", e);
+                None
+            }
             Err(e) => {
                 warn!("Failed to get block number: {}", e);
                 None
             }
         };
 
-        let network_name = match self.network_id {
-            1 => "Mainnet",
-            3 => "Ropsten",
-            4 => "Rinkeby",
-            5 => "Goerli",
-            11155111 => "Sepolia",
-            _ => "Unknown",
+        let avg_block_time_secs = match self.average_block_time().await {
+            Ok(secs) => Some(secs),
+            Err(e) => {
+                warn!("Failed to compute average block time: {}", e);
+                None
+            }
+        };
+
+        let supports_eip1559 = match self.latest_base_fee().await {
+            Ok(base_fee) => base_fee.is_some(),
+            Err(e) => {
+                warn!("Failed to check EIP-1559 support: {}", e);
+                false
+            }
         };
 
         Ok(NetworkInfo {
             network_id: self.network_id,
-            network_name: network_name.to_string(),
+            network_name: self.network_name().to_string(),
             rpc_url: self.rpc_url.clone(),
             block_number,
+            avg_block_time_secs,
+            supports_eip1559,
         })
     }
 
+    /// Connection status and current block; never fails, so unreachable networks still report
+    pub async fn get_network_status(&self) -> NetworkStatus {
+        let block_number = match self.web3() {
+            Ok(web3) => match self.timed("eth_blockNumber", web3.eth().block_number()).await {
+                Ok(Ok(block)) => Some(block.as_u64()),
+                Ok(Err(e)) => {
+                    warn!("Failed to get block number for network {}: {}", self.network_id, e);
+                    None
+                }
+                Err(e) => {
+                    warn!("Failed to get block number for network {}: {}", self.network_id, e);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+
+        NetworkStatus {
+            network_id: self.network_id,
+            network_name: self.network_name().to_string(),
+            connected: self.is_connected(),
+            block_number,
+        }
+    }
+
+    /// Configured name for the chain, else its well-known name, else "Unknown"
+    fn network_name(&self) -> &str {
+        self.network_names
+            .get(&self.network_id)
+            .map(String::as_str)
+            .or_else(|| utils::known_network_name(self.network_id))
+            .unwrap_or("Unknown")
+    }
+
+    /// Resolve an ENS name to its address, common text records and the address's reverse record
+    pub async fn ens_profile(&self, name: &str) -> AppResult<EnsProfile> {
+        let name = name.trim().to_lowercase();
+        if let Some((resolved_at, profile)) = self.ens_cache.lock().unwrap().get(&name) {
+            if resolved_at.elapsed() < ENS_CACHE_TTL {
+                return Ok(profile.clone());
+            }
+        }
+
+        let ens = self.ens()?;
+        let resolver = self.timed("ens_resolver", ens.resolver(&name)).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to look up ENS resolver: {}", e)))?;
+        if resolver.is_zero() {
+            return Err(AppError::NotFound(format!("ENS name {}", name)));
+        }
+
+        // Records a resolver does not implement come back as errors; report them as unset
+        let text = |key: &'static str| async {
+            self.timed("ens_text", ens.text(&name, key.to_string())).await
+                .ok()?
+                .ok()
+                .filter(|value| !value.is_empty())
+        };
+        let (address, avatar, url, email) = tokio::join!(
+            self.timed("ens_addr", ens.eth_address(&name)),
+            text("avatar"),
+            text("url"),
+            text("email")
+        );
+        let address = address?.ok().filter(|address| !address.is_zero());
+
+        let reverse_name = match address {
+            Some(address) => self.timed("ens_name", ens.canonical_name(address)).await?
+                .ok()
+                .filter(|reverse| !reverse.is_empty()),
+            None => None,
+        };
+
+        let profile = EnsProfile {
+            name: name.clone(),
+            address: address.map(ChecksumAddress::from),
+            avatar,
+            url,
+            email,
+            reverse_name,
+        };
+        self.ens_cache.lock().unwrap().insert(name, (Instant::now(), profile.clone()));
+        Ok(profile)
+    }
+
+    /// Resolve an ENS name to the address it points at
+    pub async fn resolve_ens(&self, name: &str) -> AppResult<Address> {
+        let name = name.trim().to_lowercase();
+        if let Some((resolved_at, address)) = self.ens_addresses.lock().unwrap().get(&name) {
+            if resolved_at.elapsed() < ENS_CACHE_TTL {
+                return Ok(*address);
+            }
+        }
+
+        let ens = self.ens()?;
+        let resolver = self.timed("ens_resolver", ens.resolver(&name)).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to look up ENS resolver: {}", e)))?;
+        if resolver.is_zero() {
+            return Err(AppError::EnsResolutionFailed(format!("{} is not registered or has no resolver", name)));
+        }
+
+        let address = self.timed("ens_addr", ens.eth_address(&name)).await?
+            .map_err(|e| AppError::EnsResolutionFailed(format!("Resolver for {} did not return an address: {}", name, e)))?;
+        if address.is_zero() {
+            return Err(AppError::EnsResolutionFailed(format!("{} has no address record", name)));
+        }
+
+        self.ens_addresses.lock().unwrap().insert(name, (Instant::now(), address));
+        Ok(address)
+    }
+
+    /// Checksummed form of a hex address (checking the casing of mixed-case input), or the address an ENS name resolves to
+    pub async fn resolve_address(&self, address: &str) -> AppResult<ChecksumAddress> {
+        if !utils::is_ens_name(address) {
+            return address.parse();
+        }
+
+        let resolved = ChecksumAddress::from(self.resolve_ens(address).await?);
+        info!("Resolved {} to {}", address, resolved);
+        Ok(resolved)
+    }
+
+    /// ENS client for the connected network, refused where the registry is not deployed
+    fn ens(&self) -> AppResult<Ens<RpcTransport>> {
+        if !ENS_NETWORKS.contains(&self.network_id) {
+            return Err(AppError::NotSupported(format!(
+                "ENS is not available on network {}",
+                self.network_id
+            )));
+        }
+        Ok(Ens::new(self.web3()?.transport().clone()))
+    }
+
+    /// Subscribe to new block headers; needs a WebSocket connection.
+    /// The stream keeps its own handle to the socket, so callers need not hold the service.
+    pub async fn subscribe_new_heads(&self) -> AppResult<SubscriptionStream<WebSocket, BlockHeader>> {
+        let Either::Left(socket) = self.web3()?.transport() else {
+            return Err(AppError::NotSupported(
+                "Block streaming needs a ws:// or wss:// ethereum.rpc_url".to_string(),
+            ));
+        };
+
+        self.timed("eth_subscribe", Web3::new(socket.clone()).eth_subscribe().subscribe_new_heads()).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to subscribe to new heads: {}", e)))
+    }
+
+    /// Number and transactions of the block with `hash`
+    pub async fn block_transactions(&self, hash: H256) -> AppResult<(Option<u64>, Vec<Transaction>)> {
+        let web3 = self.web3()?;
+
+        let block = self.timed("eth_getBlockByHash", web3.eth().block_with_txs(BlockId::Hash(hash))).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get block: {}", e)))?
+            .ok_or_else(|| AppError::NotFound(format!("Block {:?}", hash)))?;
+
+        Ok((block.number.map(|number| number.as_u64()), block.transactions))
+    }
+
+    /// Number of the latest block
+    pub async fn latest_block_number(&self) -> AppResult<u64> {
+        let web3 = self.web3()?;
+
+        let latest = self.timed("eth_blockNumber", web3.eth().block_number()).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get block number: {}", e)))?;
+        Ok(latest.as_u64())
+    }
+
+    /// Transactions sent from or to `address` in blocks `from_block..=to_block`, oldest first.
+    /// Every block in the range is fetched in full, so callers bound the range.
+    pub async fn get_history(&self, address: &str, from_block: u64, to_block: u64) -> AppResult<Vec<HistoryTransaction>> {
+        let web3 = self.web3()?;
+        let address = utils::validate_checksummed(address)?;
+
+        let blocks: Vec<AppResult<Vec<HistoryTransaction>>> = stream::iter(from_block..=to_block)
+            .map(|number| async move {
+                let block = self.timed("eth_getBlockByNumber", web3.eth().block_with_txs(BlockId::Number(number.into()))).await?
+                    .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get block {}: {}", number, e)))?
+                    .ok_or_else(|| AppError::NotFound(format!("Block {}", number)))?;
+                Ok(block
+                    .transactions
+                    .into_iter()
+                    .filter(|tx| tx.from == Some(address) || tx.to == Some(address))
+                    .map(|tx| HistoryTransaction {
+                        transaction_hash: format!("{:?}", tx.hash),
+                        block_number: tx.block_number.map(|n| n.as_u64()),
+                        from: tx.from.map(ChecksumAddress::from),
+                        to: tx.to.map(ChecksumAddress::from),
+                        value_wei: tx.value.to_string(),
+                        value_eth: utils::wei_to_eth(tx.value),
+                    })
+                    .collect())
+            })
+            .buffered(HISTORY_SCAN_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut history = Vec::new();
+        for block in blocks {
+            history.extend(block?);
+        }
+        Ok(history)
+    }
+
+    /// Whether the connection can carry subscriptions (WebSocket rather than HTTP)
+    pub fn supports_subscriptions(&self) -> bool {
+        matches!(self.connection.as_ref().map(Web3::transport), Some(Either::Left(_)))
+    }
+
+    /// Get the average interval between recent blocks, in seconds
+    pub async fn average_block_time(&self) -> AppResult<f64> {
+        if let Some((computed_at, secs)) = *self.block_time_cache.lock().unwrap() {
+            if computed_at.elapsed() < BLOCK_TIME_CACHE_TTL {
+                return Ok(secs);
+            }
+        }
+
+        let web3 = self.web3()?;
+
+        let latest = self.timed("eth_blockNumber", web3.eth().block_number()).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get block number: {}", e)))?
+            .as_u64();
+        let oldest = latest.saturating_sub(BLOCK_TIME_SAMPLE_SIZE);
+        if latest == oldest {
+            return Err(AppError::ValidationError("Not enough blocks to compute block time".to_string()));
+        }
+
+        // The mean of consecutive intervals telescopes to (newest - oldest) / count,
+        // so only the two boundary blocks need to be fetched.
+        let newest_timestamp = self.block_timestamp(latest).await?;
+        let oldest_timestamp = self.block_timestamp(oldest).await?;
+        let secs = newest_timestamp.saturating_sub(oldest_timestamp) as f64 / (latest - oldest) as f64;
+
+        *self.block_time_cache.lock().unwrap() = Some((Instant::now(), secs));
+        Ok(secs)
+    }
+
+    async fn block_timestamp(&self, number: u64) -> AppResult<u64> {
+        let web3 = self.web3()?;
+        let block = self.timed("eth_getBlockByNumber", web3.eth().block(BlockId::Number(BlockNumber::Number(number.into())))).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get block {}: {}", number, e)))?
+            .ok_or_else(|| AppError::NotFound(format!("Block {}", number)))?;
+
+        Ok(block.timestamp.as_u64())
+    }
+
     /// Get balance for an address
-    pub async fn get_balance(&self, address: &str) -> AppResult<BalanceInfo> {
-        let web3 = self.connection.as_ref()
-            .ok_or(AppError::Web3NotAvailable)?;
+    pub async fn get_balance(&self, address: ChecksumAddress) -> AppResult<BalanceInfo> {
+        self.get_balance_at(address, None).await
+    }
 
-        let addr = Address::from_str(address)
-            .map_err(|e| AppError::InvalidAddress(format!("{}: {}", address, e)))?;
+    /// Get the balance of `address` at `block`, or at the latest block when `None`
+    pub async fn get_balance_at(&self, address: ChecksumAddress, block: Option<BlockNumber>) -> AppResult<BalanceInfo> {
+        let web3 = self.web3()?;
 
-        let balance_wei = web3.eth().balance(addr, None).await
+        let balance_wei = self.timed("eth_getBalance", web3.eth().balance(address.address(), block)).await?
             .map_err(|e| AppError::BalanceQueryFailed(e.to_string()))?;
 
         let balance_eth = utils::wei_to_eth(balance_wei);
 
         Ok(BalanceInfo {
-            address: address.to_string(),
+            address,
             balance_wei: balance_wei.to_string(),
             balance_eth,
             network_id: self.network_id,
         })
     }
 
-    /// Create transaction parameters
-    pub fn create_transaction(&self, to: &str, amount_eth: f64, _gas_price: Option<u64>, _gas_limit: Option<u64>) -> AppResult<TransactionParameters> {
-        let to_address = Address::from_str(to)
-            .map_err(|e| AppError::InvalidAddress(format!("{}: {}", to, e)))?;
+    /// Balances of `addresses` in input order, fetched a few at a time.
+    /// An address that is invalid or fails to resolve gets an error entry instead of failing the batch.
+    pub async fn get_balances(&self, addresses: &[String]) -> AppResult<Vec<BalanceBatchEntry>> {
+        self.web3()?;
 
-        let tx = TransactionParameters {
-            to: Some(to_address),
-            value: utils::eth_to_wei(amount_eth),
+        let entries = stream::iter(addresses.iter().cloned())
+            .map(|address| self.balance_entry(address))
+            .buffered(BALANCE_BATCH_CONCURRENCY)
+            .collect()
+            .await;
+        Ok(entries)
+    }
+
+    /// Look up one batch entry, retrying failures on the node's side up to `balance_batch_retries` times
+    async fn balance_entry(&self, address: String) -> BalanceBatchEntry {
+        let mut retries = 0;
+        let balance = loop {
+            let balance = async {
+                let resolved = self.resolve_address(&address).await?;
+                self.get_balance(resolved).await
+            }
+            .await;
+            match balance {
+                Err(e @ (AppError::Web3Timeout(_) | AppError::Web3ConnectionFailed(_) | AppError::BalanceQueryFailed(_)))
+                    if retries < self.balance_batch_retries =>
+                {
+                    warn!("Balance of {} failed, retrying ({}/{}): {}", address, retries + 1, self.balance_batch_retries, e);
+                    tokio::time::sleep(BALANCE_RETRY_DELAY * 2u32.pow(retries)).await;
+                    retries += 1;
+                }
+                balance => break balance,
+            }
+        };
+        match balance {
+            Ok(balance) => BalanceBatchEntry {
+                address,
+                balance: Some(balance),
+                error: None,
+            },
+            Err(e) => BalanceBatchEntry {
+                address,
+                balance: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    /// Whether `address` has contract code deployed at the latest block
+    pub async fn is_contract(&self, address: &str) -> AppResult<bool> {
+        let web3 = self.web3()?;
+        let addr = utils::validate_checksummed(address)?;
+
+        let code = self.timed("eth_getCode", web3.eth().code(addr, None)).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get code: {}", e)))?;
+        Ok(!code.0.is_empty())
+    }
+
+    /// Get the ERC-20 balance of `holder` in `contract`, scaled by the token's decimals
+    pub async fn get_token_balance(&self, contract: &str, holder: &str) -> AppResult<TokenBalanceInfo> {
+        let contract_address = utils::validate_checksummed(contract)?;
+        let holder_address = utils::validate_checksummed(holder)?;
+
+        // balanceOf takes the holder left-padded to a 32-byte word
+        let mut balance_call = ERC20_BALANCE_OF_SELECTOR.to_vec();
+        balance_call.extend_from_slice(H256::from(holder_address).as_bytes());
+
+        let balance = self.call_uint(contract_address, balance_call, "balanceOf").await?;
+        let decimals = self.token_decimals(contract).await?;
+
+        Ok(TokenBalanceInfo {
+            contract: contract_address.into(),
+            holder: holder_address.into(),
+            balance_raw: balance.to_string(),
+            decimals,
+            balance: utils::format_units(balance, decimals),
+            network_id: self.network_id,
+        })
+    }
+
+    /// `eth_call` with caller-encoded calldata, returning the raw hex result.
+    /// Reverts become `ExecutionReverted` with the decoded reason when the node returns one.
+    pub async fn eth_call(&self, to: &str, data: &str, block: Option<BlockNumber>) -> AppResult<String> {
+        let to_address = utils::validate_checksummed(to)?;
+        let calldata = hex::decode(data.trim().trim_start_matches("0x"))
+            .map_err(|e| AppError::ValidationError(format!("Invalid calldata: {}", e)))?;
+
+        let output = self.call_raw(to_address, calldata, block).await?;
+        Ok(format!("0x{}", hex::encode(output)))
+    }
+
+    async fn call_raw(&self, to: Address, calldata: Vec<u8>, block: Option<BlockNumber>) -> AppResult<Vec<u8>> {
+        let web3 = self.web3()?;
+
+        let call = CallRequest {
+            to: Some(to),
+            data: Some(calldata.into()),
             ..Default::default()
         };
+        let output = self.timed("eth_call", web3.eth().call(call, block.map(BlockId::Number))).await?
+            .map_err(call_error)?;
+        Ok(output.0)
+    }
 
-        // TODO: Add gas customization
-        // if let Some(gas_price) = gas_price {
-        //     tx.gas_price = Some(U256::from(gas_price));
-        // }
-        // if let Some(gas_limit) = gas_limit {
-        //     tx.gas = Some(U256::from(gas_limit));
-        // }
+    /// Owners, threshold, nonce and EIP-712 domain separator of the Safe at `safe`
+    pub async fn safe_state(&self, safe: Address) -> AppResult<SafeState> {
+        let call = |selector: [u8; 4]| self.call_raw(safe, selector.to_vec(), None);
+        let (owners, threshold, nonce, domain_separator) = tokio::join!(
+            call(SAFE_GET_OWNERS_SELECTOR),
+            call(SAFE_GET_THRESHOLD_SELECTOR),
+            call(SAFE_NONCE_SELECTOR),
+            call(SAFE_DOMAIN_SEPARATOR_SELECTOR)
+        );
+        let (owners, threshold, nonce, domain_separator) = (owners?, threshold?, nonce?, domain_separator?);
 
-        Ok(tx)
+        // Calls to an address without code succeed with empty output
+        let not_safe = || AppError::NotSupported(format!("{} is not a Safe", utils::to_checksum(&safe)));
+        if [&threshold, &nonce, &domain_separator].iter().any(|output| output.len() < 32) {
+            return Err(not_safe());
+        }
+        let owners = safe::decode_addresses(&owners).ok_or_else(not_safe)?;
+        let threshold = U256::from_big_endian(&threshold[..32]);
+        // A set-up Safe has at least one owner and a threshold it can meet
+        if owners.is_empty() || threshold.is_zero() || threshold > U256::from(owners.len()) {
+            return Err(not_safe());
+        }
+
+        Ok(SafeState {
+            owners,
+            threshold: threshold.as_u64(),
+            nonce: U256::from_big_endian(&nonce[..32]),
+            domain_separator: H256::from_slice(&domain_separator[..32]),
+        })
     }
 
-    /// Sign and send transaction
-    pub async fn send_transaction(
+    /// Read an EIP-2612 token's `DOMAIN_SEPARATOR()` and the permit nonce of `owner`
+    pub async fn permit_domain(&self, contract: &str, owner: &str) -> AppResult<(H256, U256)> {
+        let contract_address = utils::validate_checksummed(contract)?;
+        let owner_address = utils::validate_checksummed(owner)?;
+
+        let mut nonces_call = EIP2612_NONCES_SELECTOR.to_vec();
+        nonces_call.extend_from_slice(H256::from(owner_address).as_bytes());
+
+        let (domain_separator, nonce) = tokio::join!(
+            self.call_uint(contract_address, EIP2612_DOMAIN_SEPARATOR_SELECTOR.to_vec(), "DOMAIN_SEPARATOR"),
+            self.call_uint(contract_address, nonces_call, "nonces")
+        );
+        let not_permit = |e: AppError| match e {
+            AppError::BalanceQueryFailed(msg) => {
+                AppError::NotSupported(format!("{} does not support EIP-2612 permits: {}", contract, msg))
+            }
+            e => e,
+        };
+
+        let mut separator = [0u8; 32];
+        domain_separator.map_err(not_permit)?.to_big_endian(&mut separator);
+        Ok((H256::from(separator), nonce.map_err(not_permit)?))
+    }
+
+    /// Read an ERC-20 token's `decimals()`
+    pub async fn token_decimals(&self, contract: &str) -> AppResult<u8> {
+        let contract_address = utils::validate_checksummed(contract)?;
+
+        let decimals = self.call_uint(contract_address, ERC20_DECIMALS_SELECTOR.to_vec(), "decimals").await?;
+        if decimals > U256::from(MAX_TOKEN_DECIMALS) {
+            return Err(AppError::BalanceQueryFailed(format!(
+                "{} reports an unsupported decimals value: {}",
+                contract, decimals
+            )));
+        }
+        Ok(decimals.as_u64() as u8)
+    }
+
+    /// Send `amount` (a decimal in token units, scaled by `decimals`) of an ERC-20 token to `to`
+    pub async fn send_token_transfer(
         &self,
-        request: &TransactionRequest,
-        secret_key: &SecretKey,
-        from_address: &str,
+        contract: &str,
+        to: &str,
+        amount: &str,
+        decimals: u8,
+        signer: &dyn Signer,
     ) -> AppResult<TransactionInfo> {
-        let web3 = self.connection.as_ref()
-            .ok_or(AppError::Web3NotAvailable)?;
-
-        let transaction = self.create_transaction(
-            &request.to,
-            request.amount_eth,
-            request.gas_price,
-            request.gas_limit,
-        )?;
+        let contract_address = utils::validate_checksummed(contract)?;
+        let to_address = utils::validate_checksummed(to)?;
+        let token_amount = utils::parse_units(amount, decimals).map_err(AppError::ValidationError)?;
 
-        let signed = web3
-            .accounts()
-            .sign_transaction(transaction.clone(), secret_key)
-            .await
-            .map_err(|e| AppError::TransactionFailed(format!("Failed to sign transaction: {}", e)))?;
+        let transaction_info = self
+            .send_contract_call(contract_address, erc20_transfer_data(to_address, token_amount), signer)
+            .await?;
+        info!("Token transfer of {} to {} sent via {:?}", amount, to, contract_address);
+        Ok(transaction_info)
+    }
 
-        let tx_hash = web3
-            .eth()
-            .send_raw_transaction(signed.raw_transaction)
-            .await
-            .map_err(|e| AppError::TransactionFailed(format!("Failed to send transaction: {}", e)))?;
+    /// Call `contract` with `data` in a zero-value transaction, with the gas limit from
+    /// `eth_estimateGas` and fees from the node
+    pub async fn send_contract_call(
+        &self,
+        contract: Address,
+        data: Vec<u8>,
+        signer: &dyn Signer,
+    ) -> AppResult<TransactionInfo> {
+        let call = CallRequest {
+            from: Some(signer.address()),
+            to: Some(contract),
+            data: Some(data.clone().into()),
+            ..Default::default()
+        };
+        let gas_limit = self.buffered_gas_limit(self.estimate_call_cached(call).await?);
 
-        info!("Transaction sent successfully: {:?}", tx_hash);
+        let mut transaction = TransactionParameters {
+            to: Some(contract),
+            value: U256::zero(),
+            gas: gas_limit,
+            data: data.into(),
+            ..Default::default()
+        };
+        let fees = self.resolve_fees(FeeOverrides::default()).await?;
+        self.ensure_within_gas_price_cap(&fees)?;
+        fees.apply(&mut transaction);
 
-        Ok(TransactionInfo {
-            transaction_hash: format!("{:?}", tx_hash),
-            from: from_address.to_string(),
-            to: request.to.clone(),
-            amount_eth: request.amount_eth,
-            gas_price: transaction.gas_price.map(|gp| gp.to_string()),
-            gas_limit: None, // TODO: Fix gas limit extraction
-            status: TransactionStatus::Pending,
-            timestamp: chrono::Utc::now(),
-        })
+        let tx_hash = self.sign_and_send(transaction.clone(), signer).await?;
+        Ok(self.sent_transaction_info(tx_hash, signer.address(), &transaction))
     }
 
-    /// Estimate gas for transaction
-    pub async fn estimate_gas(&self, to: &str, amount_eth: f64, from: &str) -> AppResult<u64> {
-        let web3 = self.connection.as_ref()
-            .ok_or(AppError::Web3NotAvailable)?;
+    /// `eth_call` a contract function that returns a single uint256
+    async fn call_uint(&self, contract: Address, data: Vec<u8>, function: &str) -> AppResult<U256> {
+        let web3 = self.web3()?;
 
-        let to_address = Address::from_str(to)
-            .map_err(|e| AppError::InvalidAddress(format!("{}: {}", to, e)))?;
-        
-        let from_address = Address::from_str(from)
-            .map_err(|e| AppError::InvalidAddress(format!("{}: {}", from, e)))?;
-
-        let tx = CallRequest {
-            from: Some(from_address),
-            to: Some(to_address),
-            value: Some(utils::eth_to_wei(amount_eth)),
+        let call = CallRequest {
+            to: Some(contract),
+            data: Some(data.into()),
             ..Default::default()
         };
+        let output = self.timed("eth_call", web3.eth().call(call, None)).await?
+            .map_err(|e| AppError::BalanceQueryFailed(format!("{}() call to {:?} failed: {}", function, contract, e)))?;
+
+        // Calls to an address without code succeed with empty output
+        if output.0.len() < 32 {
+            return Err(AppError::BalanceQueryFailed(format!(
+                "{:?} returned no value for {}(); is it an ERC-20 contract?",
+                contract, function
+            )));
+        }
+        Ok(U256::from_big_endian(&output.0[..32]))
+    }
 
-        let gas_estimate = web3.eth().estimate_gas(tx, None).await
-            .map_err(|e| AppError::TransactionFailed(format!("Gas estimation failed: {}", e)))?;
+    /// Get the node's mempool entries sent from an address, including transactions
+    /// submitted by other tools. Uses `txpool_content` (geth) and falls back to
+    /// `parity_pendingTransactions` (OpenEthereum/Nethermind).
+    pub async fn get_mempool_transactions(&self, address: &str) -> AppResult<MempoolInfo> {
+        let web3 = self.web3()?;
 
-        Ok(gas_estimate.as_u64())
+        let addr = utils::validate_checksummed(address)?;
+
+        match self.timed("txpool_content", web3.txpool().content()).await? {
+            Ok(content) => {
+                let mut transactions = Vec::new();
+                for (status, pool) in [("pending", &content.pending), ("queued", &content.queued)] {
+                    if let Some(entries) = pool.get(&addr) {
+                        transactions.extend(entries.values().map(|tx| mempool_transaction(tx, status)));
+                    }
+                }
+                return Ok(MempoolInfo {
+                    address: addr.into(),
+                    source: "txpool_content".to_string(),
+                    transactions,
+                });
+            }
+            Err(e) if is_method_not_found(&e) => {
+                info!("txpool_content not supported by node, trying parity_pendingTransactions");
+            }
+            Err(e) => {
+                return Err(AppError::Web3ConnectionFailed(format!("Failed to read txpool: {}", e)));
+            }
+        }
+
+        let filter = ParityPendingTransactionFilter::builder().from(addr).build();
+        match self.timed("parity_pendingTransactions", web3.parity().pending_transactions(None, Some(filter))).await? {
+            Ok(pending) => Ok(MempoolInfo {
+                address: addr.into(),
+                source: "parity_pendingTransactions".to_string(),
+                transactions: pending.iter().map(|tx| mempool_transaction(tx, "pending")).collect(),
+            }),
+            Err(e) if is_method_not_found(&e) => Err(AppError::NotSupported(
+                "Node exposes neither txpool_content nor parity_pendingTransactions".to_string(),
+            )),
+            Err(e) => Err(AppError::Web3ConnectionFailed(format!("Failed to read pending transactions: {}", e))),
+        }
+    }
+
+    /// Create transaction parameters.
+    /// Explicit gas settings are used as given; otherwise fees come from the node
+    /// (see `resolve_fees`) and the gas limit from `eth_estimateGas`.
+    pub async fn create_transaction(
+        &self,
+        from: &str,
+        request: &TransactionRequest,
+    ) -> AppResult<TransactionParameters> {
+        let fees = self.resolve_fees(FeeOverrides::from(request)).await?;
+        self.build_transaction(from, request, fees).await
     }
 
-    /// Get current gas price
-    pub async fn get_gas_price(&self) -> AppResult<u64> {
-        let web3 = self.connection.as_ref()
-            .ok_or(AppError::Web3NotAvailable)?;
+    /// Transaction parameters priced with already resolved fees
+    async fn build_transaction(
+        &self,
+        from: &str,
+        request: &TransactionRequest,
+        fees: FeeParams,
+    ) -> AppResult<TransactionParameters> {
+        self.ensure_within_gas_price_cap(&fees)?;
+        let to_address = utils::validate_checksummed(&request.to)?;
+
+        let value = request.value_wei()?;
+        let gas_limit = match request.gas_limit {
+            Some(gas_limit) => gas_limit,
+            None => self.buffered_gas_limit(self.estimate_call_cached(value_call(&request.to, value, from)?).await?).as_u64(),
+        };
 
-        let gas_price = web3.eth().gas_price().await
-            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get gas price: {}", e)))?;
+        let mut tx = TransactionParameters {
+            nonce: request.nonce.map(U256::from),
+            to: Some(to_address),
+            value,
+            gas: U256::from(gas_limit),
+            ..Default::default()
+        };
 
-        Ok(gas_price.as_u64())
+        fees.apply(&mut tx);
+        Ok(tx)
     }
-}
\ No newline at end of file
+
+    /// Decide between legacy and EIP-1559 pricing for a request.
+    /// Mixing `gas_price` with 1559 fields is rejected. Missing 1559 fields are
+    /// filled from the node (`max_fee_multiplier` scales the current base fee),
+    /// and a request with no fee fields at all is priced as `ethereum.default_transaction_type`
+    /// says: by default a base-fee-aware 1559 suggestion on London chains or the legacy gas
+    /// price elsewhere.
+    async fn resolve_fees(&self, overrides: FeeOverrides) -> AppResult<FeeParams> {
+        let FeeOverrides { gas_price, max_fee_per_gas, max_priority_fee_per_gas, max_fee_multiplier } = overrides;
+        let wants_1559 = max_fee_per_gas.is_some() || max_priority_fee_per_gas.is_some() || max_fee_multiplier.is_some();
+
+        if let Some(multiplier) = max_fee_multiplier {
+            if max_fee_per_gas.is_some() {
+                return Err(AppError::ValidationError(
+                    "Specify either max_fee_per_gas or max_fee_multiplier, not both".to_string(),
+                ));
+            }
+            if !multiplier.is_finite() || multiplier < 1.0 {
+                return Err(AppError::ValidationError("max_fee_multiplier must be at least 1".to_string()));
+            }
+        }
+
+        if let Some(gas_price) = gas_price {
+            if wants_1559 {
+                return Err(AppError::ValidationError(
+                    "Specify either gas_price or max_fee_per_gas/max_priority_fee_per_gas, not both".to_string(),
+                ));
+            }
+            return Ok(FeeParams::Legacy { gas_price: U256::from(gas_price) });
+        }
+
+        if !wants_1559 && self.default_transaction_type == DefaultTransactionType::Legacy {
+            return Ok(FeeParams::Legacy { gas_price: self.get_gas_price().await? });
+        }
+
+        let base_fee = match self.latest_base_fee().await? {
+            Some(base_fee) => base_fee,
+            None if wants_1559 => {
+                return Err(AppError::ValidationError(
+                    "Network does not support EIP-1559 fee fields".to_string(),
+                ));
+            }
+            None if self.default_transaction_type == DefaultTransactionType::Eip1559 => {
+                return Err(AppError::NotSupported(
+                    "ethereum.default_transaction_type is eip1559 but the network has no base fee; pass gas_price".to_string(),
+                ));
+            }
+            None => {
+                return Ok(FeeParams::Legacy { gas_price: self.get_gas_price().await? });
+            }
+        };
+
+        let max_priority_fee_per_gas = match max_priority_fee_per_gas {
+            Some(fee) => U256::from(fee),
+            None => self.suggest_priority_fee().await?.0,
+        };
+        let max_fee_per_gas = match (max_fee_per_gas, max_fee_multiplier) {
+            (Some(fee), _) => U256::from(fee),
+            (None, Some(multiplier)) => scale(base_fee, multiplier) + max_priority_fee_per_gas,
+            (None, None) => base_fee * BASE_FEE_HEADROOM + max_priority_fee_per_gas,
+        };
+
+        if max_fee_per_gas < max_priority_fee_per_gas {
+            return Err(AppError::ValidationError(
+                "max_fee_per_gas must be at least max_priority_fee_per_gas".to_string(),
+            ));
+        }
+
+        Ok(FeeParams::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas })
+    }
+
+    /// Reject fees above `ethereum.max_gas_price_wei`, whether requested or suggested by the node
+    fn ensure_within_gas_price_cap(&self, fees: &FeeParams) -> AppResult<()> {
+        let Some(cap) = self.max_gas_price else {
+            return Ok(());
+        };
+
+        let price = fees.max_price_per_gas();
+        if price > cap {
+            let field = match fees {
+                FeeParams::Legacy { .. } => "gas_price",
+                FeeParams::Eip1559 { .. } => "max_fee_per_gas",
+            };
+            return Err(AppError::ValidationError(format!(
+                "{} of {} wei exceeds ethereum.max_gas_price_wei ({} wei)",
+                field, price, cap
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get the base fee of the latest block, or `None` on pre-London chains
+    pub async fn latest_base_fee(&self) -> AppResult<Option<U256>> {
+        let web3 = self.web3()?;
+
+        let block = self.timed("eth_getBlockByNumber", web3.eth().block(BlockId::Number(BlockNumber::Latest))).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get latest block: {}", e)))?
+            .ok_or_else(|| AppError::NotFound("Latest block".to_string()))?;
+
+        Ok(block.base_fee_per_gas)
+    }
+
+    /// Sign and send transaction
+    pub async fn send_transaction(
+        &self,
+        request: &TransactionRequest,
+        signer: &dyn Signer,
+        from_address: &str,
+    ) -> AppResult<TransactionInfo> {
+        let transaction = self.create_transaction(from_address, request).await?;
+        if !request.skip_balance_check {
+            self.ensure_affordable(from_address, &transaction).await?;
+        }
+        if request.dry_run {
+            return self.simulate_transaction(transaction, signer).await;
+        }
+
+        let tx_hash = self.sign_and_send(transaction.clone(), signer).await?;
+        Ok(self.sent_transaction_info(tx_hash, signer.address(), &transaction))
+    }
+
+    /// Run a built transaction through `eth_call` and sign it without broadcasting.
+    /// The hash is the one the transaction would have if sent now; the nonce tracker is left untouched.
+    async fn simulate_transaction(
+        &self,
+        mut transaction: TransactionParameters,
+        signer: &dyn Signer,
+    ) -> AppResult<TransactionInfo> {
+        let web3 = self.web3()?;
+        let sender = signer.address();
+
+        let call = CallRequest {
+            from: Some(sender),
+            to: transaction.to,
+            gas: Some(transaction.gas),
+            gas_price: transaction.gas_price,
+            value: Some(transaction.value),
+            data: Some(transaction.data.clone()),
+            transaction_type: transaction.transaction_type,
+            access_list: transaction.access_list.clone(),
+            max_fee_per_gas: transaction.max_fee_per_gas,
+            max_priority_fee_per_gas: transaction.max_priority_fee_per_gas,
+        };
+        self.timed("eth_call", web3.eth().call(call, None)).await?
+            .map_err(call_error)?;
+
+        if transaction.nonce.is_none() {
+            let pending = self.pending_nonce(sender).await?;
+            transaction.nonce = Some(self.nonces.peek(sender, pending).into());
+        }
+        transaction.chain_id.get_or_insert(self.network_id);
+        self.fill_missing_fee(&mut transaction).await?;
+        let signed = signer.sign_transaction(&transaction).await?;
+
+        info!("Transaction simulated, not sent: {:?}", signed.transaction_hash);
+        let mut info = self.sent_transaction_info(signed.transaction_hash, sender, &transaction);
+        info.status = TransactionStatus::Simulated;
+        Ok(info)
+    }
+
+    /// Reject a transaction the sender cannot pay for before it takes a nonce:
+    /// the node requires `value + gas * max fee per gas` up front
+    async fn ensure_affordable(&self, from_address: &str, transaction: &TransactionParameters) -> AppResult<()> {
+        let web3 = self.web3()?;
+        let from = utils::validate_checksummed(from_address)?;
+
+        let fee_per_gas = transaction
+            .max_fee_per_gas
+            .or(transaction.gas_price)
+            .unwrap_or_default();
+        let needed = transaction
+            .value
+            .saturating_add(transaction.gas.saturating_mul(fee_per_gas));
+        let balance = self.timed("eth_getBalance", web3.eth().balance(from, None)).await?
+            .map_err(|e| AppError::BalanceQueryFailed(e.to_string()))?;
+
+        if balance < needed {
+            return Err(AppError::ValidationError(format!(
+                "insufficient funds: need {} {} have {} {}",
+                utils::format_units(needed, utils::NATIVE_DECIMALS),
+                self.native_symbol,
+                utils::format_units(balance, utils::NATIVE_DECIMALS),
+                self.native_symbol
+            )));
+        }
+        Ok(())
+    }
+
+    /// Send transactions one after another with sequential nonces, stopping at the first failure.
+    /// With `shared_fees` the fees are resolved once up front and used for the whole batch.
+    pub async fn send_batch(
+        &self,
+        requests: &[TransactionRequest],
+        shared_fees: bool,
+        signer: &dyn Signer,
+        from_address: &str,
+    ) -> AppResult<BatchTransactionResult> {
+        if requests.is_empty() || requests.len() > MAX_BATCH_SIZE {
+            return Err(AppError::ValidationError(format!(
+                "A batch must contain between 1 and {} transactions",
+                MAX_BATCH_SIZE
+            )));
+        }
+        if shared_fees && requests.iter().any(|request| !FeeOverrides::from(request).is_empty()) {
+            return Err(AppError::ValidationError(
+                "Per-transaction fee fields cannot be combined with shared_fees".to_string(),
+            ));
+        }
+
+        let fees = if shared_fees {
+            Some(self.resolve_fees(FeeOverrides::default()).await?)
+        } else {
+            None
+        };
+
+        let mut sent = Vec::with_capacity(requests.len());
+        for (index, request) in requests.iter().enumerate() {
+            let result = async {
+                let transaction = match fees {
+                    Some(fees) => self.build_transaction(from_address, request, fees).await?,
+                    None => self.create_transaction(from_address, request).await?,
+                };
+                let tx_hash = self.sign_and_send(transaction.clone(), signer).await?;
+                Ok::<_, AppError>(self.sent_transaction_info(tx_hash, signer.address(), &transaction))
+            }
+            .await;
+
+            match result {
+                Ok(info) => sent.push(info),
+                Err(e) => {
+                    warn!("Batch stopped at transaction {}: {}", index, e);
+                    return Ok(BatchTransactionResult {
+                        sent,
+                        failed: Some(BatchFailure {
+                            index,
+                            possibly_sent: matches!(e, AppError::BroadcastUncertain(_)),
+                            error: e.to_string(),
+                        }),
+                    });
+                }
+            }
+        }
+
+        Ok(BatchTransactionResult { sent, failed: None })
+    }
+
+    fn sent_transaction_info(
+        &self,
+        tx_hash: H256,
+        from_address: Address,
+        transaction: &TransactionParameters,
+    ) -> TransactionInfo {
+        let amount_eth = utils::wei_to_eth(transaction.value);
+        TransactionInfo {
+            transaction_hash: format!("{:?}", tx_hash),
+            from: from_address.into(),
+            to: transaction.to.map(|to| ChecksumAddress::from(to).to_string()).unwrap_or_default(),
+            amount_wei: transaction.value.to_string(),
+            amount_eth,
+            amount_native: NativeAmount {
+                value: amount_eth,
+                symbol: self.native_symbol.clone(),
+            },
+            transaction_type: transaction.transaction_type.map(|t| t.as_u64()).unwrap_or(0),
+            gas_price: transaction.gas_price.map(|gp| gp.to_string()),
+            max_fee_per_gas: transaction.max_fee_per_gas.map(|fee| fee.to_string()),
+            max_priority_fee_per_gas: transaction.max_priority_fee_per_gas.map(|fee| fee.to_string()),
+            gas_limit: Some(transaction.gas.as_u64()),
+            status: TransactionStatus::Pending,
+            block_number: None,
+            confirmations: None,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    /// Get a transaction receipt with its current confirmation count (0 while unmined)
+    pub async fn get_transaction_receipt(&self, hash: &str) -> AppResult<TransactionReceiptInfo> {
+        let web3 = self.web3()?;
+        let tx_hash = parse_transaction_hash(hash)?;
+
+        let (receipt, latest_block) = tokio::join!(
+            self.timed("eth_getTransactionReceipt", web3.eth().transaction_receipt(tx_hash)),
+            self.timed("eth_blockNumber", web3.eth().block_number())
+        );
+        let receipt = receipt?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get receipt: {}", e)))?;
+        let latest_block = latest_block?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get block number: {}", e)))?
+            .as_u64();
+
+        let Some(receipt) = receipt else {
+            return Ok(TransactionReceiptInfo {
+                transaction_hash: format!("{:?}", tx_hash),
+                confirmations: 0,
+                block_number: None,
+                status: None,
+                gas_used: None,
+                effective_gas_price: None,
+                contract_address: None,
+                logs: Vec::new(),
+            });
+        };
+
+        let block_number = receipt.block_number.map(|number| number.as_u64());
+        let confirmations = block_number
+            .map(|number| (latest_block + 1).saturating_sub(number))
+            .unwrap_or(0);
+
+        Ok(TransactionReceiptInfo {
+            transaction_hash: format!("{:?}", receipt.transaction_hash),
+            confirmations,
+            block_number,
+            status: receipt.status.map(|status| status.as_u64()),
+            gas_used: receipt.gas_used.map(|gas| gas.to_string()),
+            effective_gas_price: receipt.effective_gas_price.map(|price| price.to_string()),
+            contract_address: receipt.contract_address.map(ChecksumAddress::from),
+            logs: self.receipt_logs(&receipt.logs).await,
+        })
+    }
+
+    /// Raw receipt logs, with ERC-20 `Transfer`/`Approval` events decoded and scaled by the
+    /// emitting token's decimals (looked up once per token)
+    async fn receipt_logs(&self, logs: &[Log]) -> Vec<ReceiptLog> {
+        let mut decimals: HashMap<Address, Option<u8>> = HashMap::new();
+        let mut receipt_logs = Vec::with_capacity(logs.len());
+
+        for log in logs {
+            let decoded = match decode_token_event(log) {
+                Some((event, from, to, value)) => {
+                    let token_decimals = match decimals.get(&log.address) {
+                        Some(cached) => *cached,
+                        None => {
+                            let fetched = self.token_decimals(&utils::to_checksum(&log.address)).await.ok();
+                            decimals.insert(log.address, fetched);
+                            fetched
+                        }
+                    };
+                    Some(TokenEvent {
+                        event,
+                        from: from.into(),
+                        to: to.into(),
+                        value_raw: value.to_string(),
+                        value: token_decimals.map(|decimals| utils::format_units(value, decimals)),
+                        decimals: token_decimals,
+                    })
+                }
+                None => None,
+            };
+
+            receipt_logs.push(ReceiptLog {
+                address: log.address.into(),
+                topics: log.topics.iter().map(|topic| format!("{:?}", topic)).collect(),
+                data: format!("0x{}", hex::encode(&log.data.0)),
+                log_index: log.log_index.map(|index| index.as_u64()),
+                decoded,
+            });
+        }
+        receipt_logs
+    }
+
+    /// Look up a transaction by hash and report whether it is pending, confirmed or failed
+    pub async fn get_transaction_status(&self, hash: &str) -> AppResult<TransactionInfo> {
+        let web3 = self.web3()?;
+        let tx_hash = parse_transaction_hash(hash)?;
+
+        let transaction = self.timed("eth_getTransactionByHash", web3.eth().transaction(TransactionId::Hash(tx_hash))).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get transaction: {}", e)))?
+            .ok_or_else(|| AppError::NotFound(format!("Transaction {:?}", tx_hash)))?;
+        let receipt = self.get_transaction_receipt(hash).await?;
+
+        // Pre-Byzantium receipts carry no status field; a mined one counts as confirmed
+        let status = match (receipt.block_number, receipt.status) {
+            (None, _) => TransactionStatus::Pending,
+            (Some(_), Some(0)) => TransactionStatus::Failed,
+            (Some(_), _) => TransactionStatus::Confirmed,
+        };
+        let amount_eth = utils::wei_to_eth(transaction.value);
+
+        Ok(TransactionInfo {
+            transaction_hash: format!("{:?}", tx_hash),
+            from: transaction.from.unwrap_or_default().into(),
+            to: transaction.to.map(|to| ChecksumAddress::from(to).to_string()).unwrap_or_default(),
+            amount_wei: transaction.value.to_string(),
+            amount_eth,
+            amount_native: NativeAmount {
+                value: amount_eth,
+                symbol: self.native_symbol.clone(),
+            },
+            transaction_type: transaction.transaction_type.map(|t| t.as_u64()).unwrap_or(0),
+            gas_price: transaction.gas_price.map(|gp| gp.to_string()),
+            max_fee_per_gas: transaction.max_fee_per_gas.map(|fee| fee.to_string()),
+            max_priority_fee_per_gas: transaction.max_priority_fee_per_gas.map(|fee| fee.to_string()),
+            gas_limit: Some(transaction.gas.as_u64()),
+            status,
+            block_number: receipt.block_number,
+            confirmations: Some(receipt.confirmations),
+            timestamp: chrono::Utc::now(),
+        })
+    }
+
+    /// Get the confirmed (latest) and pending transaction counts for an address
+    pub async fn get_nonce_range(&self, address: &str) -> AppResult<(u64, u64)> {
+        let web3 = self.web3()?;
+
+        let addr = utils::validate_checksummed(address)?;
+
+        let confirmed = self.timed("eth_getTransactionCount", web3.eth().transaction_count(addr, Some(BlockNumber::Latest))).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get transaction count: {}", e)))?;
+        let pending = self.timed("eth_getTransactionCount", web3.eth().transaction_count(addr, Some(BlockNumber::Pending))).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get pending transaction count: {}", e)))?;
+
+        Ok((confirmed.as_u64(), pending.as_u64()))
+    }
+
+    /// Fill a nonce gap with a zero-value self-transfer at exactly `nonce`,
+    /// unsticking any later transactions queued behind it. The gap is usually at the pending count
+    /// itself: after a transaction is dropped, the node counts only those below it as pending.
+    /// Lower nonces still pending are replaced.
+    pub async fn fill_nonce_gap(
+        &self,
+        nonce: u64,
+        signer: &dyn Signer,
+        from_address: &str,
+    ) -> AppResult<TransactionInfo> {
+        let (confirmed, pending) = self.get_nonce_range(from_address).await?;
+        if nonce < confirmed || nonce > pending {
+            return Err(AppError::ValidationError(format!(
+                "Nonce {} is outside the range [{}, {}] from the confirmed to the pending count",
+                nonce, confirmed, pending
+            )));
+        }
+
+        let from = utils::validate_checksummed(from_address)?;
+        let transaction = TransactionParameters {
+            nonce: Some(nonce.into()),
+            to: Some(from),
+            gas: SIMPLE_TRANSFER_GAS.into(),
+            value: U256::zero(),
+            ..Default::default()
+        };
+
+        let tx_hash = self.sign_and_send(transaction.clone(), signer).await?;
+        info!("Filled nonce gap at {} with transaction {:?}", nonce, tx_hash);
+
+        Ok(TransactionInfo {
+            transaction_hash: format!("{:?}", tx_hash),
+            from: from.into(),
+            to: ChecksumAddress::from(from).to_string(),
+            amount_wei: "0".to_string(),
+            amount_eth: 0.0,
+            amount_native: NativeAmount {
+                value: 0.0,
+                symbol: self.native_symbol.clone(),
+            },
+            transaction_type: 0,
+            gas_price: transaction.gas_price.map(|gp| gp.to_string()),
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            gas_limit: Some(SIMPLE_TRANSFER_GAS),
+            status: TransactionStatus::Pending,
+            block_number: None,
+            confirmations: None,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+
+    /// Sign transaction parameters with the given signer and broadcast them.
+    /// Without an explicit nonce one is reserved for the sender. It is released again if the send fails
+    /// before the node has the transaction, or the node refuses it; after a broadcast that may have
+    /// reached the node (e.g. a timeout) it is kept, as reusing it could replace a live transaction,
+    /// and the send fails with `BroadcastUncertain`.
+    async fn sign_and_send(&self, mut transaction: TransactionParameters, signer: &dyn Signer) -> AppResult<H256> {
+        let web3 = self.web3()?;
+        let sender = signer.address();
+        let mut entry = AuditEntry::new("sign_transaction", &utils::to_checksum(&sender));
+        entry.target = transaction.to.map(|to| utils::to_checksum(&to));
+        entry.value_wei = Some(transaction.value.to_string());
+
+        let reserved_nonce = match transaction.nonce {
+            Some(_) => None,
+            None => {
+                let nonce = self.nonces.reserve(sender, self.pending_nonce(sender).await?);
+                transaction.nonce = Some(nonce.into());
+                Some(nonce)
+            }
+        };
+
+        // Sign for the chain checked on connect rather than asking the node again
+        transaction.chain_id.get_or_insert(self.network_id);
+        let signed = match self.sign_for_broadcast(transaction, signer, &mut entry).await {
+            Ok(signed) => signed,
+            Err(e) => {
+                if let Some(nonce) = reserved_nonce {
+                    self.nonces.release(sender, nonce);
+                }
+                return Err(e);
+            }
+        };
+
+        let transaction_hash = signed.transaction_hash;
+        let sent = self
+            .timed("eth_sendRawTransaction", web3.eth().send_raw_transaction(signed.raw_transaction))
+            .await;
+        if let Some(nonce) = reserved_nonce {
+            // An error response means the node refused the transaction; anything else may have reached it
+            match &sent {
+                Ok(Err(web3::Error::Rpc(_))) => self.nonces.release(sender, nonce),
+                _ => self.nonces.broadcast(sender),
+            }
+        }
+
+        let tx_hash = match sent {
+            Ok(Ok(tx_hash)) => tx_hash,
+            Ok(Err(e @ web3::Error::Rpc(_))) => {
+                return Err(AppError::TransactionFailed(format!("Failed to send transaction: {}", e)))
+            }
+            Ok(Err(e)) => {
+                return Err(AppError::BroadcastUncertain(format!("{:?} (send failed: {})", transaction_hash, e)))
+            }
+            Err(_) => {
+                return Err(AppError::BroadcastUncertain(format!(
+                    "{:?} (node did not answer eth_sendRawTransaction in time)",
+                    transaction_hash
+                )))
+            }
+        };
+        info!("Transaction sent successfully: {:?}", tx_hash);
+        Ok(tx_hash)
+    }
+
+    /// Price (which queries the node), sign and audit a transaction
+    async fn sign_for_broadcast(
+        &self,
+        mut transaction: TransactionParameters,
+        signer: &dyn Signer,
+        entry: &mut AuditEntry,
+    ) -> AppResult<SignedTransaction> {
+        self.fill_missing_fee(&mut transaction).await?;
+        let signed = signer.sign_transaction(&transaction).await?;
+
+        entry.transaction_hash = Some(format!("{:?}", signed.transaction_hash));
+        self.audit.record(entry)?;
+        Ok(signed)
+    }
+
+    /// Price a transaction built without fees (e.g. a nonce gap filler) at the node's gas price,
+    /// as signers need every field set
+    async fn fill_missing_fee(&self, transaction: &mut TransactionParameters) -> AppResult<()> {
+        let is_eip1559 = transaction.transaction_type == Some(EIP1559_TX_TYPE.into());
+        let fee = if is_eip1559 { &mut transaction.max_fee_per_gas } else { &mut transaction.gas_price };
+        if fee.is_none() {
+            *fee = Some(self.get_gas_price().await?);
+        }
+        Ok(())
+    }
+
+    /// The node's pending transaction count for `address`; lags behind sends it has not seen yet,
+    /// which the nonce manager accounts for
+    async fn pending_nonce(&self, address: Address) -> AppResult<u64> {
+        let web3 = self.web3()?;
+        let pending = self
+            .timed("eth_getTransactionCount", web3.eth().transaction_count(address, Some(BlockNumber::Pending)))
+            .await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get pending transaction count: {}", e)))?;
+        Ok(pending.as_u64())
+    }
+
+    /// Gas limit to send with when only an estimate is known: the estimate scaled by
+    /// `ethereum.gas_limit_multiplier` as headroom for execution cost that varies between blocks
+    fn buffered_gas_limit(&self, estimate: U256) -> U256 {
+        scale(estimate, self.gas_limit_multiplier)
+    }
+
+    /// Estimate gas for sending `value` to `to`, calling it with the hex calldata `data` when given
+    pub async fn estimate_gas(&self, to: &str, value: U256, data: Option<&str>, from: &str) -> AppResult<u64> {
+        let mut call = value_call(to, value, from)?;
+        if let Some(data) = data {
+            let calldata = hex::decode(data.trim().trim_start_matches("0x"))
+                .map_err(|e| AppError::ValidationError(format!("Invalid calldata: {}", e)))?;
+            call.data = Some(calldata.into());
+        }
+
+        let gas_estimate = self.estimate_call(call).await?;
+        Ok(gas_estimate.as_u64())
+    }
+
+    /// Gas, fees and total cost of sending `value` from `from` to `to` at current prices,
+    /// checked against `from`'s balance
+    pub async fn estimate_fee(&self, to: &str, value: U256, from: &str) -> AppResult<FeeEstimate> {
+        let web3 = self.web3()?;
+        let from_address = utils::validate_checksummed(from)?;
+
+        let (gas_limit, fees, balance) = tokio::try_join!(
+            async { Ok(self.buffered_gas_limit(self.estimate_gas(to, value, None, from).await?.into()).as_u64()) },
+            self.resolve_fees(FeeOverrides::default()),
+            async {
+                self.timed("eth_getBalance", web3.eth().balance(from_address, None)).await?
+                    .map_err(|e| AppError::BalanceQueryFailed(e.to_string()))
+            },
+        )?;
+        let gas = U256::from(gas_limit);
+
+        let mut estimate = FeeEstimate {
+            from: from_address.into(),
+            to: utils::validate_checksummed(to)?.into(),
+            gas_limit,
+            transaction_type: 0,
+            gas_price: None,
+            base_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            max_fee_per_gas: None,
+            estimated_fee_wei: String::new(),
+            estimated_fee_eth: 0.0,
+            max_fee_wei: String::new(),
+            amount_wei: value.to_string(),
+            total_cost_wei: String::new(),
+            balance_wei: balance.to_string(),
+            can_afford: false,
+        };
+
+        let (estimated_fee, max_fee) = match fees {
+            FeeParams::Legacy { gas_price } => {
+                estimate.gas_price = Some(gas_price.into());
+                (gas_price * gas, gas_price * gas)
+            }
+            FeeParams::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => {
+                let base_fee = self.latest_base_fee().await?.unwrap_or_default();
+                let effective_price = (base_fee + max_priority_fee_per_gas).min(max_fee_per_gas);
+
+                estimate.transaction_type = EIP1559_TX_TYPE;
+                estimate.base_fee_per_gas = Some(base_fee.into());
+                estimate.max_priority_fee_per_gas = Some(max_priority_fee_per_gas.into());
+                estimate.max_fee_per_gas = Some(max_fee_per_gas.into());
+                (effective_price * gas, max_fee_per_gas * gas)
+            }
+        };
+
+        let total_cost = value.saturating_add(max_fee);
+        estimate.estimated_fee_wei = estimated_fee.to_string();
+        estimate.estimated_fee_eth = utils::wei_to_eth(estimated_fee);
+        estimate.max_fee_wei = max_fee.to_string();
+        estimate.total_cost_wei = total_cost.to_string();
+        estimate.can_afford = balance >= total_cost;
+        Ok(estimate)
+    }
+
+    /// `eth_estimateGas`, coalesced: callers asking for an identical estimate while one is
+    /// in flight wait for and share its result instead of sending their own request
+    async fn estimate_call(&self, call: CallRequest) -> AppResult<U256> {
+        let web3 = self.web3()?;
+        let key = serde_json::to_string(&call)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize call: {}", e)))?;
+
+        let in_flight = self.estimates_in_flight.lock().unwrap().entry(key.clone()).or_default().clone();
+        let result = in_flight
+            .get_or_init(|| async {
+                self.timed("eth_estimateGas", web3.eth().estimate_gas(call, None)).await?
+                    .map_err(|e| AppError::TransactionFailed(format!("Gas estimation failed: {}", e)))
+            })
+            .await
+            .clone();
+
+        let mut estimates_in_flight = self.estimates_in_flight.lock().unwrap();
+        if estimates_in_flight.get(&key).is_some_and(|entry| Arc::ptr_eq(entry, &in_flight)) {
+            estimates_in_flight.remove(&key);
+        }
+        drop(estimates_in_flight);
+
+        result
+    }
+
+    /// Estimate for the send path, reusing a recent estimate for the same recipient and method
+    /// when `ethereum.gas_estimate_cache_secs` is set. A failed estimate drops the cached entry.
+    async fn estimate_call_cached(&self, call: CallRequest) -> AppResult<U256> {
+        if self.gas_estimate_ttl.is_zero() {
+            return self.estimate_call(call).await;
+        }
+
+        let selector = call
+            .data
+            .as_ref()
+            .and_then(|data| data.0.get(..4))
+            .map(|selector| [selector[0], selector[1], selector[2], selector[3]]);
+        let key = (call.to, selector);
+        if let Some((estimated_at, gas)) = self.gas_estimate_cache.lock().unwrap().get(&key) {
+            if estimated_at.elapsed() < self.gas_estimate_ttl {
+                return Ok(*gas);
+            }
+        }
+
+        let result = self.estimate_call(call).await;
+        let mut cache = self.gas_estimate_cache.lock().unwrap();
+        match &result {
+            Ok(gas) => {
+                cache.insert(key, (Instant::now(), *gas));
+            }
+            Err(_) => {
+                cache.remove(&key);
+            }
+        }
+        result
+    }
+
+    /// Get the node's suggested priority fee via `eth_maxPriorityFeePerGas`.
+    /// Returns `None` when the node doesn't implement the method.
+    pub async fn max_priority_fee(&self) -> AppResult<Option<U256>> {
+        let web3 = self.web3()?;
+
+        match self.timed("eth_maxPriorityFeePerGas", web3.transport().execute("eth_maxPriorityFeePerGas", vec![])).await? {
+            Ok(value) => serde_json::from_value(value)
+                .map(Some)
+                .map_err(|e| AppError::Web3ConnectionFailed(format!("Invalid max priority fee: {}", e))),
+            Err(e) if is_method_not_found(&e) => Ok(None),
+            Err(e) => Err(AppError::Web3ConnectionFailed(format!("Failed to get max priority fee: {}", e))),
+        }
+    }
+
+    /// Suggest a priority fee, preferring `eth_maxPriorityFeePerGas` and falling back
+    /// to recent block rewards from `eth_feeHistory` when the node doesn't support it
+    pub async fn suggest_priority_fee(&self) -> AppResult<(U256, &'static str)> {
+        if let Some(fee) = self.max_priority_fee().await? {
+            return Ok((fee, "eth_maxPriorityFeePerGas"));
+        }
+
+        info!("eth_maxPriorityFeePerGas not supported by node, using fee history");
+        let fee = self.priority_fee_from_history().await?;
+        Ok((fee, "eth_feeHistory"))
+    }
+
+    /// Average the median priority fee paid over the last few blocks
+    async fn priority_fee_from_history(&self) -> AppResult<U256> {
+        let web3 = self.web3()?;
+        let params = vec![
+            serde_json::json!(format!("{:#x}", FEE_HISTORY_BLOCKS)),
+            serde_json::json!("latest"),
+            serde_json::json!([FEE_HISTORY_PERCENTILE]),
+        ];
+        let history = self.timed("eth_feeHistory", web3.transport().execute("eth_feeHistory", params)).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get fee history: {}", e)))?;
+
+        let rewards: Vec<Vec<U256>> = serde_json::from_value(history["reward"].clone())
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Invalid fee history: {}", e)))?;
+        let samples: Vec<U256> = rewards.into_iter().filter_map(|block| block.into_iter().next()).collect();
+        if samples.is_empty() {
+            return Err(AppError::Web3ConnectionFailed("Fee history returned no rewards".to_string()));
+        }
+
+        let total = samples.iter().fold(U256::zero(), |acc, fee| acc + fee);
+        Ok(total / samples.len())
+    }
+
+    /// Get the current legacy gas price together with a priority fee suggestion
+    pub async fn get_fee_suggestion(&self) -> AppResult<FeeSuggestion> {
+        let gas_price = self.get_gas_price().await?;
+        let (priority_fee, source) = self.suggest_priority_fee().await?;
+
+        Ok(FeeSuggestion {
+            gas_price: gas_price.to_string(),
+            max_priority_fee_per_gas: priority_fee.to_string(),
+            priority_fee_source: source.to_string(),
+        })
+    }
+
+    /// Get the legacy gas price with EIP-1559 fee suggestions; chains without a base fee get only the legacy price
+    pub async fn get_gas_price_info(&self) -> AppResult<GasPriceInfo> {
+        let legacy_gas_price = self.get_gas_price().await?;
+        let Some(base_fee) = self.latest_base_fee().await? else {
+            return Ok(GasPriceInfo {
+                legacy_gas_price: legacy_gas_price.into(),
+                base_fee: None,
+                suggested_priority_fee: None,
+                suggested_max_fee: None,
+            });
+        };
+
+        let (priority_fee, _) = self.suggest_priority_fee().await?;
+        Ok(GasPriceInfo {
+            legacy_gas_price: legacy_gas_price.into(),
+            base_fee: Some(base_fee.into()),
+            suggested_priority_fee: Some(priority_fee.into()),
+            suggested_max_fee: Some((base_fee * BASE_FEE_HEADROOM + priority_fee).into()),
+        })
+    }
+
+    /// Get current gas price in wei
+    pub async fn get_gas_price(&self) -> AppResult<U256> {
+        let web3 = self.web3()?;
+
+        let gas_price = self.timed("eth_gasPrice", web3.eth().gas_price()).await?
+            .map_err(|e| AppError::Web3ConnectionFailed(format!("Failed to get gas price: {}", e)))?;
+
+        Ok(gas_price)
+    }
+
+    /// Forward a raw JSON-RPC call to the node
+    pub async fn raw_request(&self, request: &RpcRequest) -> AppResult<RpcResponse> {
+        let web3 = self.web3()?;
+
+        let (result, error) = match self.timed(&request.method, web3.transport().execute(&request.method, request.params.clone())).await? {
+            Ok(value) => (Some(value), None),
+            // Node-side errors are relayed verbatim so the caller sees the original code and message
+            Err(web3::Error::Rpc(rpc_error)) => (None, Some(serde_json::to_value(rpc_error)
+                .map_err(|e| AppError::InternalError(e.to_string()))?)),
+            Err(e) => return Err(AppError::Web3ConnectionFailed(e.to_string())),
+        };
+
+        Ok(RpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: request.id.clone(),
+            result,
+            error,
+        })
+    }
+}
+
+/// Open the transport matching the URL scheme. HTTP is connectionless, so the
+/// endpoint is probed once to fail at startup just like an unreachable WebSocket.
+async fn open_transport(rpc_url: &str) -> web3::Result<RpcTransport> {
+    if rpc_url.starts_with("ws://") || rpc_url.starts_with("wss://") {
+        return Ok(Either::Left(WebSocket::new(rpc_url).await?));
+    }
+    if rpc_url.starts_with("http://") || rpc_url.starts_with("https://") {
+        let transport = Http::new(rpc_url)?;
+        Web3::new(transport.clone()).net().version().await?;
+        return Ok(Either::Right(transport));
+    }
+    Err(web3::Error::Transport(web3::error::TransportError::Message(format!(
+        "Unsupported RPC URL scheme (expected http, https, ws or wss): {}",
+        rpc_url
+    ))))
+}
+
+/// `eth_estimateGas` request for a plain value transfer
+fn value_call(to: &str, value: U256, from: &str) -> AppResult<CallRequest> {
+    let to_address = utils::validate_checksummed(to)?;
+    let from_address = utils::validate_checksummed(from)?;
+
+    Ok(CallRequest {
+        from: Some(from_address),
+        to: Some(to_address),
+        value: Some(value),
+        ..Default::default()
+    })
+}
+
+/// `value * multiplier`, with the multiplier rounded to three decimals
+fn scale(value: U256, multiplier: f64) -> U256 {
+    value * U256::from((multiplier * 1000.0).round() as u64) / 1000
+}
+
+/// Event name, both addresses and value of an ERC-20 `Transfer` or `Approval` log.
+/// ERC-721 transfers share the `Transfer` topic but index the token id, so they have four topics and are skipped.
+fn decode_token_event(log: &Log) -> Option<(&'static str, Address, Address, U256)> {
+    if log.topics.len() != 3 || log.data.0.len() != 32 {
+        return None;
+    }
+
+    let event = match hex::encode(log.topics[0].as_bytes()).as_str() {
+        ERC20_TRANSFER_TOPIC => "Transfer",
+        ERC20_APPROVAL_TOPIC => "Approval",
+        _ => return None,
+    };
+    let from = Address::from_slice(&log.topics[1].as_bytes()[12..]);
+    let to = Address::from_slice(&log.topics[2].as_bytes()[12..]);
+    Some((event, from, to, U256::from_big_endian(&log.data.0)))
+}
+
+/// ABI-encode an ERC-20 `transfer(to, amount)` call
+fn erc20_transfer_data(to: Address, amount: U256) -> Vec<u8> {
+    let mut data = ERC20_TRANSFER_SELECTOR.to_vec();
+    data.extend_from_slice(H256::from(to).as_bytes());
+    let mut amount_word = [0u8; 32];
+    amount.to_big_endian(&mut amount_word);
+    data.extend_from_slice(&amount_word);
+    data
+}
+
+/// Parse a 32-byte transaction hash, with or without the `0x` prefix
+fn parse_transaction_hash(hash: &str) -> AppResult<H256> {
+    H256::from_str(hash.trim_start_matches("0x"))
+        .map_err(|e| AppError::ValidationError(format!("Invalid transaction hash {}: {}", hash, e)))
+}
+
+/// `ExecutionReverted` with the decoded reason for a reverted `eth_call`, `Web3ConnectionFailed` otherwise
+fn call_error(error: web3::Error) -> AppError {
+    match error {
+        web3::Error::Rpc(rpc_error)
+            if rpc_error.code.code() == EXECUTION_REVERTED_CODE || rpc_error.message.contains("revert") =>
+        {
+            let reason = rpc_error
+                .data
+                .as_ref()
+                .and_then(|data| data.as_str())
+                .and_then(|data| hex::decode(data.trim_start_matches("0x")).ok())
+                .and_then(|data| decode_revert_reason(&data))
+                .unwrap_or(rpc_error.message);
+            AppError::ExecutionReverted(reason)
+        }
+        e => AppError::Web3ConnectionFailed(format!("eth_call failed: {}", e)),
+    }
+}
+
+/// Human-readable reason from `Error(string)` or `Panic(uint256)` revert data
+fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    let (selector, payload) = (data.get(..4)?, data.get(4..)?);
+    if selector == REVERT_ERROR_SELECTOR {
+        // Offsets and lengths come from the node; anything past the payload is malformed
+        let word_at = |at: usize| {
+            let word = U256::from_big_endian(payload.get(at..at.checked_add(32)?)?);
+            (word <= U256::from(payload.len())).then(|| word.as_usize())
+        };
+        let offset = word_at(0)?;
+        let length = word_at(offset)?;
+        let text = payload.get(offset + 32..offset + 32 + length)?;
+        return Some(String::from_utf8_lossy(text).into_owned());
+    }
+    if selector == REVERT_PANIC_SELECTOR {
+        return Some(format!("panic code {:#x}", U256::from_big_endian(payload.get(..32)?)));
+    }
+    None
+}
+
+/// Whether an RPC error means the node doesn't implement the called method
+fn is_method_not_found(error: &web3::Error) -> bool {
+    match error {
+        web3::Error::Rpc(rpc_error) => {
+            let message = rpc_error.message.to_lowercase();
+            rpc_error.code.code() == METHOD_NOT_FOUND_CODE
+                || message.contains("method not found")
+                || message.contains("not supported")
+                || message.contains("does not exist")
+        }
+        _ => false,
+    }
+}
+
+fn mempool_transaction(tx: &Transaction, status: &str) -> MempoolTransaction {
+    MempoolTransaction {
+        hash: format!("{:?}", tx.hash),
+        nonce: tx.nonce.as_u64(),
+        to: tx.to.map(ChecksumAddress::from),
+        value_wei: tx.value.to_string(),
+        gas_price: tx.gas_price.map(|gp| gp.to_string()),
+        gas: tx.gas.to_string(),
+        status: status.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::SecretKeyGuard;
+    use crate::services::signer::LocalSigner;
+    use crate::test_support::{MockNode, Reply};
+    use serde_json::{json, Value};
+    use std::sync::atomic::AtomicUsize;
+
+    fn sender() -> Address {
+        Address::from_low_u64_be(0xa11ce)
+    }
+
+    fn test_signer() -> LocalSigner {
+        LocalSigner::new(SecretKeyGuard::new(secp256k1::SecretKey::from_slice(&[0x11; 32]).unwrap()))
+    }
+
+    fn transfer() -> TransactionParameters {
+        TransactionParameters {
+            to: Some(Address::from_low_u64_be(0xb0b)),
+            gas: SIMPLE_TRANSFER_GAS.into(),
+            gas_price: Some(1_000_000_000u64.into()),
+            value: 1u64.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn concurrent_reservations_get_distinct_nonces() {
+        let nonces = NonceManager::default();
+        let reserved: Vec<u64> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..16).map(|_| scope.spawn(|| nonces.reserve(sender(), 3))).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        let mut sorted = reserved.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (3..19).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn released_nonces_are_reused_before_new_ones() {
+        let nonces = NonceManager::default();
+        let first = nonces.reserve(sender(), 0);
+        let second = nonces.reserve(sender(), 0);
+        let third = nonces.reserve(sender(), 0);
+        nonces.broadcast(sender());
+        nonces.release(sender(), second);
+        nonces.broadcast(sender());
+
+        assert_eq!((first, second, third), (0, 1, 2));
+        assert_eq!(nonces.peek(sender(), 1), 1);
+        assert_eq!(nonces.reserve(sender(), 1), 1);
+        assert_eq!(nonces.reserve(sender(), 1), 3);
+    }
+
+    #[test]
+    fn released_nonce_at_the_top_is_handed_out_next() {
+        let nonces = NonceManager::default();
+        nonces.reserve(sender(), 0);
+        let failed = nonces.reserve(sender(), 0);
+        nonces.broadcast(sender());
+        nonces.release(sender(), failed);
+
+        assert_eq!(nonces.reserve(sender(), 0), 1);
+    }
+
+    #[test]
+    fn trusts_its_own_count_while_the_node_lags() {
+        let nonces = NonceManager::new(Duration::from_secs(60));
+        for _ in 0..3 {
+            nonces.reserve(sender(), 0);
+            nonces.broadcast(sender());
+        }
+
+        assert_eq!(nonces.reserve(sender(), 0), 3);
+    }
+
+    #[test]
+    fn resyncs_to_the_node_when_broadcasts_were_dropped() {
+        let nonces = NonceManager::new(Duration::ZERO);
+        for _ in 0..3 {
+            nonces.reserve(sender(), 0);
+            nonces.broadcast(sender());
+        }
+
+        // Nonce 1 never made it into a block or the mempool
+        assert_eq!(nonces.peek(sender(), 1), 1);
+        assert_eq!(nonces.reserve(sender(), 1), 1);
+        // Not while a send is in progress, as the node may not have seen it yet
+        assert_eq!(nonces.reserve(sender(), 1), 2);
+    }
+
+    /// Node at pending count 5 whose `eth_sendRawTransaction` answers are picked by `send`
+    async fn node_with_send(send: impl Fn(usize) -> Reply + Send + Sync + 'static) -> MockNode {
+        let sends = AtomicUsize::new(0);
+        MockNode::start(move |method, _| match method {
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x5"))),
+            "eth_sendRawTransaction" => Some(send(sends.fetch_add(1, Ordering::SeqCst))),
+            _ => None,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn sequential_sends_get_increasing_nonces() {
+        let node = node_with_send(|_| Reply::Value(json!(format!("{:?}", H256::repeat_byte(0xab))))).await;
+        let web3_service = node.connect().await;
+        let signer = test_signer();
+
+        web3_service.sign_and_send(transfer(), &signer).await.unwrap();
+        web3_service.sign_and_send(transfer(), &signer).await.unwrap();
+
+        let sent: Vec<u64> = node
+            .calls("eth_sendRawTransaction")
+            .iter()
+            .map(|params| raw_transaction_nonce(&params[0]))
+            .collect();
+        assert_eq!(sent, vec![5, 6]);
+    }
+
+    #[tokio::test]
+    async fn refused_send_releases_its_nonce() {
+        let node = node_with_send(|_| Reply::Error("insufficient funds for gas * price + value".to_string())).await;
+        let web3_service = node.connect().await;
+        let signer = test_signer();
+
+        let result = web3_service.sign_and_send(transfer(), &signer).await;
+        assert!(matches!(result, Err(AppError::TransactionFailed(_))));
+        assert_eq!(web3_service.nonces.peek(signer.address(), 5), 5);
+    }
+
+    #[tokio::test]
+    async fn timed_out_send_keeps_its_nonce() {
+        let node = node_with_send(|_| Reply::Stall).await;
+        let web3_service = connect_with_timeout(&node, 300).await;
+        let signer = test_signer();
+
+        let result = web3_service.sign_and_send(transfer(), &signer).await;
+        assert!(matches!(result, Err(AppError::BroadcastUncertain(_))));
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 1);
+        // The node may have the transaction at nonce 5, so the next send must not reuse it
+        assert_eq!(web3_service.nonces.peek(signer.address(), 5), 6);
+    }
+
+    #[tokio::test]
+    async fn fills_the_gap_at_the_pending_count() {
+        // Nonce 3 was dropped, so geth counts 3 both mined and pending while 4 waits in the queue
+        let node = MockNode::start(|method, _| match method {
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x3"))),
+            "eth_gasPrice" => Some(Reply::Value(json!("0x3b9aca00"))),
+            "eth_sendRawTransaction" => Some(Reply::Value(json!(format!("{:?}", H256::repeat_byte(0xcd))))),
+            _ => None,
+        })
+        .await;
+        let web3_service = node.connect().await;
+        let signer = test_signer();
+        let from = utils::to_checksum(&signer.address());
+
+        web3_service.fill_nonce_gap(3, &signer, &from).await.unwrap();
+        let sent = node.calls("eth_sendRawTransaction");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(raw_transaction_nonce(&sent[0][0]), 3);
+
+        for outside in [2, 4] {
+            let result = web3_service.fill_nonce_gap(outside, &signer, &from).await;
+            assert!(matches!(result, Err(AppError::ValidationError(_))), "nonce {}", outside);
+        }
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 1);
+    }
+
+    const HEALTHY: &str = "0x1111111111111111111111111111111111111111";
+    const FLAKY: &str = "0x2222222222222222222222222222222222222222";
+    const BROKEN: &str = "0x3333333333333333333333333333333333333333";
+
+    /// Answers `eth_getBalance` with 1 wei, except the first call for `FLAKY` and every call for `BROKEN`
+    async fn flaky_balance_node() -> MockNode {
+        let seen_flaky = std::sync::atomic::AtomicBool::new(false);
+        MockNode::start(move |method, params| match (method, params[0].as_str()) {
+            ("eth_getBalance", Some(BROKEN)) => Some(Reply::Error("upstream unavailable".to_string())),
+            ("eth_getBalance", Some(FLAKY)) if !seen_flaky.swap(true, Ordering::SeqCst) => {
+                Some(Reply::Error("upstream unavailable".to_string()))
+            }
+            ("eth_getBalance", _) => Some(Reply::Value(json!("0x1"))),
+            _ => None,
+        })
+        .await
+    }
+
+    fn balance_calls(node: &MockNode, address: &str) -> usize {
+        node.calls("eth_getBalance").iter().filter(|params| params[0] == address).count()
+    }
+
+    #[tokio::test]
+    async fn retries_balance_lookups_that_failed_on_the_node() {
+        let node = flaky_balance_node().await;
+        let web3_service = node.connect().await;
+        let addresses = [HEALTHY, FLAKY, BROKEN, "not-an-address"].map(String::from);
+
+        let entries = web3_service.get_balances(&addresses).await.unwrap();
+        assert_eq!(entries[0].balance.as_ref().unwrap().balance_wei, "1");
+        assert_eq!(entries[1].balance.as_ref().unwrap().balance_wei, "1");
+        assert!(entries[2].error.as_ref().unwrap().contains("upstream unavailable"));
+        assert!(entries[3].error.is_some());
+        assert_eq!(balance_calls(&node, HEALTHY), 1);
+        assert_eq!(balance_calls(&node, FLAKY), 2);
+        // The first attempt plus balance_batch_retries (2 by default)
+        assert_eq!(balance_calls(&node, BROKEN), 3);
+    }
+
+    #[tokio::test]
+    async fn reports_the_first_failure_without_retries() {
+        let node = flaky_balance_node().await;
+        let config = EthereumConfig {
+            balance_batch_retries: 0,
+            ..node.ethereum_config()
+        };
+        let web3_service = crate::test_support::connect(&config).await;
+
+        let entries = web3_service.get_balances(&[FLAKY.to_string()]).await.unwrap();
+        assert!(entries[0].error.is_some());
+        assert_eq!(balance_calls(&node, FLAKY), 1);
+    }
+
+    /// Answers `eth_getBalance` with the address's last byte in wei, after `delay`
+    async fn balance_node(delay: Duration) -> MockNode {
+        MockNode::start_with_delay(delay, |method, params| match method {
+            "eth_getBalance" => {
+                let address = Address::from_str(params[0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                Some(Reply::Value(json!(format!("{:#x}", address.0[19]))))
+            }
+            _ => None,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn balances_come_back_in_request_order() {
+        let node = balance_node(Duration::from_millis(10)).await;
+        let web3_service = node.connect().await;
+        let addresses: Vec<String> = (1..=30u64).map(|n| format!("{:?}", Address::from_low_u64_be(n))).collect();
+
+        let entries = web3_service.get_balances(&addresses).await.unwrap();
+        assert_eq!(entries.len(), addresses.len());
+        for (n, (entry, address)) in (1..).zip(entries.iter().zip(&addresses)) {
+            assert_eq!(&entry.address, address);
+            assert_eq!(entry.balance.as_ref().unwrap().balance_wei, n.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn a_bad_address_mid_batch_gets_an_error_entry() {
+        let node = balance_node(Duration::ZERO).await;
+        let web3_service = node.connect().await;
+        let addresses = [HEALTHY, "0x12345", FLAKY].map(String::from);
+
+        let entries = web3_service.get_balances(&addresses).await.unwrap();
+        assert_eq!(entries[0].balance.as_ref().unwrap().balance_wei, "17");
+        assert!(entries[1].balance.is_none());
+        assert!(entries[1].error.as_ref().unwrap().contains("0x12345"), "{:?}", entries[1].error);
+        assert_eq!(entries[2].balance.as_ref().unwrap().balance_wei, "34");
+        // The bad address never reaches the node
+        assert_eq!(node.calls("eth_getBalance").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn balance_lookups_are_bounded_in_flight() {
+        let node = balance_node(Duration::from_millis(50)).await;
+        let web3_service = node.connect().await;
+        let addresses: Vec<String> = (1..=40u64).map(|n| format!("{:?}", Address::from_low_u64_be(n))).collect();
+
+        web3_service.get_balances(&addresses).await.unwrap();
+        assert_eq!(node.calls("eth_getBalance").len(), 40);
+        assert_eq!(node.max_in_flight(), BALANCE_BATCH_CONCURRENCY);
+    }
+
+    const GAS_PRICE: u64 = 2_000_000_000;
+    const BASE_FEE: u64 = 1_000_000_000;
+    const PRIORITY_FEE: u64 = 100_000_000;
+    const BALANCE: u64 = 1_000_000_000_000_000;
+
+    /// Node on a London chain at pending count 0 where every account holds `BALANCE` and every send is accepted
+    async fn fee_node() -> MockNode {
+        MockNode::start(|method, _| match method {
+            "eth_gasPrice" => Some(Reply::Value(json!(format!("{:#x}", GAS_PRICE)))),
+            "eth_maxPriorityFeePerGas" => Some(Reply::Value(json!(format!("{:#x}", PRIORITY_FEE)))),
+            "eth_estimateGas" => Some(Reply::Value(json!("0x5208"))),
+            "eth_getBlockByNumber" => {
+                let block = web3::types::Block::<H256> { number: Some(100.into()), base_fee_per_gas: Some(BASE_FEE.into()), ..Default::default() };
+                Some(Reply::Value(json!(block)))
+            }
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x0"))),
+            "eth_getBalance" => Some(Reply::Value(json!(format!("{:#x}", BALANCE)))),
+            "eth_sendRawTransaction" => Some(Reply::Value(json!(format!("{:?}", H256::repeat_byte(0xab))))),
+            _ => None,
+        })
+        .await
+    }
+
+    /// A transfer of 1 wei with the given extra request fields
+    fn transaction_request(fields: Value) -> TransactionRequest {
+        let mut request = json!({ "to": utils::to_checksum(&Address::from_low_u64_be(0xb0b)), "amount_wei": "1", "skip_balance_check": true });
+        request.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(request).unwrap()
+    }
+
+    fn from_address() -> String {
+        utils::to_checksum(&test_signer().address())
+    }
+
+    #[tokio::test]
+    async fn explicit_gas_settings_are_used_as_given() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let request = transaction_request(json!({ "gas_price": 30_000_000_000u64, "gas_limit": 21_000 }));
+        let tx = web3_service.create_transaction(&from_address(), &request).await.unwrap();
+        assert_eq!(tx.gas_price, Some(30_000_000_000u64.into()));
+        assert_eq!(tx.gas, 21_000.into());
+        assert_eq!(tx.transaction_type, None);
+        assert!(node.calls("eth_gasPrice").is_empty());
+        assert!(node.calls("eth_estimateGas").is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_gas_settings_come_from_the_node() {
+        let node = fee_node().await;
+        let config = EthereumConfig { default_transaction_type: DefaultTransactionType::Legacy, ..node.ethereum_config() };
+        let web3_service = crate::test_support::connect(&config).await;
+
+        let request = transaction_request(json!({}));
+        let tx = web3_service.create_transaction(&from_address(), &request).await.unwrap();
+        assert_eq!(tx.gas_price, Some(GAS_PRICE.into()));
+        // The 21000 estimate with the default 1.2 headroom
+        assert_eq!(tx.gas, 25_200.into());
+
+        let sent = web3_service.send_transaction(&request, &test_signer(), &from_address()).await.unwrap();
+        assert_eq!(sent.gas_limit, Some(25_200));
+        assert_eq!(sent.gas_price, Some(GAS_PRICE.to_string()));
+    }
+
+    #[tokio::test]
+    async fn estimates_are_scaled_by_the_configured_multiplier() {
+        let node = fee_node().await;
+        let config = EthereumConfig {
+            default_transaction_type: DefaultTransactionType::Legacy,
+            gas_limit_multiplier: 1.5,
+            ..node.ethereum_config()
+        };
+        let web3_service = crate::test_support::connect(&config).await;
+
+        let estimated = web3_service.send_transaction(&transaction_request(json!({})), &test_signer(), &from_address()).await.unwrap();
+        assert_eq!(estimated.gas_limit, Some(31_500));
+
+        let request = transaction_request(json!({ "gas_limit": 21_000 }));
+        let tx = web3_service.create_transaction(&from_address(), &request).await.unwrap();
+        assert_eq!(tx.gas, 21_000.into());
+        assert_eq!(node.calls("eth_estimateGas").len(), 1);
+    }
+
+    /// The single raw transaction the node was sent
+    fn sent_raw_transaction(node: &MockNode) -> Vec<u8> {
+        let calls = node.calls("eth_sendRawTransaction");
+        assert_eq!(calls.len(), 1);
+        hex::decode(calls[0][0].as_str().unwrap().trim_start_matches("0x")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn eip1559_fields_send_a_type_2_transaction() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let request = transaction_request(json!({ "max_fee_per_gas": 40_000_000_000u64, "max_priority_fee_per_gas": 2_000_000_000u64 }));
+        let sent = web3_service.send_transaction(&request, &test_signer(), &from_address()).await.unwrap();
+        assert_eq!(sent.transaction_type, 2);
+
+        let raw = sent_raw_transaction(&node);
+        assert_eq!(raw[0], 0x02);
+        let decoded = crate::services::transaction_decoder::decode_raw_transaction(&hex::encode(&raw)).unwrap();
+        assert_eq!(decoded.transaction_type, 2);
+        assert_eq!(decoded.max_fee_per_gas.as_deref(), Some("40000000000"));
+        assert_eq!(decoded.max_priority_fee_per_gas.as_deref(), Some("2000000000"));
+        assert_eq!(decoded.gas_price, None);
+        assert_eq!(decoded.from.to_string(), from_address());
+    }
+
+    #[tokio::test]
+    async fn gas_price_sends_a_legacy_transaction() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let request = transaction_request(json!({ "gas_price": 30_000_000_000u64 }));
+        web3_service.send_transaction(&request, &test_signer(), &from_address()).await.unwrap();
+        // A legacy transaction is a bare RLP list
+        assert!(sent_raw_transaction(&node)[0] >= 0xc0);
+    }
+
+    #[tokio::test]
+    async fn mixing_gas_price_and_eip1559_fields_is_refused() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let request = transaction_request(json!({ "gas_price": 30_000_000_000u64, "max_priority_fee_per_gas": 2_000_000_000u64 }));
+        let result = web3_service.create_transaction(&from_address(), &request).await;
+        assert!(matches!(result, Err(AppError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn unpriced_transactions_follow_the_base_fee() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let tx = web3_service.create_transaction(&from_address(), &transaction_request(json!({}))).await.unwrap();
+        assert_eq!(tx.transaction_type, Some(EIP1559_TX_TYPE.into()));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(PRIORITY_FEE.into()));
+        assert_eq!(tx.max_fee_per_gas, Some((BASE_FEE * BASE_FEE_HEADROOM + PRIORITY_FEE).into()));
+        assert_eq!(tx.gas_price, None);
+    }
+
+    #[test]
+    fn encodes_erc20_transfer_calls() {
+        assert_eq!(ERC20_TRANSFER_SELECTOR, web3::signing::keccak256(b"transfer(address,uint256)")[..4]);
+
+        let to = Address::from_str("5B38Da6a701c568545dCfcB03FcB875f56beddC4").unwrap();
+        assert_eq!(
+            hex::encode(erc20_transfer_data(to, 1_000_000u64.into())),
+            "a9059cbb\
+             0000000000000000000000005b38da6a701c568545dcfcb03fcb875f56beddc4\
+             00000000000000000000000000000000000000000000000000000000000f4240"
+        );
+    }
+
+    #[tokio::test]
+    async fn token_transfers_call_the_contract_with_the_scaled_amount() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+        let contract = utils::to_checksum(&Address::from_low_u64_be(0x70c));
+        let to = Address::from_low_u64_be(0xb0b);
+
+        let sent = web3_service
+            .send_token_transfer(&contract, &utils::to_checksum(&to), "1.5", 6, &test_signer())
+            .await
+            .unwrap();
+        assert_eq!(sent.to, contract);
+        assert_eq!(sent.amount_wei, "0");
+
+        let raw = sent_raw_transaction(&node);
+        let decoded = crate::services::transaction_decoder::decode_raw_transaction(&hex::encode(&raw)).unwrap();
+        assert_eq!(decoded.to.unwrap().to_string(), contract);
+        assert_eq!(decoded.value, "0");
+        assert_eq!(decoded.data, format!("0x{}", hex::encode(erc20_transfer_data(to, 1_500_000u64.into()))));
+    }
+
+    /// Node at block 100 that knows transactions `0x0101…` (unmined), `0x0202…` (succeeded in block 90)
+    /// and `0x0303…` (reverted in block 90)
+    async fn receipt_node() -> MockNode {
+        MockNode::start(|method, params| {
+            let hash = H256::from_str(params[0].as_str().unwrap_or_default().trim_start_matches("0x")).ok();
+            match method {
+                "eth_blockNumber" => Some(Reply::Value(json!("0x64"))),
+                "eth_getTransactionByHash" => {
+                    let transaction = Transaction { hash: hash?, from: Some(sender()), value: 5u64.into(), ..Default::default() };
+                    Some(Reply::Value(json!(transaction)))
+                }
+                "eth_getTransactionReceipt" => {
+                    let status = match hash?.0[0] {
+                        0x02 => 1u64,
+                        0x03 => 0,
+                        _ => return Some(Reply::Value(Value::Null)),
+                    };
+                    let receipt = web3::types::TransactionReceipt {
+                        transaction_hash: hash?,
+                        block_number: Some(90u64.into()),
+                        status: Some(status.into()),
+                        ..Default::default()
+                    };
+                    Some(Reply::Value(json!(receipt)))
+                }
+                _ => None,
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn transaction_status_follows_the_receipt() {
+        let node = receipt_node().await;
+        let web3_service = node.connect().await;
+
+        let pending = web3_service.get_transaction_status(&format!("{:?}", H256::repeat_byte(0x01))).await.unwrap();
+        assert!(matches!(pending.status, TransactionStatus::Pending));
+        assert_eq!(pending.block_number, None);
+        assert_eq!(pending.confirmations, Some(0));
+
+        let confirmed = web3_service.get_transaction_status(&format!("{:?}", H256::repeat_byte(0x02))).await.unwrap();
+        assert!(matches!(confirmed.status, TransactionStatus::Confirmed));
+        assert_eq!(confirmed.block_number, Some(90));
+        assert_eq!(confirmed.confirmations, Some(11));
+        assert_eq!(confirmed.amount_wei, "5");
+
+        let failed = web3_service.get_transaction_status(&format!("{:?}", H256::repeat_byte(0x03))).await.unwrap();
+        assert!(matches!(failed.status, TransactionStatus::Failed));
+        assert_eq!(failed.block_number, Some(90));
+    }
+
+    #[tokio::test]
+    async fn malformed_transaction_hashes_are_refused() {
+        let node = receipt_node().await;
+        let web3_service = node.connect().await;
+
+        for hash in ["0x1234", "not a hash"] {
+            let result = web3_service.get_transaction_status(hash).await;
+            assert!(matches!(result, Err(AppError::ValidationError(_))), "{}", hash);
+        }
+        assert!(node.calls("eth_getTransactionByHash").is_empty());
+    }
+
+    #[tokio::test]
+    async fn picks_the_transport_from_the_url_scheme() {
+        let node = MockNode::start(|method, _| (method == "eth_blockNumber").then(|| Reply::Value(json!("0x2a")))).await;
+
+        let http = node.connect().await;
+        assert!(!http.supports_subscriptions());
+        assert!(matches!(http.web3().unwrap().transport(), Either::Right(_)));
+
+        let ws = crate::test_support::connect(&EthereumConfig { rpc_url: node.ws_url.clone(), ..node.ethereum_config() }).await;
+        assert!(ws.supports_subscriptions());
+        assert!(matches!(ws.web3().unwrap().transport(), Either::Left(_)));
+        assert_eq!(ws.latest_block_number().await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn new_heads_need_a_websocket_node() {
+        let node = MockNode::start(|_, _| None).await;
+
+        let Err(error) = node.connect().await.subscribe_new_heads().await else {
+            panic!("subscribed over HTTP");
+        };
+        assert!(matches!(error, AppError::NotSupported(_)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn configured_network_names_win_over_the_built_in_list() {
+        let network_names = vec![
+            crate::config::NetworkName { network_id: 8453, name: "Base (staging)".to_string() },
+            crate::config::NetworkName { network_id: 424242, name: "Devnet".to_string() },
+        ];
+        let config = |network_id| EthereumConfig { network_id, network_names: network_names.clone(), ..crate::config::AppConfig::default().ethereum };
+
+        for (network_id, name) in [(8453, "Base (staging)"), (424242, "Devnet"), (42161, "Arbitrum One"), (999_999, "Unknown")] {
+            let status = Web3Service::new(&config(network_id)).get_network_status().await;
+            assert_eq!(status.network_name, name, "network {}", network_id);
+        }
+    }
+
+    /// Node on chain `chain_id` that takes explicitly priced transfers
+    async fn chain_node(chain_id: u64) -> MockNode {
+        MockNode::start(move |method, _| match method {
+            "eth_chainId" => Some(Reply::Value(json!(format!("{:#x}", chain_id)))),
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x0"))),
+            "eth_sendRawTransaction" => Some(Reply::Value(json!(format!("{:?}", H256::repeat_byte(0xab))))),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Chain ID the node was sent a signed transfer for
+    async fn signed_chain_id(node: &MockNode, web3_service: &Web3Service) -> Option<u64> {
+        let request = transaction_request(json!({ "gas_price": GAS_PRICE, "gas_limit": 21_000 }));
+        web3_service.send_transaction(&request, &test_signer(), &from_address()).await.unwrap();
+        let raw = sent_raw_transaction(node);
+        crate::services::transaction_decoder::decode_raw_transaction(&hex::encode(raw)).unwrap().chain_id
+    }
+
+    #[tokio::test]
+    async fn a_matching_chain_id_keeps_the_configuration() {
+        let node = chain_node(1).await;
+        let web3_service = node.connect().await;
+
+        assert_eq!(web3_service.network_id, 1);
+        assert_eq!(web3_service.native_symbol, "ETH");
+        assert_eq!(signed_chain_id(&node, &web3_service).await, Some(1));
+        // Signing reuses the chain ID read on connect
+        assert_eq!(node.calls("eth_chainId").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_mismatched_chain_id_is_adopted_by_default() {
+        let node = chain_node(137).await;
+        let web3_service = node.connect().await;
+
+        assert_eq!(web3_service.network_id, 137);
+        assert_eq!(web3_service.native_symbol, "MATIC");
+        assert_eq!(signed_chain_id(&node, &web3_service).await, Some(137));
+        assert_eq!(node.calls("eth_chainId").len(), 1);
+
+        let config = EthereumConfig { native_symbol: Some("POL".to_string()), ..node.ethereum_config() };
+        let web3_service = crate::test_support::connect(&config).await;
+        assert_eq!(web3_service.network_id, 137);
+        assert_eq!(web3_service.native_symbol, "POL");
+    }
+
+    #[tokio::test]
+    async fn a_mismatched_chain_id_fails_under_strict_chain_id() {
+        let node = chain_node(137).await;
+        let mut web3_service = Web3Service::new(&EthereumConfig { strict_chain_id: true, ..node.ethereum_config() });
+
+        let result = web3_service.connect().await;
+        assert!(matches!(result, Err(AppError::ConfigurationError(_))));
+        assert!(!web3_service.is_connected());
+        assert_eq!(web3_service.network_id, 1);
+
+        let node = chain_node(1).await;
+        let strict = crate::test_support::connect(&EthereumConfig { strict_chain_id: true, ..node.ethereum_config() }).await;
+        assert!(strict.is_connected());
+    }
+
+    #[tokio::test]
+    async fn unreachable_nodes_fail_to_connect_on_either_scheme() {
+        let closed_port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        for rpc_url in [format!("http://{}", closed_port), format!("ws://{}", closed_port), "ftp://localhost".to_string()] {
+            let config = EthereumConfig { rpc_url: rpc_url.clone(), ..crate::config::AppConfig::default().ethereum };
+            let mut web3_service = Web3Service::new(&config);
+            let result = web3_service.connect().await;
+            assert!(matches!(result, Err(AppError::Web3ConnectionFailed(_))), "{}", rpc_url);
+            assert!(!web3_service.is_connected());
+        }
+    }
+
+    #[tokio::test]
+    async fn liveness_probe_follows_the_node() {
+        let up = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let node_up = up.clone();
+        let node = MockNode::start(move |method, _| match method {
+            "eth_blockNumber" if node_up.load(Ordering::SeqCst) => Some(Reply::Value(json!("0x1"))),
+            "eth_blockNumber" => Some(Reply::Stall),
+            _ => None,
+        })
+        .await;
+
+        assert!(!Web3Service::new(&node.ethereum_config()).is_live().await);
+        let web3_service = connect_with_timeout(&node, 200).await;
+        assert!(web3_service.is_live().await);
+        up.store(false, Ordering::SeqCst);
+        assert!(!web3_service.is_live().await);
+    }
+
+    #[tokio::test]
+    async fn sends_to_a_mistyped_checksum_are_refused() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let mut request = transaction_request(json!({ "gas_price": 30_000_000_000u64, "gas_limit": 21_000 }));
+        request.to = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD".to_string();
+        let result = web3_service.create_transaction(&from_address(), &request).await;
+        assert!(matches!(result, Err(AppError::InvalidAddress(_))));
+    }
+
+    #[tokio::test]
+    async fn balance_lookups_forward_the_requested_block() {
+        let node = balance_node(Duration::ZERO).await;
+        let web3_service = node.connect().await;
+        let address = ChecksumAddress::from(Address::from_low_u64_be(0x2a));
+
+        let balance = web3_service.get_balance_at(address, Some(BlockNumber::Number(18_000_000u64.into()))).await.unwrap();
+        assert_eq!(balance.balance_wei, "42");
+        web3_service.get_balance_at(address, Some(BlockNumber::Earliest)).await.unwrap();
+        web3_service.get_balance(address).await.unwrap();
+
+        let blocks: Vec<Value> = node.calls("eth_getBalance").iter().map(|params| params[1].clone()).collect();
+        assert_eq!(blocks, [json!("0x112a880"), json!("earliest"), json!("latest")]);
+    }
+
+    const ENS_REGISTRY: &str = "0x00000000000c2e074ec69a0dfb2997ba6c7d2e1e";
+    const ENS_RESOLVER: u64 = 0x4e5;
+
+    /// EIP-137 namehash
+    fn namehash(name: &str) -> [u8; 32] {
+        name.rsplit('.').fold([0u8; 32], |node, label| {
+            web3::signing::keccak256(&[node, web3::signing::keccak256(label.as_bytes())].concat())
+        })
+    }
+
+    /// Node with an ENS registry where `wallet.eth` points at `0xb0b`, `empty.eth` has a resolver but
+    /// no address record and every other name is unregistered
+    async fn ens_node(chain_id: u64) -> MockNode {
+        MockNode::start(move |method, params| match method {
+            "eth_chainId" => Some(Reply::Value(json!(format!("{:#x}", chain_id)))),
+            "eth_call" => {
+                let to = params[0]["to"].as_str().unwrap().to_lowercase();
+                let data = hex::decode(params[0]["data"].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                let (selector, argument) = data.split_at(4);
+                let registered = argument == namehash("wallet.eth") || argument == namehash("empty.eth");
+                let word = match hex::encode(selector).as_str() {
+                    // resolver(bytes32) on the registry
+                    "0178b8bf" if to == ENS_REGISTRY && registered => H256::from(Address::from_low_u64_be(ENS_RESOLVER)),
+                    "0178b8bf" => H256::zero(),
+                    // supportsInterface(bytes4) and addr(bytes32) on the resolver
+                    "01ffc9a7" => H256::from_low_u64_be(1),
+                    "3b3b57de" if argument == namehash("wallet.eth") => H256::from(Address::from_low_u64_be(0xb0b)),
+                    "3b3b57de" => H256::zero(),
+                    _ => return None,
+                };
+                Some(Reply::Value(json!(format!("{:?}", word))))
+            }
+            _ => None,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn resolves_ens_names_through_the_registry() {
+        let node = ens_node(1).await;
+        let web3_service = node.connect().await;
+
+        assert_eq!(web3_service.resolve_ens("Wallet.eth").await.unwrap(), Address::from_low_u64_be(0xb0b));
+        let calls = node.calls("eth_call").len();
+        // Served from the cache the second time
+        let resolved = web3_service.resolve_address("wallet.eth").await.unwrap();
+        assert_eq!(resolved, ChecksumAddress::from(Address::from_low_u64_be(0xb0b)));
+        assert_eq!(node.calls("eth_call").len(), calls);
+
+        // Hex addresses never reach the resolver
+        web3_service.resolve_address(&utils::to_checksum(&Address::from_low_u64_be(0xb0b))).await.unwrap();
+        assert_eq!(node.calls("eth_call").len(), calls);
+    }
+
+    #[tokio::test]
+    async fn unresolvable_ens_names_are_a_distinct_error() {
+        let node = ens_node(1).await;
+        let web3_service = node.connect().await;
+
+        for name in ["nobody.eth", "empty.eth"] {
+            let error = web3_service.resolve_ens(name).await.unwrap_err();
+            assert!(matches!(error, AppError::EnsResolutionFailed(_)), "{}: {}", name, error);
+            assert_eq!(axum::response::IntoResponse::into_response(error).status(), axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        }
+    }
+
+    #[tokio::test]
+    async fn ens_is_refused_where_the_registry_is_not_deployed() {
+        let node = ens_node(137).await;
+        let config = EthereumConfig { network_id: 137, ..node.ethereum_config() };
+        let web3_service = crate::test_support::connect(&config).await;
+
+        let result = web3_service.resolve_ens("wallet.eth").await;
+        assert!(matches!(result, Err(AppError::NotSupported(_))));
+        assert!(node.calls("eth_call").is_empty());
+    }
+
+    #[tokio::test]
+    async fn fee_estimates_price_the_gas_at_the_current_fees() {
+        let node = fee_node().await;
+        let to = utils::to_checksum(&Address::from_low_u64_be(0xb0b));
+
+        let eip1559 = node.connect().await.estimate_fee(&to, 5u64.into(), &from_address()).await.unwrap();
+        assert_eq!(eip1559.transaction_type, 2);
+        assert_eq!(eip1559.gas_limit, 25_200);
+        // 25200 gas at base fee plus priority fee, and at most at twice the base fee plus priority fee
+        assert_eq!(eip1559.estimated_fee_wei, (25_200 * (BASE_FEE + PRIORITY_FEE)).to_string());
+        assert_eq!(eip1559.max_fee_wei, (25_200 * (BASE_FEE * BASE_FEE_HEADROOM + PRIORITY_FEE)).to_string());
+        assert_eq!(eip1559.total_cost_wei, (25_200 * (BASE_FEE * BASE_FEE_HEADROOM + PRIORITY_FEE) + 5).to_string());
+        assert_eq!(eip1559.balance_wei, BALANCE.to_string());
+
+        let config = EthereumConfig { default_transaction_type: DefaultTransactionType::Legacy, ..node.ethereum_config() };
+        let legacy = crate::test_support::connect(&config).await.estimate_fee(&to, 5u64.into(), &from_address()).await.unwrap();
+        assert_eq!(legacy.transaction_type, 0);
+        assert_eq!(legacy.estimated_fee_wei, (25_200 * GAS_PRICE).to_string());
+        assert_eq!(legacy.max_fee_wei, legacy.estimated_fee_wei);
+    }
+
+    #[tokio::test]
+    async fn fee_estimates_flag_amounts_the_balance_cannot_cover() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+        let to = utils::to_checksum(&Address::from_low_u64_be(0xb0b));
+        let max_fee = 25_200 * (BASE_FEE * BASE_FEE_HEADROOM + PRIORITY_FEE);
+
+        let all_in = web3_service.estimate_fee(&to, (BALANCE - max_fee).into(), &from_address()).await.unwrap();
+        assert!(all_in.can_afford);
+        let one_wei_over = web3_service.estimate_fee(&to, (BALANCE - max_fee + 1).into(), &from_address()).await.unwrap();
+        assert!(!one_wei_over.can_afford);
+        assert_eq!(one_wei_over.total_cost_wei, (BALANCE + 1).to_string());
+    }
+
+    /// A balance-checked legacy send of `amount` wei at 1 gwei, costing 21000 gwei in fees
+    fn checked_send(amount: u64) -> TransactionRequest {
+        transaction_request(json!({
+            "amount_wei": amount.to_string(),
+            "gas_price": 1_000_000_000u64,
+            "gas_limit": 21_000,
+            "skip_balance_check": false,
+        }))
+    }
+
+    #[tokio::test]
+    async fn sends_the_balance_covers_go_out() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let request = checked_send(BALANCE - 21_000 * 1_000_000_000);
+        web3_service.send_transaction(&request, &test_signer(), &from_address()).await.unwrap();
+        assert_eq!(node.calls("eth_getBalance").len(), 1);
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sends_the_balance_cannot_cover_are_refused_before_taking_a_nonce() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let request = checked_send(BALANCE - 21_000 * 1_000_000_000 + 1);
+        let Err(error) = web3_service.send_transaction(&request, &test_signer(), &from_address()).await else {
+            panic!("an unaffordable send went out");
+        };
+        assert!(matches!(error, AppError::ValidationError(_)), "{}", error);
+        assert!(error.to_string().contains("insufficient funds: need 0.001000000000000001 ETH have 0.001 ETH"), "{}", error);
+        assert!(node.calls("eth_getTransactionCount").is_empty());
+        assert!(node.calls("eth_sendRawTransaction").is_empty());
+
+        // Unless the check is skipped
+        let request = TransactionRequest { skip_balance_check: true, ..request };
+        web3_service.send_transaction(&request, &test_signer(), &from_address()).await.unwrap();
+        assert_eq!(node.calls("eth_getBalance").len(), 1);
+        assert_eq!(node.calls("eth_sendRawTransaction").len(), 1);
+    }
+
+    /// ABI-encoded `Error(string)` revert data
+    fn error_revert(reason: &str) -> Vec<u8> {
+        let mut data = REVERT_ERROR_SELECTOR.to_vec();
+        data.extend_from_slice(H256::from_low_u64_be(32).as_bytes());
+        data.extend_from_slice(H256::from_low_u64_be(reason.len() as u64).as_bytes());
+        let mut text = reason.as_bytes().to_vec();
+        text.resize(reason.len().div_ceil(32) * 32, 0);
+        data.extend_from_slice(&text);
+        data
+    }
+
+    /// Node whose `eth_call` answers depend on the first calldata byte: 0x01 returns a word,
+    /// 0x02 reverts with a reason, 0x03 panics and 0x04 reverts without data
+    async fn contract_node() -> MockNode {
+        MockNode::start(|method, params| {
+            if method != "eth_call" {
+                return None;
+            }
+            let data = params[0]["data"].as_str().unwrap();
+            Some(match &data[2..4] {
+                "01" => Reply::Value(json!(format!("{:?}", H256::from_low_u64_be(0x2a)))),
+                "02" => Reply::Revert(error_revert("Ownable: caller is not the owner")),
+                "03" => Reply::Revert([REVERT_PANIC_SELECTOR.as_slice(), H256::from_low_u64_be(0x11).as_bytes()].concat()),
+                _ => Reply::Error("execution reverted".to_string()),
+            })
+        })
+        .await
+    }
+
+    fn contract() -> String {
+        utils::to_checksum(&Address::from_low_u64_be(0xc0de))
+    }
+
+    #[tokio::test]
+    async fn eth_call_returns_the_raw_result() {
+        let node = contract_node().await;
+        let web3_service = node.connect().await;
+
+        let result = web3_service.eth_call(&contract(), "0x01aabbcc", Some(BlockNumber::Number(7u64.into()))).await.unwrap();
+        assert_eq!(result, format!("{:?}", H256::from_low_u64_be(0x2a)));
+        let call = &node.calls("eth_call")[0];
+        assert_eq!(call[0]["to"], json!(contract().to_lowercase()));
+        assert_eq!(call[0]["data"], json!("0x01aabbcc"));
+        assert_eq!(call[1], json!("0x7"));
+    }
+
+    #[tokio::test]
+    async fn reverted_calls_carry_the_decoded_reason() {
+        let node = contract_node().await;
+        let web3_service = node.connect().await;
+
+        let cases = [
+            ("0x02", "Ownable: caller is not the owner"),
+            ("0x03", "panic code 0x11"),
+            ("0x04", "execution reverted"),
+        ];
+        for (data, reason) in cases {
+            match web3_service.eth_call(&contract(), data, None).await {
+                Err(AppError::ExecutionReverted(decoded)) => assert_eq!(decoded, reason),
+                other => panic!("{}: {:?}", data, other.err()),
+            }
+        }
+        let error = AppError::ExecutionReverted(String::new());
+        assert_eq!(axum::response::IntoResponse::into_response(error).status(), axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn malformed_calldata_is_refused() {
+        let node = contract_node().await;
+        let web3_service = node.connect().await;
+
+        let result = web3_service.eth_call(&contract(), "0xnothex", None).await;
+        assert!(matches!(result, Err(AppError::ValidationError(_))));
+        assert!(node.calls("eth_call").is_empty());
+    }
+
+    #[test]
+    fn revert_data_pointing_past_its_end_is_not_decoded() {
+        let mut data = error_revert("too short");
+        data.truncate(4 + 64 + 4);
+        assert_eq!(decode_revert_reason(&data), None);
+
+        let mut data = error_revert("x");
+        data[4 + 31] = 0xff;
+        assert_eq!(decode_revert_reason(&data), None);
+        assert_eq!(decode_revert_reason(&[0x08, 0xc3]), None);
+    }
+
+    const GAS_PRICE_CAP: u64 = 30_000_000_000;
+
+    async fn capped_service(node: &MockNode, cap: u64, default_transaction_type: DefaultTransactionType) -> Web3Service {
+        let config = EthereumConfig { max_gas_price_wei: Some(cap), default_transaction_type, ..node.ethereum_config() };
+        crate::test_support::connect(&config).await
+    }
+
+    #[tokio::test]
+    async fn fees_up_to_the_cap_are_accepted() {
+        let node = fee_node().await;
+        let web3_service = capped_service(&node, GAS_PRICE_CAP, DefaultTransactionType::Auto).await;
+
+        for fields in [json!({ "gas_price": GAS_PRICE_CAP }), json!({ "max_fee_per_gas": GAS_PRICE_CAP, "max_priority_fee_per_gas": 1 })] {
+            let request = transaction_request(fields.clone());
+            assert!(web3_service.create_transaction(&from_address(), &request).await.is_ok(), "{}", fields);
+        }
+    }
+
+    #[tokio::test]
+    async fn fees_one_wei_over_the_cap_are_refused() {
+        let node = fee_node().await;
+        let web3_service = capped_service(&node, GAS_PRICE_CAP, DefaultTransactionType::Auto).await;
+
+        for (fields, field) in [
+            (json!({ "gas_price": GAS_PRICE_CAP + 1 }), "gas_price"),
+            (json!({ "max_fee_per_gas": GAS_PRICE_CAP + 1, "max_priority_fee_per_gas": 1 }), "max_fee_per_gas"),
+        ] {
+            let request = transaction_request(fields);
+            match web3_service.send_transaction(&request, &test_signer(), &from_address()).await {
+                Err(AppError::ValidationError(message)) => assert!(message.starts_with(field), "{}", message),
+                other => panic!("{}: {:?}", field, other.err()),
+            }
+        }
+        assert!(node.calls("eth_sendRawTransaction").is_empty());
+    }
+
+    #[tokio::test]
+    async fn node_suggested_fees_are_capped_too() {
+        let node = fee_node().await;
+
+        let legacy = capped_service(&node, GAS_PRICE - 1, DefaultTransactionType::Legacy).await;
+        let result = legacy.create_transaction(&from_address(), &transaction_request(json!({}))).await;
+        assert!(matches!(result, Err(AppError::ValidationError(_))));
+
+        let eip1559 = capped_service(&node, BASE_FEE * BASE_FEE_HEADROOM + PRIORITY_FEE - 1, DefaultTransactionType::Auto).await;
+        let result = eip1559.create_transaction(&from_address(), &transaction_request(json!({}))).await;
+        assert!(matches!(result, Err(AppError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn slow_calls_time_out_as_web3_timeout() {
+        let config = EthereumConfig { rpc_timeout_ms: 100, ..crate::config::AppConfig::default().ethereum };
+        let web3_service = Web3Service::new(&config);
+
+        let started = Instant::now();
+        let result = web3_service.timed("eth_blockNumber", std::future::pending::<()>()).await;
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let Err(error) = result else { panic!("a pending call completed") };
+        assert!(matches!(&error, AppError::Web3Timeout(method) if method == "eth_blockNumber"), "{}", error);
+        assert_eq!(axum::response::IntoResponse::into_response(error).status(), axum::http::StatusCode::GATEWAY_TIMEOUT);
+
+        let answered = web3_service.timed("eth_blockNumber", async { 7 }).await;
+        assert_eq!(answered.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn a_stalled_node_fails_reads_with_web3_timeout() {
+        let node = MockNode::start(|method, _| (method == "eth_getBalance").then_some(Reply::Stall)).await;
+        let web3_service = connect_with_timeout(&node, 200).await;
+
+        let result = web3_service.get_balance(ChecksumAddress::from(Address::from_low_u64_be(0xb0b))).await;
+        assert!(matches!(result, Err(AppError::Web3Timeout(method)) if method == "eth_getBalance"));
+    }
+
+    /// Wei amount of an optional gas price, for comparisons
+    fn wei(price: &Option<crate::models::GasPrice>) -> Option<String> {
+        price.as_ref().map(|price| price.wei.clone())
+    }
+
+    #[tokio::test]
+    async fn gas_price_info_suggests_1559_fees_after_london() {
+        let node = fee_node().await;
+        let web3_service = node.connect().await;
+
+        let info = web3_service.get_gas_price_info().await.unwrap();
+        assert_eq!(info.legacy_gas_price.wei, GAS_PRICE.to_string());
+        assert_eq!(wei(&info.base_fee), Some(BASE_FEE.to_string()));
+        assert_eq!(wei(&info.suggested_priority_fee), Some(PRIORITY_FEE.to_string()));
+        // Twice the base fee plus the tip
+        assert_eq!(wei(&info.suggested_max_fee), Some((2 * BASE_FEE + PRIORITY_FEE).to_string()));
+    }
+
+    #[tokio::test]
+    async fn gas_price_info_falls_back_to_fee_history_for_the_tip() {
+        let node = MockNode::start(|method, _| match method {
+            "eth_gasPrice" => Some(Reply::Value(json!(format!("{:#x}", GAS_PRICE)))),
+            "eth_getBlockByNumber" => {
+                let block = web3::types::Block::<H256> { base_fee_per_gas: Some(BASE_FEE.into()), ..Default::default() };
+                Some(Reply::Value(json!(block)))
+            }
+            "eth_feeHistory" => Some(Reply::Value(json!({ "reward": [["0x64"], ["0xc8"], ["0x12c"]] }))),
+            _ => None,
+        })
+        .await;
+        let web3_service = node.connect().await;
+
+        let info = web3_service.get_gas_price_info().await.unwrap();
+        assert_eq!(wei(&info.suggested_priority_fee), Some("200".to_string()));
+        assert_eq!(wei(&info.suggested_max_fee), Some((2 * BASE_FEE + 200).to_string()));
+    }
+
+    #[tokio::test]
+    async fn gas_price_info_before_london_has_only_the_legacy_price() {
+        let node = MockNode::start(|method, _| match method {
+            "eth_gasPrice" => Some(Reply::Value(json!(format!("{:#x}", GAS_PRICE)))),
+            "eth_maxPriorityFeePerGas" => Some(Reply::Value(json!(format!("{:#x}", PRIORITY_FEE)))),
+            "eth_getBlockByNumber" => Some(Reply::Value(json!(web3::types::Block::<H256>::default()))),
+            _ => None,
+        })
+        .await;
+        let web3_service = node.connect().await;
+
+        let info = web3_service.get_gas_price_info().await.unwrap();
+        assert_eq!(info.legacy_gas_price.wei, GAS_PRICE.to_string());
+        assert!(info.base_fee.is_none());
+        assert!(info.suggested_priority_fee.is_none());
+        assert!(info.suggested_max_fee.is_none());
+        assert!(node.calls("eth_maxPriorityFeePerGas").is_empty());
+    }
+
+    #[tokio::test]
+    async fn gas_prices_above_u64_are_not_truncated() {
+        // 2^64 + 1 wei
+        let node = MockNode::start(|method, _| match method {
+            "eth_gasPrice" => Some(Reply::Value(json!("0x10000000000000001"))),
+            "eth_estimateGas" => Some(Reply::Value(json!("0x5208"))),
+            "eth_getBalance" => Some(Reply::Value(json!("0x0"))),
+            "eth_getBlockByNumber" => Some(Reply::Value(json!(web3::types::Block::<H256>::default()))),
+            _ => None,
+        })
+        .await;
+        let web3_service = node.connect().await;
+        let above_u64 = U256::from(u64::MAX) + 2;
+
+        let info = web3_service.get_gas_price_info().await.unwrap();
+        assert_eq!(info.legacy_gas_price.wei, above_u64.to_string());
+
+        // A chain without a base fee is priced with the legacy gas price
+        let to = utils::to_checksum(&Address::from_low_u64_be(0xb0b));
+        let estimate = web3_service.estimate_fee(&to, U256::zero(), &from_address()).await.unwrap();
+        assert_eq!(estimate.gas_price.unwrap().wei, above_u64.to_string());
+        assert_eq!(estimate.estimated_fee_wei, (above_u64 * U256::from(25_200)).to_string());
+        assert!(!estimate.can_afford);
+    }
+
+    async fn connect_with_timeout(node: &MockNode, rpc_timeout_ms: u64) -> Web3Service {
+        let config = EthereumConfig {
+            rpc_timeout_ms,
+            ..node.ethereum_config()
+        };
+        crate::test_support::connect(&config).await
+    }
+
+    /// Nonce of a signed legacy transaction, its first RLP field
+    fn raw_transaction_nonce(raw: &Value) -> u64 {
+        let bytes = hex::decode(raw.as_str().unwrap().trim_start_matches("0x")).unwrap();
+        rlp::Rlp::new(&bytes).val_at(0).unwrap()
+    }
+
+    /// Fee node whose `eth_call` succeeds, or reverts with `revert` when given
+    async fn simulation_node(revert: Option<Vec<u8>>) -> MockNode {
+        MockNode::start(move |method, _| match method {
+            "eth_call" => Some(match &revert {
+                Some(data) => Reply::Revert(data.clone()),
+                None => Reply::Value(json!("0x")),
+            }),
+            "eth_gasPrice" => Some(Reply::Value(json!(format!("{:#x}", GAS_PRICE)))),
+            "eth_estimateGas" => Some(Reply::Value(json!("0x5208"))),
+            "eth_getTransactionCount" => Some(Reply::Value(json!("0x0"))),
+            "eth_sendRawTransaction" => Some(Reply::Value(json!(format!("{:?}", H256::repeat_byte(0xab))))),
+            _ => None,
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn dry_runs_sign_without_broadcasting() {
+        let node = simulation_node(None).await;
+        let config = EthereumConfig { default_transaction_type: DefaultTransactionType::Legacy, ..node.ethereum_config() };
+        let web3_service = crate::test_support::connect(&config).await;
+
+        let simulated = web3_service
+            .send_transaction(&transaction_request(json!({ "dry_run": true })), &test_signer(), &from_address())
+            .await
+            .unwrap();
+        assert!(matches!(simulated.status, TransactionStatus::Simulated));
+        assert_eq!(simulated.gas_limit, Some(25_200));
+        assert_eq!(node.calls("eth_call").len(), 1);
+        assert!(node.calls("eth_sendRawTransaction").is_empty());
+
+        // The nonce is still free, so the real send is the transaction the dry run signed
+        web3_service.send_transaction(&transaction_request(json!({})), &test_signer(), &from_address()).await.unwrap();
+        let raw = sent_raw_transaction(&node);
+        assert_eq!(simulated.transaction_hash, format!("{:?}", H256::from(web3::signing::keccak256(&raw))));
+    }
+
+    #[tokio::test]
+    async fn a_dry_run_that_reverts_is_an_error() {
+        let node = simulation_node(Some(error_revert("transfers paused"))).await;
+        let web3_service = node.connect().await;
+
+        let request = transaction_request(json!({ "dry_run": true, "gas_price": GAS_PRICE, "gas_limit": 21_000 }));
+        let Err(error) = web3_service.send_transaction(&request, &test_signer(), &from_address()).await else {
+            panic!("reverting dry run succeeded");
+        };
+        assert!(matches!(&error, AppError::ExecutionReverted(reason) if reason == "transfers paused"), "{}", error);
+        assert!(node.calls("eth_sendRawTransaction").is_empty());
+    }
+}
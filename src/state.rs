@@ -1,13 +1,89 @@
+use crate::address::ChecksumAddress;
 use crate::config::AppConfig;
+use crate::middleware::rate_limit::RateLimiter;
+use crate::errors::{AppError, AppResult};
 use crate::models::Account;
+use crate::services::account_store::AccountStore;
+use crate::services::address_watcher::WatchList;
+use crate::services::hd_addresses::HdAddressCache;
+use crate::services::idempotency::IdempotencyCache;
+use crate::services::safe::SafeQueue;
+use crate::services::spend_limiter::SpendLimiter;
 use crate::services::{WalletService, Web3Service};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use zeroize::Zeroizing;
 
 #[derive(Clone)]
 pub struct AppState {
     pub wallet_service: Arc<WalletService>,
     pub web3_service: Arc<RwLock<Web3Service>>,
-    pub account: Arc<RwLock<Account>>,
+    /// Active signing account; `None` when started read-only with `wallet.optional`
+    pub account: Arc<RwLock<Option<Account>>>,
+    /// Additional accounts selectable with `from`
+    pub account_store: Arc<AccountStore>,
+    /// BIP-39 seed of the last mnemonic import, used to derive accounts by index; scrubbed when replaced
+    pub hd_seed: Arc<RwLock<Option<Zeroizing<[u8; 64]>>>>,
+    /// Addresses derived from `hd_seed`, preloaded at startup with `wallet.preload_accounts`
+    pub hd_addresses: Arc<HdAddressCache>,
     pub config: AppConfig,
-}
\ No newline at end of file
+    pub rate_limiter: Arc<RateLimiter>,
+    pub spend_limiter: Arc<SpendLimiter>,
+    /// Completed sends by `Idempotency-Key`
+    pub idempotency: Arc<IdempotencyCache>,
+    /// Addresses whose incoming transactions are reported to a webhook
+    pub watches: WatchList,
+    /// Proposed Safe transactions collecting owner confirmations
+    pub safe_queue: Arc<SafeQueue>,
+}
+
+impl AppState {
+    /// Copy of the active account, or `WalletUnavailable` on a read-only instance
+    pub async fn active_account(&self) -> AppResult<Account> {
+        self.account.read().await.clone().ok_or(AppError::WalletUnavailable)
+    }
+
+    /// Account selected by a request's `from` address, defaulting to the active account
+    pub async fn account_for(&self, from: Option<&str>) -> AppResult<Account> {
+        let Some(from) = from else {
+            return self.active_account().await;
+        };
+        let from = from.parse::<ChecksumAddress>()?.to_string();
+
+        if let Some(account) = self.account.read().await.as_ref() {
+            if account.public_address == from {
+                return Ok(account.clone());
+            }
+        }
+        self.account_store.get(&from)
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State for handler and middleware tests: no node connection, no active account, and wallet files
+    /// under a fresh temporary directory
+    pub fn for_tests(mut config: AppConfig) -> Self {
+        use std::time::Duration;
+
+        let directory = std::env::temp_dir().join(format!("wallet-test-{}", uuid::Uuid::from_bytes(rand::random())));
+        std::fs::create_dir_all(&directory).unwrap();
+        config.wallet.config_file = directory.join("account_config.json").to_string_lossy().into_owned();
+
+        let wallet_service = Arc::new(WalletService::new());
+        Self {
+            account_store: Arc::new(AccountStore::open(wallet_service.clone(), &config.wallet.config_file)),
+            wallet_service,
+            web3_service: Arc::new(RwLock::new(Web3Service::new(&config.ethereum))),
+            account: Arc::new(RwLock::new(None)),
+            hd_seed: Arc::new(RwLock::new(None)),
+            hd_addresses: Arc::default(),
+            rate_limiter: Arc::new(RateLimiter::new(config.server.rate_limit_per_minute, Vec::new())),
+            spend_limiter: Arc::new(SpendLimiter::new(None, None)),
+            idempotency: Arc::new(IdempotencyCache::new(Duration::from_secs(config.server.idempotency_ttl_secs))),
+            watches: WatchList::default(),
+            safe_queue: Arc::default(),
+            config,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Helpers shared by unit tests: a JSON-RPC node stub served over HTTP and WebSocket on a local port.
+
+use crate::config::{AppConfig, EthereumConfig};
+use crate::services::Web3Service;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::{routing::post, Json, Router};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How the stub answers one call
+pub enum Reply {
+    Value(Value),
+    /// A JSON-RPC error response with this message
+    Error(String),
+    /// An `execution reverted` error (code 3) carrying this revert data
+    Revert(Vec<u8>),
+    /// No answer until long after any test timeout
+    Stall,
+}
+
+type Handler = dyn Fn(&str, &Value) -> Option<Reply> + Send + Sync;
+
+struct NodeState {
+    handler: Box<Handler>,
+    delay: Duration,
+    calls: Mutex<Vec<(String, Value)>>,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+/// JSON-RPC node stub. Calls the handler answers with `None` fall back to chain 1 for `eth_chainId` and
+/// `net_version`, and to a "method not found" error for anything else.
+pub struct MockNode {
+    pub url: String,
+    /// The same node over WebSocket
+    pub ws_url: String,
+    state: Arc<NodeState>,
+}
+
+impl MockNode {
+    pub async fn start(handler: impl Fn(&str, &Value) -> Option<Reply> + Send + Sync + 'static) -> Self {
+        Self::start_with_delay(Duration::ZERO, handler).await
+    }
+
+    /// Like `start`, but every call takes `delay` to answer, so concurrent calls overlap
+    pub async fn start_with_delay(
+        delay: Duration,
+        handler: impl Fn(&str, &Value) -> Option<Reply> + Send + Sync + 'static,
+    ) -> Self {
+        let state = Arc::new(NodeState {
+            handler: Box::new(handler),
+            delay,
+            calls: Mutex::new(Vec::new()),
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+        });
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let node = state.clone();
+        let ws_node = state.clone();
+        let app = Router::new().route(
+            "/",
+            post(move |Json(body): Json<Value>| {
+                let node = node.clone();
+                async move {
+                    Json(match body {
+                        Value::Array(requests) => {
+                            let mut responses = Vec::new();
+                            for request in requests {
+                                responses.push(answer(&node, request).await);
+                            }
+                            Value::Array(responses)
+                        }
+                        request => answer(&node, request).await,
+                    })
+                }
+            })
+            .get(move |upgrade: WebSocketUpgrade| {
+                let node = ws_node.clone();
+                async move { upgrade.on_upgrade(move |socket| serve_socket(node, socket)) }
+            }),
+        );
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        Self { url, ws_url, state }
+    }
+
+    /// Ethereum settings pointing at this node
+    pub fn ethereum_config(&self) -> EthereumConfig {
+        EthereumConfig {
+            rpc_url: self.url.clone(),
+            rpc_timeout_ms: 2_000,
+            ..AppConfig::default().ethereum
+        }
+    }
+
+    /// A service connected to this node with the default settings
+    pub async fn connect(&self) -> Web3Service {
+        connect(&self.ethereum_config()).await
+    }
+
+    /// Most calls the node was answering at the same time
+    pub fn max_in_flight(&self) -> usize {
+        self.state.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Params of every call to `method` so far, in order
+    pub fn calls(&self, method: &str) -> Vec<Value> {
+        let calls = self.state.calls.lock().unwrap();
+        calls.iter().filter(|(name, _)| name == method).map(|(_, params)| params.clone()).collect()
+    }
+}
+
+pub async fn connect(config: &EthereumConfig) -> Web3Service {
+    let mut web3_service = Web3Service::new(config);
+    web3_service.connect().await.unwrap();
+    web3_service
+}
+
+/// Answer every text frame as one JSON-RPC request until the client hangs up
+async fn serve_socket(node: Arc<NodeState>, mut socket: WebSocket) {
+    while let Some(Ok(message)) = socket.recv().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let Ok(request) = serde_json::from_str(&text) else {
+            continue;
+        };
+        let response = answer(&node, request).await;
+        if socket.send(Message::Text(response.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn answer(node: &NodeState, request: Value) -> Value {
+    let method = request["method"].as_str().unwrap_or_default().to_string();
+    let params = request["params"].clone();
+    let id = request["id"].clone();
+    node.calls.lock().unwrap().push((method.clone(), params.clone()));
+
+    let in_flight = node.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+    node.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+    tokio::time::sleep(node.delay).await;
+    node.in_flight.fetch_sub(1, Ordering::SeqCst);
+    let reply = (node.handler)(&method, &params);
+
+    let reply = reply.unwrap_or_else(|| match method.as_str() {
+        "eth_chainId" => Reply::Value(json!("0x1")),
+        "net_version" => Reply::Value(json!("1")),
+        _ => Reply::Error(format!("the method {} does not exist", method)),
+    });
+    match reply {
+        Reply::Value(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Reply::Error(message) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32000, "message": message } }),
+        Reply::Revert(data) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": 3, "message": "execution reverted", "data": format!("0x{}", hex::encode(data)) }
+        }),
+        Reply::Stall => {
+            tokio::time::sleep(Duration::from_secs(300)).await;
+            json!({ "jsonrpc": "2.0", "id": id, "result": null })
+        }
+    }
+}
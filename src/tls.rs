@@ -0,0 +1,120 @@
+//! TLS server settings built from the configured PEM certificate chain and private key.
+
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, ClientConnection, Connection, PrivateKey, ServerConfig, ServerConnection, ServerName};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Enough round trips for a full TLS 1.2 or 1.3 handshake
+const SELF_TEST_ROUNDS: usize = 8;
+
+/// Server settings for a PEM certificate chain and its private key (PKCS#8, PKCS#1 or SEC1).
+/// rustls only notices a key that does not belong to the certificate when a client connects, so a
+/// handshake is run in memory first and a mismatch is reported here instead.
+pub fn server_config(cert_pem: &[u8], key_pem: &[u8]) -> Result<ServerConfig, String> {
+    let certs = rustls_pemfile::certs(&mut &cert_pem[..]).map_err(|e| format!("malformed certificate: {}", e))?;
+    if certs.is_empty() {
+        return Err("no certificate found".to_string());
+    }
+    let key = rustls_pemfile::read_all(&mut &key_pem[..])
+        .map_err(|e| format!("malformed key: {}", e))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) | rustls_pemfile::Item::ECKey(key) => Some(key),
+            _ => None,
+        })
+        .ok_or_else(|| "no private key found".to_string())?;
+
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs.into_iter().map(Certificate).collect(), PrivateKey(key))
+        .map_err(|e| e.to_string())?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    self_test(Arc::new(config.clone())).map_err(|e| format!("the private key does not match the certificate ({})", e))?;
+    Ok(config)
+}
+
+/// Accepts any certificate chain; the handshake signature is still checked against the certificate's key
+struct AcceptAnyCertificate;
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Complete a handshake against `config` over an in-memory pipe
+fn self_test(config: Arc<ServerConfig>) -> Result<(), rustls::Error> {
+    let client_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate))
+        .with_no_client_auth();
+    let server_name = ServerName::try_from("localhost").expect("valid server name");
+    let mut client = Connection::from(ClientConnection::new(Arc::new(client_config), server_name)?);
+    let mut server = Connection::from(ServerConnection::new(config)?);
+
+    for _ in 0..SELF_TEST_ROUNDS {
+        if !client.is_handshaking() && !server.is_handshaking() {
+            return Ok(());
+        }
+        transfer(&mut client, &mut server)?;
+        transfer(&mut server, &mut client)?;
+    }
+    Err(rustls::Error::General("handshake did not complete".to_string()))
+}
+
+/// Move everything `from` has to send into `to` and let `to` process it
+fn transfer(from: &mut Connection, to: &mut Connection) -> Result<(), rustls::Error> {
+    let mut records = Vec::new();
+    while from.wants_write() {
+        from.write_tls(&mut records).map_err(|e| rustls::Error::General(e.to_string()))?;
+    }
+    let mut records = records.as_slice();
+    while !records.is_empty() {
+        to.read_tls(&mut records).map_err(|e| rustls::Error::General(e.to_string()))?;
+        to.process_new_packets()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate() -> rcgen::Certificate {
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+    }
+
+    #[test]
+    fn accepts_a_matching_certificate_and_key() {
+        let cert = certificate();
+        let config = server_config(cert.serialize_pem().unwrap().as_bytes(), cert.serialize_private_key_pem().as_bytes());
+        assert!(config.is_ok(), "{:?}", config.err());
+    }
+
+    #[test]
+    fn rejects_a_key_from_another_certificate() {
+        let cert = certificate();
+        let other = certificate();
+        let error = server_config(cert.serialize_pem().unwrap().as_bytes(), other.serialize_private_key_pem().as_bytes())
+            .expect_err("mismatched key accepted");
+        assert!(error.contains("does not match"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_a_file_without_a_key() {
+        let cert = certificate();
+        let pem = cert.serialize_pem().unwrap();
+        assert_eq!(server_config(pem.as_bytes(), pem.as_bytes()).unwrap_err(), "no private key found");
+    }
+}
@@ -1,31 +1,283 @@
-use std::{
-    fs,
-    time::{SystemTime, UNIX_EPOCH},
-};
-use web3::types::U256;
+use crate::errors::{AppError, AppResult};
+use std::{fs, str::FromStr};
+use tiny_keccak::keccak256;
+use web3::types::{Address, BlockNumber, U256};
 extern crate rustc_serialize;
 
-pub fn get_nstime() -> u64 {
-    let dur = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-    // The correct way to calculate the current time is
-    // `dur.as_secs() * 1_000_000_000 + dur.subsec_nanos() as u64`
-    // But this is faster, and the difference in terms of entropy is
-    // negligible (log2(10^9) == 29.9).
-    dur.as_secs() << 30 | dur.subsec_nanos() as u64
+/// Nearest float to a wei amount in ETH, for display only; exact values stay in wei
+pub fn wei_to_eth(wei_val: U256) -> f64 {
+    format_units(wei_val, NATIVE_DECIMALS).parse().unwrap_or(f64::MAX)
 }
 
-pub fn wei_to_eth(wei_val: U256) -> f64 {
-    let res = wei_val.as_u128() as f64;
-    res / 1_000_000_000_000_000_000.0
+/// Nearest float to a wei amount in gwei, for display only
+pub fn wei_to_gwei(wei_val: U256) -> f64 {
+    format_units(wei_val, GWEI_DECIMALS).parse().unwrap_or(f64::MAX)
+}
+
+/// EIP-55 mixed-case checksum encoding of a hex address
+pub fn to_checksum_address(address: &str) -> String {
+    let address = address.trim_start_matches("0x").to_lowercase();
+    let hash = keccak256(address.as_bytes());
+
+    let checksummed: String = address
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+/// EIP-55 checksummed form of an address
+pub fn to_checksum(address: &Address) -> String {
+    to_checksum_address(&format!("{:x}", address))
+}
+
+/// Parse an address, rejecting mixed-case input whose casing is not its EIP-55 checksum.
+/// All-lowercase and all-uppercase addresses carry no checksum and are accepted as-is.
+pub fn validate_checksummed(address: &str) -> AppResult<Address> {
+    let parsed = Address::from_str(address)
+        .map_err(|e| AppError::InvalidAddress(format!("{}: {}", address, e)))?;
+
+    let hex = address.trim_start_matches("0x");
+    let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+    if mixed_case && to_checksum(&parsed).trim_start_matches("0x") != hex {
+        return Err(AppError::InvalidAddress(format!("{}: checksum mismatch", address)));
+    }
+    Ok(parsed)
 }
 
-pub fn eth_to_wei(eth_val: f64) -> U256 {
-    let result = eth_val * 1_000_000_000_000_000_000.0;
-    let result = result as u128;
+/// Whether a recipient or lookup target is an ENS name rather than a hex address
+pub fn is_ens_name(value: &str) -> bool {
+    value.to_ascii_lowercase().ends_with(".eth")
+}
+
+/// Parse a block specifier: a decimal or 0x-prefixed hex number, or `latest`, `earliest` or `pending`
+pub fn parse_block_number(block: &str) -> AppResult<BlockNumber> {
+    let invalid = || {
+        AppError::ValidationError(format!(
+            "Invalid block {}: expected a block number, latest, earliest or pending",
+            block
+        ))
+    };
+
+    match block {
+        "latest" => Ok(BlockNumber::Latest),
+        "earliest" => Ok(BlockNumber::Earliest),
+        "pending" => Ok(BlockNumber::Pending),
+        _ => {
+            let number = match block.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => block.parse::<u64>(),
+            };
+            number.map(|n| BlockNumber::Number(n.into())).map_err(|_| invalid())
+        }
+    }
+}
+
+/// Format an integer token amount with `decimals` fractional digits, without rounding
+pub fn format_units(value: U256, decimals: u8) -> String {
+    let scale = U256::exp10(decimals as usize);
+    let whole = value / scale;
+    let fraction = value % scale;
+    if fraction.is_zero() {
+        return whole.to_string();
+    }
 
-    U256::from(result)
+    let fraction = format!("{:0>width$}", fraction.to_string(), width = decimals as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Decimals of the native currency (wei per ETH = 10^18)
+pub const NATIVE_DECIMALS: u8 = 18;
+/// Decimal places of wei in a gwei
+pub const GWEI_DECIMALS: u8 = 9;
+
+/// Parse a decimal token amount such as "1.25" into base units with `decimals` fractional digits
+pub fn parse_units(amount: &str, decimals: u8) -> Result<U256, String> {
+    let amount = amount.trim();
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(format!("Invalid amount: {}", amount));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(format!("Amount {} has more than {} decimal places", amount, decimals));
+    }
+
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    U256::from_dec_str(&digits).map_err(|_| format!("Amount {} is too large", amount))
+}
+
+/// Display names of well-known EVM chains by chain ID
+const KNOWN_NETWORKS: &[(u64, &str)] = &[
+    (1, "Mainnet"),
+    (3, "Ropsten"),
+    (4, "Rinkeby"),
+    (5, "Goerli"),
+    (10, "Optimism"),
+    (56, "BNB Smart Chain"),
+    (97, "BNB Smart Chain Testnet"),
+    (100, "Gnosis"),
+    (137, "Polygon"),
+    (250, "Fantom"),
+    (324, "zkSync Era"),
+    (1101, "Polygon zkEVM"),
+    (1337, "Local Development"),
+    (8453, "Base"),
+    (17000, "Holesky"),
+    (31337, "Hardhat"),
+    (42161, "Arbitrum One"),
+    (42170, "Arbitrum Nova"),
+    (42220, "Celo"),
+    (43113, "Avalanche Fuji"),
+    (43114, "Avalanche C-Chain"),
+    (59144, "Linea"),
+    (80001, "Polygon Mumbai"),
+    (80002, "Polygon Amoy"),
+    (84532, "Base Sepolia"),
+    (421614, "Arbitrum Sepolia"),
+    (534352, "Scroll"),
+    (11155111, "Sepolia"),
+    (11155420, "OP Sepolia"),
+];
+
+/// Name of a well-known chain, or `None` for chains not in the built-in list
+pub fn known_network_name(network_id: u64) -> Option<&'static str> {
+    KNOWN_NETWORKS
+        .iter()
+        .find(|(id, _)| *id == network_id)
+        .map(|(_, name)| *name)
+}
+
+/// Native currency symbol of well-known EVM chains, "ETH" for anything else
+pub fn default_native_symbol(network_id: u64) -> &'static str {
+    match network_id {
+        56 | 97 => "BNB",
+        100 => "xDAI",
+        137 | 80001 => "MATIC",
+        250 => "FTM",
+        43114 | 43113 => "AVAX",
+        42220 => "CELO",
+        _ => "ETH",
+    }
 }
 
 pub fn path_exists(path: &str) -> bool {
     fs::metadata(path).is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_well_known_chains() {
+        assert_eq!(known_network_name(1), Some("Mainnet"));
+        assert_eq!(known_network_name(10), Some("Optimism"));
+        assert_eq!(known_network_name(56), Some("BNB Smart Chain"));
+        assert_eq!(known_network_name(137), Some("Polygon"));
+        assert_eq!(known_network_name(8453), Some("Base"));
+        assert_eq!(known_network_name(42161), Some("Arbitrum One"));
+        assert_eq!(known_network_name(11155111), Some("Sepolia"));
+        assert_eq!(known_network_name(987_654_321), None);
+    }
+
+    #[test]
+    fn adds_decimal_amounts_without_float_error() {
+        assert_ne!(0.1f64 + 0.2f64, 0.3f64);
+        let sum = parse_units("0.1", NATIVE_DECIMALS).unwrap() + parse_units("0.2", NATIVE_DECIMALS).unwrap();
+        assert_eq!(sum, parse_units("0.3", NATIVE_DECIMALS).unwrap());
+        assert_eq!(sum, U256::from(300_000_000_000_000_000u64));
+        assert_eq!(format_units(sum, NATIVE_DECIMALS), "0.3");
+    }
+
+    #[test]
+    fn formats_balances_above_two_to_the_53_exactly() {
+        let wei = U256::from_dec_str("12345678901234567891").unwrap();
+        assert!(wei > U256::from(1u64 << 53));
+        assert_ne!((wei.as_u128() as f64) as u128, wei.as_u128());
+        assert_eq!(format_units(wei, NATIVE_DECIMALS), "12.345678901234567891");
+        assert_eq!(parse_units("12.345678901234567891", NATIVE_DECIMALS).unwrap(), wei);
+
+        let just_above = U256::from((1u64 << 53) + 1);
+        assert_eq!(format_units(just_above, 0), "9007199254740993");
+        assert_eq!(parse_units("9007199254740993", 0).unwrap(), just_above);
+    }
+
+    #[test]
+    fn parses_and_formats_unit_amounts() {
+        assert_eq!(parse_units("1", NATIVE_DECIMALS).unwrap(), U256::exp10(18));
+        assert_eq!(parse_units("1.", 6).unwrap(), U256::from(1_000_000));
+        assert_eq!(parse_units(".5", 6).unwrap(), U256::from(500_000));
+        assert_eq!(parse_units("0.000000000000000001", NATIVE_DECIMALS).unwrap(), U256::one());
+        assert_eq!(format_units(U256::one(), NATIVE_DECIMALS), "0.000000000000000001");
+        assert_eq!(format_units(U256::from(1_500_000), 6), "1.5");
+        assert_eq!(format_units(U256::MAX, NATIVE_DECIMALS).replace('.', ""), U256::MAX.to_string());
+    }
+
+    #[test]
+    fn rejects_malformed_amounts() {
+        for amount in ["", ".", "-1", "1e18", "0x10", "1.2.3", "one"] {
+            assert!(parse_units(amount, NATIVE_DECIMALS).is_err(), "{:?}", amount);
+        }
+        assert!(parse_units("0.0000000000000000001", NATIVE_DECIMALS).unwrap_err().contains("decimal places"));
+        assert!(parse_units(&U256::MAX.to_string(), NATIVE_DECIMALS).unwrap_err().contains("too large"));
+    }
+
+    /// Mixed-case examples from EIP-55
+    const EIP55_EXAMPLES: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn checksums_match_the_eip55_examples() {
+        for example in EIP55_EXAMPLES {
+            assert_eq!(to_checksum_address(&example.to_lowercase()), example);
+            let address = validate_checksummed(example).unwrap();
+            assert_eq!(to_checksum(&address), example);
+        }
+        // An address whose checksum casing happens to be all lowercase
+        assert_eq!(to_checksum_address("0xDE709F2102306220921060314715629080E2FB77"), "0xde709f2102306220921060314715629080e2fb77");
+    }
+
+    #[test]
+    fn single_case_addresses_carry_no_checksum() {
+        for example in EIP55_EXAMPLES {
+            assert!(validate_checksummed(&example.to_lowercase()).is_ok());
+            assert!(validate_checksummed(&format!("0x{}", example[2..].to_uppercase())).is_ok());
+        }
+    }
+
+    #[test]
+    fn rejects_a_broken_checksum() {
+        for example in EIP55_EXAMPLES {
+            // Flip the case of the first letter
+            let at = example[2..].find(|c: char| c.is_ascii_alphabetic()).unwrap() + 2;
+            let mut typo = example.to_string();
+            let flipped = typo[at..=at].chars().map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect::<String>();
+            typo.replace_range(at..=at, &flipped);
+
+            let error = validate_checksummed(&typo).unwrap_err();
+            assert!(matches!(error, AppError::InvalidAddress(_)), "{}", typo);
+        }
+    }
+
+    #[test]
+    fn parses_block_numbers_and_tags() {
+        assert_eq!(parse_block_number("18000000").unwrap(), BlockNumber::Number(18_000_000u64.into()));
+        assert_eq!(parse_block_number("0x112a880").unwrap(), BlockNumber::Number(18_000_000u64.into()));
+        assert_eq!(parse_block_number("latest").unwrap(), BlockNumber::Latest);
+        assert_eq!(parse_block_number("earliest").unwrap(), BlockNumber::Earliest);
+        assert_eq!(parse_block_number("pending").unwrap(), BlockNumber::Pending);
+
+        for block in ["", "-1", "1.5", "0x", "0xzz", "finalized", "Latest"] {
+            assert!(matches!(parse_block_number(block), Err(AppError::ValidationError(_))), "{}", block);
+        }
+    }
+}